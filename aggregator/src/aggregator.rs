@@ -1,14 +1,15 @@
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     fmt::Debug,
-    time::Instant,
+    time::{Duration, Instant},
 };
 
 use aleph_bft_rmc::{DoublingDelayScheduler, MultiKeychain, Multisigned, Service as RmcService};
 use aleph_bft_types::Recipient;
 use log::{debug, info, trace, warn};
+use tokio::time::{self, Interval, MissedTickBehavior};
 
-use crate::{Hash, ProtocolSink, RmcNetworkData, SignableHash};
+use crate::{Hash, Metrics, ProtocolSink, RmcNetworkData, SignableHash};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum AggregatorError {
@@ -25,27 +26,30 @@ pub type IOResult = Result<(), IOError>;
 type Rmc<H, MK, S, PMS> =
     RmcService<SignableHash<H>, MK, DoublingDelayScheduler<RmcNetworkData<H, S, PMS>>>;
 
+struct PendingHash {
+    started: Instant,
+    remulticast: bool,
+}
+
 /// A wrapper around an `rmc::Multicast` returning the signed hashes in the order of the [`Multicast::start_multicast`] calls.
-pub struct BlockSignatureAggregator<H: Hash + Copy, PMS> {
+pub struct BlockSignatureAggregator<H: Hash + Copy, PMS, M: Metrics<H, PMS>> {
     signatures: HashMap<H, PMS>,
     hash_queue: VecDeque<H>,
     started_hashes: HashSet<H>,
+    pending_hashes: HashMap<H, PendingHash>,
     last_change: Instant,
+    metrics: M,
 }
 
-impl<H: Copy + Hash, PMS> Default for BlockSignatureAggregator<H, PMS> {
-    fn default() -> Self {
-        Self::new()
-    }
-}
-
-impl<H: Copy + Hash, PMS> BlockSignatureAggregator<H, PMS> {
-    pub fn new() -> Self {
+impl<H: Copy + Hash, PMS, M: Metrics<H, PMS>> BlockSignatureAggregator<H, PMS, M> {
+    pub fn new(metrics: M) -> Self {
         BlockSignatureAggregator {
             signatures: HashMap::new(),
             hash_queue: VecDeque::new(),
             started_hashes: HashSet::new(),
+            pending_hashes: HashMap::new(),
             last_change: Instant::now(),
+            metrics,
         }
     }
 
@@ -53,19 +57,54 @@ impl<H: Copy + Hash, PMS> BlockSignatureAggregator<H, PMS> {
         if !self.started_hashes.insert(hash) {
             return Err(AggregatorError::DuplicateHash);
         }
+        let now = Instant::now();
         if self.hash_queue.is_empty() {
-            self.last_change = Instant::now();
+            self.last_change = now;
         }
         self.hash_queue.push_back(hash);
+        self.pending_hashes.insert(
+            hash,
+            PendingHash {
+                started: now,
+                remulticast: false,
+            },
+        );
+        self.metrics.report_pending(self.pending_hashes.len());
 
         Ok(())
     }
 
     fn on_multisigned_hash(&mut self, hash: H, signature: PMS) {
         debug!(target: "aleph-aggregator", "New multisigned_hash {:?}.", hash);
+        if let Some(PendingHash { started, .. }) = self.pending_hashes.remove(&hash) {
+            self.metrics.report_aggregation_complete(
+                hash,
+                Instant::now().saturating_duration_since(started),
+                &signature,
+            );
+            self.metrics.report_pending(self.pending_hashes.len());
+        }
         self.signatures.insert(hash, signature);
     }
 
+    /// Returns the hashes that did not get multisigned within `timeout` since they were started,
+    /// and marks them as multicast again. Every hash is returned at most once, as the RMC keeps
+    /// resending the messages of a started multicast by itself, so starting it once more is
+    /// enough to recover from a lost one.
+    fn take_stuck_hashes(&mut self, timeout: Duration, now: Instant) -> Vec<H> {
+        let mut stuck = Vec::new();
+        for (hash, pending) in self.pending_hashes.iter_mut() {
+            if !pending.remulticast && now.saturating_duration_since(pending.started) >= timeout {
+                pending.remulticast = true;
+                stuck.push(*hash);
+            }
+        }
+        for hash in stuck.iter() {
+            self.metrics.report_remulticast(*hash);
+        }
+        stuck
+    }
+
     fn try_pop_hash(&mut self) -> AggregatorResult<(H, PMS)> {
         match self.hash_queue.pop_front() {
             Some(hash) => {
@@ -96,6 +135,11 @@ impl<H: Copy + Hash, PMS> BlockSignatureAggregator<H, PMS> {
 
         status.push_str(&format!("hashes in queue - {:?}; ", self.hash_queue.len()));
 
+        status.push_str(&format!(
+            "hashes pending signatures - {:?}; ",
+            self.pending_hashes.len()
+        ));
+
         if let Some(hash) = self.hash_queue.front() {
             status.push_str(&format!(
                 "front of hash queue - {} for - {:.2} s; ",
@@ -114,29 +158,41 @@ pub struct IO<
     H: Hash + Copy,
     N: ProtocolSink<RmcNetworkData<H, MK::Signature, MK::PartialMultisignature>>,
     MK: MultiKeychain,
+    M: Metrics<H, MK::PartialMultisignature>,
 > {
     network: N,
     rmc_service: Rmc<H, MK, MK::Signature, MK::PartialMultisignature>,
-    aggregator: BlockSignatureAggregator<H, MK::PartialMultisignature>,
+    aggregator: BlockSignatureAggregator<H, MK::PartialMultisignature, M>,
     multisigned_events: VecDeque<Multisigned<SignableHash<H>, MK>>,
+    remulticast_timeout: Duration,
+    remulticast_ticker: Interval,
 }
 
 impl<
         H: Copy + Hash,
         N: ProtocolSink<RmcNetworkData<H, MK::Signature, MK::PartialMultisignature>>,
         MK: MultiKeychain,
-    > IO<H, N, MK>
+        M: Metrics<H, MK::PartialMultisignature>,
+    > IO<H, N, MK, M>
 {
+    /// Creates the aggregator IO. Hashes that do not get multisigned within `remulticast_timeout`
+    /// are multicast again, once. Has to be called within a tokio runtime, `remulticast_timeout` has to
+    /// be non-zero.
     pub fn new(
         network: N,
         rmc_service: Rmc<H, MK, MK::Signature, MK::PartialMultisignature>,
-        aggregator: BlockSignatureAggregator<H, MK::PartialMultisignature>,
+        aggregator: BlockSignatureAggregator<H, MK::PartialMultisignature, M>,
+        remulticast_timeout: Duration,
     ) -> Self {
+        let mut remulticast_ticker = time::interval(remulticast_timeout);
+        remulticast_ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
         IO {
             network,
             rmc_service,
             aggregator,
             multisigned_events: VecDeque::new(),
+            remulticast_timeout,
+            remulticast_ticker,
         }
     }
 
@@ -155,6 +211,18 @@ impl<
         }
     }
 
+    fn remulticast_stuck_hashes(&mut self) {
+        for hash in self
+            .aggregator
+            .take_stuck_hashes(self.remulticast_timeout, Instant::now())
+        {
+            debug!(target: "aleph-aggregator", "No multisignature for block hash {:?} after {:?}, multicasting it again.", hash, self.remulticast_timeout);
+            if let Some(multisigned) = self.rmc_service.start_rmc(SignableHash::new(hash)) {
+                self.multisigned_events.push_back(multisigned);
+            }
+        }
+    }
+
     async fn wait_for_next_signature(&mut self) -> IOResult {
         loop {
             if let Some(multisigned) = self.multisigned_events.pop_front() {
@@ -182,6 +250,9 @@ impl<
                         // In case the network is down we can terminate (?).
                         return Err(IOError::NetworkChannelClosed);
                     }
+                },
+                _ = self.remulticast_ticker.tick() => {
+                    self.remulticast_stuck_hashes();
                 }
             }
         }
@@ -216,11 +287,16 @@ mod tests {
     use std::{
         fmt::{Debug, Display, Formatter},
         hash::Hash,
+        sync::{Arc, Mutex},
+        time::{Duration, Instant},
     };
 
     use parity_scale_codec::{Decode, Encode};
 
-    use crate::aggregator::{AggregatorError, BlockSignatureAggregator};
+    use crate::{
+        aggregator::{AggregatorError, BlockSignatureAggregator},
+        Metrics,
+    };
 
     #[derive(Hash, PartialEq, Eq, Clone, Copy, Encode, Decode, Debug)]
     struct MockHash(pub [u8; 32]);
@@ -239,8 +315,41 @@ mod tests {
     type TestMultisignature = usize;
     const TEST_SIGNATURE: TestMultisignature = 42;
 
-    fn build_aggregator() -> BlockSignatureAggregator<MockHash, TestMultisignature> {
-        BlockSignatureAggregator::new()
+    #[derive(Default)]
+    struct Reports {
+        pending: Vec<usize>,
+        completed: Vec<(MockHash, TestMultisignature)>,
+        remulticast: Vec<MockHash>,
+    }
+
+    #[derive(Clone, Default)]
+    struct MockMetrics(Arc<Mutex<Reports>>);
+
+    impl Metrics<MockHash, TestMultisignature> for MockMetrics {
+        fn report_pending(&mut self, pending: usize) {
+            self.0.lock().unwrap().pending.push(pending);
+        }
+
+        fn report_aggregation_complete(
+            &mut self,
+            hash: MockHash,
+            _: Duration,
+            multisignature: &TestMultisignature,
+        ) {
+            self.0
+                .lock()
+                .unwrap()
+                .completed
+                .push((hash, *multisignature));
+        }
+
+        fn report_remulticast(&mut self, hash: MockHash) {
+            self.0.lock().unwrap().remulticast.push(hash);
+        }
+    }
+
+    fn build_aggregator() -> BlockSignatureAggregator<MockHash, TestMultisignature, MockMetrics> {
+        BlockSignatureAggregator::new(MockMetrics::default())
     }
 
     fn build_hash(b0: u8) -> MockHash {
@@ -272,4 +381,56 @@ mod tests {
         let res = aggregator.try_pop_hash();
         assert_eq!(res, Err(AggregatorError::NoHashFound));
     }
+
+    #[test]
+    fn reports_pending_and_completed_hashes() {
+        let metrics = MockMetrics::default();
+        let mut aggregator = BlockSignatureAggregator::new(metrics.clone());
+        aggregator.on_start(build_hash(0)).unwrap();
+        aggregator.on_start(build_hash(1)).unwrap();
+
+        aggregator.on_multisigned_hash(build_hash(1), TEST_SIGNATURE);
+        // Hashes we did not start aggregating are not reported.
+        aggregator.on_multisigned_hash(build_hash(2), TEST_SIGNATURE);
+
+        let reports = metrics.0.lock().unwrap();
+        assert_eq!(reports.pending, vec![1, 2, 1]);
+        assert_eq!(reports.completed, vec![(build_hash(1), TEST_SIGNATURE)]);
+    }
+
+    #[test]
+    fn takes_only_stuck_hashes() {
+        let metrics = MockMetrics::default();
+        let mut aggregator = BlockSignatureAggregator::new(metrics.clone());
+        let timeout = Duration::from_secs(10);
+        aggregator.on_start(build_hash(0)).unwrap();
+        aggregator.on_start(build_hash(1)).unwrap();
+        aggregator.on_multisigned_hash(build_hash(1), TEST_SIGNATURE);
+
+        let now = Instant::now();
+        assert!(aggregator.take_stuck_hashes(timeout, now).is_empty());
+
+        let later = now + timeout;
+        assert_eq!(
+            aggregator.take_stuck_hashes(timeout, later),
+            vec![build_hash(0)]
+        );
+        assert_eq!(metrics.0.lock().unwrap().remulticast, vec![build_hash(0)]);
+    }
+
+    #[test]
+    fn stuck_hash_is_multicast_again_only_once() {
+        let metrics = MockMetrics::default();
+        let mut aggregator = BlockSignatureAggregator::new(metrics.clone());
+        let timeout = Duration::from_secs(10);
+        aggregator.on_start(build_hash(0)).unwrap();
+
+        let now = Instant::now();
+        let restarted: Vec<_> = (1..=5)
+            .flat_map(|timeouts| aggregator.take_stuck_hashes(timeout, now + timeout * timeouts))
+            .collect();
+
+        assert_eq!(restarted, vec![build_hash(0)]);
+        assert_eq!(metrics.0.lock().unwrap().remulticast, vec![build_hash(0)]);
+    }
 }
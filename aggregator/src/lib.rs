@@ -1,6 +1,7 @@
 use std::{
     fmt::{Debug, Display},
     hash::Hash as StdHash,
+    time::Duration,
 };
 
 use aleph_bft_rmc::{Message as RmcMessage, Signable};
@@ -11,6 +12,26 @@ mod aggregator;
 
 pub use crate::aggregator::{BlockSignatureAggregator, IO};
 
+/// Receives reports about the progress of the aggregation.
+pub trait Metrics<H, PMS>: Send {
+    /// The number of hashes for which the aggregation started, but which are not multisigned yet.
+    fn report_pending(&mut self, pending: usize);
+    /// A hash got multisigned `duration` after its aggregation started.
+    fn report_aggregation_complete(&mut self, hash: H, duration: Duration, multisignature: &PMS);
+    /// A hash did not get multisigned in time and was multicast again.
+    fn report_remulticast(&mut self, hash: H);
+}
+
+/// Metrics that ignore all the reports.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopMetrics;
+
+impl<H, PMS> Metrics<H, PMS> for NoopMetrics {
+    fn report_pending(&mut self, _: usize) {}
+    fn report_aggregation_complete(&mut self, _: H, _: Duration, _: &PMS) {}
+    fn report_remulticast(&mut self, _: H) {}
+}
+
 pub type RmcNetworkData<H, S, SS> = RmcMessage<SignableHash<H>, S, SS>;
 
 /// A convenience trait for gathering all of the desired hash characteristics.
@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+
 use codec::Encode;
+use serde::{Deserialize, Serialize};
 use subxt::rpc_params;
 
 use crate::{
@@ -7,14 +10,38 @@ use crate::{
         pallet_aleph::pallet::Call::set_emergency_finalizer, primitives::app::Public,
         sp_core::ed25519::Public as EdPublic,
     },
-    connections::TxInfo,
+    connections::{AsConnection, TxInfo},
     pallet_aleph::pallet::Call::schedule_finality_version_change,
     sp_core::Bytes,
     AccountId, AlephKeyPair, BlockHash, BlockNumber,
     Call::Aleph,
-    ConnectionApi, Pair, RootConnection, SessionIndex, SudoCall, TxStatus, Version,
+    ConnectionApi, EmergencyFinalizers, Pair, RootConnection, SessionIndex, SudoCall, TxStatus,
+    Version,
 };
 
+/// Range of finality versions a validator advertises support for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FinalityVersions {
+    /// The lowest supported version.
+    pub lowest: Version,
+    /// The highest supported version.
+    pub highest: Version,
+}
+
+/// Whether the committee of the session in which the scheduled finality version change happens
+/// advertises support for the incoming version, as seen by the queried node.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FinalityVersionCompatibility {
+    /// The version that is scheduled to be used.
+    pub version_incoming: Version,
+    /// The session from which the version is going to be used.
+    pub session: SessionIndex,
+    /// Members of the committee that advertised support for the incoming version.
+    pub supporting: Vec<AccountId>,
+    /// Members of the committee that did not, with the versions they advertised, if any.
+    pub not_supporting: HashMap<AccountId, Option<FinalityVersions>>,
+}
+
 // TODO replace docs with link to pallet aleph docs, once they are published
 /// Pallet aleph API which does not require sudo.
 #[async_trait::async_trait]
@@ -25,6 +52,8 @@ pub trait AlephApi {
     async fn next_session_finality_version(&self, at: Option<BlockHash>) -> Version;
     /// Gets the emergency finalizer
     async fn emergency_finalizer(&self, at: Option<BlockHash>) -> Option<[u8; 32]>;
+    /// Gets the emergency finalizer set with its threshold, if there is one.
+    async fn emergency_finalizers(&self, at: Option<BlockHash>) -> Option<EmergencyFinalizers>;
 }
 
 /// Pallet aleph API that requires sudo.
@@ -67,6 +96,23 @@ pub trait AlephRpc {
         hash: BlockHash,
         key_pair: AlephKeyPair,
     ) -> anyhow::Result<()>;
+
+    /// Finalize the block with given hash and number using signatures of the emergency finalizer
+    /// set, each paired with the index of its key in the set.
+    async fn emergency_finalize_multisigned(
+        &self,
+        number: BlockNumber,
+        hash: BlockHash,
+        signatures: Vec<(u32, Vec<u8>)>,
+    ) -> anyhow::Result<()>;
+
+    /// Checks whether the committee of the session of the scheduled finality version change
+    /// advertises support for the incoming version.
+    /// # Returns
+    /// `None` if no finality version change is scheduled.
+    async fn finality_version_compatibility(
+        &self,
+    ) -> anyhow::Result<Option<FinalityVersionCompatibility>>;
 }
 
 #[async_trait::async_trait]
@@ -92,6 +138,14 @@ impl<C: ConnectionApi> AlephApi for C {
             .await
             .map(|public| public.0 .0)
     }
+
+    async fn emergency_finalizers(&self, at: Option<BlockHash>) -> Option<EmergencyFinalizers> {
+        let method = "state_call";
+        let api_method = "AlephSessionApi_emergency_finalizers";
+        let params = rpc_params![api_method, "0x", at];
+
+        self.rpc_call(method.to_string(), params).await.unwrap()
+    }
 }
 
 #[async_trait::async_trait]
@@ -123,7 +177,7 @@ impl AlephSudoApi for RootConnection {
 }
 
 #[async_trait::async_trait]
-impl<C: ConnectionApi> AlephRpc for C {
+impl<C: ConnectionApi + AsConnection> AlephRpc for C {
     async fn emergency_finalize(
         &self,
         number: BlockNumber,
@@ -139,4 +193,35 @@ impl<C: ConnectionApi> AlephRpc for C {
 
         Ok(())
     }
+
+    async fn emergency_finalize_multisigned(
+        &self,
+        number: BlockNumber,
+        hash: BlockHash,
+        signatures: Vec<(u32, Vec<u8>)>,
+    ) -> anyhow::Result<()> {
+        let method = "alephNode_emergencyFinalizeMultisigned";
+        let signatures: Vec<_> = signatures
+            .into_iter()
+            .map(|(index, signature)| (index, Bytes::from(signature)))
+            .collect();
+        let params = rpc_params![signatures, hash, number];
+
+        let _: () = self.rpc_call_no_return(method.to_string(), params).await?;
+
+        Ok(())
+    }
+
+    async fn finality_version_compatibility(
+        &self,
+    ) -> anyhow::Result<Option<FinalityVersionCompatibility>> {
+        let method = "alephNode_unstable_finalityVersionCompatibility";
+
+        Ok(self
+            .as_connection()
+            .as_client()
+            .rpc()
+            .request(method, rpc_params![])
+            .await?)
+    }
 }
@@ -499,6 +499,10 @@ pub enum Command {
         expected_state: ExtrinsicState,
     },
 
+    /// Checks whether the committee of the session of the scheduled version upgrade advertises
+    /// support for the incoming version. Requires the node to cache validator network info.
+    VersionUpgradeCheck,
+
     /// Interact with `pallet_baby_liminal`.
     #[cfg(feature = "liminal")]
     #[clap(subcommand)]
@@ -30,7 +30,7 @@ pub use treasury::{
     approve as treasury_approve, propose as treasury_propose, reject as treasury_reject,
 };
 pub use validators::change_validators;
-pub use version_upgrade::schedule_upgrade;
+pub use version_upgrade::{check_upgrade, schedule_upgrade};
 pub use vesting::{vest, vest_other, vested_transfer};
 #[cfg(feature = "liminal")]
 pub use {
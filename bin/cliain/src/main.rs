@@ -3,7 +3,7 @@ use std::env;
 use aleph_client::{account_from_keypair, aleph_keypair_from_string, keypair_from_string, Pair};
 use clap::Parser;
 use cliain::{
-    bond, call, change_validators, code_info, finalize, force_new_era, instantiate,
+    bond, call, change_validators, check_upgrade, code_info, finalize, force_new_era, instantiate,
     instantiate_with_code, next_session_keys, nominate, prepare_keys, prompt_password_hidden,
     remove_code, rotate_keys, schedule_upgrade, set_emergency_finalizer, set_keys,
     set_staking_limits, transfer_keep_alive, treasury_approve, treasury_propose, treasury_reject,
@@ -45,7 +45,8 @@ fn read_seed(command: &Command, seed: Option<String>) -> String {
         | Command::NextSessionKeys { .. }
         | Command::RotateKeys
         | Command::SeedToSS58 { .. }
-        | Command::ContractCodeInfo { .. } => String::new(),
+        | Command::ContractCodeInfo { .. }
+        | Command::VersionUpgradeCheck => String::new(),
         #[cfg(feature = "liminal")]
         Command::SnarkRelation { .. } => String::new(),
         _ => read_secret(seed, "Provide seed for the signer account:"),
@@ -240,6 +241,11 @@ async fn main() -> anyhow::Result<()> {
             Ok(_) => {}
             Err(why) => error!("Unable to schedule an upgrade {:?}", why),
         },
+        Command::VersionUpgradeCheck => {
+            if let Err(why) = check_upgrade(cfg.get_connection().await).await {
+                error!("Version upgrade check failed {:?}", why)
+            }
+        }
 
         #[cfg(feature = "liminal")]
         Command::BabyLiminal(cmd) => match cmd {
@@ -1,4 +1,8 @@
-use aleph_client::{pallets::aleph::AlephSudoApi, RootConnection};
+use aleph_client::{
+    pallets::aleph::{AlephRpc, AlephSudoApi},
+    Connection, RootConnection,
+};
+use anyhow::anyhow;
 use primitives::SessionIndex;
 
 use crate::commands::{ExtrinsicState, Version};
@@ -15,3 +19,41 @@ pub async fn schedule_upgrade(
 
     Ok(())
 }
+
+/// Checks whether every member of the committee of the session in which the scheduled version
+/// change happens advertises support for the incoming version, as seen by the connected node.
+pub async fn check_upgrade(connection: Connection) -> anyhow::Result<()> {
+    let compatibility = match connection.finality_version_compatibility().await? {
+        Some(compatibility) => compatibility,
+        None => {
+            println!("No finality version change is scheduled.");
+            return Ok(());
+        }
+    };
+
+    println!(
+        "Finality version {} is scheduled for session {}.",
+        compatibility.version_incoming, compatibility.session
+    );
+    println!(
+        "{} members of the committee advertise support for it.",
+        compatibility.supporting.len()
+    );
+    if compatibility.not_supporting.is_empty() {
+        return Ok(());
+    }
+    for (account, versions) in &compatibility.not_supporting {
+        match versions {
+            Some(versions) => println!(
+                "{} supports only versions {} to {}.",
+                account, versions.lowest, versions.highest
+            ),
+            None => println!("{account} did not advertise supported versions."),
+        }
+    }
+    Err(anyhow!(
+        "{} members of the committee do not advertise support for version {}",
+        compatibility.not_supporting.len(),
+        compatibility.version_incoming
+    ))
+}
@@ -6,8 +6,12 @@ use std::{
 };
 
 use aleph_client::{
-    aleph_keypair_from_string, api, pallets::aleph::AlephRpc, primitives::app::Public,
-    sp_core::H256, AlephKeyPair, BlockNumber, Connection, ConnectionApi, Pair,
+    aleph_keypair_from_string, api,
+    codec::Encode,
+    pallets::aleph::{AlephApi, AlephRpc},
+    primitives::app::Public,
+    sp_core::{ed25519, H256},
+    AlephKeyPair, BlockNumber, Connection, ConnectionApi, EmergencyFinalizers, Pair,
 };
 use anyhow::Result;
 use dialoguer::Confirm;
@@ -296,16 +300,272 @@ pub async fn try_finalize(
         plan.target.num,
         hex::encode(plan.target.hash),
     );
+    confirm()?;
+
+    for num in (plan.finalized_base.num + 1)..=plan.target.num {
+        try_finalize_single_block(&connections, &key, num).await?;
+    }
+    Ok(())
+}
+
+/// Signatures of a range of blocks made by a single member of the emergency finalizer set.
+struct PartialSignatures {
+    public: [u8; 32],
+    signatures: Vec<(HashNum, [u8; 64])>,
+}
+
+impl PartialSignatures {
+    /// The first line contains the public key, every following one the number and hash of a block
+    /// and the signature of the hash, all hex encoded but the number.
+    fn to_file_contents(&self) -> String {
+        let mut contents = format!("{}\n", hex::encode(self.public));
+        for (HashNum { num, hash }, signature) in &self.signatures {
+            contents.push_str(&format!(
+                "{} {} {}\n",
+                num,
+                hex::encode(hash),
+                hex::encode(signature)
+            ));
+        }
+        contents
+    }
+
+    fn from_file_contents(contents: &str) -> Result<Self> {
+        let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+        let public = decode_hex_array(
+            lines
+                .next()
+                .ok_or_else(|| anyhow::anyhow!("Missing the public key."))?,
+        )?;
+        let mut signatures = Vec::new();
+        for line in lines {
+            let parts: Vec<_> = line.split_whitespace().collect();
+            let (num, hash, signature) = match parts[..] {
+                [num, hash, signature] => (num, hash, signature),
+                _ => return Err(anyhow::anyhow!("Malformed signature line {}.", line)),
+            };
+            signatures.push((
+                HashNum {
+                    num: num.parse()?,
+                    hash: H256(decode_hex_array(hash)?),
+                },
+                decode_hex_array(signature)?,
+            ));
+        }
+        Ok(PartialSignatures { public, signatures })
+    }
+
+    fn signature_at(&self, block: &HashNum) -> Option<[u8; 64]> {
+        self.signatures
+            .iter()
+            .find(|(signed, _)| signed == block)
+            .map(|(_, signature)| *signature)
+    }
+}
+
+fn decode_hex_array<const N: usize>(data: &str) -> Result<[u8; N]> {
+    hex::decode(data.trim().trim_start_matches("0x"))?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("Expected {} hex encoded bytes, got {}.", N, data))
+}
+
+async fn get_emergency_finalizers(connection: &Connection) -> Result<EmergencyFinalizers> {
+    connection
+        .emergency_finalizers(None)
+        .await
+        .ok_or_else(|| anyhow::anyhow!("No emergency finalizer set on chain."))
+}
+
+fn finalizer_index(emergency_finalizers: &EmergencyFinalizers, public: &[u8; 32]) -> Option<u32> {
+    emergency_finalizers
+        .keys
+        .iter()
+        .position(|key| AsRef::<[u8]>::as_ref(key) == &public[..])
+        .map(|index| index as u32)
+}
+
+fn confirm() -> Result<()> {
     let proceed = Confirm::new()
         .with_prompt("Do you want to continue?")
         .default(true)
         .interact()?;
-    if !proceed {
-        return Err(anyhow::anyhow!("Cancelled by user."));
+    match proceed {
+        true => Ok(()),
+        false => Err(anyhow::anyhow!("Cancelled by user.")),
     }
+}
 
+/// Signs the blocks that would be finalized with a key of the emergency finalizer set and saves
+/// the signatures, so that they can be combined with signatures of other members of the set.
+pub async fn sign(
+    connections: Connections,
+    seed_path: PathBuf,
+    how_many: BlockNumber,
+    output_path: PathBuf,
+) -> Result<()> {
+    let key = read_key_from_file(seed_path)?;
+    let emergency_finalizers = get_emergency_finalizers(&connections.primary).await?;
+    let index = finalizer_index(&emergency_finalizers, &key.public().0).ok_or_else(|| {
+        anyhow::anyhow!(
+            "Key from file {} is not in the emergency finalizer set on chain.",
+            hex::encode(key.public().0)
+        )
+    })?;
+    let plan = pre_sequence_finalization_check(&connections, how_many).await?;
+    println!(
+        "Sanity check passed. Will sign blocks from {} to {} (last hash {}) as member {} of the emergency finalizer set",
+        plan.finalized_base.num + 1,
+        plan.target.num,
+        hex::encode(plan.target.hash),
+        index,
+    );
+    confirm()?;
+
+    let mut signatures = Vec::new();
     for num in (plan.finalized_base.num + 1)..=plan.target.num {
-        try_finalize_single_block(&connections, &key, num).await?;
+        let blocks = get_all_blocks_at_num(&connections, num).await?;
+        assert_blocks_match(&blocks, num)?;
+        let signature = key.sign(&blocks.primary.hash.encode());
+        signatures.push((blocks.primary, signature.0));
+    }
+    let partial_signatures = PartialSignatures {
+        public: key.public().0,
+        signatures,
+    };
+    fs::write(&output_path, partial_signatures.to_file_contents())?;
+    println!("Signatures saved to {:?}", &output_path);
+    Ok(())
+}
+
+fn collect_signatures(
+    emergency_finalizers: &EmergencyFinalizers,
+    partial_signatures: &[PartialSignatures],
+    block: &HashNum,
+) -> Result<Vec<(u32, Vec<u8>)>> {
+    let mut signatures = Vec::new();
+    for partial in partial_signatures {
+        let index = match finalizer_index(emergency_finalizers, &partial.public) {
+            Some(index) => index,
+            None => continue,
+        };
+        if let Some(signature) = partial.signature_at(block) {
+            if signatures.iter().any(|(signer, _)| *signer == index) {
+                continue;
+            }
+            if !AlephKeyPair::verify(
+                &ed25519::Signature::from_raw(signature),
+                block.hash.encode(),
+                &ed25519::Public::from_raw(partial.public),
+            ) {
+                return Err(anyhow::anyhow!(
+                    "Invalid signature of {} by {}.",
+                    block,
+                    hex::encode(partial.public)
+                ));
+            }
+            signatures.push((index, signature.to_vec()));
+        }
+    }
+    if signatures.len() < emergency_finalizers.threshold as usize {
+        return Err(anyhow::anyhow!(
+            "Only {} of the required {} signatures of {} collected.",
+            signatures.len(),
+            emergency_finalizers.threshold,
+            block
+        ));
+    }
+    Ok(signatures)
+}
+
+async fn try_finalize_single_block_multisigned(
+    connections: &Connections,
+    emergency_finalizers: &EmergencyFinalizers,
+    partial_signatures: &[PartialSignatures],
+    num: BlockNumber,
+) -> Result<()> {
+    println!("Trying to finalize block number {num}");
+    loop {
+        match pre_single_finalization_check(connections, num).await {
+            Ok(block) => {
+                let signatures =
+                    collect_signatures(emergency_finalizers, partial_signatures, &block)?;
+                println!(
+                    "Sanity check passed. Sending finalization call for {} and {} with {} signatures",
+                    block.num,
+                    hex::encode(block.hash),
+                    signatures.len()
+                );
+                connections
+                    .primary
+                    .emergency_finalize_multisigned(block.num, block.hash, signatures)
+                    .await?;
+                println!("Finalization call for {num} sent.",);
+                break;
+            }
+            Err(e) => {
+                println!("Not all preconditions for finalizing {num} satisfied: {e:?}.");
+                println!("We wait 1000ms and will try again. You can cancel by ctrl-c.\n");
+                tokio::time::sleep(std::time::Duration::from_millis(1000)).await;
+                continue;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Finalizes the blocks signed by a threshold of the emergency finalizer set, using the
+/// signatures saved by the `sign` command of its members.
+pub async fn try_finalize_multisigned(
+    connections: Connections,
+    signature_paths: Vec<PathBuf>,
+) -> Result<()> {
+    let emergency_finalizers = get_emergency_finalizers(&connections.primary).await?;
+    let mut partial_signatures = Vec::new();
+    for path in signature_paths {
+        println!("Reading signatures from file {:?}", &path);
+        let partial = PartialSignatures::from_file_contents(&fs::read_to_string(path)?)?;
+        match finalizer_index(&emergency_finalizers, &partial.public) {
+            Some(index) => println!(
+                "Read {} signatures of member {} of the emergency finalizer set\n",
+                partial.signatures.len(),
+                index
+            ),
+            None => {
+                return Err(anyhow::anyhow!(
+                    "Key {} is not in the emergency finalizer set on chain.",
+                    hex::encode(partial.public)
+                ))
+            }
+        }
+        partial_signatures.push(partial);
+    }
+
+    let statuses = get_all_chain_statuses(&connections).await?;
+    assert_best_finalized_match(&statuses)?;
+    let first = statuses.primary.finalized.num + 1;
+    let last = partial_signatures
+        .iter()
+        .flat_map(|partial| partial.signatures.iter().map(|(block, _)| block.num))
+        .max()
+        .filter(|last| *last >= first)
+        .ok_or_else(|| anyhow::anyhow!("No signatures of blocks above the finalized one."))?;
+    println!(
+        "Will proceed to finalizing blocks from {} to {} with the threshold of {} out of {} signatures",
+        first,
+        last,
+        emergency_finalizers.threshold,
+        emergency_finalizers.keys.len(),
+    );
+    confirm()?;
+
+    for num in first..=last {
+        try_finalize_single_block_multisigned(
+            &connections,
+            &emergency_finalizers,
+            &partial_signatures,
+            num,
+        )
+        .await?;
     }
     Ok(())
 }
@@ -4,7 +4,7 @@ use aleph_client::BlockNumber;
 use anyhow::Result;
 use clap::{Parser, Subcommand};
 
-use crate::commands::{status, try_finalize, Connections};
+use crate::commands::{sign, status, try_finalize, try_finalize_multisigned, Connections};
 mod commands;
 
 #[derive(Debug, Parser, Clone)]
@@ -37,6 +37,27 @@ pub enum Command {
         #[clap(long)]
         how_many: BlockNumber,
     },
+    /// Sign the specified number of blocks with a key of the emergency finalizer set and save
+    /// the signatures to a file, to be combined with signatures of other members of the set
+    Sign {
+        /// Path to the seed phrase of a member of the emergency finalizer set.
+        #[clap(long, default_value = "seed.txt")]
+        seed_path: PathBuf,
+
+        /// The number of blocks to sign. Should be no more than 20.
+        #[clap(long)]
+        how_many: BlockNumber,
+
+        /// Path to the file the signatures are saved to.
+        #[clap(long)]
+        output_path: PathBuf,
+    },
+    /// Attempt finalizing blocks using signatures of a threshold of the emergency finalizer set
+    TryFinalizeMultisigned {
+        /// Paths to the files with signatures saved by members of the emergency finalizer set.
+        #[clap(long, value_delimiter = ',', required = true)]
+        signature_paths: Vec<PathBuf>,
+    },
 }
 
 #[tokio::main]
@@ -61,6 +82,14 @@ async fn main() -> Result<()> {
             seed_path,
             how_many,
         } => try_finalize(connections, seed_path, how_many).await?,
+        Command::Sign {
+            seed_path,
+            how_many,
+            output_path,
+        } => sign(connections, seed_path, how_many, output_path).await?,
+        Command::TryFinalizeMultisigned { signature_paths } => {
+            try_finalize_multisigned(connections, signature_paths).await?
+        }
     }
     Ok(())
 }
@@ -1,17 +1,50 @@
-use std::path::PathBuf;
-
-use finality_aleph::UnitCreationDelay;
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
+
+use finality_aleph::{
+    ProposalStrategyKind, RemulticastTimeout, SessionHistoryDepth, UnitCreationDelay,
+};
 use log::warn;
-use sc_cli::clap::{self, ArgGroup, Parser};
+use sc_cli::clap::{self, ArgGroup, Parser, ValueEnum};
+
+use crate::{
+    aleph_primitives::DEFAULT_UNIT_CREATION_DELAY,
+    nat::{NatConfig, NatProtocol},
+};
+
+/// How the node chooses the branch it proposes for finalization.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProposalStrategy {
+    /// Propose the branch leading to the local best block.
+    LocalBest,
+    /// Prefer the branch most of the peers have, based on their declared favourite blocks.
+    MajorityFork,
+}
 
-use crate::aleph_primitives::DEFAULT_UNIT_CREATION_DELAY;
+impl From<ProposalStrategy> for ProposalStrategyKind {
+    fn from(strategy: ProposalStrategy) -> Self {
+        match strategy {
+            ProposalStrategy::LocalBest => ProposalStrategyKind::LocalBest,
+            ProposalStrategy::MajorityFork => ProposalStrategyKind::MajorityFork,
+        }
+    }
+}
 
 #[derive(Debug, Parser, Clone)]
 #[clap(group(ArgGroup::new("backup")))]
 pub struct AlephCli {
+    /// Delay between creating AlephBFT units, in milliseconds. Only used for sessions of runtimes
+    /// that do not provide the AlephBFT config, which otherwise determines it for all validators.
     #[clap(long, default_value_t = DEFAULT_UNIT_CREATION_DELAY)]
     unit_creation_delay: u64,
 
+    /// Time in milliseconds after which a block hash that did not collect enough signatures
+    /// for a justification is multicast again.
+    #[clap(long, default_value_t = 30_000, value_parser = clap::value_parser!(u64).range(1..))]
+    remulticast_timeout: u64,
+
     /// The addresses at which the node will be externally reachable for validator network
     /// purposes. Have to be provided for validators.
     #[clap(long)]
@@ -21,6 +54,21 @@ pub struct AlephCli {
     #[clap(long, default_value_t = 30343)]
     validator_port: u16,
 
+    /// Try to map the validator port on the NAT gateway using the given protocol, and advertise
+    /// the resulting external address in addition to the public validator addresses.
+    /// Off by default.
+    #[clap(long, value_enum)]
+    nat_port_mapping: Option<NatProtocol>,
+
+    /// The gateway to send NAT-PMP requests to. Defaults to the default gateway of the machine.
+    #[clap(long, requires = "nat_port_mapping")]
+    nat_pmp_gateway: Option<IpAddr>,
+
+    /// The URL of the UPnP device description of the gateway. If not provided, the gateway is
+    /// discovered using SSDP.
+    #[clap(long, value_name = "URL", requires = "nat_port_mapping")]
+    upnp_description_url: Option<String>,
+
     /// Turn off backups, at the cost of limiting crash recoverability.
     ///
     /// If backups are turned off and the node crashes, it most likely will not be able to continue
@@ -38,7 +86,8 @@ pub struct AlephCli {
 
     /// The maximum number of nonfinalized blocks, after which block production should be locally
     /// stopped. DO NOT CHANGE THIS, PRODUCING MORE OR FEWER BLOCKS MIGHT BE CONSIDERED MALICIOUS
-    /// BEHAVIOUR AND PUNISHED ACCORDINGLY!
+    /// BEHAVIOUR AND PUNISHED ACCORDINGLY! Once the runtime sets block production backoff
+    /// parameters, they take precedence over this limit.
     #[clap(long, default_value_t = 20)]
     max_nonfinalized_blocks: u32,
 
@@ -56,6 +105,34 @@ pub struct AlephCli {
     /// By default collecting is enabled, as the impact on performance is negligible, if any.
     #[clap(long, default_value_t = false)]
     no_collection_of_extra_debugging_data: bool,
+
+    /// The path to a file in which the history of validator network addresses is kept, so that it
+    /// survives restarts. If not provided, the history is only kept in memory. Has no effect if
+    /// collecting extra debugging data is turned off.
+    #[clap(long, value_name = "PATH")]
+    validator_network_history_path: Option<PathBuf>,
+
+    /// The number of the most recent sessions for which authority data is kept in the database,
+    /// to be served over RPC and reused after restarts. If not provided, the history is never
+    /// pruned.
+    #[clap(long, value_parser = clap::value_parser!(u32).range(1..))]
+    session_history_depth: Option<u32>,
+
+    /// Also serve the validator health report, available over RPC, as JSON over plain HTTP at the
+    /// given address. Every request is answered with the report, regardless of its path. The report
+    /// reveals which keys this node holds, so the address should not be publicly reachable.
+    #[clap(long, value_name = "ADDRESS")]
+    health_report_address: Option<SocketAddr>,
+
+    /// Append consensus-significant events, such as sessions starting and stopping, finality
+    /// version switches, equivocations, imported justifications, emergency finalizations and
+    /// validator bans, as newline-delimited JSON to the file at the given path.
+    #[clap(long, value_name = "PATH")]
+    consensus_event_log: Option<PathBuf>,
+
+    /// Experimental: how to choose the branch proposed for finalization.
+    #[clap(long, value_enum, default_value_t = ProposalStrategy::LocalBest)]
+    proposal_strategy: ProposalStrategy,
 }
 
 impl AlephCli {
@@ -63,6 +140,10 @@ impl AlephCli {
         UnitCreationDelay(self.unit_creation_delay)
     }
 
+    pub fn remulticast_timeout(&self) -> RemulticastTimeout {
+        RemulticastTimeout(self.remulticast_timeout)
+    }
+
     pub fn external_addresses(&self) -> Vec<String> {
         self.public_validator_addresses.clone().unwrap_or_default()
     }
@@ -75,6 +156,14 @@ impl AlephCli {
         self.validator_port
     }
 
+    pub fn nat_config(&self) -> Option<NatConfig> {
+        self.nat_port_mapping.map(|protocol| NatConfig {
+            protocol,
+            nat_pmp_gateway: self.nat_pmp_gateway,
+            upnp_description_url: self.upnp_description_url.clone(),
+        })
+    }
+
     pub fn backup_path(&self) -> Option<PathBuf> {
         self.backup_path.clone()
     }
@@ -101,4 +190,24 @@ impl AlephCli {
     pub fn no_collection_of_extra_debugging_data(&self) -> bool {
         self.no_collection_of_extra_debugging_data
     }
+
+    pub fn validator_network_history_path(&self) -> Option<PathBuf> {
+        self.validator_network_history_path.clone()
+    }
+
+    pub fn session_history_depth(&self) -> Option<SessionHistoryDepth> {
+        self.session_history_depth.map(SessionHistoryDepth)
+    }
+
+    pub fn health_report_address(&self) -> Option<SocketAddr> {
+        self.health_report_address
+    }
+
+    pub fn consensus_event_log(&self) -> Option<PathBuf> {
+        self.consensus_event_log.clone()
+    }
+
+    pub fn proposal_strategy(&self) -> ProposalStrategyKind {
+        self.proposal_strategy.into()
+    }
 }
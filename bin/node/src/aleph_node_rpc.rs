@@ -1,28 +1,47 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, iter, sync::Arc};
 
 use finality_aleph::{
-    AlephJustification, BlockId, Justification, JustificationTranslator, ValidatorAddressCache,
-    ValidatorAddressingInfo,
+    backwards_compatible_decode, AlephJustification, BlockId, FinalityParticipation,
+    FinalityVersions, Justification, JustificationTranslator, SessionHistory, SessionId,
+    SessionParticipation, ValidatorAddressCache, ValidatorAddressRecord, ValidatorAddressingInfo,
 };
-use futures::channel::mpsc;
+use futures::{channel::mpsc, stream, FutureExt, StreamExt};
 use jsonrpsee::{
     core::{error::Error as JsonRpseeError, RpcResult},
     proc_macros::rpc,
-    types::error::{CallError, ErrorObject},
+    types::{
+        error::{CallError, ErrorObject},
+        SubscriptionResult,
+    },
+    SubscriptionSink,
 };
+use log::warn;
 use parity_scale_codec::Decode;
-use primitives::{AccountId, Block, BlockHash, BlockNumber, Signature};
-use sc_client_api::StorageProvider;
+use primitives::{
+    AccountId, AlephSessionApi, AuraId, AuthorityId, Block, BlockHash, BlockNumber, Header,
+    Signature, Version, VersionChange, ALEPH_ENGINE_ID, KEY_TYPE,
+};
+use sc_client_api::{AuxStore, BlockBackend, BlockchainEvents, StorageProvider};
+use sc_rpc::SubscriptionTaskExecutor;
+use sc_rpc_api::DenyUnsafe;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
 use sp_arithmetic::traits::Zero;
 use sp_blockchain::HeaderBackend;
 use sp_consensus::SyncOracle;
-use sp_consensus_aura::digests::CompatibleDigestItem;
+use sp_consensus_aura::{digests::CompatibleDigestItem, AuraApi};
 use sp_core::{twox_128, Bytes};
+use sp_keystore::{Keystore, KeystorePtr};
 use sp_runtime::{
     traits::{Block as BlockT, Header as HeaderT},
     DigestItem,
 };
 
+use crate::{
+    health::{HealthReport, LastHealthReport},
+    service::session_boundary_info,
+};
+
 /// System RPC errors.
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -61,6 +80,21 @@ pub enum Error {
     /// Network info caching is not enabled.
     #[error("Unable to get any data, because network info caching is not enabled.")]
     NetworkInfoCachingNotEnabled,
+    /// Failed to predict the committee of a session.
+    #[error("Failed to predict the committee of session {0}: {1}.")]
+    CommitteeNotAvailable(u32, String),
+    /// Failed to read the session history.
+    #[error("Failed to read authorities of session {0} from the session history: {1}.")]
+    FailedSessionHistoryRead(u32, String),
+    /// Failed to call the runtime API.
+    #[error("Failed to call runtime API {0}: {1}.")]
+    FailedRuntimeApiCall(&'static str, String),
+    /// Failed to find a block with provided number.
+    #[error("Failed to find a block with number {0}.")]
+    UnknownBlockNumber(BlockNumber),
+    /// Failed to gather the health report.
+    #[error("Failed to gather the health report: {0}.")]
+    HealthReportNotAvailable(String),
 }
 
 // Base code for all system errors.
@@ -85,6 +119,16 @@ const FAILED_HEADER_DECODING_ERROR: i32 = BASE_ERROR + 8;
 const UNKNOWN_HASH_ERROR: i32 = BASE_ERROR + 9;
 /// Network info caching is not enabled.
 const NETWORK_INFO_CACHING_NOT_ENABLED_ERROR: i32 = BASE_ERROR + 10;
+/// Failed to predict the committee of a session.
+const COMMITTEE_NOT_AVAILABLE_ERROR: i32 = BASE_ERROR + 11;
+/// Failed to read the session history.
+const FAILED_SESSION_HISTORY_READ_ERROR: i32 = BASE_ERROR + 12;
+/// Failed to call the runtime API.
+const FAILED_RUNTIME_API_CALL_ERROR: i32 = BASE_ERROR + 13;
+/// Failed to find a block with provided number.
+const UNKNOWN_BLOCK_NUMBER_ERROR: i32 = BASE_ERROR + 14;
+/// Failed to gather the health report.
+const HEALTH_REPORT_NOT_AVAILABLE_ERROR: i32 = BASE_ERROR + 15;
 
 impl From<Error> for JsonRpseeError {
     fn from(e: Error) -> Self {
@@ -145,11 +189,116 @@ impl From<Error> for JsonRpseeError {
                 "Unable to get any data, because network info caching is not enabled.",
                 None::<()>,
             )),
+            Error::CommitteeNotAvailable(session, err) => CallError::Custom(ErrorObject::owned(
+                COMMITTEE_NOT_AVAILABLE_ERROR,
+                format!("Failed to predict the committee of session {session}: {err}."),
+                None::<()>,
+            )),
+            Error::FailedSessionHistoryRead(session, err) => CallError::Custom(ErrorObject::owned(
+                FAILED_SESSION_HISTORY_READ_ERROR,
+                format!(
+                    "Failed to read authorities of session {session} from the session history: {err}."
+                ),
+                None::<()>,
+            )),
+            Error::FailedRuntimeApiCall(method, err) => CallError::Custom(ErrorObject::owned(
+                FAILED_RUNTIME_API_CALL_ERROR,
+                format!("Failed to call runtime API {method}: {err}."),
+                None::<()>,
+            )),
+            Error::UnknownBlockNumber(number) => CallError::Custom(ErrorObject::owned(
+                UNKNOWN_BLOCK_NUMBER_ERROR,
+                format!("Failed to find a block with number {number}."),
+                None::<()>,
+            )),
+            Error::HealthReportNotAvailable(err) => CallError::Custom(ErrorObject::owned(
+                HEALTH_REPORT_NOT_AVAILABLE_ERROR,
+                format!("Failed to gather the health report: {err}."),
+                None::<()>,
+            )),
         }
         .into()
     }
 }
 
+/// Whether the committee of the session in which the scheduled finality version change happens
+/// advertises support for the incoming version.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalityVersionCompatibility {
+    /// The version that is scheduled to be used.
+    pub version_incoming: Version,
+    /// The session from which the version is going to be used.
+    pub session: u32,
+    /// Members of the committee that advertised support for the incoming version.
+    pub supporting: Vec<AccountId>,
+    /// Members of the committee that did not, with the versions they advertised, if any.
+    pub not_supporting: HashMap<AccountId, Option<FinalityVersions>>,
+}
+
+/// The authorities of a session, used for verifying its justifications.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionAuthorities {
+    /// Members of the finality committee, in the order of their indices in justifications.
+    pub authorities: Vec<AuthorityId>,
+    /// The key allowed to sign emergency justifications, if any.
+    pub emergency_finalizer: Option<AuthorityId>,
+}
+
+/// The committee of a session and the role this node plays in it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionCommitteeInfo {
+    /// The session.
+    pub session: u32,
+    /// The first block of the session.
+    pub first_block: BlockNumber,
+    /// The last block of the session.
+    pub last_block: BlockNumber,
+    /// The finality version used in the session.
+    pub finality_version: Version,
+    /// Members of the finality committee.
+    pub finality_committee: Vec<AccountId>,
+    /// Accounts producing blocks in the session.
+    pub block_producers: Vec<AccountId>,
+    /// The owner of the Aleph key kept in the keystore of this node, if any.
+    pub own_account: Option<AccountId>,
+    /// The index of this node in the finality committee, if the node knows the authorities
+    /// of the session and is one of them.
+    pub own_index: Option<u32>,
+    /// Whether this node is a member of the finality committee.
+    pub is_finality_committee_member: bool,
+    /// Whether this node produces blocks in the session.
+    pub is_block_producer: bool,
+}
+
+/// The kind of a justification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum JustificationKind {
+    /// Signatures of the committee.
+    CommitteeMultisignature,
+    /// A signature of the emergency finalizer.
+    EmergencySignature,
+    /// An aggregated BLS signature of the committee.
+    CommitteeBlsMultisignature,
+    /// Signatures of a threshold of the emergency finalizer set.
+    EmergencyMultisignature,
+}
+
+/// A block finalized by Aleph together with its justification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JustificationNotification {
+    /// Header of the finalized block.
+    pub header: Header,
+    /// The session the block belongs to.
+    pub session: u32,
+    /// The kind of the justification.
+    pub kind: JustificationKind,
+    /// Indices of the signers, within the committee for committee justifications and within
+    /// the emergency finalizer set for emergency multisignatures.
+    pub signers: Vec<u32>,
+    /// The justification, encoded the same way as in the block.
+    pub encoded: Bytes,
+}
+
 /// Aleph Node RPC API
 #[rpc(client, server, namespace = "alephNode")]
 pub trait AlephNodeApi<BE> {
@@ -162,6 +311,16 @@ pub trait AlephNodeApi<BE> {
         number: BlockNumber,
     ) -> RpcResult<()>;
 
+    /// Finalize the block with given hash and number using signatures of the emergency finalizer
+    /// set, each paired with the index of its key in the set. Returns the empty string or an error.
+    #[method(name = "emergencyFinalizeMultisigned")]
+    fn emergency_finalize_multisigned(
+        &self,
+        signatures: Vec<(u32, Bytes)>,
+        hash: BlockHash,
+        number: BlockNumber,
+    ) -> RpcResult<()>;
+
     /// Get the author of the block with given hash.
     #[method(name = "getBlockAuthor")]
     fn block_author(&self, hash: BlockHash) -> RpcResult<Option<AccountId>>;
@@ -170,8 +329,65 @@ pub trait AlephNodeApi<BE> {
     #[method(name = "ready")]
     fn ready(&self) -> RpcResult<bool>;
 
+    /// Get the network details of validators, either the most recent ones or the ones seen in the
+    /// given session, optionally only for the given validator.
     #[method(name = "unstable_validatorNetworkInfo")]
-    fn validator_network_info(&self) -> RpcResult<HashMap<AccountId, ValidatorAddressingInfo>>;
+    fn validator_network_info(
+        &self,
+        session: Option<u32>,
+        account: Option<AccountId>,
+    ) -> RpcResult<HashMap<AccountId, ValidatorAddressingInfo>>;
+
+    /// Get the history of network addresses used by validators, optionally only for the given validator.
+    #[method(name = "unstable_validatorNetworkHistory")]
+    fn validator_network_history(
+        &self,
+        account: Option<AccountId>,
+    ) -> RpcResult<HashMap<AccountId, Vec<ValidatorAddressRecord>>>;
+
+    /// Check whether all the members of the committee of the session in which the scheduled
+    /// finality version change happens advertise support for the incoming version. Returns
+    /// nothing if no change is scheduled.
+    #[method(name = "unstable_finalityVersionCompatibility")]
+    fn finality_version_compatibility(&self) -> RpcResult<Option<FinalityVersionCompatibility>>;
+
+    /// Get the authorities of the given session from the session history kept by the node.
+    /// Returns nothing if the session is not in the history, e.g. because it was pruned or
+    /// has not been seen by the node yet.
+    #[method(name = "unstable_sessionAuthorities")]
+    fn session_authorities(&self, session: u32) -> RpcResult<Option<SessionAuthorities>>;
+
+    /// Get the committee of the given session, by default the current one, together with the
+    /// boundaries and finality version of the session and the role of this node in it. Unsafe,
+    /// since the role reveals which keys this node holds.
+    #[method(name = "unstable_sessionCommittee")]
+    fn session_committee(&self, session: Option<u32>) -> RpcResult<SessionCommitteeInfo>;
+
+    /// Get a summary of the health of this validator: its keys, connectivity, block production,
+    /// finalization lag and whether backups can be written. The report is gathered every few
+    /// seconds, this returns the last one. Unsafe, since it reveals which keys this node holds.
+    #[method(name = "unstable_healthReport")]
+    fn health_report(&self) -> RpcResult<HealthReport>;
+
+    /// Get the fraction of committee justifications of finalized blocks signed by every member
+    /// of the committee of the given session, by default the most recent one. Returns nothing
+    /// if the node has not seen any committee justifications of the session since it started.
+    /// Justifications only hold the signatures needed to reach the threshold, usually those of
+    /// the fastest members, so a member missing from them is not necessarily offline.
+    #[method(name = "unstable_finalityParticipation")]
+    fn finality_participation(
+        &self,
+        session: Option<u32>,
+    ) -> RpcResult<Option<SessionParticipation>>;
+
+    /// Subscribe to blocks finalized with Aleph justifications. Blocks finalized implicitly,
+    /// without justifications of their own, are skipped.
+    #[subscription(
+        name = "subscribeJustifications" => "justifications",
+        unsubscribe = "unsubscribeJustifications",
+        item = JustificationNotification
+    )]
+    fn subscribe_justifications(&self);
 }
 
 /// Aleph Node API implementation
@@ -181,18 +397,29 @@ pub struct AlephNode<Client, SO> {
     client: Arc<Client>,
     sync_oracle: SO,
     validator_address_cache: Option<ValidatorAddressCache>,
+    keystore: KeystorePtr,
+    deny_unsafe: DenyUnsafe,
+    last_health_report: LastHealthReport,
+    finality_participation: FinalityParticipation,
+    executor: SubscriptionTaskExecutor,
 }
 
 impl<Client, SO> AlephNode<Client, SO>
 where
     SO: SyncOracle,
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         import_justification_tx: mpsc::UnboundedSender<Justification>,
         justification_translator: JustificationTranslator,
         client: Arc<Client>,
         sync_oracle: SO,
         validator_address_cache: Option<ValidatorAddressCache>,
+        keystore: KeystorePtr,
+        deny_unsafe: DenyUnsafe,
+        last_health_report: LastHealthReport,
+        finality_participation: FinalityParticipation,
+        executor: SubscriptionTaskExecutor,
     ) -> Self {
         AlephNode {
             import_justification_tx,
@@ -200,28 +427,22 @@ where
             client,
             sync_oracle,
             validator_address_cache,
+            keystore,
+            deny_unsafe,
+            last_health_report,
+            finality_participation,
+            executor,
         }
     }
 }
 
-impl<Client, BE, SO> AlephNodeApiServer<BE> for AlephNode<Client, SO>
-where
-    BE: sc_client_api::Backend<Block> + 'static,
-    Client: HeaderBackend<Block> + StorageProvider<Block, BE> + 'static,
-    SO: SyncOracle + Send + Sync + 'static,
-{
-    fn emergency_finalize(
+impl<Client, SO> AlephNode<Client, SO> {
+    fn import_justification(
         &self,
-        justification: Bytes,
+        justification: AlephJustification,
         hash: BlockHash,
         number: BlockNumber,
     ) -> RpcResult<()> {
-        let justification: AlephJustification =
-            AlephJustification::EmergencySignature(justification.0.try_into().map_err(|_| {
-                Error::MalformedJustificationArg(
-                    "Provided justification cannot be converted into correct type".into(),
-                )
-            })?);
         let justification = self
             .justification_translator
             .translate(justification, BlockId::new(hash, number))
@@ -236,6 +457,64 @@ where
             })?;
         Ok(())
     }
+}
+
+impl<Client, BE, SO> AlephNodeApiServer<BE> for AlephNode<Client, SO>
+where
+    BE: sc_client_api::Backend<Block> + 'static,
+    Client: HeaderBackend<Block>
+        + StorageProvider<Block, BE>
+        + ProvideRuntimeApi<Block>
+        + AuxStore
+        + BlockBackend<Block>
+        + BlockchainEvents<Block>
+        + Send
+        + Sync
+        + 'static,
+    Client::Api: AlephSessionApi<Block> + AuraApi<Block, AuraId>,
+    SO: SyncOracle + Send + Sync + 'static,
+{
+    fn emergency_finalize(
+        &self,
+        justification: Bytes,
+        hash: BlockHash,
+        number: BlockNumber,
+    ) -> RpcResult<()> {
+        let justification: AlephJustification =
+            AlephJustification::EmergencySignature(justification.0.try_into().map_err(|_| {
+                Error::MalformedJustificationArg(
+                    "Provided justification cannot be converted into correct type".into(),
+                )
+            })?);
+        self.import_justification(justification, hash, number)
+    }
+
+    fn emergency_finalize_multisigned(
+        &self,
+        signatures: Vec<(u32, Bytes)>,
+        hash: BlockHash,
+        number: BlockNumber,
+    ) -> RpcResult<()> {
+        let signatures = signatures
+            .into_iter()
+            .map(|(index, signature)| {
+                signature
+                    .0
+                    .try_into()
+                    .map(|signature| (index, signature))
+                    .map_err(|_| {
+                        Error::MalformedJustificationArg(format!(
+                            "Provided signature of key {index} cannot be converted into correct type"
+                        ))
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        self.import_justification(
+            AlephJustification::EmergencyMultisignature(signatures),
+            hash,
+            number,
+        )
+    }
 
     fn block_author(&self, hash: BlockHash) -> RpcResult<Option<AccountId>> {
         let header = self
@@ -268,11 +547,280 @@ where
         Ok(!self.sync_oracle.is_offline() && !self.sync_oracle.is_major_syncing())
     }
 
-    fn validator_network_info(&self) -> RpcResult<HashMap<AccountId, ValidatorAddressingInfo>> {
-        self.validator_address_cache
+    fn validator_network_info(
+        &self,
+        session: Option<u32>,
+        account: Option<AccountId>,
+    ) -> RpcResult<HashMap<AccountId, ValidatorAddressingInfo>> {
+        let cache = self
+            .validator_address_cache
+            .as_ref()
+            .ok_or(Error::NetworkInfoCachingNotEnabled)?;
+        let snapshot = match session {
+            Some(session) => cache.snapshot_at(SessionId(session)),
+            None => cache.snapshot(),
+        };
+        Ok(filter_by_account(snapshot, account))
+    }
+
+    fn validator_network_history(
+        &self,
+        account: Option<AccountId>,
+    ) -> RpcResult<HashMap<AccountId, Vec<ValidatorAddressRecord>>> {
+        let cache = self
+            .validator_address_cache
+            .as_ref()
+            .ok_or(Error::NetworkInfoCachingNotEnabled)?;
+        Ok(filter_by_account(cache.history(), account))
+    }
+
+    fn finality_version_compatibility(&self) -> RpcResult<Option<FinalityVersionCompatibility>> {
+        let cache = self
+            .validator_address_cache
+            .as_ref()
+            .ok_or(Error::NetworkInfoCachingNotEnabled)?;
+        let best_hash = self.client.info().best_hash;
+        let VersionChange {
+            version_incoming,
+            session,
+        } = match read_storage_maybe(
+            "Aleph",
+            "FinalityScheduledVersionChange",
+            &self.client,
+            best_hash,
+        )? {
+            Some(version_change) => version_change,
+            None => return Ok(None),
+        };
+        let committee = self
+            .client
+            .runtime_api()
+            .predict_session_committee(best_hash, session)
+            .map_err(|e| Error::CommitteeNotAvailable(session, format!("{e}")))?
+            .map_err(|e| Error::CommitteeNotAvailable(session, format!("{e:?}")))?;
+
+        let snapshot = cache.snapshot();
+        let mut supporting = Vec::new();
+        let mut not_supporting = HashMap::new();
+        for account in committee.finality_committee {
+            let finality_versions = snapshot
+                .get(&account)
+                .and_then(|info| info.finality_versions);
+            match finality_versions {
+                Some(versions) if versions.contains(version_incoming) => supporting.push(account),
+                _ => {
+                    not_supporting.insert(account, finality_versions);
+                }
+            }
+        }
+        Ok(Some(FinalityVersionCompatibility {
+            version_incoming,
+            session,
+            supporting,
+            not_supporting,
+        }))
+    }
+
+    fn session_authorities(&self, session: u32) -> RpcResult<Option<SessionAuthorities>> {
+        let authority_data = SessionHistory::new(self.client.clone())
+            .get(SessionId(session))
+            .map_err(|e| Error::FailedSessionHistoryRead(session, format!("{e}")))?;
+        Ok(authority_data.map(|authority_data| SessionAuthorities {
+            authorities: authority_data.authorities().clone(),
+            emergency_finalizer: authority_data.emergency_finalizer().clone(),
+        }))
+    }
+
+    fn session_committee(&self, session: Option<u32>) -> RpcResult<SessionCommitteeInfo> {
+        self.deny_unsafe.check_if_safe()?;
+        let info = self.client.info();
+        let runtime_api = self.client.runtime_api();
+        let session_info = session_boundary_info(&*self.client, info.best_hash)
+            .map_err(|e| Error::FailedRuntimeApiCall("session_timings", format!("{e}")))?;
+        let session = session
+            .map(SessionId)
+            .unwrap_or_else(|| session_info.session_id_from_block_num(info.best_number));
+        let boundaries = session_info.boundaries_for_session(session);
+
+        // The state at the first block of a session describes that session, sessions that did
+        // not start yet can only be predicted from the best block.
+        let (finality_version, at) = if boundaries.first_block() <= info.best_number {
+            let first_block_hash = self
+                .client
+                .hash(boundaries.first_block())
+                .map_err(|e| {
+                    Error::FailedHeaderDecoding(format!("#{}", boundaries.first_block()), e)
+                })?
+                .ok_or(Error::UnknownBlockNumber(boundaries.first_block()))?;
+            let finality_version = runtime_api
+                .finality_version(first_block_hash)
+                .map_err(|e| Error::FailedRuntimeApiCall("finality_version", format!("{e}")))?;
+            (finality_version, first_block_hash)
+        } else {
+            let current_version = runtime_api
+                .finality_version(info.best_hash)
+                .map_err(|e| Error::FailedRuntimeApiCall("finality_version", format!("{e}")))?;
+            let finality_version = match read_storage_maybe::<VersionChange, _, _, _>(
+                "Aleph",
+                "FinalityScheduledVersionChange",
+                &self.client,
+                info.best_hash,
+            )? {
+                Some(change) if change.session <= session.0 => change.version_incoming,
+                _ => current_version,
+            };
+            (finality_version, info.best_hash)
+        };
+        let committee = runtime_api
+            .predict_session_committee(at, session.0)
+            .map_err(|e| Error::CommitteeNotAvailable(session.0, format!("{e}")))?
+            .map_err(|e| Error::CommitteeNotAvailable(session.0, format!("{e:?}")))?;
+
+        let own_keys: Vec<AuthorityId> = self
+            .keystore
+            .ed25519_public_keys(KEY_TYPE)
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        let mut own_account = None;
+        for key in &own_keys {
+            own_account = runtime_api
+                .key_owner(info.best_hash, key.clone())
+                .map_err(|e| Error::FailedRuntimeApiCall("key_owner", format!("{e}")))?;
+            if own_account.is_some() {
+                break;
+            }
+        }
+        let own_index = SessionHistory::new(self.client.clone())
+            .get(session)
+            .map_err(|e| Error::FailedSessionHistoryRead(session.0, format!("{e}")))?
+            .and_then(|authority_data| {
+                authority_data
+                    .authorities()
+                    .iter()
+                    .position(|authority| own_keys.contains(authority))
+            })
+            .map(|index| index as u32);
+        let is_finality_committee_member = own_account.as_ref().map_or(false, |account| {
+            committee.finality_committee.contains(account)
+        });
+        let is_block_producer = own_account
             .as_ref()
-            .map(|c| c.snapshot())
-            .ok_or(Error::NetworkInfoCachingNotEnabled.into())
+            .map_or(false, |account| committee.block_producers.contains(account));
+
+        Ok(SessionCommitteeInfo {
+            session: session.0,
+            first_block: boundaries.first_block(),
+            last_block: boundaries.last_block(),
+            finality_version,
+            finality_committee: committee.finality_committee,
+            block_producers: committee.block_producers,
+            own_account,
+            own_index,
+            is_finality_committee_member,
+            is_block_producer,
+        })
+    }
+
+    fn health_report(&self) -> RpcResult<HealthReport> {
+        self.deny_unsafe.check_if_safe()?;
+        self.last_health_report
+            .get()
+            .map_err(|e| Error::HealthReportNotAvailable(e).into())
+    }
+
+    fn finality_participation(
+        &self,
+        session: Option<u32>,
+    ) -> RpcResult<Option<SessionParticipation>> {
+        Ok(match session {
+            Some(session) => self.finality_participation.session(SessionId(session)),
+            None => self.finality_participation.latest(),
+        })
+    }
+
+    fn subscribe_justifications(&self, mut sink: SubscriptionSink) -> SubscriptionResult {
+        let client = self.client.clone();
+        let stream = self
+            .client
+            .finality_notification_stream()
+            .flat_map(move |notification| {
+                let notifications: Vec<_> = notification
+                    .tree_route
+                    .iter()
+                    .cloned()
+                    .chain(iter::once(notification.hash))
+                    .filter_map(|hash| justification_notification(&client, hash))
+                    .collect();
+                stream::iter(notifications)
+            });
+
+        let fut = async move {
+            sink.pipe_from_stream(stream).await;
+        };
+
+        self.executor
+            .spawn("aleph-justification-subscription", Some("rpc"), fut.boxed());
+        Ok(())
+    }
+}
+
+fn justification_notification<Client>(
+    client: &Arc<Client>,
+    hash: BlockHash,
+) -> Option<JustificationNotification>
+where
+    Client: HeaderBackend<Block> + BlockBackend<Block> + ProvideRuntimeApi<Block>,
+    Client::Api: AlephSessionApi<Block>,
+{
+    let encoded = match client.justifications(hash) {
+        Ok(justifications) => justifications?.into_justification(ALEPH_ENGINE_ID)?,
+        Err(e) => {
+            warn!(target: "aleph-rpc", "Failed to read justifications of block {}: {}", hash, e);
+            return None;
+        }
+    };
+    let justification = match backwards_compatible_decode(encoded.clone()) {
+        Ok(justification) => justification,
+        Err(e) => {
+            warn!(target: "aleph-rpc", "Failed to decode justification of block {}: {:?}", hash, e);
+            return None;
+        }
+    };
+    let header = client.header(hash).ok()??;
+    let session_info = session_boundary_info(&**client, hash).ok()?;
+    let kind = match justification {
+        AlephJustification::CommitteeMultisignature(_) => {
+            JustificationKind::CommitteeMultisignature
+        }
+        AlephJustification::EmergencySignature(_) => JustificationKind::EmergencySignature,
+        AlephJustification::CommitteeBlsMultisignature(_) => {
+            JustificationKind::CommitteeBlsMultisignature
+        }
+        AlephJustification::EmergencyMultisignature(_) => {
+            JustificationKind::EmergencyMultisignature
+        }
+    };
+    Some(JustificationNotification {
+        session: session_info.session_id_from_block_num(header.number).0,
+        header,
+        kind,
+        signers: justification
+            .signers()
+            .into_iter()
+            .map(|index| index.0 as u32)
+            .collect(),
+        encoded: encoded.into(),
+    })
+}
+
+fn filter_by_account<T>(
+    data: HashMap<AccountId, T>,
+    account: Option<AccountId>,
+) -> HashMap<AccountId, T> {
+    match account {
+        Some(account) => data.into_iter().filter(|(k, _)| *k == account).collect(),
+        None => data,
     }
 }
 
@@ -287,6 +835,22 @@ fn read_storage<
     storage_provider: &Arc<SP>,
     block_hash: Block::Hash,
 ) -> RpcResult<T> {
+    read_storage_maybe(pallet, pallet_item, storage_provider, block_hash)?.ok_or_else(|| {
+        Error::StorageItemNotAvailable(pallet, pallet_item, block_hash.to_string()).into()
+    })
+}
+
+fn read_storage_maybe<
+    T: Decode,
+    Block: BlockT,
+    Backend: sc_client_api::Backend<Block>,
+    SP: StorageProvider<Block, Backend>,
+>(
+    pallet: &'static str,
+    pallet_item: &'static str,
+    storage_provider: &Arc<SP>,
+    block_hash: Block::Hash,
+) -> RpcResult<Option<T>> {
     let storage_key = [
         twox_128(pallet.as_bytes()),
         twox_128(pallet_item.as_bytes()),
@@ -297,11 +861,7 @@ fn read_storage<
         .storage(block_hash, &sc_client_api::StorageKey(storage_key))
     {
         Ok(Some(bytes)) => bytes,
-        Ok(None) => {
-            return Err(
-                Error::StorageItemNotAvailable(pallet, pallet_item, block_hash.to_string()).into(),
-            )
-        }
+        Ok(None) => return Ok(None),
         Err(e) => {
             return Err(
                 Error::FailedStorageRead(pallet, pallet_item, block_hash.to_string(), e).into(),
@@ -309,7 +869,9 @@ fn read_storage<
         }
     };
 
-    T::decode(&mut item_encoded.0.as_ref()).map_err(|e| {
-        Error::FailedStorageDecoding(pallet, pallet_item, block_hash.to_string(), e).into()
-    })
+    T::decode(&mut item_encoded.0.as_ref())
+        .map(Some)
+        .map_err(|e| {
+            Error::FailedStorageDecoding(pallet, pallet_item, block_hash.to_string(), e).into()
+        })
 }
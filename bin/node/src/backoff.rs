@@ -0,0 +1,327 @@
+//! Strategies deciding whether block production should back off because finalization falls
+//! behind.
+
+use std::sync::{Arc, Mutex};
+
+use log::{debug, info, warn};
+use primitives::{AlephSessionApi, AuthoringBackoffConfig, Block};
+use sc_consensus_slots::BackoffAuthoringBlocksStrategy;
+use sp_api::ProvideRuntimeApi;
+use sp_arithmetic::traits::BaseArithmetic;
+use sp_blockchain::HeaderBackend;
+use sp_consensus_aura::Slot;
+
+/// Stops block production once the given number of blocks is not finalized.
+pub struct LimitNonfinalized(pub u32);
+
+impl<N: BaseArithmetic> BackoffAuthoringBlocksStrategy<N> for LimitNonfinalized {
+    fn should_backoff(
+        &self,
+        chain_head_number: N,
+        _chain_head_slot: Slot,
+        finalized_number: N,
+        _slow_now: Slot,
+        _logging_target: &str,
+    ) -> bool {
+        let nonfinalized_blocks: u32 = chain_head_number
+            .saturating_sub(finalized_number)
+            .unique_saturated_into();
+        match nonfinalized_blocks >= self.0 {
+            true => {
+                warn!("We have {} nonfinalized blocks, with the limit being {}, delaying block production.", nonfinalized_blocks, self.0);
+                true
+            }
+            false => false,
+        }
+    }
+}
+
+/// Source of the parameters of the adaptive backoff.
+pub trait AuthoringBackoffConfigProvider {
+    /// The current parameters, if the runtime sets them.
+    fn authoring_backoff_config(&self) -> Option<AuthoringBackoffConfig>;
+}
+
+impl<C> AuthoringBackoffConfigProvider for Arc<C>
+where
+    C: HeaderBackend<Block> + ProvideRuntimeApi<Block>,
+    C::Api: AlephSessionApi<Block>,
+{
+    fn authoring_backoff_config(&self) -> Option<AuthoringBackoffConfig> {
+        let best_hash = self.info().best_hash;
+        match self.runtime_api().authoring_backoff_config(best_hash) {
+            Ok(config) => config,
+            Err(e) => {
+                debug!(
+                    "Failed to read the authoring backoff config at {}: {}.",
+                    best_hash, e
+                );
+                None
+            }
+        }
+    }
+}
+
+/// Slows block production down gradually as finalization falls behind, instead of stopping it
+/// at once. From `slowdown_start` nonfinalized blocks on, no block is produced in a number of
+/// slots following the slot of the best block, one more for every `blocks_per_skipped_slot`
+/// further nonfinalized blocks. At `max_nonfinalized_blocks` block production stops.
+pub struct AdaptiveBackoff(pub AuthoringBackoffConfig);
+
+/// The number of slots after the slot of the best block in which no block should be produced,
+/// `None` if block production should stop.
+fn skipped_slots(config: &AuthoringBackoffConfig, nonfinalized_blocks: u32) -> Option<u64> {
+    if nonfinalized_blocks >= config.max_nonfinalized_blocks {
+        return None;
+    }
+    match nonfinalized_blocks.checked_sub(config.slowdown_start) {
+        Some(excess) => Some((excess / config.blocks_per_skipped_slot.max(1)) as u64 + 1),
+        None => Some(0),
+    }
+}
+
+impl<N: BaseArithmetic> BackoffAuthoringBlocksStrategy<N> for AdaptiveBackoff {
+    fn should_backoff(
+        &self,
+        chain_head_number: N,
+        chain_head_slot: Slot,
+        finalized_number: N,
+        slot_now: Slot,
+        logging_target: &str,
+    ) -> bool {
+        let nonfinalized_blocks: u32 = chain_head_number
+            .saturating_sub(finalized_number)
+            .unique_saturated_into();
+        match skipped_slots(&self.0, nonfinalized_blocks) {
+            None => {
+                warn!("We have {} nonfinalized blocks, with the limit being {}, delaying block production.", nonfinalized_blocks, self.0.max_nonfinalized_blocks);
+                true
+            }
+            Some(0) => false,
+            Some(skipped_slots) => {
+                let backoff =
+                    u64::from(slot_now) <= u64::from(chain_head_slot).saturating_add(skipped_slots);
+                if backoff {
+                    debug!(
+                        target: logging_target,
+                        "We have {} nonfinalized blocks, skipping {} slot(s) after the best block.",
+                        nonfinalized_blocks,
+                        skipped_slots
+                    );
+                }
+                backoff
+            }
+        }
+    }
+}
+
+/// Backs block production off adaptively, following the parameters set in the runtime, so that
+/// all the producers slow down alike. While the runtime sets none, block production stops at the
+/// local limit of nonfinalized blocks instead.
+pub struct AuthoringBackoff<P: AuthoringBackoffConfigProvider> {
+    config_provider: P,
+    limit: LimitNonfinalized,
+    last_config: Mutex<Option<AuthoringBackoffConfig>>,
+}
+
+impl<P: AuthoringBackoffConfigProvider> AuthoringBackoff<P> {
+    pub fn new(config_provider: P, max_nonfinalized_blocks: u32) -> Self {
+        AuthoringBackoff {
+            config_provider,
+            limit: LimitNonfinalized(max_nonfinalized_blocks),
+            last_config: Mutex::new(None),
+        }
+    }
+
+    /// Reads the parameters from the runtime, logging whenever they change, so that it is clear
+    /// which limit of nonfinalized blocks is in force.
+    fn config(&self) -> Option<AuthoringBackoffConfig> {
+        let config = self.config_provider.authoring_backoff_config();
+        let mut last_config = self
+            .last_config
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if *last_config != config {
+            match config {
+                Some(config) => info!(
+                    "Following the block production backoff set in the runtime: {:?}. The local limit of {} nonfinalized blocks no longer applies.",
+                    config, self.limit.0
+                ),
+                None => info!(
+                    "The runtime sets no block production backoff, stopping block production at {} nonfinalized blocks.",
+                    self.limit.0
+                ),
+            }
+            *last_config = config;
+        }
+        config
+    }
+}
+
+impl<N: BaseArithmetic, P: AuthoringBackoffConfigProvider> BackoffAuthoringBlocksStrategy<N>
+    for AuthoringBackoff<P>
+{
+    fn should_backoff(
+        &self,
+        chain_head_number: N,
+        chain_head_slot: Slot,
+        finalized_number: N,
+        slot_now: Slot,
+        logging_target: &str,
+    ) -> bool {
+        match self.config() {
+            Some(config) => AdaptiveBackoff(config).should_backoff(
+                chain_head_number,
+                chain_head_slot,
+                finalized_number,
+                slot_now,
+                logging_target,
+            ),
+            None => self.limit.should_backoff(
+                chain_head_number,
+                chain_head_slot,
+                finalized_number,
+                slot_now,
+                logging_target,
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use primitives::AuthoringBackoffConfig;
+    use sc_consensus_slots::BackoffAuthoringBlocksStrategy;
+    use sp_consensus_aura::Slot;
+
+    use super::{
+        AdaptiveBackoff, AuthoringBackoff, AuthoringBackoffConfigProvider, LimitNonfinalized,
+    };
+
+    const CONFIG: AuthoringBackoffConfig = AuthoringBackoffConfig {
+        slowdown_start: 10,
+        blocks_per_skipped_slot: 2,
+        max_nonfinalized_blocks: 20,
+    };
+
+    impl AuthoringBackoffConfigProvider for Option<AuthoringBackoffConfig> {
+        fn authoring_backoff_config(&self) -> Option<AuthoringBackoffConfig> {
+            *self
+        }
+    }
+
+    fn should_backoff<S: BackoffAuthoringBlocksStrategy<u32>>(
+        strategy: &S,
+        head: u32,
+        head_slot: u64,
+        finalized: u32,
+        slot_now: u64,
+    ) -> bool {
+        strategy.should_backoff(
+            head,
+            Slot::from(head_slot),
+            finalized,
+            Slot::from(slot_now),
+            "test",
+        )
+    }
+
+    /// Runs block production for `slots` slots starting after the slot of the best block,
+    /// finalizing blocks according to `finalized_at`. Returns the slots in which blocks were
+    /// produced.
+    fn produce<S: BackoffAuthoringBlocksStrategy<u32>>(
+        strategy: &S,
+        slots: u64,
+        finalized_at: impl Fn(u64, u32) -> u32,
+    ) -> Vec<u64> {
+        let (mut head, mut head_slot, mut finalized) = (0, 0, 0);
+        let mut produced = Vec::new();
+        for slot in 1..=slots {
+            finalized = finalized_at(slot, head).clamp(finalized, head);
+            if !should_backoff(strategy, head, head_slot, finalized, slot) {
+                head += 1;
+                head_slot = slot;
+                produced.push(slot);
+            }
+        }
+        produced
+    }
+
+    #[test]
+    fn does_not_back_off_before_slowdown_start() {
+        let strategy = AdaptiveBackoff(CONFIG);
+        for nonfinalized in 0..CONFIG.slowdown_start {
+            assert!(!should_backoff(&strategy, 100 + nonfinalized, 7, 100, 8));
+        }
+    }
+
+    #[test]
+    fn skips_more_slots_as_finalization_falls_behind() {
+        let strategy = AdaptiveBackoff(CONFIG);
+        for (nonfinalized, skipped) in [(10, 1), (11, 1), (12, 2), (15, 3), (19, 5)] {
+            for slot in 8..=7 + skipped {
+                assert!(should_backoff(&strategy, 100 + nonfinalized, 7, 100, slot));
+            }
+            assert!(!should_backoff(
+                &strategy,
+                100 + nonfinalized,
+                7,
+                100,
+                8 + skipped
+            ));
+        }
+    }
+
+    #[test]
+    fn stops_at_max_nonfinalized_blocks() {
+        let strategy = AdaptiveBackoff(CONFIG);
+        assert!(should_backoff(&strategy, 120, 7, 100, 1000));
+        assert!(should_backoff(&strategy, 130, 7, 100, 1000));
+    }
+
+    #[test]
+    fn stops_at_local_limit_without_runtime_config() {
+        let strategy = AuthoringBackoff::new(None::<AuthoringBackoffConfig>, 15);
+        assert!(!should_backoff(&strategy, 114, 7, 100, 8));
+        assert!(should_backoff(&strategy, 115, 7, 100, 1000));
+    }
+
+    #[test]
+    fn follows_runtime_config_when_set() {
+        let strategy = AuthoringBackoff::new(Some(CONFIG), 15);
+        assert!(!should_backoff(&strategy, 109, 7, 100, 8));
+        assert!(should_backoff(&strategy, 110, 7, 100, 8));
+        assert!(!should_backoff(&strategy, 115, 7, 100, 1000));
+        assert!(should_backoff(&strategy, 120, 7, 100, 1000));
+    }
+
+    #[test]
+    fn slows_down_gradually_when_finalization_stalls() {
+        let produced = produce(&AdaptiveBackoff(CONFIG), 200, |_, _| 0);
+        assert_eq!(produced.len(), CONFIG.max_nonfinalized_blocks as usize);
+        let gaps: Vec<_> = produced.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(gaps.windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(gaps[..CONFIG.slowdown_start as usize - 1], [1; 9]);
+        assert!(*gaps.last().unwrap() > 1);
+
+        let limited = produce(
+            &LimitNonfinalized(CONFIG.max_nonfinalized_blocks),
+            200,
+            |_, _| 0,
+        );
+        assert_eq!(limited, (1..=20).collect::<Vec<_>>());
+        assert!(produced.last() > limited.last());
+    }
+
+    #[test]
+    fn recovers_when_finalization_resumes() {
+        // Finalization stalls for 50 slots and then keeps up with a lag of 2 blocks.
+        let finalized_at = |slot, head: u32| match slot {
+            0..=50 => 0,
+            _ => head.saturating_sub(2),
+        };
+        let produced = produce(&AdaptiveBackoff(CONFIG), 100, finalized_at);
+        let after_stall: Vec<_> = produced.iter().filter(|slot| **slot > 50).collect();
+        assert_eq!(after_stall.len(), 50);
+    }
+}
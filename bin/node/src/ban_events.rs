@@ -0,0 +1,179 @@
+//! Reporting of the ban-related events of finalized blocks to the consensus event log.
+use std::{iter, sync::Arc};
+
+use aleph_runtime::RuntimeEvent;
+use finality_aleph::{BannedValidator, ConsensusEvent, EventLog};
+use frame_system::EventRecord;
+use futures::StreamExt;
+use log::warn;
+use pallet_committee_management::Event as CommitteeManagementEvent;
+use parity_scale_codec::Decode;
+use primitives::{BanReason, Block, BlockHash, BlockNumber};
+use sc_client_api::{Backend, BlockchainEvents, StorageKey, StorageProvider};
+use sp_blockchain::HeaderBackend;
+use sp_core::twox_128;
+
+const LOG_TARGET: &str = "aleph-event-log";
+
+fn reason(reason: &BanReason) -> String {
+    match reason {
+        BanReason::InsufficientUptime(sessions) => {
+            format!("insufficient uptime in {sessions} sessions")
+        }
+        BanReason::OtherReason(reason) => String::from_utf8_lossy(reason).into_owned(),
+    }
+}
+
+fn ban_events(
+    records: Vec<EventRecord<RuntimeEvent, BlockHash>>,
+    hash: BlockHash,
+    number: BlockNumber,
+) -> impl Iterator<Item = ConsensusEvent> {
+    records
+        .into_iter()
+        .filter_map(move |record| match record.event {
+            RuntimeEvent::CommitteeManagement(CommitteeManagementEvent::SetBanConfig(config)) => {
+                Some(ConsensusEvent::BanConfigChanged {
+                    hash,
+                    number,
+                    config,
+                })
+            }
+            RuntimeEvent::CommitteeManagement(CommitteeManagementEvent::BanValidators(bans)) => {
+                Some(ConsensusEvent::ValidatorsBanned {
+                    hash,
+                    number,
+                    validators: bans
+                        .into_iter()
+                        .map(|(account, info)| BannedValidator {
+                            account,
+                            reason: reason(&info.reason),
+                            start_era: info.start,
+                        })
+                        .collect(),
+                })
+            }
+            _ => None,
+        })
+}
+
+fn read_events<C, BE>(
+    client: &C,
+    hash: BlockHash,
+) -> Option<Vec<EventRecord<RuntimeEvent, BlockHash>>>
+where
+    C: StorageProvider<Block, BE>,
+    BE: Backend<Block>,
+{
+    let key = StorageKey([twox_128(b"System"), twox_128(b"Events")].concat());
+    let encoded = match client.storage(hash, &key) {
+        Ok(encoded) => encoded?,
+        Err(e) => {
+            warn!(
+                target: LOG_TARGET,
+                "Failed to read events of block {}: {}", hash, e
+            );
+            return None;
+        }
+    };
+    match Decode::decode(&mut encoded.0.as_slice()) {
+        Ok(events) => Some(events),
+        Err(e) => {
+            warn!(
+                target: LOG_TARGET,
+                "Failed to decode events of block {}: {}", hash, e
+            );
+            None
+        }
+    }
+}
+
+/// Writes the ban-related events of the committee management pallet emitted in every finalized
+/// block to the event log.
+pub async fn report_ban_events<C, BE>(client: Arc<C>, event_log: EventLog)
+where
+    C: BlockchainEvents<Block> + HeaderBackend<Block> + StorageProvider<Block, BE>,
+    BE: Backend<Block>,
+{
+    let mut finality_notifications = client.finality_notification_stream();
+    while let Some(notification) = finality_notifications.next().await {
+        for hash in notification
+            .tree_route
+            .iter()
+            .cloned()
+            .chain(iter::once(notification.hash))
+        {
+            let number = match client.number(hash) {
+                Ok(Some(number)) => number,
+                _ => {
+                    warn!(
+                        target: LOG_TARGET,
+                        "Failed to read the number of finalized block {}.", hash
+                    );
+                    continue;
+                }
+            };
+            if let Some(records) = read_events(client.as_ref(), hash) {
+                for event in ban_events(records, hash, number) {
+                    event_log.emit(event);
+                }
+            }
+        }
+    }
+    warn!(
+        target: LOG_TARGET,
+        "Finality notification stream ended unexpectedly."
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use aleph_runtime::RuntimeEvent;
+    use finality_aleph::{BannedValidator, ConsensusEvent};
+    use frame_system::{EventRecord, Phase};
+    use pallet_committee_management::Event as CommitteeManagementEvent;
+    use primitives::{AccountId, BanInfo, BanReason, BlockHash};
+
+    use super::ban_events;
+
+    fn record(event: RuntimeEvent) -> EventRecord<RuntimeEvent, BlockHash> {
+        EventRecord {
+            phase: Phase::Initialization,
+            event,
+            topics: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn picks_ban_events_only() {
+        let hash = BlockHash::repeat_byte(7);
+        let account = AccountId::new([1; 32]);
+        let records = vec![
+            record(RuntimeEvent::System(frame_system::Event::CodeUpdated)),
+            record(RuntimeEvent::CommitteeManagement(
+                CommitteeManagementEvent::BanValidators(vec![(
+                    account.clone(),
+                    BanInfo {
+                        reason: BanReason::InsufficientUptime(3),
+                        start: 11,
+                    },
+                )]),
+            )),
+        ];
+
+        let events: Vec<_> = ban_events(records, hash, 42).collect();
+
+        assert_eq!(
+            events,
+            vec![ConsensusEvent::ValidatorsBanned {
+                hash,
+                number: 42,
+                validators: vec![BannedValidator {
+                    account,
+                    reason: "insufficient uptime in 3 sessions".to_string(),
+                    start_era: 11,
+                }],
+            }]
+        );
+    }
+}
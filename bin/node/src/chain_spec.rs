@@ -2,8 +2,8 @@ use std::{collections::HashSet, str::FromStr, string::ToString};
 
 use aleph_runtime::{
     AccountId, AlephConfig, AuraConfig, BalancesConfig, CommitteeManagementConfig, ElectionsConfig,
-    Perbill, RuntimeGenesisConfig, SessionConfig, SessionKeys, StakingConfig, SudoConfig,
-    SystemConfig, VestingConfig, WASM_BINARY,
+    NominationPoolsConfig, Perbill, RuntimeGenesisConfig, SessionConfig, SessionKeys,
+    StakingConfig, SudoConfig, SystemConfig, VestingConfig, WASM_BINARY,
 };
 use libp2p::PeerId;
 use pallet_staking::{Forcing, StakerStatus};
@@ -19,8 +19,9 @@ use sp_core::{sr25519, Pair};
 
 use crate::aleph_primitives::{
     staking::{MIN_NOMINATOR_BOND, MIN_VALIDATOR_BOND},
-    AuraId, AuthorityId as AlephId, SessionValidators, Version as FinalityVersion,
-    ADDRESSES_ENCODING, LEGACY_FINALITY_VERSION, TOKEN_DECIMALS,
+    AuraId, AuthorityId as AlephId, BanConfig, BlockNumber, BlsPublicKey, CommitteeSeats,
+    SessionValidators, Version as FinalityVersion, ADDRESSES_ENCODING, LEGACY_FINALITY_VERSION,
+    TOKEN_DECIMALS,
 };
 
 pub const CHAINTYPE_DEV: &str = "dev";
@@ -37,7 +38,7 @@ pub const DEFAULT_BACKUP_FOLDER: &str = "backup-stash";
 /// Specialized `ChainSpec`. This is a specialization of the general Substrate ChainSpec type.
 pub type ChainSpec = sc_service::GenericChainSpec<RuntimeGenesisConfig>;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct SerializablePeerId {
     inner: PeerId,
 }
@@ -85,12 +86,17 @@ fn parse_account_id(s: &str) -> Result<AccountId, CliError> {
 }
 
 fn parse_chaintype(s: &str) -> Result<ChainType, CliError> {
-    Ok(match s {
-        CHAINTYPE_DEV => ChainType::Development,
-        CHAINTYPE_LOCAL => ChainType::Local,
-        CHAINTYPE_LIVE => ChainType::Live,
-        s => panic!("Wrong chain type {s} Possible values: dev local live"),
-    })
+    Ok(chain_type_from_str(s)
+        .unwrap_or_else(|| panic!("Wrong chain type {s} Possible values: dev local live")))
+}
+
+pub(crate) fn chain_type_from_str(s: &str) -> Option<ChainType> {
+    match s {
+        CHAINTYPE_DEV => Some(ChainType::Development),
+        CHAINTYPE_LOCAL => Some(ChainType::Local),
+        CHAINTYPE_LIVE => Some(ChainType::Live),
+        _ => None,
+    }
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -98,6 +104,7 @@ pub struct AuthorityKeys {
     pub account_id: AccountId,
     pub aura_key: AuraId,
     pub aleph_key: AlephId,
+    pub bls_key: BlsPublicKey,
     pub peer_id: SerializablePeerId,
 }
 
@@ -205,39 +212,33 @@ pub fn config(
     chain_params: ChainParams,
     authorities: Vec<AuthorityKeys>,
 ) -> Result<ChainSpec, String> {
-    generate_chain_spec_config(chain_params, authorities)
+    let genesis_params = cli_genesis_params(&chain_params, authorities);
+    generate_chain_spec_config(
+        chain_params.chain_name(),
+        chain_params.chain_id(),
+        chain_params.chain_type(),
+        chain_params.token_symbol(),
+        genesis_params,
+    )
 }
 
-fn generate_chain_spec_config(
-    chain_params: ChainParams,
-    authorities: Vec<AuthorityKeys>,
+pub(crate) fn generate_chain_spec_config(
+    chain_name: &str,
+    chain_id: &str,
+    chain_type: ChainType,
+    token_symbol: &str,
+    genesis_params: GenesisParams,
 ) -> Result<ChainSpec, String> {
     let wasm_binary = WASM_BINARY.ok_or_else(|| "Development wasm not available".to_string())?;
-    let token_symbol = String::from(chain_params.token_symbol());
-    let chain_name = String::from(chain_params.chain_name());
-    let chain_id = String::from(chain_params.chain_id());
-    let chain_type = chain_params.chain_type();
-    let sudo_account = chain_params.sudo_account_id();
-    let rich_accounts = chain_params.rich_account_ids();
-    let faucet_account = chain_params.faucet_account_id();
-    let finality_version = chain_params.finality_version();
+    let token_symbol = String::from(token_symbol);
 
     Ok(ChainSpec::from_genesis(
         // Name
-        &chain_name,
+        chain_name,
         // ID
-        &chain_id,
+        chain_id,
         chain_type,
-        move || {
-            generate_genesis_config(
-                wasm_binary,
-                authorities.clone(), // Initial PoA authorities, will receive funds
-                sudo_account.clone(), // Sudo account, will also be pre funded
-                rich_accounts.clone(), // Pre-funded accounts
-                faucet_account.clone(), // Pre-funded faucet account
-                finality_version,
-            )
-        },
+        move || generate_genesis_config(wasm_binary, genesis_params.clone()),
         // Bootnodes
         vec![],
         // Telemetry
@@ -267,97 +268,197 @@ fn calculate_initial_endowment(accounts: &[AccountId]) -> u128 {
     TOTAL_ISSUANCE / (accounts.len() as u128)
 }
 
-/// Provides configuration for staking by defining balances, members, keys and stakers.
+/// A validator present at the genesis.
+#[derive(Clone)]
+pub struct GenesisValidator {
+    pub keys: AuthorityKeys,
+    pub reserved: bool,
+    pub stake: u128,
+}
+
+/// Everything the initial state of the chain is built from.
+#[derive(Clone)]
+pub struct GenesisParams {
+    pub validators: Vec<GenesisValidator>,
+    pub sudo_account: AccountId,
+    pub balances: Vec<(AccountId, u128)>,
+    /// Vesting schedules as (account, begin, length, liquid balance).
+    pub vesting: Vec<(AccountId, BlockNumber, BlockNumber, u128)>,
+    pub committee_seats: CommitteeSeats,
+    pub ban_config: BanConfig,
+    pub finality_version: FinalityVersion,
+    /// Limits of the nomination pools, the runtime defaults if not provided.
+    pub nomination_pools: Option<NominationPoolsParams>,
+}
+
+/// Genesis limits of the nomination pools.
+#[derive(Clone)]
+pub struct NominationPoolsParams {
+    pub min_join_bond: u128,
+    pub min_create_bond: u128,
+    pub max_pools: Option<u32>,
+    pub max_members_per_pool: Option<u32>,
+    pub max_members: Option<u32>,
+}
+
+impl From<NominationPoolsParams> for NominationPoolsConfig {
+    fn from(params: NominationPoolsParams) -> Self {
+        NominationPoolsConfig {
+            min_join_bond: params.min_join_bond,
+            min_create_bond: params.min_create_bond,
+            max_pools: params.max_pools,
+            max_members_per_pool: params.max_members_per_pool,
+            max_members: params.max_members,
+            ..Default::default()
+        }
+    }
+}
+
+/// Genesis parameters for the accounts passed on the command line. All the authorities are
+/// reserved validators.
+fn cli_genesis_params(
+    chain_params: &ChainParams,
+    authorities: Vec<AuthorityKeys>,
+) -> GenesisParams {
+    let sudo_account = chain_params.sudo_account_id();
+    let special_accounts = {
+        let mut all = chain_params.rich_account_ids().unwrap_or_default();
+        all.push(sudo_account.clone());
+        if let Some(faucet_account) = chain_params.faucet_account_id() {
+            all.push(faucet_account);
+        }
+        all
+    };
+
+    // NOTE: some combinations of bootstrap chain arguments can potentially
+    // lead to duplicated rich accounts, e.g. if a sudo account is also an authority
+    // which is why we remove the duplicates if any here
+    let unique_accounts = deduplicate(
+        to_account_ids(&authorities)
+            .chain(special_accounts)
+            .collect(),
+    );
+
+    let endowment = calculate_initial_endowment(&unique_accounts);
+
+    let balances = unique_accounts
+        .into_iter()
+        .map(|account| (account, endowment))
+        .collect();
+
+    let validators = authorities
+        .into_iter()
+        .enumerate()
+        .map(|(validator_idx, keys)| GenesisValidator {
+            keys,
+            reserved: true,
+            stake: (validator_idx + 1) as u128 * MIN_VALIDATOR_BOND,
+        })
+        .collect();
+
+    GenesisParams {
+        validators,
+        sudo_account,
+        balances,
+        vesting: vec![],
+        committee_seats: Default::default(),
+        ban_config: Default::default(),
+        finality_version: chain_params.finality_version(),
+        nomination_pools: None,
+    }
+}
+
+/// Provides configuration for staking by defining members, keys and stakers.
 struct AccountsConfig {
-    balances: Vec<(AccountId, u128)>,
-    members: Vec<AccountId>,
+    reserved: Vec<AccountId>,
+    non_reserved: Vec<AccountId>,
     keys: Vec<(AccountId, AccountId, SessionKeys)>,
     stakers: Vec<(AccountId, AccountId, u128, StakerStatus<AccountId>)>,
 }
 
 /// Provides accounts for RuntimeGenesisConfig setup based on distinct staking accounts.
 /// Assumes validator == stash, but controller is a distinct account
-fn configure_chain_spec_fields(
-    balances: Vec<(AccountId, u128)>,
-    authorities: Vec<AuthorityKeys>,
-) -> AccountsConfig {
-    let keys = authorities
+fn configure_chain_spec_fields(validators: &[GenesisValidator]) -> AccountsConfig {
+    let keys = validators
         .iter()
-        .map(|auth| {
+        .map(|validator| {
+            let auth = &validator.keys;
             (
                 auth.account_id.clone(),
                 auth.account_id.clone(),
                 SessionKeys {
                     aura: auth.aura_key.clone(),
                     aleph: auth.aleph_key.clone(),
+                    bls: auth.bls_key,
                 },
             )
         })
         .collect();
 
-    let stakers = authorities
+    let stakers = validators
         .iter()
-        .enumerate()
-        .map(|(validator_idx, validator)| {
+        .map(|validator| {
             (
-                validator.account_id.clone(),
+                validator.keys.account_id.clone(),
                 // this is controller account but in Substrate 1.0.0, it is omitted anyway,
                 // so it does not matter what we pass in the below line as always stash == controller
-                validator.account_id.clone(),
-                (validator_idx + 1) as u128 * MIN_VALIDATOR_BOND,
+                validator.keys.account_id.clone(),
+                validator.stake,
                 StakerStatus::Validator,
             )
         })
         .collect();
 
-    let members = to_account_ids(&authorities).collect();
+    let (reserved, non_reserved): (Vec<_>, Vec<_>) =
+        validators.iter().partition(|validator| validator.reserved);
+    let account_ids = |validators: Vec<&GenesisValidator>| {
+        validators
+            .into_iter()
+            .map(|validator| validator.keys.account_id.clone())
+            .collect()
+    };
 
     AccountsConfig {
-        balances,
-        members,
+        reserved: account_ids(reserved),
+        non_reserved: account_ids(non_reserved),
         keys,
         stakers,
     }
 }
 
 /// Configure initial storage state for FRAME modules.
-#[allow(clippy::too_many_arguments)]
 fn generate_genesis_config(
     wasm_binary: &[u8],
-    authorities: Vec<AuthorityKeys>,
-    sudo_account: AccountId,
-    rich_accounts: Option<Vec<AccountId>>,
-    faucet_account: Option<AccountId>,
-    finality_version: FinalityVersion,
+    genesis_params: GenesisParams,
 ) -> RuntimeGenesisConfig {
-    let special_accounts = {
-        let mut all = rich_accounts.unwrap_or_default();
-        all.push(sudo_account.clone());
-        if let Some(faucet_account) = faucet_account {
-            all.push(faucet_account);
-        }
-        all
-    };
-
-    // NOTE: some combinations of bootstrap chain arguments can potentially
-    // lead to duplicated rich accounts, e.g. if a sudo account is also an authority
-    // which is why we remove the duplicates if any here
-    let unique_accounts = deduplicate(
-        to_account_ids(&authorities)
-            .chain(special_accounts)
-            .collect(),
-    );
-
-    let endowment = calculate_initial_endowment(&unique_accounts);
-
-    let unique_accounts_balances = unique_accounts
-        .into_iter()
-        .map(|account| (account, endowment))
-        .collect::<Vec<_>>();
-
-    let validator_count = authorities.len() as u32;
-
-    let accounts_config = configure_chain_spec_fields(unique_accounts_balances, authorities);
+    let GenesisParams {
+        validators,
+        sudo_account,
+        balances,
+        vesting,
+        committee_seats,
+        ban_config,
+        finality_version,
+        nomination_pools,
+    } = genesis_params;
+
+    let validator_count = validators.len() as u32;
+
+    let accounts_config = configure_chain_spec_fields(&validators);
+
+    // All the reserved validators and as many non-reserved ones as there are seats for them
+    // form the committee of the first session.
+    let non_reserved_in_committee = accounts_config
+        .non_reserved
+        .len()
+        .min(committee_seats.non_reserved_seats as usize);
+    let committee = accounts_config
+        .reserved
+        .iter()
+        .chain(&accounts_config.non_reserved[..non_reserved_in_committee])
+        .cloned()
+        .collect();
+    let non_committee = accounts_config.non_reserved[non_reserved_in_committee..].to_vec();
 
     RuntimeGenesisConfig {
         system: SystemConfig {
@@ -367,7 +468,7 @@ fn generate_genesis_config(
         },
         balances: BalancesConfig {
             // Configure endowed accounts with an initial, significant balance
-            balances: accounts_config.balances,
+            balances,
         },
         aura: AuraConfig {
             authorities: vec![],
@@ -377,9 +478,9 @@ fn generate_genesis_config(
             key: Some(sudo_account),
         },
         elections: ElectionsConfig {
-            reserved_validators: accounts_config.members.clone(),
-            non_reserved_validators: vec![],
-            committee_seats: Default::default(),
+            reserved_validators: accounts_config.reserved,
+            non_reserved_validators: accounts_config.non_reserved,
+            committee_seats,
         },
         session: SessionConfig {
             keys: accounts_config.keys,
@@ -399,14 +500,14 @@ fn generate_genesis_config(
             ..Default::default()
         },
         treasury: Default::default(),
-        vesting: VestingConfig { vesting: vec![] },
-        nomination_pools: Default::default(),
+        vesting: VestingConfig { vesting },
+        nomination_pools: nomination_pools.map(Into::into).unwrap_or_default(),
         transaction_payment: Default::default(),
         committee_management: CommitteeManagementConfig {
-            committee_ban_config: Default::default(),
+            committee_ban_config: ban_config,
             session_validators: SessionValidators {
-                committee: accounts_config.members,
-                non_committee: vec![],
+                committee,
+                non_committee,
             },
         },
     }
@@ -6,7 +6,10 @@ use sc_cli::{
 use crate::{
     aleph_cli::AlephCli,
     chain_spec,
-    commands::{BootstrapChainCmd, BootstrapNodeCmd, ConvertChainspecToRawCmd},
+    commands::{
+        BootstrapChainCmd, BootstrapNodeCmd, ConvertChainspecToRawCmd, ExportSnapshotCmd,
+        ImportSnapshotCmd, ReplaySessionCmd,
+    },
 };
 
 #[derive(Debug, Parser)]
@@ -90,12 +93,21 @@ pub enum Subcommand {
     /// Import blocks.
     ImportBlocks(sc_cli::ImportBlocksCmd),
 
+    /// Export the finalized state at the end of a session into a snapshot for bootstrapping nodes.
+    ExportSnapshot(ExportSnapshotCmd),
+
+    /// Restore the database of a fresh node from a snapshot.
+    ImportSnapshot(ImportSnapshotCmd),
+
     /// Remove the whole chain.
     PurgeChain(PurgeChainCmd),
 
     /// Revert the chain to a previous state.
     Revert(sc_cli::RevertCmd),
 
+    /// Replay the AlephBFT ordering of a session from the backups of its committee members.
+    ReplaySession(ReplaySessionCmd),
+
     /// Try some command against runtime state.
     #[cfg(feature = "try-runtime")]
     TryRuntime(try_runtime_cli::TryRuntimeCmd),
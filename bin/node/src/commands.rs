@@ -2,25 +2,42 @@ use std::{
     fs,
     io::Write,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
 };
 
-use aleph_runtime::AccountId;
+use aleph_runtime::{opaque::Block, AccountId};
+use finality_aleph::{
+    bls_secret_key, replay_session, ClientForAleph, Interpretation, MemberBackup, SessionId,
+};
 use libp2p::identity::{ed25519 as libp2p_ed25519, PublicKey};
 use sc_cli::{
     clap::{self, Args, Parser},
-    Error, KeystoreParams,
+    CliConfiguration, DatabaseParams, Error, KeystoreParams, SharedParams,
 };
+use sc_client_api::{Backend, HeaderBackend};
+use sc_consensus::BlockImport;
 use sc_keystore::LocalKeystore;
-use sc_service::config::{BasePath, KeystoreConfig};
+use sc_service::{
+    config::{BasePath, KeystoreConfig},
+    SpawnTaskHandle,
+};
 use sp_application_crypto::{key_types, Ss58Codec};
+use sp_consensus::Error as ConsensusError;
+use sp_consensus_aura::AuraApi;
 use sp_keystore::Keystore;
 
 use crate::{
-    aleph_primitives::{AuraId, AuthorityId as AlephId},
+    aleph_primitives::{
+        AlephSessionApi, AuraId, AuthorityId as AlephId, BlockHash, BlsPublicKey, BLS_KEY_TYPE,
+    },
     chain_spec::{
         self, account_id_from_string, AuthorityKeys, ChainParams, ChainSpec, SerializablePeerId,
         DEFAULT_BACKUP_FOLDER,
     },
+    network_config::{NetworkConfig, CONTRACT_DEPLOYMENTS_FILE},
+    service::session_boundary_info,
+    snapshot::Snapshot,
 };
 
 #[derive(Debug, Args)]
@@ -76,6 +93,19 @@ fn aleph_key(keystore: &impl Keystore) -> AlephId {
         .into()
 }
 
+/// returns BLS key, if its seed key is absent a new one is generated
+fn bls_key(keystore: &impl Keystore) -> BlsPublicKey {
+    let seed_key = Keystore::ed25519_public_keys(keystore, BLS_KEY_TYPE)
+        .pop()
+        .unwrap_or_else(|| {
+            Keystore::ed25519_generate_new(keystore, BLS_KEY_TYPE, None)
+                .expect("Could not create BLS seed key")
+        });
+    bls_secret_key(keystore, &seed_key)
+        .expect("the keystore holds the BLS seed key")
+        .public()
+}
+
 /// Returns peer id, if not p2p key found under base_path/node-key-file a new private key gets generated
 fn p2p_key(node_key_path: &Path) -> SerializablePeerId {
     if node_key_path.exists() {
@@ -135,6 +165,7 @@ fn authority_keys(
 ) -> AuthorityKeys {
     let aura_key = aura_key(keystore);
     let aleph_key = aleph_key(keystore);
+    let bls_key = bls_key(keystore);
     let node_key_path = base_path.join(node_key_file);
     let peer_id = p2p_key(node_key_path.as_path());
 
@@ -142,6 +173,7 @@ fn authority_keys(
         account_id,
         aura_key,
         aleph_key,
+        bls_key,
         peer_id,
     }
 }
@@ -163,34 +195,54 @@ pub struct BootstrapChainCmd {
 
     #[clap(flatten)]
     pub node_params: NodeParams,
+
+    /// Read the chain parameters, validators, endowments, vesting schedules and nomination pools
+    /// limits from a TOML description of the network (or a JSON one, if the file has the `.json`
+    /// extension) instead of the command line. Keystores are generated for the validators whose
+    /// keys are not given in the description. Contracts to deploy are written to
+    /// `contracts.json` under the base path, for `scripts/deploy_contracts.sh` to instantiate
+    /// once the chain is running.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["account_ids", "rich_account_ids", "faucet_account_id"])]
+    pub from_config: Option<PathBuf>,
 }
 
 /// Assumes an input path: some_path/, which is appended to finally become: some_path/account_id
 impl BootstrapChainCmd {
     pub fn run(&self) -> Result<(), Error> {
-        let base_path = self.node_params.base_path();
-        let backup_dir = self.node_params.backup_dir();
-        let node_key_file = self.node_params.node_key_file();
-        let chain_id = self.chain_params.chain_id();
-        let genesis_authorities = self
-            .chain_params
-            .account_ids()
-            .into_iter()
-            .map(|account_id| {
-                let account_base_path: BasePath =
-                    base_path.path().join(account_id.to_string()).into();
-                bootstrap_backup(account_base_path.path(), backup_dir);
-                let keystore = open_keystore(&self.keystore_params, chain_id, &account_base_path);
-                authority_keys(
-                    &keystore,
-                    account_base_path.path(),
-                    node_key_file,
-                    account_id,
-                )
-            })
-            .collect();
-
-        let chain_spec = chain_spec::config(self.chain_params.clone(), genesis_authorities)?;
+        let chain_spec = match &self.from_config {
+            Some(path) => {
+                let network_config = NetworkConfig::from_file(path)?;
+                let chain_spec = network_config.chain_spec(|account_id| {
+                    self.local_authority_keys(&network_config.chain_id, account_id)
+                })?;
+                let deployments = network_config
+                    .contract_deployments(path.parent().unwrap_or_else(|| Path::new(".")))?;
+                if !deployments.is_empty() {
+                    let base_path = self.node_params.base_path();
+                    let deployments_path = base_path.path().join(CONTRACT_DEPLOYMENTS_FILE);
+                    let json = serde_json::to_string_pretty(&deployments)
+                        .map_err(|e| format!("Could not serialize contract deployments: {e}"))?;
+                    fs::create_dir_all(base_path.path())
+                        .and_then(|_| fs::write(&deployments_path, json))
+                        .map_err(|e| {
+                            format!(
+                                "Could not write contract deployments to {deployments_path:?}: {e}"
+                            )
+                        })?;
+                }
+                chain_spec
+            }
+            None => {
+                let chain_id = self.chain_params.chain_id();
+                let genesis_authorities = self
+                    .chain_params
+                    .account_ids()
+                    .into_iter()
+                    .map(|account_id| self.local_authority_keys(chain_id, account_id))
+                    .collect();
+                chain_spec::config(self.chain_params.clone(), genesis_authorities)?
+            }
+        };
 
         let json = sc_service::chain_ops::build_spec(&chain_spec, self.raw)?;
         if std::io::stdout().write_all(json.as_bytes()).is_err() {
@@ -199,6 +251,21 @@ impl BootstrapChainCmd {
 
         Ok(())
     }
+
+    /// Generates the keystore, the p2p key and the backup directory of a validator under
+    /// base_path/account_id, unless they are already there.
+    fn local_authority_keys(&self, chain_id: &str, account_id: AccountId) -> AuthorityKeys {
+        let base_path = self.node_params.base_path();
+        let account_base_path: BasePath = base_path.path().join(account_id.to_string()).into();
+        bootstrap_backup(account_base_path.path(), self.node_params.backup_dir());
+        let keystore = open_keystore(&self.keystore_params, chain_id, &account_base_path);
+        authority_keys(
+            &keystore,
+            account_base_path.path(),
+            self.node_params.node_key_file(),
+            account_id,
+        )
+    }
 }
 
 /// The `bootstrap-node` command is used to generate key pairs and AlephBFT backup folder for a single authority
@@ -283,3 +350,204 @@ impl ConvertChainspecToRawCmd {
         Ok(())
     }
 }
+
+fn parse_member_backup(arg: &str) -> Result<MemberBackup, String> {
+    let (node_id, backup_path) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected INDEX=PATH, got {arg}"))?;
+    let node_id = node_id
+        .parse()
+        .map_err(|e| format!("invalid member index {node_id}: {e}"))?;
+    Ok(MemberBackup {
+        node_id,
+        backup_path: backup_path.into(),
+    })
+}
+
+/// The `replay-session` command replays the AlephBFT ordering of a session from the backups of its committee
+/// members, and prints the ordered data together with the blocks it would finalize.
+#[derive(Debug, Parser)]
+pub struct ReplaySessionCmd {
+    /// The session to replay
+    #[arg(long)]
+    pub session: u32,
+
+    /// The backup of a committee member, as the index of the member in the committee and the path to its backup
+    /// directory (i.e. its `--backup-path`). Can be passed multiple times
+    #[arg(long = "backup", value_name = "INDEX=PATH", value_parser = parse_member_backup, required = true)]
+    pub backups: Vec<MemberBackup>,
+
+    /// How long, in seconds, to wait for more ordered data before deciding a backup was fully replayed
+    #[arg(long, default_value_t = 10)]
+    pub idle_timeout: u64,
+
+    #[clap(flatten)]
+    pub shared_params: SharedParams,
+
+    #[clap(flatten)]
+    pub database_params: DatabaseParams,
+}
+
+impl ReplaySessionCmd {
+    pub async fn run<C, BE>(
+        &self,
+        client: Arc<C>,
+        spawn_handle: SpawnTaskHandle,
+    ) -> Result<(), Error>
+    where
+        C: ClientForAleph<Block, BE> + Send + Sync + 'static,
+        C::Api: AlephSessionApi<Block> + AuraApi<Block, AuraId>,
+        BE: Backend<Block> + 'static,
+    {
+        let finalized = client.info().finalized_hash;
+        let session_info = session_boundary_info(&*client, finalized)
+            .map_err(|e| Error::Application(Box::new(e)))?;
+        let replay = replay_session(
+            client,
+            session_info,
+            SessionId(self.session),
+            self.backups.clone(),
+            spawn_handle.into(),
+            Duration::from_secs(self.idle_timeout),
+        )
+        .await
+        .map_err(|e| Error::Application(Box::new(e)))?;
+
+        for (node_id, ordered) in &replay.ordered_per_member {
+            println!("Member {node_id}: {ordered} items ordered");
+        }
+        for (node_id, position) in &replay.divergences {
+            println!("Member {node_id}: DIVERGES from the longest order at item {position}");
+        }
+        for (position, item) in replay.ordered.iter().enumerate() {
+            let outcome = match &item.interpretation {
+                Interpretation::Finalize(blocks) => blocks
+                    .iter()
+                    .map(|block| block.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                Interpretation::Ignore => "nothing".to_string(),
+                Interpretation::Unavailable(status) => format!("unknown, {status:?}"),
+            };
+            println!(
+                "{position}: proposal up to {} by member {}, finalizes {outcome}",
+                item.data.head_proposal.top_block(),
+                item.creator,
+            );
+        }
+        match replay.finalized_blocks().last() {
+            Some(block) => println!("Session {} finalizes up to {block}", self.session),
+            None => println!("Session {} finalizes nothing", self.session),
+        }
+        Ok(())
+    }
+}
+
+impl CliConfiguration for ReplaySessionCmd {
+    fn shared_params(&self) -> &SharedParams {
+        &self.shared_params
+    }
+
+    fn database_params(&self) -> Option<&DatabaseParams> {
+        Some(&self.database_params)
+    }
+}
+
+/// The `export-snapshot` command writes the finalized state at the end of a session, together with everything needed
+/// to verify it, to a file that `import-snapshot` can bootstrap a node from.
+#[derive(Debug, Parser)]
+pub struct ExportSnapshotCmd {
+    /// The file to write the snapshot to
+    #[arg(long)]
+    pub output: PathBuf,
+
+    /// The session at the end of which the snapshot is taken. Defaults to the last fully finalized session
+    #[arg(long)]
+    pub session: Option<u32>,
+
+    #[clap(flatten)]
+    pub shared_params: SharedParams,
+
+    #[clap(flatten)]
+    pub database_params: DatabaseParams,
+}
+
+impl ExportSnapshotCmd {
+    pub async fn run<C, BE>(&self, client: Arc<C>) -> Result<(), Error>
+    where
+        C: ClientForAleph<Block, BE> + Send + Sync + 'static,
+        C::Api: AlephSessionApi<Block>,
+        BE: Backend<Block> + 'static,
+    {
+        let snapshot = Snapshot::export::<C, BE>(&*client, self.session)
+            .map_err(|e| Error::Application(Box::new(e)))?;
+        snapshot
+            .write(&self.output)
+            .map_err(|e| Error::Application(Box::new(e)))?;
+        println!(
+            "Exported the snapshot of session {} at block {} to {:?}",
+            snapshot.session,
+            snapshot.block_id(),
+            self.output
+        );
+        Ok(())
+    }
+}
+
+impl CliConfiguration for ExportSnapshotCmd {
+    fn shared_params(&self) -> &SharedParams {
+        &self.shared_params
+    }
+
+    fn database_params(&self) -> Option<&DatabaseParams> {
+        Some(&self.database_params)
+    }
+}
+
+/// The `import-snapshot` command verifies a snapshot made with `export-snapshot` and restores the database of a fresh
+/// node from it. The node then follows the chain from the snapshot block, without the blocks below it.
+#[derive(Debug, Parser)]
+pub struct ImportSnapshotCmd {
+    /// The file to read the snapshot from
+    #[arg(long)]
+    pub input: PathBuf,
+
+    /// The hash of the block the snapshot was taken at, obtained from a source you trust, e.g. a node of your own.
+    /// The snapshot is not linked to the genesis block, so this hash is all that ties it to the real chain
+    #[arg(long)]
+    pub trusted_block_hash: BlockHash,
+
+    #[clap(flatten)]
+    pub shared_params: SharedParams,
+
+    #[clap(flatten)]
+    pub database_params: DatabaseParams,
+}
+
+impl ImportSnapshotCmd {
+    pub async fn run<C, BE>(&self, client: Arc<C>) -> Result<(), Error>
+    where
+        C: ClientForAleph<Block, BE> + Send + Sync + 'static,
+        for<'a> &'a C: BlockImport<Block, Error = ConsensusError>,
+        BE: Backend<Block> + 'static,
+    {
+        let snapshot = Snapshot::read(&self.input).map_err(|e| Error::Application(Box::new(e)))?;
+        let session = snapshot.session;
+        let block = snapshot
+            .import(client, self.trusted_block_hash)
+            .await
+            .map_err(|e| Error::Application(Box::new(e)))?;
+        println!("Imported the snapshot of session {session} at block {block}");
+        Ok(())
+    }
+}
+
+impl CliConfiguration for ImportSnapshotCmd {
+    fn shared_params(&self) -> &SharedParams {
+        &self.shared_params
+    }
+
+    fn database_params(&self) -> Option<&DatabaseParams> {
+        Some(&self.database_params)
+    }
+}
@@ -0,0 +1,614 @@
+//! A summary of the health of the local validator, gathered periodically and served over RPC,
+//! exposed as Prometheus metrics and optionally served as JSON over plain HTTP, so that alerting
+//! systems do not need to speak JSON-RPC.
+
+use std::{
+    fs,
+    io::{Error as IoError, ErrorKind},
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use finality_aleph::{SessionConnectivity, ValidatorNetworkStatus};
+use log::{debug, info, warn};
+use parity_scale_codec::{Decode, Encode};
+use primitives::{
+    AccountId, AlephSessionApi, AuraId, AuthorityId, Block, BlockCount, BlockHash, BlockNumber,
+    KEY_TYPE,
+};
+use sc_client_api::StorageProvider;
+use serde::{Deserialize, Serialize};
+use sp_api::ProvideRuntimeApi;
+use sp_blockchain::HeaderBackend;
+use sp_consensus_aura::AuraApi;
+use sp_core::{
+    crypto::{key_types::AURA, KeyTypeId},
+    twox_128, twox_64,
+};
+use sp_keystore::{Keystore, KeystorePtr};
+use substrate_prometheus_endpoint::{
+    register, Gauge, GaugeVec, Opts, PrometheusError, Registry, I64, U64,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::watch,
+    time::{interval, timeout},
+};
+
+use crate::service::session_boundary_info;
+
+const LOG_TARGET: &str = "aleph-health";
+const VALIDATOR_PORT_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+const HEALTH_REPORT_INTERVAL: Duration = Duration::from_secs(12);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+const BACKUP_PROBE_FILE: &str = ".health-probe";
+
+/// What can go wrong when gathering the health report.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("runtime API call failed: {0}")]
+    RuntimeApi(#[from] sp_api::ApiError),
+    #[error("failed to read storage: {0}")]
+    Storage(#[from] sp_blockchain::Error),
+    #[error("failed to decode storage: {0}")]
+    Decoding(#[from] parity_scale_codec::Error),
+}
+
+/// Node-local settings the health report depends on.
+#[derive(Clone)]
+pub struct HealthConfig {
+    pub validator_network_status: ValidatorNetworkStatus,
+    pub validator_port: u16,
+    pub max_nonfinalized_blocks: u32,
+    pub backup_path: Option<PathBuf>,
+}
+
+/// Keys of the authorities of a session that are present in the keystore of this node.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionKeys {
+    /// The Aleph key, used for finality.
+    pub aleph: Option<AuthorityId>,
+    /// The Aura key, used for block production.
+    pub aura: Option<AuraId>,
+}
+
+/// A summary of the health of this validator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// The session of the best block.
+    pub session: u32,
+    /// Keys of this node among the authorities of the current session.
+    pub current_session_keys: SessionKeys,
+    /// Keys of this node among the authorities of the next session.
+    pub next_session_keys: SessionKeys,
+    /// Whether the validator port accepts connections.
+    pub validator_port_listening: bool,
+    /// Connectivity within the committee of the current session, if this node is a member.
+    pub committee_connectivity: Option<SessionConnectivity>,
+    /// Blocks produced by this node in the current session, if it is a block producer.
+    pub blocks_produced: Option<BlockCount>,
+    /// Blocks this node should have produced so far in the current session, if it is a block
+    /// producer.
+    pub blocks_expected: Option<BlockCount>,
+    /// The number of blocks between the best and the finalized block.
+    pub nonfinalized_blocks: BlockNumber,
+    /// The number of nonfinalized blocks after which block production stops, set in the runtime
+    /// or locally.
+    pub max_nonfinalized_blocks: u32,
+    /// Whether backups can be written, nothing if they are turned off.
+    pub backup_writable: Option<bool>,
+}
+
+/// The most recent health report, or why it could not be gathered.
+type ReportResult = Option<Result<HealthReport, String>>;
+
+/// The health report last gathered by the [`HealthReporter`], to be served without waiting for
+/// the probes.
+#[derive(Clone)]
+pub struct LastHealthReport(watch::Receiver<ReportResult>);
+
+impl LastHealthReport {
+    pub fn get(&self) -> Result<HealthReport, String> {
+        self.0
+            .borrow()
+            .clone()
+            .unwrap_or_else(|| Err("the health report has not been gathered yet".into()))
+    }
+}
+
+/// Periodically gathers the health report from the client, the keystore and the validator
+/// network.
+pub struct HealthReporter<C> {
+    client: Arc<C>,
+    keystore: KeystorePtr,
+    config: HealthConfig,
+    last_report: watch::Sender<ReportResult>,
+}
+
+impl<C, BE> HealthReporter<C>
+where
+    BE: sc_client_api::Backend<Block>,
+    C: HeaderBackend<Block> + StorageProvider<Block, BE> + ProvideRuntimeApi<Block>,
+    C::Api: AlephSessionApi<Block> + AuraApi<Block, AuraId>,
+{
+    pub fn new(client: Arc<C>, keystore: KeystorePtr, config: HealthConfig) -> Self {
+        HealthReporter {
+            client,
+            keystore,
+            config,
+            last_report: watch::channel(None).0,
+        }
+    }
+
+    /// A handle to the report last gathered by this reporter.
+    pub fn last_report(&self) -> LastHealthReport {
+        LastHealthReport(self.last_report.subscribe())
+    }
+
+    /// Gathers the report periodically, updating the last report and the metrics, if any.
+    pub async fn run(self, metrics: Option<HealthMetrics>) {
+        let mut ticks = interval(HEALTH_REPORT_INTERVAL);
+        loop {
+            ticks.tick().await;
+            let validator_port_listening = accepts_connections(self.config.validator_port).await;
+            let report = self.report(validator_port_listening).map_err(|e| {
+                warn!(
+                    target: LOG_TARGET,
+                    "Failed to gather the health report: {}.", e
+                );
+                e.to_string()
+            });
+            if let (Some(metrics), Ok(report)) = (&metrics, &report) {
+                metrics.update(report);
+            }
+            self.last_report.send_replace(Some(report));
+        }
+    }
+
+    fn report(&self, validator_port_listening: bool) -> Result<HealthReport, Error> {
+        let info = self.client.info();
+        let best_hash = info.best_hash;
+        let runtime_api = self.client.runtime_api();
+        let session_info = session_boundary_info(&*self.client, best_hash)?;
+        let session = session_info.session_id_from_block_num(info.best_number);
+
+        let current_session_keys = SessionKeys {
+            aleph: self.own_key(
+                AlephSessionApi::authorities(&*runtime_api, best_hash)?,
+                KEY_TYPE,
+            ),
+            aura: self.own_key(AuraApi::authorities(&*runtime_api, best_hash)?, AURA),
+        };
+        let next_session_keys = SessionKeys {
+            aleph: self.own_key(
+                runtime_api
+                    .next_session_authorities(best_hash)?
+                    .unwrap_or_default(),
+                KEY_TYPE,
+            ),
+            aura: self.own_key(
+                runtime_api
+                    .next_session_aura_authorities(best_hash)?
+                    .into_iter()
+                    .map(|(_, key)| key),
+                AURA,
+            ),
+        };
+
+        let own_account = match &current_session_keys.aleph {
+            Some(key) => runtime_api.key_owner(best_hash, key.clone())?,
+            None => None,
+        };
+        let (blocks_produced, blocks_expected) = match own_account {
+            Some(account) => self.block_production(
+                &account,
+                best_hash,
+                info.best_number
+                    .saturating_sub(session_info.first_block_of_session(session))
+                    + 1,
+            )?,
+            None => (None, None),
+        };
+
+        Ok(HealthReport {
+            session: session.0,
+            committee_connectivity: current_session_keys
+                .aleph
+                .as_ref()
+                .and_then(|_| self.config.validator_network_status.session(session)),
+            current_session_keys,
+            next_session_keys,
+            validator_port_listening,
+            blocks_produced,
+            blocks_expected,
+            nonfinalized_blocks: info.best_number.saturating_sub(info.finalized_number),
+            max_nonfinalized_blocks: runtime_api
+                .authoring_backoff_config(best_hash)
+                .ok()
+                .flatten()
+                .map(|config| config.max_nonfinalized_blocks)
+                .unwrap_or(self.config.max_nonfinalized_blocks),
+            backup_writable: self.config.backup_path.as_deref().map(is_writable),
+        })
+    }
+
+    fn own_key<K: AsRef<[u8]>>(
+        &self,
+        keys: impl IntoIterator<Item = K>,
+        key_type: KeyTypeId,
+    ) -> Option<K> {
+        keys.into_iter()
+            .find(|key| self.keystore.has_keys(&[(key.as_ref().to_vec(), key_type)]))
+    }
+
+    /// Blocks produced by the account in the current session and the number it should have
+    /// produced in the elapsed part of the session, if it is a block producer.
+    fn block_production(
+        &self,
+        account: &AccountId,
+        best_hash: BlockHash,
+        elapsed_blocks: BlockNumber,
+    ) -> Result<(Option<BlockCount>, Option<BlockCount>), Error> {
+        let block_producers: Vec<AccountId> = self
+            .read_storage(storage_key("Session", "Validators", None), best_hash)?
+            .unwrap_or_default();
+        if !block_producers.contains(account) {
+            return Ok((None, None));
+        }
+        let blocks_produced = self
+            .read_storage(
+                storage_key(
+                    "CommitteeManagement",
+                    "SessionValidatorBlockCount",
+                    Some(account.encode()),
+                ),
+                best_hash,
+            )?
+            .unwrap_or_default();
+        Ok((
+            Some(blocks_produced),
+            Some(elapsed_blocks / block_producers.len() as BlockCount),
+        ))
+    }
+
+    fn read_storage<T: Decode>(
+        &self,
+        key: Vec<u8>,
+        block_hash: BlockHash,
+    ) -> Result<Option<T>, Error> {
+        match self
+            .client
+            .storage(block_hash, &sc_client_api::StorageKey(key))?
+        {
+            Some(encoded) => Ok(Some(T::decode(&mut encoded.0.as_ref())?)),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The key of a storage value, or of an entry of a `Twox64Concat` map if `map_key` is provided.
+pub(crate) fn storage_key(pallet: &str, item: &str, map_key: Option<Vec<u8>>) -> Vec<u8> {
+    let mut key = [twox_128(pallet.as_bytes()), twox_128(item.as_bytes())].concat();
+    if let Some(map_key) = map_key {
+        key.extend(twox_64(&map_key));
+        key.extend(map_key);
+    }
+    key
+}
+
+async fn accepts_connections(port: u16) -> bool {
+    let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    matches!(
+        timeout(VALIDATOR_PORT_PROBE_TIMEOUT, TcpStream::connect(address)).await,
+        Ok(Ok(_))
+    )
+}
+
+fn is_writable(path: &Path) -> bool {
+    let probe = path.join(BACKUP_PROBE_FILE);
+    let writable = fs::write(&probe, []).is_ok();
+    if writable {
+        let _ = fs::remove_file(probe);
+    }
+    writable
+}
+
+/// The health report exposed as Prometheus metrics, so that alerting systems can use it without
+/// speaking JSON-RPC. Values that do not apply to this node are reported as -1.
+pub struct HealthMetrics {
+    session: Gauge<U64>,
+    aleph_key: GaugeVec<U64>,
+    aura_key: GaugeVec<U64>,
+    validator_port_listening: Gauge<U64>,
+    committee_authenticated: Gauge<I64>,
+    committee_size: Gauge<I64>,
+    blocks_produced: Gauge<I64>,
+    blocks_expected: Gauge<I64>,
+    nonfinalized_blocks: Gauge<U64>,
+    max_nonfinalized_blocks: Gauge<U64>,
+    backup_writable: Gauge<I64>,
+}
+
+impl HealthMetrics {
+    pub fn new(registry: &Registry) -> Result<Self, PrometheusError> {
+        Ok(HealthMetrics {
+            session: register(
+                Gauge::new("aleph_health_session", "Session of the best block")?,
+                registry,
+            )?,
+            aleph_key: register(
+                GaugeVec::new(
+                    Opts::new(
+                        "aleph_health_aleph_key",
+                        "Whether the keystore holds an Aleph key of the committee of the session",
+                    ),
+                    &["session"],
+                )?,
+                registry,
+            )?,
+            aura_key: register(
+                GaugeVec::new(
+                    Opts::new(
+                        "aleph_health_aura_key",
+                        "Whether the keystore holds an Aura key of the block producers of the session",
+                    ),
+                    &["session"],
+                )?,
+                registry,
+            )?,
+            validator_port_listening: register(
+                Gauge::new(
+                    "aleph_health_validator_port_listening",
+                    "Whether the validator port accepts connections",
+                )?,
+                registry,
+            )?,
+            committee_authenticated: register(
+                Gauge::new(
+                    "aleph_health_committee_authenticated",
+                    "Committee members authenticated with, including this node, -1 if not a member",
+                )?,
+                registry,
+            )?,
+            committee_size: register(
+                Gauge::new(
+                    "aleph_health_committee_size",
+                    "Size of the current committee, -1 if not a member",
+                )?,
+                registry,
+            )?,
+            blocks_produced: register(
+                Gauge::new(
+                    "aleph_health_blocks_produced",
+                    "Blocks produced in the current session, -1 if not a block producer",
+                )?,
+                registry,
+            )?,
+            blocks_expected: register(
+                Gauge::new(
+                    "aleph_health_blocks_expected",
+                    "Blocks that should have been produced so far in the current session, -1 if not a block producer",
+                )?,
+                registry,
+            )?,
+            nonfinalized_blocks: register(
+                Gauge::new(
+                    "aleph_health_nonfinalized_blocks",
+                    "Number of blocks between the best and the finalized block",
+                )?,
+                registry,
+            )?,
+            max_nonfinalized_blocks: register(
+                Gauge::new(
+                    "aleph_health_max_nonfinalized_blocks",
+                    "Number of nonfinalized blocks after which block production stops",
+                )?,
+                registry,
+            )?,
+            backup_writable: register(
+                Gauge::new(
+                    "aleph_health_backup_writable",
+                    "Whether backups can be written, -1 if they are turned off",
+                )?,
+                registry,
+            )?,
+        })
+    }
+
+    fn update(&self, report: &HealthReport) {
+        let flag = |value: bool| value as u64;
+        let optional = |value: Option<i64>| value.unwrap_or(-1);
+        self.session.set(report.session as u64);
+        for (session, keys) in [
+            ("current", &report.current_session_keys),
+            ("next", &report.next_session_keys),
+        ] {
+            self.aleph_key
+                .with_label_values(&[session])
+                .set(flag(keys.aleph.is_some()));
+            self.aura_key
+                .with_label_values(&[session])
+                .set(flag(keys.aura.is_some()));
+        }
+        self.validator_port_listening
+            .set(flag(report.validator_port_listening));
+        self.committee_authenticated.set(optional(
+            report
+                .committee_connectivity
+                .as_ref()
+                .map(|connectivity| connectivity.authenticated as i64),
+        ));
+        self.committee_size.set(optional(
+            report
+                .committee_connectivity
+                .as_ref()
+                .map(|connectivity| connectivity.committee_size as i64),
+        ));
+        self.blocks_produced
+            .set(optional(report.blocks_produced.map(i64::from)));
+        self.blocks_expected
+            .set(optional(report.blocks_expected.map(i64::from)));
+        self.nonfinalized_blocks
+            .set(report.nonfinalized_blocks as u64);
+        self.max_nonfinalized_blocks
+            .set(report.max_nonfinalized_blocks as u64);
+        self.backup_writable
+            .set(optional(report.backup_writable.map(i64::from)));
+    }
+}
+
+/// Serves the last health report as JSON in response to every HTTP request received at
+/// `address`, regardless of its method and path. Requests are handled one at a time, which is
+/// plenty for alerting systems polling the node.
+pub async fn serve_health_report(last_report: LastHealthReport, address: SocketAddr) {
+    let listener = match TcpListener::bind(address).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            warn!(
+                target: LOG_TARGET,
+                "Failed to serve the health report at {}: {}.", address, e
+            );
+            return;
+        }
+    };
+    info!(
+        target: LOG_TARGET,
+        "Serving the health report at {}.", address
+    );
+    loop {
+        let result = match listener.accept().await {
+            Ok((stream, _)) => timeout(HTTP_TIMEOUT, respond(&last_report, stream))
+                .await
+                .unwrap_or_else(|_| Err(ErrorKind::TimedOut.into())),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            debug!(
+                target: LOG_TARGET,
+                "Failed to serve a health report request: {}.", e
+            );
+        }
+    }
+}
+
+async fn respond(last_report: &LastHealthReport, mut stream: TcpStream) -> Result<(), IoError> {
+    // Every request gets the same answer, so we only wait for the beginning of it.
+    let mut request = [0; 1024];
+    let _ = stream.read(&mut request).await?;
+    let (status, body) = match last_report.get() {
+        Ok(report) => ("200 OK", serde_json::to_string(&report)?),
+        Err(e) => (
+            "503 Service Unavailable",
+            serde_json::json!({ "error": e }).to_string(),
+        ),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        sync::watch,
+    };
+
+    use super::{
+        accepts_connections, is_writable, respond, storage_key, HealthReport, LastHealthReport,
+        SessionKeys,
+    };
+
+    fn health_report() -> HealthReport {
+        let no_keys = SessionKeys {
+            aleph: None,
+            aura: None,
+        };
+        HealthReport {
+            session: 7,
+            current_session_keys: no_keys.clone(),
+            next_session_keys: no_keys,
+            validator_port_listening: true,
+            committee_connectivity: None,
+            blocks_produced: None,
+            blocks_expected: None,
+            nonfinalized_blocks: 3,
+            max_nonfinalized_blocks: 20,
+            backup_writable: None,
+        }
+    }
+
+    async fn request(last_report: &LastHealthReport) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+        let (stream, _) = listener.accept().await.unwrap();
+        respond(last_report, stream).await.unwrap();
+        let mut response = String::new();
+        client.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn serves_last_report() {
+        let (last_report_tx, last_report_rx) = watch::channel(None);
+        let last_report = LastHealthReport(last_report_rx);
+        let response = request(&last_report).await;
+        assert!(response.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(response.ends_with(r#"{"error":"the health report has not been gathered yet"}"#));
+
+        last_report_tx.send_replace(Some(Ok(health_report())));
+        let response = request(&last_report).await;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let report: HealthReport = serde_json::from_str(body).unwrap();
+        assert_eq!(report.session, 7);
+        assert_eq!(report.nonfinalized_blocks, 3);
+    }
+
+    #[tokio::test]
+    async fn probes_validator_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(accepts_connections(port).await);
+        drop(listener);
+        assert!(!accepts_connections(port).await);
+    }
+
+    #[test]
+    fn detects_writable_directory() {
+        let path = env::temp_dir().join("aleph-health-writable");
+        fs::create_dir_all(&path).unwrap();
+        assert!(is_writable(&path));
+        assert!(fs::read_dir(&path).unwrap().next().is_none());
+        fs::remove_dir(&path).unwrap();
+    }
+
+    #[test]
+    fn detects_missing_directory() {
+        let path = env::temp_dir().join("aleph-health-missing");
+        let _ = fs::remove_dir_all(&path);
+        assert!(!is_writable(&path));
+    }
+
+    #[test]
+    fn builds_map_storage_key() {
+        let value_key = storage_key("Session", "Validators", None);
+        assert_eq!(value_key.len(), 32);
+        let map_key = storage_key("Session", "Validators", Some(vec![7; 3]));
+        assert_eq!(map_key[..32], value_key[..]);
+        assert_eq!(map_key.len(), 32 + 8 + 3);
+        assert_eq!(map_key[40..], [7; 3]);
+    }
+}
@@ -1,12 +1,19 @@
 mod aleph_cli;
 mod aleph_node_rpc;
+mod backoff;
+mod ban_events;
 mod chain_spec;
 mod cli;
 mod commands;
 mod executor;
+mod health;
+mod nat;
+mod network_config;
 mod resources;
 mod rpc;
 mod service;
+mod slot_worker;
+mod snapshot;
 
 pub use cli::{Cli, Subcommand};
 pub use executor::ExecutorDispatch;
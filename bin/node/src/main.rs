@@ -93,6 +93,28 @@ fn main() -> sc_cli::Result<()> {
                 Ok((cmd.run(client, import_queue), task_manager))
             })
         }
+        Some(Subcommand::ExportSnapshot(cmd)) => {
+            let runner = cli.create_runner(cmd)?;
+            runner.async_run(|config| {
+                let PartialComponents {
+                    client,
+                    task_manager,
+                    ..
+                } = new_partial(&config)?;
+                Ok((cmd.run(client), task_manager))
+            })
+        }
+        Some(Subcommand::ImportSnapshot(cmd)) => {
+            let runner = cli.create_runner(cmd)?;
+            runner.async_run(|config| {
+                let PartialComponents {
+                    client,
+                    task_manager,
+                    ..
+                } = new_partial(&config)?;
+                Ok((cmd.run(client), task_manager))
+            })
+        }
         Some(Subcommand::PurgeChain(cmd)) => {
             let runner = cli.create_runner(cmd)?;
             runner.sync_run(|config| cmd.run(config.database))
@@ -109,6 +131,18 @@ fn main() -> sc_cli::Result<()> {
                 Ok((cmd.run(client, backend, None), task_manager))
             })
         }
+        Some(Subcommand::ReplaySession(cmd)) => {
+            let runner = cli.create_runner(cmd)?;
+            runner.async_run(|config| {
+                let PartialComponents {
+                    client,
+                    task_manager,
+                    ..
+                } = new_partial(&config)?;
+                let spawn_handle = task_manager.spawn_handle();
+                Ok((cmd.run(client, spawn_handle), task_manager))
+            })
+        }
         #[cfg(feature = "try-runtime")]
         Some(Subcommand::TryRuntime(cmd)) => {
             use primitives::MILLISECS_PER_BLOCK;
@@ -172,6 +206,9 @@ fn main() -> sc_cli::Result<()> {
                             "A non-validator node cannot be run with external addresses specified."
                         );
                     }
+                    if aleph_cli_config.nat_config().is_some() {
+                        panic!("A non-validator node cannot be run with NAT port mapping.");
+                    }
                     // We ensure that external addresses for non-validator nodes are set, but to a
                     // value that is not routable. This will no longer be neccessary once we have
                     // proper support for non-validator nodes, but this requires a major
@@ -0,0 +1,617 @@
+//! Optional mapping of the validator port on a NAT gateway, using UPnP-IGD or NAT-PMP.
+//!
+//! Both protocols are implemented directly on top of blocking sockets, as they are only used for
+//! a couple of short exchanges at startup and when renewing the lease.
+
+use std::{
+    fs,
+    io::{Error as IoError, Read, Write},
+    net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    thread,
+    time::Duration,
+};
+
+use log::{debug, info, warn};
+use sc_cli::clap::ValueEnum;
+
+const LOG_TARGET: &str = "aleph-nat";
+
+/// How long the gateway should keep the mapping, it is renewed after half of that time.
+const LEASE_DURATION: Duration = Duration::from_secs(3600);
+const NAT_PMP_PORT: u16 = 5351;
+const NAT_PMP_ATTEMPTS: u32 = 4;
+const NAT_PMP_INITIAL_TIMEOUT: Duration = Duration::from_millis(250);
+const SSDP_ADDRESS: &str = "239.255.255.250:1900";
+const SSDP_TIMEOUT: Duration = Duration::from_secs(3);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+const MAPPING_DESCRIPTION: &str = "aleph-node validator";
+const WAN_CONNECTION_SERVICES: [&str; 3] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:2",
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+/// Protocol used to request a port mapping from the gateway.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum NatProtocol {
+    Upnp,
+    NatPmp,
+}
+
+/// What can go wrong when mapping the port.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("IO error: {0}")]
+    Io(#[from] IoError),
+    #[error("no gateway found")]
+    NoGateway,
+    #[error("gateway responded with result code {0}")]
+    ResultCode(u16),
+    #[error("malformed response from gateway: {0}")]
+    MalformedResponse(String),
+    #[error("gateway does not offer a WAN connection service")]
+    NoWanConnectionService,
+}
+
+/// Something that can map a local TCP port on a gateway.
+pub trait PortMapper: Send {
+    /// Maps the local `port` to the same external port for `lease`, returns the external address.
+    fn map_port(&mut self, port: u16, lease: Duration) -> Result<SocketAddr, Error>;
+}
+
+/// Client of the NAT-PMP protocol (RFC 6886).
+pub struct NatPmp {
+    gateway: SocketAddr,
+}
+
+impl NatPmp {
+    pub fn new(gateway: SocketAddr) -> Self {
+        NatPmp { gateway }
+    }
+
+    /// Uses the default gateway of the machine.
+    pub fn with_default_gateway() -> Result<Self, Error> {
+        let gateway = default_gateway().ok_or(Error::NoGateway)?;
+        Ok(Self::new(SocketAddr::new(gateway.into(), NAT_PMP_PORT)))
+    }
+
+    fn request(&self, request: &[u8], response_len: usize) -> Result<Vec<u8>, Error> {
+        let socket = UdpSocket::bind(unspecified_address(&self.gateway))?;
+        socket.connect(self.gateway)?;
+        let mut timeout = NAT_PMP_INITIAL_TIMEOUT;
+        let mut buffer = [0; 16];
+        for _ in 0..NAT_PMP_ATTEMPTS {
+            socket.send(request)?;
+            socket.set_read_timeout(Some(timeout))?;
+            match socket.recv(&mut buffer) {
+                Ok(len) if len >= response_len => {
+                    let response = buffer[..response_len].to_vec();
+                    if response[1] != request[1] + 128 {
+                        return Err(Error::MalformedResponse(format!(
+                            "unexpected opcode {}",
+                            response[1]
+                        )));
+                    }
+                    let result_code = u16::from_be_bytes([response[2], response[3]]);
+                    if result_code != 0 {
+                        return Err(Error::ResultCode(result_code));
+                    }
+                    return Ok(response);
+                }
+                Ok(len) => {
+                    return Err(Error::MalformedResponse(format!(
+                        "response too short: {len} bytes"
+                    )))
+                }
+                Err(e) => debug!(target: LOG_TARGET, "No NAT-PMP response yet: {}.", e),
+            }
+            timeout *= 2;
+        }
+        Err(Error::NoGateway)
+    }
+
+    fn external_ip(&self) -> Result<Ipv4Addr, Error> {
+        let response = self.request(&[0, 0], 12)?;
+        Ok(Ipv4Addr::new(
+            response[8],
+            response[9],
+            response[10],
+            response[11],
+        ))
+    }
+}
+
+impl PortMapper for NatPmp {
+    fn map_port(&mut self, port: u16, lease: Duration) -> Result<SocketAddr, Error> {
+        let ip = self.external_ip()?;
+        let mut request = vec![0, 2, 0, 0];
+        request.extend_from_slice(&port.to_be_bytes());
+        request.extend_from_slice(&port.to_be_bytes());
+        request.extend_from_slice(&(lease.as_secs() as u32).to_be_bytes());
+        let response = self.request(&request, 16)?;
+        let external_port = u16::from_be_bytes([response[10], response[11]]);
+        Ok(SocketAddr::new(ip.into(), external_port))
+    }
+}
+
+/// Client of the UPnP Internet Gateway Device protocol.
+pub struct UpnpIgd {
+    control_url: HttpUrl,
+    service_type: String,
+    local_ip: IpAddr,
+}
+
+impl UpnpIgd {
+    /// Finds the gateway using SSDP.
+    pub fn discover() -> Result<Self, Error> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_read_timeout(Some(SSDP_TIMEOUT))?;
+        let search = format!(
+            "M-SEARCH * HTTP/1.1\r\nHOST: {SSDP_ADDRESS}\r\nMAN: \"ssdp:discover\"\r\nMX: 2\r\nST: urn:schemas-upnp-org:device:InternetGatewayDevice:1\r\n\r\n"
+        );
+        socket.send_to(search.as_bytes(), SSDP_ADDRESS)?;
+        let mut buffer = [0; 2048];
+        let (len, _) = socket
+            .recv_from(&mut buffer)
+            .map_err(|_| Error::NoGateway)?;
+        let response = String::from_utf8_lossy(&buffer[..len]);
+        let location = response
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("location")
+                    .then(|| value.trim().to_string())
+            })
+            .ok_or_else(|| Error::MalformedResponse("no location in SSDP response".into()))?;
+        Self::from_description_url(&location)
+    }
+
+    /// Uses the gateway with the device description at the given URL, skipping discovery.
+    pub fn from_description_url(description_url: &str) -> Result<Self, Error> {
+        let description_url = HttpUrl::parse(description_url)?;
+        let description = description_url.request("GET", &[], "")?;
+        let (service_type, control_path) = WAN_CONNECTION_SERVICES
+            .iter()
+            .find_map(|service_type| {
+                let service = element_containing(&description, "service", service_type)?;
+                let control_path = element_text(service, "controlURL")?;
+                Some((service_type.to_string(), control_path.to_string()))
+            })
+            .ok_or(Error::NoWanConnectionService)?;
+        let control_url = description_url.join(&control_path);
+        let local_ip = {
+            let socket = UdpSocket::bind(unspecified_address(&control_url.address))?;
+            socket.connect(control_url.address)?;
+            socket.local_addr()?.ip()
+        };
+        Ok(UpnpIgd {
+            control_url,
+            service_type,
+            local_ip,
+        })
+    }
+
+    fn soap(&self, action: &str, arguments: &[(&str, String)]) -> Result<String, Error> {
+        let arguments: String = arguments
+            .iter()
+            .map(|(name, value)| format!("<{name}>{value}</{name}>"))
+            .collect();
+        let body = format!(
+            "<?xml version=\"1.0\"?><s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body><u:{action} xmlns:u=\"{}\">{arguments}</u:{action}></s:Body></s:Envelope>",
+            self.service_type
+        );
+        let soap_action = format!("\"{}#{action}\"", self.service_type);
+        self.control_url.request(
+            "POST",
+            &[
+                ("Content-Type", "text/xml; charset=\"utf-8\""),
+                ("SOAPAction", &soap_action),
+            ],
+            &body,
+        )
+    }
+
+    fn external_ip(&self) -> Result<IpAddr, Error> {
+        let response = self.soap("GetExternalIPAddress", &[])?;
+        element_text(&response, "NewExternalIPAddress")
+            .and_then(|ip| ip.trim().parse().ok())
+            .ok_or_else(|| Error::MalformedResponse("no external IP address".into()))
+    }
+}
+
+impl PortMapper for UpnpIgd {
+    fn map_port(&mut self, port: u16, lease: Duration) -> Result<SocketAddr, Error> {
+        self.soap(
+            "AddPortMapping",
+            &[
+                ("NewRemoteHost", String::new()),
+                ("NewExternalPort", port.to_string()),
+                ("NewProtocol", "TCP".to_string()),
+                ("NewInternalPort", port.to_string()),
+                ("NewInternalClient", self.local_ip.to_string()),
+                ("NewEnabled", "1".to_string()),
+                ("NewPortMappingDescription", MAPPING_DESCRIPTION.to_string()),
+                ("NewLeaseDuration", lease.as_secs().to_string()),
+            ],
+        )?;
+        Ok(SocketAddr::new(self.external_ip()?, port))
+    }
+}
+
+/// Configuration of the port mapping, built from the command line.
+pub struct NatConfig {
+    pub protocol: NatProtocol,
+    pub nat_pmp_gateway: Option<IpAddr>,
+    pub upnp_description_url: Option<String>,
+}
+
+impl NatConfig {
+    fn mapper(&self) -> Result<Box<dyn PortMapper>, Error> {
+        Ok(match self.protocol {
+            NatProtocol::NatPmp => Box::new(match self.nat_pmp_gateway {
+                Some(gateway) => NatPmp::new(SocketAddr::new(gateway, NAT_PMP_PORT)),
+                None => NatPmp::with_default_gateway()?,
+            }),
+            NatProtocol::Upnp => Box::new(match &self.upnp_description_url {
+                Some(url) => UpnpIgd::from_description_url(url)?,
+                None => UpnpIgd::discover()?,
+            }),
+        })
+    }
+}
+
+/// Maps the validator port on the gateway, returning the mapper for renewals and the external
+/// address under which the port is reachable.
+pub fn map_validator_port(
+    config: &NatConfig,
+    port: u16,
+) -> Result<(Box<dyn PortMapper>, SocketAddr), Error> {
+    let mut mapper = config.mapper()?;
+    let external_address = mapper.map_port(port, LEASE_DURATION)?;
+    info!(
+        target: LOG_TARGET,
+        "Mapped validator port {} to external address {} using {:?}.",
+        port,
+        external_address,
+        config.protocol
+    );
+    Ok((mapper, external_address))
+}
+
+/// Renews the mapping every half of the lease. The external address is a part of the signed
+/// identity of the node, so if it changes we can only warn that a restart is needed.
+///
+/// The mappers use blocking sockets, so this is meant to run on its own thread.
+pub fn renew_port_mapping(
+    mut mapper: Box<dyn PortMapper>,
+    port: u16,
+    external_address: SocketAddr,
+) {
+    loop {
+        thread::sleep(LEASE_DURATION / 2);
+        match mapper.map_port(port, LEASE_DURATION) {
+            Ok(address) if address == external_address => {
+                debug!(target: LOG_TARGET, "Renewed mapping of validator port {}.", port)
+            }
+            Ok(address) => warn!(
+                target: LOG_TARGET,
+                "External address changed from {} to {}, restart the node to advertise the new one.",
+                external_address,
+                address
+            ),
+            Err(e) => warn!(
+                target: LOG_TARGET,
+                "Failed to renew mapping of validator port {}: {}.", port, e
+            ),
+        }
+    }
+}
+
+fn unspecified_address(remote: &SocketAddr) -> SocketAddr {
+    match remote {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (std::net::Ipv6Addr::UNSPECIFIED, 0).into(),
+    }
+}
+
+/// Reads the default IPv4 gateway from the kernel routing table.
+fn default_gateway() -> Option<Ipv4Addr> {
+    fs::read_to_string("/proc/net/route")
+        .ok()?
+        .lines()
+        .skip(1)
+        .find_map(|line| {
+            let fields: Vec<_> = line.split_whitespace().collect();
+            match fields.get(1..3) {
+                Some(["00000000", gateway]) => u32::from_str_radix(gateway, 16)
+                    .ok()
+                    .map(|gateway| Ipv4Addr::from(gateway.to_le_bytes())),
+                _ => None,
+            }
+        })
+}
+
+/// Returns the first `tag` element containing `needle`.
+fn element_containing<'a>(document: &'a str, tag: &str, needle: &str) -> Option<&'a str> {
+    let open = format!("<{tag}>");
+    let close = format!("</{tag}>");
+    document
+        .split(open.as_str())
+        .skip(1)
+        .filter_map(|rest| rest.split_once(close.as_str()).map(|(element, _)| element))
+        .find(|element| element.contains(needle))
+}
+
+/// Returns the text of the first `tag` element, ignoring namespace prefixes.
+fn element_text<'a>(document: &'a str, tag: &str) -> Option<&'a str> {
+    let start = document
+        .match_indices(tag)
+        .map(|(index, _)| index)
+        .find(|index| document[..*index].ends_with('<') || document[..*index].ends_with(':'))?;
+    let rest = &document[start + tag.len()..];
+    let rest = &rest[rest.find('>')? + 1..];
+    Some(&rest[..rest.find('<')?])
+}
+
+#[derive(Debug, Clone)]
+struct HttpUrl {
+    host: String,
+    address: SocketAddr,
+    path: String,
+}
+
+impl HttpUrl {
+    fn parse(url: &str) -> Result<Self, Error> {
+        let malformed = || Error::MalformedResponse(format!("unsupported URL {url}"));
+        let rest = url.strip_prefix("http://").ok_or_else(malformed)?;
+        let (host, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let address = match host.contains(':') {
+            true => host.to_socket_addrs(),
+            false => (host, 80).to_socket_addrs(),
+        }?
+        .next()
+        .ok_or_else(malformed)?;
+        Ok(HttpUrl {
+            host: host.to_string(),
+            address,
+            path: path.to_string(),
+        })
+    }
+
+    fn join(&self, path: &str) -> Self {
+        match HttpUrl::parse(path) {
+            Ok(url) => url,
+            Err(_) => HttpUrl {
+                path: match path.starts_with('/') {
+                    true => path.to_string(),
+                    false => format!("/{path}"),
+                },
+                ..self.clone()
+            },
+        }
+    }
+
+    fn request(&self, method: &str, headers: &[(&str, &str)], body: &str) -> Result<String, Error> {
+        let mut stream = TcpStream::connect_timeout(&self.address, HTTP_TIMEOUT)?;
+        stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+        let headers: String = headers
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}\r\n"))
+            .collect();
+        // HTTP/1.0 does not allow chunked responses, which many gateways use otherwise.
+        let request = format!(
+            "{method} {} HTTP/1.0\r\nHost: {}\r\n{headers}Content-Length: {}\r\n\r\n{body}",
+            self.path,
+            self.host,
+            body.len()
+        );
+        stream.write_all(request.as_bytes())?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        response_body(&response)
+    }
+}
+
+/// Returns the body of a successful HTTP response. Some gateways answer with a chunked body even
+/// to HTTP/1.0 requests, so it is decoded if needed.
+fn response_body(response: &[u8]) -> Result<String, Error> {
+    let head_len = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| Error::MalformedResponse("no HTTP headers".into()))?;
+    let head = String::from_utf8_lossy(&response[..head_len]);
+    let body = &response[head_len + 4..];
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .ok_or_else(|| Error::MalformedResponse("no HTTP status".into()))?;
+    if status != "200" {
+        return Err(Error::MalformedResponse(format!("HTTP status {status}")));
+    }
+    let chunked = head.lines().skip(1).any(|line| {
+        line.split_once(':').map_or(false, |(name, value)| {
+            name.trim().eq_ignore_ascii_case("transfer-encoding")
+                && value.trim().eq_ignore_ascii_case("chunked")
+        })
+    });
+    let body = match chunked {
+        true => decode_chunked(body)?,
+        false => body.to_vec(),
+    };
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Decodes a body in the chunked transfer coding, ignoring chunk extensions and trailers.
+fn decode_chunked(mut body: &[u8]) -> Result<Vec<u8>, Error> {
+    let malformed = || Error::MalformedResponse("malformed chunked body".into());
+    let mut decoded = Vec::new();
+    loop {
+        let size_len = body
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .ok_or_else(malformed)?;
+        let size_line = String::from_utf8_lossy(&body[..size_len]);
+        let size = size_line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16).map_err(|_| malformed())?;
+        if size == 0 {
+            return Ok(decoded);
+        }
+        let rest = &body[size_len + 2..];
+        decoded.extend_from_slice(rest.get(..size).ok_or_else(malformed)?);
+        body = rest
+            .get(size..)
+            .and_then(|rest| rest.strip_prefix(b"\r\n"))
+            .ok_or_else(malformed)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::{Ipv4Addr, SocketAddr, TcpListener, UdpSocket},
+        thread,
+        time::Duration,
+    };
+
+    use super::{decode_chunked, NatPmp, PortMapper, UpnpIgd};
+
+    const EXTERNAL_IP: [u8; 4] = [203, 0, 113, 7];
+
+    fn nat_pmp_responder(result_code: u16) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").expect("should bind");
+        let address = socket.local_addr().expect("should have an address");
+        thread::spawn(move || {
+            let mut buffer = [0; 16];
+            while let Ok((_, peer)) = socket.recv_from(&mut buffer) {
+                let mut response = vec![0, buffer[1] + 128];
+                response.extend_from_slice(&result_code.to_be_bytes());
+                response.extend_from_slice(&1000u32.to_be_bytes());
+                match buffer[1] {
+                    0 => response.extend_from_slice(&EXTERNAL_IP),
+                    _ => response.extend_from_slice(&buffer[4..12]),
+                }
+                socket.send_to(&response, peer).expect("should respond");
+            }
+        });
+        address
+    }
+
+    // Sends the body in chunks of a few bytes, as some gateways do.
+    fn chunked(body: &str) -> String {
+        let mut chunked: String = body
+            .as_bytes()
+            .chunks(16)
+            .map(|chunk| {
+                format!(
+                    "{:x}\r\n{}\r\n",
+                    chunk.len(),
+                    String::from_utf8_lossy(chunk)
+                )
+            })
+            .collect();
+        chunked.push_str("0\r\n\r\n");
+        chunked
+    }
+
+    fn igd_responder(chunked_responses: bool) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("should bind");
+        let address = listener.local_addr().expect("should have an address");
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.expect("should accept");
+                let mut request = String::new();
+                let mut buffer = [0; 4096];
+                let complete = |request: &str| {
+                    request.ends_with("</s:Envelope>")
+                        || (request.starts_with("GET") && request.ends_with("\r\n\r\n"))
+                };
+                while !complete(&request) {
+                    let len = stream.read(&mut buffer).expect("should read");
+                    request.push_str(&String::from_utf8_lossy(&buffer[..len]));
+                }
+                let body = if request.starts_with("GET /description.xml") {
+                    "<root><device><serviceList><service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType><controlURL>/control</controlURL></service></serviceList></device></root>".to_string()
+                } else if request.contains("GetExternalIPAddress") {
+                    format!("<s:Envelope><s:Body><u:GetExternalIPAddressResponse><NewExternalIPAddress>{}</NewExternalIPAddress></u:GetExternalIPAddressResponse></s:Body></s:Envelope>", Ipv4Addr::from(EXTERNAL_IP))
+                } else if request.contains("AddPortMapping")
+                    && request.contains("<NewExternalPort>30343</NewExternalPort>")
+                {
+                    "<s:Envelope><s:Body><u:AddPortMappingResponse/></s:Body></s:Envelope>"
+                        .to_string()
+                } else {
+                    write!(stream, "HTTP/1.1 500 Internal Server Error\r\n\r\n")
+                        .expect("should write");
+                    continue;
+                };
+                assert!(request.contains(" HTTP/1.0\r\n"));
+                match chunked_responses {
+                    true => write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{}",
+                        chunked(&body)
+                    ),
+                    false => write!(
+                        stream,
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{body}",
+                        body.len()
+                    ),
+                }
+                .expect("should write");
+            }
+        });
+        format!("http://{address}/description.xml")
+    }
+
+    #[test]
+    fn nat_pmp_maps_port() {
+        let mut mapper = NatPmp::new(nat_pmp_responder(0));
+        assert_eq!(
+            mapper
+                .map_port(30343, Duration::from_secs(60))
+                .expect("mapping should succeed"),
+            SocketAddr::from((EXTERNAL_IP, 30343))
+        );
+    }
+
+    #[test]
+    fn nat_pmp_reports_failure() {
+        let mut mapper = NatPmp::new(nat_pmp_responder(2));
+        assert!(mapper.map_port(30343, Duration::from_secs(60)).is_err());
+    }
+
+    fn upnp_maps_port(chunked_responses: bool) {
+        let mut mapper = UpnpIgd::from_description_url(&igd_responder(chunked_responses))
+            .expect("should find the WAN connection service");
+        assert_eq!(
+            mapper
+                .map_port(30343, Duration::from_secs(60))
+                .expect("mapping should succeed"),
+            SocketAddr::from((EXTERNAL_IP, 30343))
+        );
+        assert!(mapper.map_port(30344, Duration::from_secs(60)).is_err());
+    }
+
+    #[test]
+    fn upnp_maps_port_with_plain_responses() {
+        upnp_maps_port(false);
+    }
+
+    #[test]
+    fn upnp_maps_port_with_chunked_responses() {
+        upnp_maps_port(true);
+    }
+
+    #[test]
+    fn decodes_chunked_body_with_extensions() {
+        assert_eq!(
+            decode_chunked(b"5;name=value\r\nhello\r\n1\r\n!\r\n0\r\nTrailer: x\r\n\r\n")
+                .expect("body is well formed"),
+            b"hello!"
+        );
+        assert!(decode_chunked(b"5\r\nhel").is_err());
+    }
+}
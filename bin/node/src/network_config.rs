@@ -0,0 +1,615 @@
+//! A declarative description of a network, from which `bootstrap-chain --from-config` generates
+//! the chain specification. The description is read from a TOML file, or from a JSON one if the
+//! file has the `.json` extension. Amounts of tokens are given in whole tokens.
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use aleph_runtime::AccountId;
+use serde::{Deserialize, Serialize};
+use sp_application_crypto::Ss58Codec;
+use sp_core::{sr25519, Pair};
+
+use crate::{
+    aleph_primitives::{
+        staking::MIN_VALIDATOR_BOND, AuraId, AuthorityId as AlephId, BanConfig, BlockNumber,
+        BlsPublicKey, CommitteeSeats, Version as FinalityVersion, DEFAULT_SESSION_PERIOD,
+        LEGACY_FINALITY_VERSION, TOKEN_DECIMALS,
+    },
+    chain_spec::{
+        chain_type_from_str, generate_chain_spec_config, AuthorityKeys, ChainSpec, GenesisParams,
+        GenesisValidator, NominationPoolsParams, SerializablePeerId, CHAINTYPE_LIVE,
+        DEFAULT_CHAIN_ID, DEFAULT_SUDO_ACCOUNT,
+    },
+};
+
+/// The file under the base path to which `bootstrap-chain --from-config` writes the contracts to
+/// deploy.
+pub const CONTRACT_DEPLOYMENTS_FILE: &str = "contracts.json";
+
+// Balance of the validators and the sudo account that are not endowed explicitly.
+const DEFAULT_ENDOWMENT: u64 = 1_000_000;
+
+fn default_chain_id() -> String {
+    DEFAULT_CHAIN_ID.to_string()
+}
+
+fn default_chain_type() -> String {
+    CHAINTYPE_LIVE.to_string()
+}
+
+fn default_chain_name() -> String {
+    "Aleph Zero Development".to_string()
+}
+
+fn default_token_symbol() -> String {
+    "DZERO".to_string()
+}
+
+fn default_sudo() -> String {
+    DEFAULT_SUDO_ACCOUNT.to_string()
+}
+
+fn default_finality_version() -> FinalityVersion {
+    LEGACY_FINALITY_VERSION
+}
+
+fn default_endowment() -> u64 {
+    DEFAULT_ENDOWMENT
+}
+
+fn default_reserved() -> bool {
+    true
+}
+
+fn default_constructor() -> String {
+    "new".to_string()
+}
+
+/// Public keys of a validator whose keystore is not generated by `bootstrap-chain`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidatorKeys {
+    pub aura: AuraId,
+    pub aleph: AlephId,
+    /// The BLS key. Without it the validator gets a placeholder, until it sets its session keys.
+    pub bls: Option<BlsPublicKey>,
+    pub peer_id: SerializablePeerId,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidatorConfig {
+    /// The account of the validator, as an SS58 address or a secret URI like `//Alice`.
+    pub account: String,
+    /// Whether the validator is reserved, true by default.
+    #[serde(default = "default_reserved")]
+    pub reserved: bool,
+    /// The bond of the validator, the minimal validator bond by default.
+    pub stake: Option<u64>,
+    /// Keys of a validator run elsewhere. Keystores of validators without them are generated
+    /// under the base path.
+    pub keys: Option<ValidatorKeys>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EndowmentConfig {
+    pub account: String,
+    pub balance: u64,
+}
+
+/// A vesting schedule of an endowed account, which unlocks everything but `liquid` linearly over
+/// `length` blocks starting at block `begin`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VestingConfig {
+    pub account: String,
+    pub begin: BlockNumber,
+    pub length: BlockNumber,
+    pub liquid: u64,
+}
+
+/// A contract to instantiate once the chain is running, since contracts cannot be instantiated in
+/// the genesis. Paths are relative to the description file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContractConfig {
+    /// The `.wasm` artifact of the contract.
+    pub wasm: PathBuf,
+    /// The `.json` metadata of the contract.
+    pub metadata: PathBuf,
+    /// The constructor to call, `new` by default.
+    #[serde(default = "default_constructor")]
+    pub constructor: String,
+    /// The constructor arguments, encoded as strings.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// The secret URI of the endowed account instantiating the contract.
+    pub deployer: String,
+    /// Tokens transferred from the deployer to the contract.
+    #[serde(default)]
+    pub balance: u64,
+}
+
+/// A contract deployment, as written by `bootstrap-chain --from-config` for
+/// `scripts/deploy_contracts.sh`, which instantiates the contracts with `cliain`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContractDeployment {
+    pub wasm_path: PathBuf,
+    pub metadata_path: PathBuf,
+    pub constructor: String,
+    pub args: Vec<String>,
+    pub deployer_seed: String,
+    /// In the smallest units, as a string, so that JSON tools do not round it.
+    pub balance: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NominationPoolsConfig {
+    #[serde(default)]
+    pub min_join_bond: u64,
+    #[serde(default)]
+    pub min_create_bond: u64,
+    pub max_pools: Option<u32>,
+    pub max_members_per_pool: Option<u32>,
+    pub max_members: Option<u32>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkConfig {
+    #[serde(default = "default_chain_id")]
+    pub chain_id: String,
+    /// One of "dev", "local" and "live".
+    #[serde(default = "default_chain_type")]
+    pub chain_type: String,
+    #[serde(default = "default_chain_name")]
+    pub chain_name: String,
+    #[serde(default = "default_token_symbol")]
+    pub token_symbol: String,
+    /// The sudo account, as an SS58 address or a secret URI.
+    #[serde(default = "default_sudo")]
+    pub sudo: String,
+    #[serde(default = "default_finality_version")]
+    pub finality_version: FinalityVersion,
+    /// The genesis session period is a default of the runtime, so it can only be checked against
+    /// the one of the runtime of this binary. It can be changed once the chain is running.
+    pub session_period: Option<u32>,
+    #[serde(default)]
+    pub committee_seats: CommitteeSeats,
+    #[serde(default)]
+    pub ban_config: BanConfig,
+    pub validators: Vec<ValidatorConfig>,
+    /// Balance of the validators and the sudo account that are not endowed explicitly.
+    #[serde(default = "default_endowment")]
+    pub default_endowment: u64,
+    #[serde(default)]
+    pub endowments: Vec<EndowmentConfig>,
+    #[serde(default)]
+    pub vesting: Vec<VestingConfig>,
+    /// Limits of the nomination pools, the runtime defaults if not provided.
+    pub nomination_pools: Option<NominationPoolsConfig>,
+    /// Contracts to instantiate once the chain is running.
+    #[serde(default)]
+    pub contracts: Vec<ContractConfig>,
+}
+
+fn tokens(amount: u64) -> u128 {
+    amount as u128 * 10u128.pow(TOKEN_DECIMALS)
+}
+
+/// Parses an account given as an SS58 address or as a secret URI like `//Alice`.
+fn parse_account(s: &str) -> Result<AccountId, String> {
+    if let Ok(account) = AccountId::from_ss58check(s) {
+        return Ok(account);
+    }
+    sr25519::Pair::from_string(s, None)
+        .map(|pair| AccountId::from(pair.public()))
+        .map_err(|_| format!("{s} is neither an SS58 address nor a secret URI"))
+}
+
+impl NetworkConfig {
+    /// Reads the description from a TOML file, or a JSON one if the file has the `.json`
+    /// extension.
+    pub fn from_file(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read the network description {path:?}: {e}"))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => serde_json::from_str(&content).map_err(|e| e.to_string()),
+            _ => toml::from_str(&content).map_err(|e| e.to_string()),
+        }
+        .map_err(|e| format!("Invalid network description {path:?}: {e}"))
+    }
+
+    /// Checks the description and turns it into the genesis parameters. Keys of the local
+    /// validators are provided by `local_keys`.
+    pub fn genesis_params(
+        &self,
+        mut local_keys: impl FnMut(AccountId) -> AuthorityKeys,
+    ) -> Result<GenesisParams, String> {
+        self.check_static()?;
+
+        let mut validators = Vec::new();
+        let mut validator_accounts = HashSet::new();
+        for validator in &self.validators {
+            let account_id = parse_account(&validator.account)?;
+            if !validator_accounts.insert(account_id.clone()) {
+                return Err(format!("Validator {account_id} is listed more than once"));
+            }
+            let stake = validator.stake.map(tokens).unwrap_or(MIN_VALIDATOR_BOND);
+            if stake < MIN_VALIDATOR_BOND {
+                return Err(format!(
+                    "Validator {account_id} stakes less than the minimal bond of {MIN_VALIDATOR_BOND}"
+                ));
+            }
+            let keys = match &validator.keys {
+                Some(keys) => AuthorityKeys {
+                    bls_key: keys
+                        .bls
+                        .unwrap_or_else(|| BlsPublicKey::placeholder(account_id.as_ref())),
+                    account_id,
+                    aura_key: keys.aura.clone(),
+                    aleph_key: keys.aleph.clone(),
+                    peer_id: keys.peer_id.clone(),
+                },
+                None => local_keys(account_id),
+            };
+            validators.push(GenesisValidator {
+                keys,
+                reserved: validator.reserved,
+                stake,
+            });
+        }
+        self.check_committee_seats(&validators)?;
+
+        let sudo_account = parse_account(&self.sudo)?;
+        let mut balances = Vec::new();
+        let mut endowed = HashSet::new();
+        for endowment in &self.endowments {
+            let account_id = parse_account(&endowment.account)?;
+            if !endowed.insert(account_id.clone()) {
+                return Err(format!("Account {account_id} is endowed more than once"));
+            }
+            balances.push((account_id, tokens(endowment.balance)));
+        }
+        let implicitly_endowed: Vec<_> = validators
+            .iter()
+            .map(|validator| validator.keys.account_id.clone())
+            .chain([sudo_account.clone()])
+            .collect();
+        for account_id in implicitly_endowed {
+            if endowed.insert(account_id.clone()) {
+                balances.push((account_id, tokens(self.default_endowment)));
+            }
+        }
+        for validator in &validators {
+            let account_id = &validator.keys.account_id;
+            if balance_of(&balances, account_id) < validator.stake {
+                return Err(format!(
+                    "Validator {account_id} is endowed with less than its stake"
+                ));
+            }
+        }
+
+        let vesting = self
+            .vesting
+            .iter()
+            .map(|schedule| {
+                let account_id = parse_account(&schedule.account)?;
+                if !endowed.contains(&account_id) {
+                    return Err(format!("Vested account {account_id} is not endowed"));
+                }
+                Ok((
+                    account_id,
+                    schedule.begin,
+                    schedule.length,
+                    tokens(schedule.liquid),
+                ))
+            })
+            .collect::<Result<_, String>>()?;
+
+        for contract in &self.contracts {
+            let deployer = sr25519::Pair::from_string(&contract.deployer, None)
+                .map(|pair| AccountId::from(pair.public()))
+                .map_err(|_| {
+                    format!(
+                        "Deployer {} of {:?} is not a secret URI",
+                        contract.deployer, contract.wasm
+                    )
+                })?;
+            if balance_of(&balances, &deployer) <= tokens(contract.balance) {
+                return Err(format!(
+                    "Deployer {deployer} of {:?} cannot afford the contract balance",
+                    contract.wasm
+                ));
+            }
+        }
+
+        Ok(GenesisParams {
+            validators,
+            sudo_account,
+            balances,
+            vesting,
+            committee_seats: self.committee_seats,
+            ban_config: self.ban_config.clone(),
+            finality_version: self.finality_version,
+            nomination_pools: self
+                .nomination_pools
+                .as_ref()
+                .map(|pools| NominationPoolsParams {
+                    min_join_bond: tokens(pools.min_join_bond),
+                    min_create_bond: tokens(pools.min_create_bond),
+                    max_pools: pools.max_pools,
+                    max_members_per_pool: pools.max_members_per_pool,
+                    max_members: pools.max_members,
+                }),
+        })
+    }
+
+    /// Generates the chain specification described. Keys of the local validators are provided by
+    /// `local_keys`.
+    pub fn chain_spec(
+        &self,
+        local_keys: impl FnMut(AccountId) -> AuthorityKeys,
+    ) -> Result<ChainSpec, String> {
+        let chain_type = chain_type_from_str(&self.chain_type).ok_or_else(|| {
+            format!(
+                "Wrong chain type {} Possible values: dev local live",
+                self.chain_type
+            )
+        })?;
+        let genesis_params = self.genesis_params(local_keys)?;
+        generate_chain_spec_config(
+            &self.chain_name,
+            &self.chain_id,
+            chain_type,
+            &self.token_symbol,
+            genesis_params,
+        )
+    }
+
+    /// The contracts to instantiate once the chain is running, with paths resolved against
+    /// `base_dir`, the directory of the description file.
+    pub fn contract_deployments(&self, base_dir: &Path) -> Result<Vec<ContractDeployment>, String> {
+        self.contracts
+            .iter()
+            .map(|contract| {
+                let wasm_path = base_dir.join(&contract.wasm);
+                let metadata_path = base_dir.join(&contract.metadata);
+                for path in [&wasm_path, &metadata_path] {
+                    if !path.is_file() {
+                        return Err(format!("Contract artifact {path:?} does not exist"));
+                    }
+                }
+                Ok(ContractDeployment {
+                    wasm_path,
+                    metadata_path,
+                    constructor: contract.constructor.clone(),
+                    args: contract.args.clone(),
+                    deployer_seed: contract.deployer.clone(),
+                    balance: tokens(contract.balance).to_string(),
+                })
+            })
+            .collect()
+    }
+
+    /// Checks the parts of the description that do not depend on the keys.
+    fn check_static(&self) -> Result<(), String> {
+        if self.validators.is_empty() {
+            return Err("At least one validator is required".to_string());
+        }
+        if let Some(session_period) = self.session_period {
+            if session_period != DEFAULT_SESSION_PERIOD {
+                return Err(format!(
+                    "Session period of {session_period} requested, but the runtime of this binary starts with {DEFAULT_SESSION_PERIOD}, schedule the change once the chain is running"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The same conditions as the elections pallet puts on changing the validators.
+    fn check_committee_seats(&self, validators: &[GenesisValidator]) -> Result<(), String> {
+        let CommitteeSeats {
+            reserved_seats,
+            non_reserved_seats,
+            non_reserved_finality_seats,
+        } = self.committee_seats;
+        let reserved = validators
+            .iter()
+            .filter(|validator| validator.reserved)
+            .count() as u32;
+        let non_reserved = validators.len() as u32 - reserved;
+        if non_reserved_finality_seats > non_reserved_seats {
+            return Err(
+                "There are more non-reserved finality seats than non-reserved seats".to_string(),
+            );
+        }
+        if reserved_seats > reserved {
+            return Err(format!(
+                "There are {reserved_seats} reserved seats, but only {reserved} reserved validators"
+            ));
+        }
+        if non_reserved_seats > non_reserved {
+            return Err(format!(
+                "There are {non_reserved_seats} non-reserved seats, but only {non_reserved} non-reserved validators"
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn balance_of(balances: &[(AccountId, u128)], account_id: &AccountId) -> u128 {
+    balances
+        .iter()
+        .find(|(account, _)| account == account_id)
+        .map(|(_, balance)| *balance)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use libp2p::PeerId;
+    use sp_core::{ed25519, sr25519, Pair};
+
+    use super::{parse_account, tokens, ContractDeployment, NetworkConfig};
+    use crate::{
+        aleph_primitives::{staking::MIN_VALIDATOR_BOND, BlsSecretKey},
+        chain_spec::{AuthorityKeys, SerializablePeerId},
+    };
+
+    const DESCRIPTION: &str = r#"
+        chain_id = "a0tnet1"
+        chain_type = "local"
+        sudo = "//Alice"
+
+        [committee_seats]
+        reserved_seats = 2
+        non_reserved_seats = 1
+        non_reserved_finality_seats = 1
+
+        [[validators]]
+        account = "//1"
+
+        [[validators]]
+        account = "//2"
+        stake = 50000
+
+        [[validators]]
+        account = "//3"
+        reserved = false
+
+        [[endowments]]
+        account = "//Bob"
+        balance = 1000
+
+        [[vesting]]
+        account = "//Bob"
+        begin = 0
+        length = 100
+        liquid = 100
+
+        [nomination_pools]
+        min_join_bond = 1
+        max_pools = 10
+    "#;
+
+    fn local_keys(account_id: aleph_runtime::AccountId) -> AuthorityKeys {
+        AuthorityKeys {
+            account_id,
+            aura_key: sr25519::Pair::generate().0.public().into(),
+            aleph_key: ed25519::Pair::generate().0.public().into(),
+            bls_key: BlsSecretKey::from_seed(&ed25519::Pair::generate().1).public(),
+            peer_id: SerializablePeerId::new(PeerId::random()),
+        }
+    }
+
+    fn parse(description: &str) -> NetworkConfig {
+        toml::from_str(description).expect("description should parse")
+    }
+
+    #[test]
+    fn builds_genesis_params_from_description() {
+        let config = parse(DESCRIPTION);
+        let params = config.genesis_params(local_keys).unwrap();
+
+        let reserved: Vec<_> = params.validators.iter().map(|v| v.reserved).collect();
+        assert_eq!(reserved, [true, true, false]);
+        assert_eq!(params.validators[0].stake, MIN_VALIDATOR_BOND);
+        assert_eq!(params.validators[1].stake, tokens(50_000));
+        assert_eq!(params.sudo_account, parse_account("//Alice").unwrap());
+        // Bob, the validators and the sudo account.
+        assert_eq!(params.balances.len(), 5);
+        assert_eq!(params.vesting.len(), 1);
+        assert_eq!(params.committee_seats.non_reserved_seats, 1);
+        let pools = params.nomination_pools.unwrap();
+        assert_eq!(pools.min_join_bond, tokens(1));
+        assert_eq!(pools.max_pools, Some(10));
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        let description = format!("{DESCRIPTION}\nfaucet = \"//Faucet\"");
+        assert!(toml::from_str::<NetworkConfig>(&description).is_err());
+    }
+
+    #[test]
+    fn rejects_too_many_seats() {
+        let description = DESCRIPTION.replace("non_reserved_seats = 1", "non_reserved_seats = 2");
+        assert!(parse(&description).genesis_params(local_keys).is_err());
+    }
+
+    #[test]
+    fn rejects_duplicated_validators() {
+        let description = DESCRIPTION.replace("\"//2\"", "\"//1\"");
+        assert!(parse(&description).genesis_params(local_keys).is_err());
+    }
+
+    #[test]
+    fn rejects_vesting_of_not_endowed_accounts() {
+        let description = DESCRIPTION.replace(
+            "account = \"//Bob\"\n        begin",
+            "account = \"//Dave\"\n        begin",
+        );
+        assert!(parse(&description).genesis_params(local_keys).is_err());
+    }
+
+    #[test]
+    fn resolves_contract_deployments() {
+        let dir = env::temp_dir().join("aleph-network-config-contracts");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("flipper.wasm"), []).unwrap();
+        fs::write(dir.join("flipper.json"), []).unwrap();
+        let description = format!(
+            "{DESCRIPTION}\n[[contracts]]\nwasm = \"flipper.wasm\"\nmetadata = \"flipper.json\"\nargs = [\"true\"]\ndeployer = \"//Bob\"\nbalance = 10"
+        );
+        let config = parse(&description);
+        assert!(config.genesis_params(local_keys).is_ok());
+
+        let deployments = config.contract_deployments(&dir).unwrap();
+        assert_eq!(
+            deployments,
+            [ContractDeployment {
+                wasm_path: dir.join("flipper.wasm"),
+                metadata_path: dir.join("flipper.json"),
+                constructor: "new".to_string(),
+                args: vec!["true".to_string()],
+                deployer_seed: "//Bob".to_string(),
+                balance: tokens(10).to_string(),
+            }]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(config.contract_deployments(&dir).is_err());
+    }
+
+    #[test]
+    fn rejects_contracts_of_unusable_deployers() {
+        let contract = "[[contracts]]\nwasm = \"flipper.wasm\"\nmetadata = \"flipper.json\"";
+        for deployer in [
+            // Not a secret URI, so it cannot sign the deployment.
+            "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+            // Not endowed.
+            "//Dave",
+        ] {
+            let description = format!("{DESCRIPTION}\n{contract}\ndeployer = \"{deployer}\"");
+            assert!(parse(&description).genesis_params(local_keys).is_err());
+        }
+    }
+
+    #[test]
+    fn parses_json_description() {
+        let config: NetworkConfig = serde_json::from_str(
+            r#"{"validators": [{"account": "//1"}, {"account": "//2", "reserved": false}]}"#,
+        )
+        .unwrap();
+        assert_eq!(config.validators.len(), 2);
+        assert!(!config.validators[1].reserved);
+    }
+}
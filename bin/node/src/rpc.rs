@@ -8,16 +8,24 @@
 use std::sync::Arc;
 
 use aleph_runtime::{opaque::Block, AccountId, Balance, Nonce};
-use finality_aleph::{Justification, JustificationTranslator, ValidatorAddressCache};
+use finality_aleph::{
+    FinalityParticipation, Justification, JustificationTranslator, ValidatorAddressCache,
+};
 use futures::channel::mpsc;
 use jsonrpsee::RpcModule;
-use sc_client_api::StorageProvider;
+use primitives::{AlephSessionApi, AuraId};
+use sc_client_api::{AuxStore, BlockBackend, BlockchainEvents, StorageProvider};
+use sc_rpc::SubscriptionTaskExecutor;
 pub use sc_rpc_api::DenyUnsafe;
 use sc_transaction_pool_api::TransactionPool;
 use sp_api::ProvideRuntimeApi;
 use sp_block_builder::BlockBuilder;
 use sp_blockchain::{Error as BlockChainError, HeaderBackend, HeaderMetadata};
 use sp_consensus::SyncOracle;
+use sp_consensus_aura::AuraApi;
+use sp_keystore::KeystorePtr;
+
+use crate::health::LastHealthReport;
 
 /// Full client dependencies.
 pub struct FullDeps<C, P, SO> {
@@ -31,24 +39,33 @@ pub struct FullDeps<C, P, SO> {
     pub justification_translator: JustificationTranslator,
     pub sync_oracle: SO,
     pub validator_address_cache: Option<ValidatorAddressCache>,
+    pub keystore: KeystorePtr,
+    pub last_health_report: LastHealthReport,
+    pub finality_participation: FinalityParticipation,
 }
 
 /// Instantiate all full RPC extensions.
 pub fn create_full<C, P, BE, SO>(
     deps: FullDeps<C, P, SO>,
+    subscription_executor: SubscriptionTaskExecutor,
 ) -> Result<RpcModule<()>, Box<dyn std::error::Error + Send + Sync>>
 where
     C: ProvideRuntimeApi<Block>
         + HeaderBackend<Block>
         + HeaderMetadata<Block, Error = BlockChainError>
         + StorageProvider<Block, BE>
+        + AuxStore
+        + BlockBackend<Block>
+        + BlockchainEvents<Block>
         + Send
         + Sync
         + 'static,
     BE: sc_client_api::Backend<Block> + 'static,
     C::Api: substrate_frame_rpc_system::AccountNonceApi<Block, AccountId, Nonce>
         + pallet_transaction_payment_rpc::TransactionPaymentRuntimeApi<Block, Balance>
-        + BlockBuilder<Block>,
+        + BlockBuilder<Block>
+        + AlephSessionApi<Block>
+        + AuraApi<Block, AuraId>,
     P: TransactionPool + 'static,
     SO: SyncOracle + Send + Sync + 'static,
 {
@@ -64,6 +81,9 @@ where
         justification_translator,
         sync_oracle,
         validator_address_cache,
+        keystore,
+        last_health_report,
+        finality_participation,
     } = deps;
 
     module.merge(System::new(client.clone(), pool, deny_unsafe).into_rpc())?;
@@ -78,6 +98,11 @@ where
             client,
             sync_oracle,
             validator_address_cache,
+            keystore,
+            deny_unsafe,
+            last_health_report,
+            finality_participation,
+            subscription_executor,
         )
         .into_rpc(),
     )?;
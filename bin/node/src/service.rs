@@ -3,21 +3,22 @@
 use std::{
     path::{Path, PathBuf},
     sync::Arc,
+    thread,
 };
 
 use aleph_runtime::{self, opaque::Block, RuntimeApi};
 use finality_aleph::{
-    run_validator_node, AlephBlockImport, AlephConfig, BlockImporter, Justification,
-    JustificationTranslator, MillisecsPerBlock, Protocol, ProtocolNaming, RateLimiterConfig,
-    RedirectingBlockImport, SessionPeriod, SubstrateChainStatus, SyncOracle, TimingBlockMetrics,
-    TracingBlockImport, ValidatorAddressCache,
+    run_validator_node, AlephBlockImport, AlephConfig, BlockImporter, EventLog,
+    FinalityParticipation, Justification, JustificationTranslator, MillisecsPerBlock, Protocol,
+    ProtocolNaming, RateLimiterConfig, RedirectingBlockImport, SessionBoundaryInfo, SessionPeriod,
+    SubstrateChainStatus, SyncOracle, TimingBlockMetrics, TracingBlockImport,
+    ValidatorAddressCache, ValidatorNetworkStatus,
 };
 use futures::channel::mpsc;
 use log::warn;
 use sc_client_api::{BlockBackend, HeaderBackend};
 use sc_consensus::ImportQueue;
-use sc_consensus_aura::{ImportQueueParams, SlotProportion, StartAuraParams};
-use sc_consensus_slots::BackoffAuthoringBlocksStrategy;
+use sc_consensus_aura::{BuildAuraWorkerParams, ImportQueueParams, SlotProportion};
 use sc_network::NetworkService;
 use sc_network_sync::SyncingService;
 use sc_service::{
@@ -25,46 +26,28 @@ use sc_service::{
     TFullClient, TaskManager,
 };
 use sc_telemetry::{Telemetry, TelemetryWorker};
-use sp_api::ProvideRuntimeApi;
-use sp_arithmetic::traits::BaseArithmetic;
-use sp_consensus_aura::{sr25519::AuthorityPair as AuraPair, Slot};
+use sp_api::{ApiError, ApiExt, ProvideRuntimeApi};
+use sp_consensus_aura::{sr25519::AuthorityPair as AuraPair, SlotDuration};
 
 use crate::{
     aleph_cli::AlephCli,
-    aleph_primitives::{AlephSessionApi, BlockHash, MAX_BLOCK_SIZE},
+    aleph_primitives::{
+        AlephSessionApi, BlockHash, SessionTimings, MAX_BLOCK_SIZE, SESSION_TIMINGS_API_VERSION,
+    },
+    backoff::AuthoringBackoff,
+    ban_events::report_ban_events,
     chain_spec::DEFAULT_BACKUP_FOLDER,
     executor::AlephExecutor,
+    health::{serve_health_report, HealthConfig, HealthMetrics, HealthReporter, LastHealthReport},
+    nat::{map_validator_port, renew_port_mapping},
     rpc::{create_full as create_full_rpc, FullDeps as RpcFullDeps},
+    slot_worker::run_slot_worker,
 };
 
 type FullClient = sc_service::TFullClient<Block, RuntimeApi, AlephExecutor>;
 type FullBackend = sc_service::TFullBackend<Block>;
 type FullSelectChain = sc_consensus::LongestChain<FullBackend, Block>;
 
-struct LimitNonfinalized(u32);
-
-impl<N: BaseArithmetic> BackoffAuthoringBlocksStrategy<N> for LimitNonfinalized {
-    fn should_backoff(
-        &self,
-        chain_head_number: N,
-        _chain_head_slot: Slot,
-        finalized_number: N,
-        _slow_now: Slot,
-        _logging_target: &str,
-    ) -> bool {
-        let nonfinalized_blocks: u32 = chain_head_number
-            .saturating_sub(finalized_number)
-            .unique_saturated_into();
-        match nonfinalized_blocks >= self.0 {
-            true => {
-                warn!("We have {} nonfinalized blocks, with the limit being {}, delaying block production.", nonfinalized_blocks, self.0);
-                true
-            }
-            false => false,
-        }
-    }
-}
-
 fn backup_path(aleph_config: &AlephCli, base_path: &Path) -> Option<PathBuf> {
     if aleph_config.no_backup() {
         return None;
@@ -78,6 +61,63 @@ fn backup_path(aleph_config: &AlephCli, base_path: &Path) -> Option<PathBuf> {
     }
 }
 
+/// The schedule of session timings as of the given block, nothing if the runtime predates it.
+fn session_timings<C>(client: &C, at: BlockHash) -> Result<Option<SessionTimings>, ApiError>
+where
+    C: ProvideRuntimeApi<Block>,
+    C::Api: AlephSessionApi<Block>,
+{
+    let runtime_api = client.runtime_api();
+    match runtime_api.api_version::<dyn AlephSessionApi<Block>>(at)? {
+        Some(version) if version >= SESSION_TIMINGS_API_VERSION => {
+            runtime_api.session_timings(at).map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Session boundaries following the schedule of session timings as of the given block.
+pub(crate) fn session_boundary_info<C>(
+    client: &C,
+    at: BlockHash,
+) -> Result<SessionBoundaryInfo, ApiError>
+where
+    C: ProvideRuntimeApi<Block>,
+    C::Api: AlephSessionApi<Block>,
+{
+    match session_timings(client, at)? {
+        Some(timings) => Ok(SessionBoundaryInfo::from_timings(timings)),
+        // Runtimes with fixed session timings only report the session period.
+        None => client
+            .runtime_api()
+            .session_period(at)
+            .map(|period| SessionBoundaryInfo::new(SessionPeriod(period))),
+    }
+}
+
+/// Slot duration to use for a child of the given block, i.e. the block time that is in force for
+/// the child. Falls back to the default when the runtime does not schedule block time changes.
+fn slot_duration_for_child(
+    client: &FullClient,
+    parent: BlockHash,
+    default: SlotDuration,
+) -> SlotDuration {
+    let number = match client.number(parent) {
+        Ok(Some(number)) => number,
+        _ => return default,
+    };
+    match session_timings(client, parent) {
+        Ok(Some(timings)) => {
+            SlotDuration::from_millis(timings.at_block(number + 1).millisecs_per_block)
+        }
+        Ok(None) => default,
+        Err(e) => {
+            warn!("Failed to read session timings at {}: {}.", parent, e);
+            default
+        }
+    }
+}
+
 #[allow(clippy::type_complexity)]
 pub fn new_partial(
     config: &Configuration,
@@ -157,6 +197,7 @@ pub fn new_partial(
     );
 
     let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
+    let client_for_slots = client.clone();
 
     // DO NOT change Aura parameters without updating the finality-aleph sync accordingly,
     // in particular the code responsible for verifying incoming Headers, as it is supposed
@@ -166,16 +207,20 @@ pub fn new_partial(
             block_import: aleph_block_import.clone(),
             justification_import: Some(Box::new(aleph_block_import)),
             client: client.clone(),
-            create_inherent_data_providers: move |_, ()| async move {
-                let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
-
-                let slot =
-                    sp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
-                        *timestamp,
-                        slot_duration,
-                    );
-
-                Ok((slot, timestamp))
+            create_inherent_data_providers: move |parent, ()| {
+                let slot_duration =
+                    slot_duration_for_child(&client_for_slots, parent, slot_duration);
+                async move {
+                    let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
+
+                    let slot =
+                        sp_consensus_aura::inherents::InherentDataProvider::from_timestamp_and_slot_duration(
+                            *timestamp,
+                            slot_duration,
+                        );
+
+                    Ok((slot, timestamp))
+                }
             },
             spawner: &task_manager.spawn_essential_handle(),
             registry: config.prometheus_registry(),
@@ -210,7 +255,9 @@ fn setup(
     client: Arc<FullClient>,
     telemetry: &mut Option<Telemetry>,
     import_justification_tx: mpsc::UnboundedSender<Justification>,
-    collect_extra_debugging_data: bool,
+    validator_address_cache: Option<ValidatorAddressCache>,
+    last_health_report: LastHealthReport,
+    finality_participation: FinalityParticipation,
 ) -> Result<
     (
         RpcHandlers,
@@ -258,17 +305,13 @@ fn setup(
 
     let sync_oracle = SyncOracle::new();
 
-    let validator_address_cache = match collect_extra_debugging_data {
-        true => Some(ValidatorAddressCache::new()),
-        false => None,
-    };
-
     let rpc_builder = {
         let client = client.clone();
         let pool = transaction_pool.clone();
         let sync_oracle = sync_oracle.clone();
         let validator_address_cache = validator_address_cache.clone();
-        Box::new(move |deny_unsafe, _| {
+        let keystore = keystore_container.keystore();
+        Box::new(move |deny_unsafe, subscription_executor| {
             let deps = RpcFullDeps {
                 client: client.clone(),
                 pool: pool.clone(),
@@ -277,9 +320,12 @@ fn setup(
                 justification_translator: JustificationTranslator::new(chain_status.clone()),
                 sync_oracle: sync_oracle.clone(),
                 validator_address_cache: validator_address_cache.clone(),
+                keystore: keystore.clone(),
+                last_health_report: last_health_report.clone(),
+                finality_participation: finality_participation.clone(),
             };
 
-            Ok(create_full_rpc(deps)?)
+            Ok(create_full_rpc(deps, subscription_executor)?)
         })
     };
 
@@ -331,13 +377,17 @@ pub fn new_authority(
 
     let finalized = client.info().finalized_hash;
 
-    let session_period = SessionPeriod(client.runtime_api().session_period(finalized).unwrap());
+    let session_info = session_boundary_info(&*client, finalized).unwrap();
 
     let millisecs_per_block =
         MillisecsPerBlock(client.runtime_api().millisecs_per_block(finalized).unwrap());
 
     let force_authoring = config.force_authoring;
-    let backoff_authoring_blocks = Some(LimitNonfinalized(aleph_config.max_nonfinalized_blocks()));
+    let max_nonfinalized_blocks = aleph_config.max_nonfinalized_blocks();
+    let backoff_authoring_blocks = Some(AuthoringBackoff::new(
+        client.clone(),
+        max_nonfinalized_blocks,
+    ));
     let prometheus_registry = config.prometheus_registry().cloned();
 
     let import_queue_handle = BlockImporter::new(import_queue.service());
@@ -345,7 +395,35 @@ pub fn new_authority(
     let chain_status = SubstrateChainStatus::new(backend.clone())
         .map_err(|e| ServiceError::Other(format!("failed to set up chain status: {e}")))?;
 
-    let collect_extra_debugging_data = !aleph_config.no_collection_of_extra_debugging_data();
+    let event_log = match aleph_config.consensus_event_log() {
+        Some(path) => EventLog::new(&path).map_err(|e| {
+            ServiceError::Other(format!(
+                "failed to open the consensus event log at {path:?}: {e}"
+            ))
+        })?,
+        None => EventLog::disabled(),
+    };
+
+    let validator_address_cache = match (
+        aleph_config.no_collection_of_extra_debugging_data(),
+        aleph_config.validator_network_history_path(),
+    ) {
+        (true, _) => None,
+        (false, Some(path)) => Some(ValidatorAddressCache::new_persistent(path)),
+        (false, None) => Some(ValidatorAddressCache::new()),
+    };
+
+    let validator_network_status = ValidatorNetworkStatus::new();
+    let health_config = HealthConfig {
+        validator_network_status: validator_network_status.clone(),
+        validator_port: aleph_config.validator_port(),
+        max_nonfinalized_blocks,
+        backup_path: backup_path.clone(),
+    };
+    let health_reporter =
+        HealthReporter::new(client.clone(), keystore_container.keystore(), health_config);
+    let last_health_report = health_reporter.last_report();
+    let finality_participation = FinalityParticipation::new();
 
     let (
         _rpc_handlers,
@@ -366,9 +444,40 @@ pub fn new_authority(
         client.clone(),
         &mut telemetry,
         justification_tx,
-        collect_extra_debugging_data,
+        validator_address_cache,
+        last_health_report.clone(),
+        finality_participation.clone(),
     )?;
 
+    let health_metrics = match prometheus_registry.as_ref().map(HealthMetrics::new) {
+        Some(Ok(metrics)) => Some(metrics),
+        Some(Err(e)) => {
+            warn!("Failed to register health metrics: {:?}.", e);
+            None
+        }
+        None => None,
+    };
+    task_manager.spawn_handle().spawn(
+        "aleph-health-report",
+        None,
+        health_reporter.run(health_metrics),
+    );
+    if let Some(address) = aleph_config.health_report_address() {
+        task_manager.spawn_handle().spawn(
+            "aleph-health-http",
+            None,
+            serve_health_report(last_health_report, address),
+        );
+    }
+
+    if event_log.is_enabled() {
+        task_manager.spawn_handle().spawn(
+            "aleph-ban-events",
+            None,
+            report_ban_events(client.clone(), event_log.clone()),
+        );
+    }
+
     let mut proposer_factory = sc_basic_authorship::ProposerFactory::new(
         task_manager.spawn_handle(),
         client.clone(),
@@ -379,15 +488,32 @@ pub fn new_authority(
     proposer_factory.set_default_block_size_limit(MAX_BLOCK_SIZE as usize);
 
     let slot_duration = sc_consensus_aura::slot_duration(&*client)?;
+    let client_for_slots = client.clone();
+    let client_for_slot_durations = client.clone();
 
-    let aura = sc_consensus_aura::start_aura::<AuraPair, _, _, _, _, _, _, _, _, _, _>(
-        StartAuraParams {
-            slot_duration,
+    let aura_worker = sc_consensus_aura::build_aura_worker::<AuraPair, _, _, _, _, _, _, _, _>(
+        BuildAuraWorkerParams {
             client: client.clone(),
-            select_chain: select_chain.clone(),
             block_import,
             proposer_factory,
-            create_inherent_data_providers: move |_, ()| async move {
+            keystore: keystore_container.keystore(),
+            sync_oracle: sync_oracle.clone(),
+            justification_sync_link: (),
+            force_authoring,
+            backoff_authoring_blocks,
+            telemetry: telemetry.as_ref().map(|x| x.handle()),
+            block_proposal_slot_portion: SlotProportion::new(2f32 / 3f32),
+            max_block_proposal_slot_portion: None,
+            compatibility_mode: Default::default(),
+        },
+    );
+    let aura = run_slot_worker(
+        aura_worker,
+        select_chain.clone(),
+        sync_oracle.clone(),
+        move |parent, ()| {
+            let slot_duration = slot_duration_for_child(&client_for_slots, parent, slot_duration);
+            async move {
                 let timestamp = sp_timestamp::InherentDataProvider::from_system_time();
 
                 let slot =
@@ -397,24 +523,40 @@ pub fn new_authority(
                     );
 
                 Ok((slot, timestamp))
-            },
-            force_authoring,
-            backoff_authoring_blocks,
-            keystore: keystore_container.keystore(),
-            sync_oracle: sync_oracle.clone(),
-            justification_sync_link: (),
-            block_proposal_slot_portion: SlotProportion::new(2f32 / 3f32),
-            max_block_proposal_slot_portion: None,
-            telemetry: telemetry.as_ref().map(|x| x.handle()),
-            compatibility_mode: Default::default(),
+            }
         },
-    )?;
+        move |parent| slot_duration_for_child(&client_for_slot_durations, parent, slot_duration),
+        slot_duration,
+    );
 
     task_manager
         .spawn_essential_handle()
         .spawn_blocking("aura", None, aura);
 
-    if aleph_config.external_addresses().is_empty() {
+    let mut external_addresses = aleph_config.external_addresses();
+    if let Some(nat_config) = aleph_config.nat_config() {
+        match map_validator_port(&nat_config, aleph_config.validator_port()) {
+            Ok((mapper, external_address)) => {
+                external_addresses.push(external_address.to_string());
+                let validator_port = aleph_config.validator_port();
+                if let Err(e) = thread::Builder::new()
+                    .name("aleph-nat".into())
+                    .spawn(move || renew_port_mapping(mapper, validator_port, external_address))
+                {
+                    warn!(
+                        "Failed to start renewing the validator port mapping: {}.",
+                        e
+                    );
+                }
+            }
+            Err(e) => warn!(
+                "Failed to map the validator port on the NAT gateway: {}.",
+                e
+            ),
+        }
+    }
+
+    if external_addresses.is_empty() {
         panic!("Cannot run a validator node without external addresses, stopping.");
     }
 
@@ -432,7 +574,7 @@ pub fn new_authority(
         chain_status,
         import_queue_handle,
         select_chain,
-        session_period,
+        session_info,
         millisecs_per_block,
         spawn_handle: task_manager.spawn_handle().into(),
         keystore: keystore_container.keystore(),
@@ -441,13 +583,19 @@ pub fn new_authority(
         metrics,
         registry: prometheus_registry,
         unit_creation_delay: aleph_config.unit_creation_delay(),
+        remulticast_timeout: aleph_config.remulticast_timeout(),
+        session_history_depth: aleph_config.session_history_depth(),
         backup_saving_path: backup_path,
-        external_addresses: aleph_config.external_addresses(),
+        external_addresses,
         validator_port: aleph_config.validator_port(),
         protocol_naming,
         rate_limiter_config,
         sync_oracle,
         validator_address_cache,
+        validator_network_status,
+        finality_participation,
+        event_log,
+        proposal_strategy: aleph_config.proposal_strategy(),
     };
 
     task_manager.spawn_essential_handle().spawn_blocking(
@@ -0,0 +1,127 @@
+//! Drives the Aura slot worker with slots that follow the block time scheduled in the session
+//! timings. The slot worker started by `sc_consensus_aura::start_aura` ticks with the slot duration
+//! from startup, so after the block time shrinks it would only see some of the slots.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{debug, warn};
+use sc_consensus_slots::{InherentDataProviderExt, SimpleSlotWorker, SlotInfo};
+use sp_consensus::{SelectChain, SyncOracle};
+use sp_consensus_aura::{Slot, SlotDuration};
+use sp_inherents::CreateInherentDataProviders;
+use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
+
+/// How long after `now` the next slot of the given duration starts, both measured from the epoch.
+fn until_next_slot(now: Duration, slot_duration: Duration) -> Duration {
+    let slot_duration = slot_duration.as_millis().max(1);
+    let now = now.as_millis();
+    let next_slot_start = (now / slot_duration + 1) * slot_duration;
+    Duration::from_millis((next_slot_start - now) as u64)
+}
+
+fn time_until_next_slot(slot_duration: SlotDuration) -> Duration {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    until_next_slot(now, slot_duration.as_duration())
+}
+
+/// Runs the slot worker, waiting for every slot with the duration returned by
+/// `slot_duration_for_child` for the current best block. Slots are skipped while major syncing.
+pub async fn run_slot_worker<B, W, SC, SO, CIDP, SD>(
+    mut worker: W,
+    select_chain: SC,
+    sync_oracle: SO,
+    create_inherent_data_providers: CIDP,
+    slot_duration_for_child: SD,
+    initial_slot_duration: SlotDuration,
+) where
+    B: BlockT,
+    W: SimpleSlotWorker<B> + Send,
+    SC: SelectChain<B>,
+    SO: SyncOracle + Send,
+    CIDP: CreateInherentDataProviders<B, ()> + Send + 'static,
+    CIDP::InherentDataProviders: InherentDataProviderExt + Send,
+    SD: Fn(B::Hash) -> SlotDuration + Send,
+{
+    let mut slot_duration = initial_slot_duration;
+    let mut last_slot = Slot::from(0);
+    loop {
+        tokio::time::sleep(time_until_next_slot(slot_duration)).await;
+
+        let chain_head = match select_chain.best_chain().await {
+            Ok(chain_head) => chain_head,
+            Err(e) => {
+                warn!(
+                    "Unable to author block in slot, no best block header: {}.",
+                    e
+                );
+                continue;
+            }
+        };
+        // Right after the block time changes we might wake up in the middle of a slot, in which
+        // case the slot info only leaves the remaining part of it for proposing.
+        slot_duration = slot_duration_for_child(chain_head.hash());
+
+        let inherent_data_providers = match create_inherent_data_providers
+            .create_inherent_data_providers(chain_head.hash(), ())
+            .await
+        {
+            Ok(inherent_data_providers) => inherent_data_providers,
+            Err(e) => {
+                warn!(
+                    "Unable to author block in slot, failed to create inherent data providers: {}.",
+                    e
+                );
+                continue;
+            }
+        };
+        let slot = inherent_data_providers.slot();
+        if slot <= last_slot {
+            continue;
+        }
+        last_slot = slot;
+
+        if sync_oracle.is_major_syncing() {
+            debug!("Skipping proposal slot due to sync.");
+            continue;
+        }
+
+        let _ = worker
+            .on_slot(SlotInfo::new(
+                slot,
+                Box::new(inherent_data_providers),
+                slot_duration.as_duration(),
+                chain_head,
+                None,
+            ))
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::until_next_slot;
+
+    #[test]
+    fn waits_until_next_slot_boundary() {
+        let second = Duration::from_secs(1);
+        let half_second = Duration::from_millis(500);
+        assert_eq!(
+            until_next_slot(Duration::from_millis(10_200), second),
+            Duration::from_millis(800)
+        );
+        assert_eq!(
+            until_next_slot(Duration::from_millis(10_200), half_second),
+            Duration::from_millis(300)
+        );
+        // Exactly at a boundary we wait for the next one.
+        assert_eq!(until_next_slot(Duration::from_secs(10), second), second);
+        assert_eq!(
+            until_next_slot(Duration::from_secs(10), half_second),
+            half_second
+        );
+    }
+}
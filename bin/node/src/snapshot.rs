@@ -0,0 +1,529 @@
+//! Snapshots of the finalized state, used to bootstrap nodes without syncing the whole chain.
+//!
+//! A snapshot is taken at the last block of a session, which always carries a justification. It
+//! contains the state at that block, the block itself, the headers of its session, the
+//! justification and the authorities of the session needed to verify it, as well as the
+//! authorities of the next session, so that the restored node can follow the chain from there.
+//!
+//! Nothing in a snapshot links it to the genesis block, so a snapshot made up together with its
+//! authorities would verify on its own. The hash of the snapshot block therefore has to be known
+//! from a trusted source, and the import refuses snapshots of any other block.
+
+use std::{fs, io::Error as IoError, path::Path, sync::Arc};
+
+use finality_aleph::{
+    backwards_compatible_decode, BlockId, SessionHistory, SessionHistoryError, SessionId,
+    SessionVerifier,
+};
+use parity_scale_codec::{Decode, DecodeAll, Encode, Error as CodecError};
+use primitives::{
+    AlephSessionApi, ApiError as AlephApiError, AuthorityId, Block, BlockHash, BlockNumber,
+    BlsPublicKey, EmergencyFinalizers, Header, SessionAuthorityData, ALEPH_ENGINE_ID,
+};
+use sc_client_api::{Backend, BlockBackend, StorageProvider};
+use sc_consensus::{
+    BlockImport, BlockImportParams, ForkChoiceStrategy, ImportResult, ImportedState, StateAction,
+    StorageChanges,
+};
+use sp_api::{ApiError, ProvideRuntimeApi};
+use sp_blockchain::{Error as BlockchainError, HeaderBackend};
+use sp_consensus::{BlockOrigin, Error as ConsensusError};
+use sp_core::storage::{well_known_keys::DEFAULT_CHILD_STORAGE_KEY_PREFIX, ChildInfo};
+use sp_runtime::{
+    traits::{Block as BlockT, Header as HeaderT},
+    Justifications,
+};
+use sp_state_machine::{KeyValueStates, KeyValueStorageLevel};
+
+use crate::{health::storage_key, service::session_boundary_info};
+
+/// Version of the snapshot format, to be bumped on incompatible changes.
+const SNAPSHOT_VERSION: u32 = 1;
+
+/// What can go wrong when exporting or importing a snapshot.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("blockchain error: {0}")]
+    Blockchain(#[from] BlockchainError),
+    #[error("runtime API call failed: {0}")]
+    RuntimeApi(#[from] ApiError),
+    #[error("authorities of the next session unavailable: {0:?}")]
+    NextAuthorities(AlephApiError),
+    #[error("failed to access the snapshot file: {0}")]
+    Io(#[from] IoError),
+    #[error("malformed snapshot: {0}")]
+    Codec(#[from] CodecError),
+    #[error("unsupported snapshot version {0}, expected {SNAPSHOT_VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("no session has been fully finalized yet")]
+    NoFinalizedSession,
+    #[error("session {0} has not been fully finalized yet")]
+    SessionNotFinalized(u32),
+    #[error("block #{0} is not available")]
+    MissingBlock(BlockNumber),
+    #[error("block #{0} has no justification")]
+    MissingJustification(BlockNumber),
+    #[error("the snapshot is of block {0}, not of the trusted block {1}")]
+    UntrustedBlock(BlockHash, BlockHash),
+    #[error("the snapshot is of a chain with a different genesis block")]
+    GenesisMismatch,
+    #[error("the database already contains blocks up to #{0}, purge the chain first")]
+    DatabaseNotEmpty(BlockNumber),
+    #[error("the headers of the snapshot do not form a chain at block #{0}")]
+    BrokenHeaderChain(BlockNumber),
+    #[error("the authorities of session {0} do not match the state of the snapshot")]
+    AuthoritiesMismatch(u32),
+    #[error("invalid justification: {0}")]
+    Justification(String),
+    #[error("failed to import the snapshot block: {0}")]
+    Import(#[from] ConsensusError),
+    #[error("the snapshot block was not imported: {0}")]
+    NotImported(String),
+    #[error("failed to store the session authorities: {0}")]
+    SessionHistory(SessionHistoryError),
+}
+
+/// The storage of a default child trie.
+#[derive(Clone, Debug, Encode, Decode, PartialEq, Eq)]
+pub struct ChildStorage {
+    /// The key under which the root of the child trie is kept in the top trie.
+    pub prefixed_key: Vec<u8>,
+    pub key_values: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+/// The finalized state at the last block of a session, with everything needed to verify it.
+#[derive(Clone, Debug, Encode, Decode, PartialEq, Eq)]
+pub struct Snapshot {
+    pub version: u32,
+    pub genesis_hash: BlockHash,
+    pub session: u32,
+    /// Headers of the session, from its first block up to the parent of `block`.
+    pub headers: Vec<Header>,
+    pub block: Block,
+    /// The encoded justification of `block`.
+    pub justification: Vec<u8>,
+    pub authority_data: SessionAuthorityData,
+    pub bls_keys: Option<Vec<BlsPublicKey>>,
+    pub emergency_finalizers: Option<EmergencyFinalizers>,
+    pub next_authority_data: SessionAuthorityData,
+    pub top: Vec<(Vec<u8>, Vec<u8>)>,
+    pub children: Vec<ChildStorage>,
+}
+
+impl Snapshot {
+    /// Takes a snapshot at the last block of the given session, by default of the last session
+    /// that was fully finalized.
+    pub fn export<C, BE>(client: &C, session: Option<u32>) -> Result<Self, Error>
+    where
+        C: HeaderBackend<Block>
+            + BlockBackend<Block>
+            + StorageProvider<Block, BE>
+            + ProvideRuntimeApi<Block>,
+        C::Api: AlephSessionApi<Block>,
+        BE: Backend<Block>,
+    {
+        let info = client.info();
+        let session_info = session_boundary_info(client, info.finalized_hash)?;
+        let session = match session {
+            Some(session) => SessionId(session),
+            None => {
+                let current = session_info.session_id_from_block_num(info.finalized_number);
+                match session_info.last_block_of_session(current) == info.finalized_number {
+                    true => current,
+                    false => SessionId(current.0.checked_sub(1).ok_or(Error::NoFinalizedSession)?),
+                }
+            }
+        };
+        let number = session_info.last_block_of_session(session);
+        if number > info.finalized_number {
+            return Err(Error::SessionNotFinalized(session.0));
+        }
+
+        let hash_of = |number| client.hash(number)?.ok_or(Error::MissingBlock(number));
+        let hash = hash_of(number)?;
+        let block = client
+            .block(hash)?
+            .ok_or(Error::MissingBlock(number))?
+            .block;
+        let justification = client
+            .justifications(hash)?
+            .and_then(|justifications| justifications.into_justification(ALEPH_ENGINE_ID))
+            .ok_or(Error::MissingJustification(number))?;
+        let headers = (session_info.first_block_of_session(session)..number)
+            .map(|number| {
+                client
+                    .header(hash_of(number)?)?
+                    .ok_or(Error::MissingBlock(number))
+            })
+            .collect::<Result<_, _>>()?;
+
+        let runtime_api = client.runtime_api();
+        let authority_data = runtime_api.authority_data(hash)?;
+        let next_authority_data = runtime_api
+            .next_session_authority_data(hash)?
+            .map_err(Error::NextAuthorities)?;
+        // Runtimes without BLS keys or emergency finalizers return errors here.
+        let bls_keys = runtime_api.bls_keys(hash).ok();
+        let emergency_finalizers = runtime_api.emergency_finalizers(hash).ok().flatten();
+
+        let mut top = Vec::new();
+        let mut children = Vec::new();
+        for (key, value) in client.storage_pairs(hash, None, None)? {
+            if let Some(child_key) = key.0.strip_prefix(DEFAULT_CHILD_STORAGE_KEY_PREFIX) {
+                let child_info = ChildInfo::new_default(child_key);
+                let key_values = client
+                    .child_storage_keys(hash, child_info.clone(), None, None)?
+                    .map(|child_key| {
+                        let value = client
+                            .child_storage(hash, &child_info, &child_key)?
+                            .unwrap_or_default();
+                        Ok((child_key.0, value.0))
+                    })
+                    .collect::<Result<_, BlockchainError>>()?;
+                children.push(ChildStorage {
+                    prefixed_key: key.0.clone(),
+                    key_values,
+                });
+            }
+            top.push((key.0, value.0));
+        }
+
+        Ok(Snapshot {
+            version: SNAPSHOT_VERSION,
+            genesis_hash: info.genesis_hash,
+            session: session.0,
+            headers,
+            block,
+            justification,
+            authority_data,
+            bls_keys,
+            emergency_finalizers,
+            next_authority_data,
+            top,
+            children,
+        })
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Error> {
+        Ok(fs::write(path, self.encode())?)
+    }
+
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let version = u32::decode(&mut &bytes[..])?;
+        if version != SNAPSHOT_VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        Ok(Self::decode_all(&mut &bytes[..])?)
+    }
+
+    /// The block the snapshot was taken at.
+    pub fn block_id(&self) -> BlockId {
+        BlockId::new(self.block.header.hash(), self.block.header.number)
+    }
+
+    fn verify_header_chain(&self) -> Result<(), Error> {
+        let header = &self.block.header;
+        for (parent, child) in self
+            .headers
+            .iter()
+            .zip(self.headers.iter().skip(1).chain([header]))
+        {
+            if child.parent_hash != parent.hash() || child.number != parent.number + 1 {
+                return Err(Error::BrokenHeaderChain(child.number));
+            }
+        }
+        Ok(())
+    }
+
+    /// The value of a storage item of the Aleph pallet in the state of the snapshot.
+    fn aleph_storage<T: Decode>(&self, item: &str) -> Result<Option<T>, Error> {
+        let key = storage_key("Aleph", item, None);
+        Ok(self
+            .top
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| T::decode(&mut value.as_slice()))
+            .transpose()?)
+    }
+
+    /// Checks that the authorities are the ones kept by the Aleph pallet in the state, which is
+    /// in turn checked against the state root of the block during the import. Otherwise the
+    /// justification could be checked against authorities made up together with it.
+    fn verify_authorities(&self) -> Result<(), Error> {
+        let authority_data = SessionAuthorityData::new(
+            self.aleph_storage::<Vec<AuthorityId>>("Authorities")?
+                .unwrap_or_default(),
+            self.aleph_storage("EmergencyFinalizer")?,
+        );
+        let emergency_finalizers: Option<EmergencyFinalizers> =
+            self.aleph_storage("EmergencyFinalizerSet")?;
+        if authority_data != self.authority_data
+            || emergency_finalizers != self.emergency_finalizers
+        {
+            return Err(Error::AuthoritiesMismatch(self.session));
+        }
+        let next_authority_data = SessionAuthorityData::new(
+            self.aleph_storage::<Vec<AuthorityId>>("NextAuthorities")?
+                .unwrap_or_default(),
+            self.aleph_storage("QueuedEmergencyFinalizer")?,
+        );
+        if next_authority_data != self.next_authority_data {
+            return Err(Error::AuthoritiesMismatch(self.session + 1));
+        }
+        Ok(())
+    }
+
+    /// Checks that the snapshot is of the trusted block, that the headers form a chain, that the
+    /// authorities are the ones in the state and that the justification of the block is signed by
+    /// them.
+    pub fn verify(&self, trusted_hash: BlockHash) -> Result<(), Error> {
+        let hash = self.block.header.hash();
+        if hash != trusted_hash {
+            return Err(Error::UntrustedBlock(hash, trusted_hash));
+        }
+        self.verify_header_chain()?;
+        self.verify_authorities()?;
+        let justification = backwards_compatible_decode(self.justification.clone())
+            .map_err(|e| Error::Justification(e.to_string()))?;
+        SessionVerifier::new(
+            self.authority_data.clone(),
+            self.bls_keys.clone(),
+            self.emergency_finalizers.clone(),
+        )
+        .verify_bytes(&justification, self.block.header.hash().encode())
+        .map_err(|e| Error::Justification(e.to_string()))
+    }
+
+    fn key_value_states(&self) -> KeyValueStates {
+        let root_of = |prefixed_key: &Vec<u8>| {
+            self.top
+                .iter()
+                .find(|(key, _)| key == prefixed_key)
+                .map(|(_, root)| root.clone())
+                .unwrap_or_default()
+        };
+        let mut levels = vec![KeyValueStorageLevel {
+            state_root: Vec::new(),
+            parent_storage_keys: Vec::new(),
+            key_values: self.top.clone(),
+        }];
+        levels.extend(self.children.iter().map(|child| KeyValueStorageLevel {
+            state_root: root_of(&child.prefixed_key),
+            parent_storage_keys: vec![child.prefixed_key.clone()],
+            key_values: child.key_values.clone(),
+        }));
+        KeyValueStates(levels)
+    }
+
+    /// Verifies the snapshot against the hash of the trusted block and restores it into a database
+    /// containing only the genesis block. The state is checked against the state root of the block
+    /// during the import. The node will not have the blocks below the snapshot block.
+    pub async fn import<C>(self, client: Arc<C>, trusted_hash: BlockHash) -> Result<BlockId, Error>
+    where
+        C: HeaderBackend<Block> + sc_client_api::AuxStore,
+        for<'a> &'a C: BlockImport<Block, Error = ConsensusError>,
+    {
+        let info = client.info();
+        if info.genesis_hash != self.genesis_hash {
+            return Err(Error::GenesisMismatch);
+        }
+        if info.best_number != 0 {
+            return Err(Error::DatabaseNotEmpty(info.best_number));
+        }
+        self.verify(trusted_hash)?;
+
+        let id = self.block_id();
+        let (header, body) = self.block.clone().deconstruct();
+        let mut params = BlockImportParams::new(BlockOrigin::File, header);
+        params.body = Some(body);
+        params.justifications = Some(Justifications::from((
+            ALEPH_ENGINE_ID,
+            self.justification.clone(),
+        )));
+        params.state_action = StateAction::ApplyChanges(StorageChanges::Import(ImportedState {
+            block: id.hash(),
+            state: self.key_value_states(),
+        }));
+        params.finalized = true;
+        params.fork_choice = Some(ForkChoiceStrategy::Custom(true));
+        params.import_existing = true;
+        let mut importer = &*client;
+        match importer.import_block(params).await? {
+            ImportResult::Imported(_) => (),
+            result => return Err(Error::NotImported(format!("{result:?}"))),
+        }
+
+        // The state of the first blocks of these sessions is not available, so the authorities
+        // have to be known up front.
+        let session_history = SessionHistory::new(client);
+        for (session, authority_data) in [
+            (self.session, &self.authority_data),
+            (self.session + 1, &self.next_authority_data),
+        ] {
+            session_history
+                .insert(SessionId(session), authority_data)
+                .map_err(Error::SessionHistory)?;
+        }
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use finality_aleph::{versioned_encode, AlephJustification};
+    use parity_scale_codec::Encode;
+    use primitives::{AuthorityPair, Block, Header, SessionAuthorityData};
+    use sp_core::Pair;
+    use sp_runtime::{traits::Header as HeaderT, Digest};
+
+    use super::{Error, Snapshot, SNAPSHOT_VERSION};
+    use crate::health::storage_key;
+
+    fn header_chain(length: u32) -> Vec<Header> {
+        let mut headers: Vec<Header> = Vec::new();
+        for number in 0..length {
+            let parent_hash = headers.last().map(|h| h.hash()).unwrap_or_default();
+            headers.push(Header::new(
+                number,
+                Default::default(),
+                Default::default(),
+                parent_hash,
+                Digest::default(),
+            ));
+        }
+        headers
+    }
+
+    fn pair(seed: u8) -> AuthorityPair {
+        AuthorityPair::from_seed(&[seed; 32])
+    }
+
+    /// A snapshot with a state holding a single authority and the emergency finalizer `pair(1)`,
+    /// whose block is justified by an emergency signature of `signer`.
+    fn snapshot_signed_by(mut headers: Vec<Header>, signer: &AuthorityPair) -> Snapshot {
+        let header = headers.pop().expect("there are headers");
+        let authorities = vec![pair(0).public()];
+        let emergency_finalizer = pair(1).public();
+        let authority_data =
+            SessionAuthorityData::new(authorities.clone(), Some(emergency_finalizer.clone()));
+        let justification = versioned_encode(AlephJustification::EmergencySignature(
+            signer.sign(&header.hash().encode()),
+        ));
+        let aleph_storage = |item, value: Vec<u8>| (storage_key("Aleph", item, None), value);
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            genesis_hash: Default::default(),
+            session: 1,
+            headers,
+            block: Block::new(header, Vec::new()),
+            justification,
+            authority_data: authority_data.clone(),
+            bls_keys: None,
+            emergency_finalizers: None,
+            next_authority_data: authority_data,
+            top: vec![
+                aleph_storage("Authorities", authorities.encode()),
+                aleph_storage("NextAuthorities", authorities.encode()),
+                aleph_storage("EmergencyFinalizer", emergency_finalizer.encode()),
+                aleph_storage("QueuedEmergencyFinalizer", emergency_finalizer.encode()),
+                (b"key".to_vec(), b"value".to_vec()),
+            ],
+            children: Vec::new(),
+        }
+    }
+
+    fn snapshot(headers: Vec<Header>) -> Snapshot {
+        snapshot_signed_by(headers, &pair(1))
+    }
+
+    impl Snapshot {
+        fn verify_trusted(&self) -> Result<(), Error> {
+            self.verify(self.block.header.hash())
+        }
+    }
+
+    #[test]
+    fn accepts_header_chain() {
+        assert!(snapshot(header_chain(10)).verify_header_chain().is_ok());
+        assert!(snapshot(header_chain(1)).verify_header_chain().is_ok());
+    }
+
+    #[test]
+    fn rejects_broken_header_chain() {
+        let mut headers = header_chain(10);
+        headers.remove(4);
+        assert!(matches!(
+            snapshot(headers).verify_header_chain(),
+            Err(Error::BrokenHeaderChain(5))
+        ));
+    }
+
+    #[test]
+    fn verifies_justification_of_authorities_in_state() {
+        assert!(snapshot(header_chain(3)).verify_trusted().is_ok());
+    }
+
+    #[test]
+    fn rejects_untrusted_block() {
+        // Everything in the snapshot is consistent, but it is not of the block we trust.
+        let trusted_hash = header_chain(4).pop().expect("there are headers").hash();
+        assert!(matches!(
+            snapshot(header_chain(3)).verify(trusted_hash),
+            Err(Error::UntrustedBlock(_, hash)) if hash == trusted_hash
+        ));
+    }
+
+    #[test]
+    fn rejects_justification_of_other_signers() {
+        assert!(matches!(
+            snapshot_signed_by(header_chain(3), &pair(2)).verify_trusted(),
+            Err(Error::Justification(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_authorities_not_in_state() {
+        // The justification is valid for the forged authorities, but they are not the ones in
+        // the state.
+        let forger = pair(2);
+        let mut forged = snapshot_signed_by(header_chain(3), &forger);
+        forged.authority_data =
+            SessionAuthorityData::new(vec![forger.public()], Some(forger.public()));
+        assert!(matches!(
+            forged.verify_trusted(),
+            Err(Error::AuthoritiesMismatch(1))
+        ));
+
+        let mut forged = snapshot(header_chain(3));
+        forged.next_authority_data = SessionAuthorityData::new(vec![forger.public()], None);
+        assert!(matches!(
+            forged.verify_trusted(),
+            Err(Error::AuthoritiesMismatch(2))
+        ));
+    }
+
+    #[test]
+    fn decodes_encoded_snapshot() {
+        let snapshot = snapshot(header_chain(3));
+        let bytes = parity_scale_codec::Encode::encode(&snapshot);
+        assert_eq!(
+            Snapshot::from_bytes(&bytes).expect("should decode"),
+            snapshot
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_version() {
+        let mut snapshot = snapshot(header_chain(3));
+        snapshot.version = SNAPSHOT_VERSION + 1;
+        let bytes = parity_scale_codec::Encode::encode(&snapshot);
+        assert!(matches!(
+            Snapshot::from_bytes(&bytes),
+            Err(Error::UnsupportedVersion(_))
+        ));
+    }
+}
@@ -23,8 +23,8 @@ pub use frame_support::{
 use frame_support::{
     sp_runtime::Perquintill,
     traits::{
-        ConstBool, ConstU32, EqualPrivilegeOnly, EstimateNextSessionRotation, SortedMembers,
-        WithdrawReasons,
+        ConstBool, ConstU32, EqualPrivilegeOnly, EstimateNextSessionRotation, OnRuntimeUpgrade,
+        SortedMembers, StorageVersion, WithdrawReasons,
     },
     weights::constants::WEIGHT_REF_TIME_PER_MILLIS,
     PalletId,
@@ -38,12 +38,13 @@ use pallet_session::QueuedKeys;
 pub use pallet_timestamp::Call as TimestampCall;
 use pallet_transaction_payment::{CurrencyAdapter, Multiplier, TargetedFeeAdjustment};
 use primitives::{
-    staking::MAX_NOMINATORS_REWARDED_PER_VALIDATOR, wrap_methods, ApiError as AlephApiError,
-    AuraId, AuthorityId as AlephId, Block as AlephBlock, BlockId as AlephBlockId,
-    BlockNumber as AlephBlockNumber, Header as AlephHeader, SessionAuthorityData, SessionCommittee,
-    SessionIndex, SessionInfoProvider, SessionValidatorError, Version as FinalityVersion,
-    ADDRESSES_ENCODING, DEFAULT_BAN_REASON_LENGTH, DEFAULT_MAX_WINNERS, DEFAULT_SESSIONS_PER_ERA,
-    DEFAULT_SESSION_PERIOD, MAX_BLOCK_SIZE, MILLISECS_PER_BLOCK, TOKEN,
+    staking::MAX_NOMINATORS_REWARDED_PER_VALIDATOR, wrap_methods, AbftConfig,
+    ApiError as AlephApiError, AuraId, AuthoringBackoffConfig, AuthorityId as AlephId,
+    Block as AlephBlock, BlockId as AlephBlockId, BlockNumber as AlephBlockNumber, BlsPublicKey,
+    EmergencyFinalizers, Header as AlephHeader, SessionAuthorityData, SessionCommittee,
+    SessionIndex, SessionInfoProvider, SessionTimings, SessionValidatorError,
+    Version as FinalityVersion, ADDRESSES_ENCODING, DEFAULT_BAN_REASON_LENGTH, DEFAULT_MAX_WINNERS,
+    DEFAULT_SESSIONS_PER_ERA, MAX_BLOCK_SIZE, MILLISECS_PER_BLOCK, TOKEN,
 };
 pub use primitives::{AccountId, AccountIndex, Balance, Hash, Nonce, Signature};
 use sp_api::impl_runtime_apis;
@@ -87,6 +88,7 @@ pub mod opaque {
         pub struct SessionKeys {
             pub aura: Aura,
             pub aleph: Aleph,
+            pub bls: pallet_aleph::BlsSessionKeys<Runtime>,
         }
     }
 }
@@ -96,7 +98,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     spec_name: create_runtime_str!("aleph-node"),
     impl_name: create_runtime_str!("aleph-node"),
     authoring_version: 1,
-    spec_version: 67,
+    spec_version: 73,
     impl_version: 1,
     apis: RUNTIME_API_VERSIONS,
     transaction_version: 17,
@@ -335,6 +337,7 @@ impl pallet_aleph::Config for Runtime {
         Runtime,
     >;
     type NextSessionAuthorityProvider = Session;
+    type SessionsPerEra = SessionsPerEra;
 }
 
 #[cfg(feature = "liminal")]
@@ -359,11 +362,44 @@ impl_opaque_keys! {
     pub struct SessionKeys {
         pub aura: Aura,
         pub aleph: Aleph,
+        pub bls: pallet_aleph::BlsSessionKeys<Runtime>,
+    }
+}
+
+/// Session keys from before the BLS keys were added.
+mod session_keys_v1 {
+    use super::*;
+
+    impl_opaque_keys! {
+        pub struct SessionKeys {
+            pub aura: Aura,
+            pub aleph: Aleph,
+        }
+    }
+}
+
+/// Gives every validator a placeholder BLS session key, until they set their keys again. Until
+/// enough members of a committee do, it keeps justifying blocks with ed25519 signatures.
+pub struct AddBlsSessionKeys;
+
+impl OnRuntimeUpgrade for AddBlsSessionKeys {
+    fn on_runtime_upgrade() -> Weight {
+        if StorageVersion::get::<Aleph>() != StorageVersion::new(2) {
+            return RocksDbWeight::get().reads(1);
+        }
+
+        Session::upgrade_keys::<session_keys_v1::SessionKeys, _>(|account_id, keys| SessionKeys {
+            aura: keys.aura,
+            aleph: keys.aleph,
+            bls: BlsPublicKey::placeholder(account_id.as_ref()),
+        });
+        StorageVersion::new(3).put::<Aleph>();
+
+        BlockWeights::get().max_block
     }
 }
 
 parameter_types! {
-    pub const SessionPeriod: u32 = DEFAULT_SESSION_PERIOD;
     pub const MaximumBanReasonLength: u32 = DEFAULT_BAN_REASON_LENGTH;
     pub const MaxWinners: u32 = DEFAULT_MAX_WINNERS;
 }
@@ -384,21 +420,17 @@ impl pallet_committee_management::Config for Runtime {
     type ValidatorRewardsHandler = Staking;
     type ValidatorExtractor = Staking;
     type FinalityCommitteeManager = Aleph;
-    type SessionPeriod = SessionPeriod;
+    type SessionPeriod = pallet_aleph::CurrentSessionPeriod<Runtime>;
 }
 
 impl pallet_insecure_randomness_collective_flip::Config for Runtime {}
 
-parameter_types! {
-    pub const Offset: u32 = 0;
-}
-
 impl pallet_session::Config for Runtime {
     type RuntimeEvent = RuntimeEvent;
     type ValidatorId = <Self as frame_system::Config>::AccountId;
     type ValidatorIdOf = pallet_staking::StashOf<Self>;
-    type ShouldEndSession = pallet_session::PeriodicSessions<SessionPeriod, Offset>;
-    type NextSessionRotation = pallet_session::PeriodicSessions<SessionPeriod, Offset>;
+    type ShouldEndSession = pallet_aleph::SessionTimingRotation<Runtime>;
+    type NextSessionRotation = pallet_aleph::SessionTimingRotation<Runtime>;
     type SessionManager = Aleph;
     type SessionHandler = <SessionKeys as OpaqueKeys>::KeyTypeIdProviders;
     type Keys = SessionKeys;
@@ -582,15 +614,11 @@ impl pallet_staking::Config for Runtime {
     type EventListeners = NominationPools;
 }
 
-parameter_types! {
-    pub const MinimumPeriod: u64 = MILLISECS_PER_BLOCK / 2;
-}
-
 impl pallet_timestamp::Config for Runtime {
     /// A timestamp: milliseconds since the unix epoch.
     type Moment = u64;
     type OnTimestampSet = Aura;
-    type MinimumPeriod = MinimumPeriod;
+    type MinimumPeriod = pallet_aleph::MinimumTimestampPeriod<Runtime>;
     type WeightInfo = ();
 }
 
@@ -858,6 +886,7 @@ pub type Executive = frame_executive::Executive<
     frame_system::ChainContext<Runtime>,
     Runtime,
     AllPalletsWithSystem,
+    AddBlsSessionKeys,
 >;
 
 #[cfg(feature = "runtime-benchmarks")]
@@ -987,11 +1016,15 @@ impl_runtime_apis! {
 
     impl primitives::AlephSessionApi<Block> for Runtime {
         fn millisecs_per_block() -> u64 {
-            MILLISECS_PER_BLOCK
+            Aleph::current_session_timing().millisecs_per_block
         }
 
         fn session_period() -> u32 {
-            SessionPeriod::get()
+            Aleph::current_session_timing().session_period
+        }
+
+        fn session_timings() -> SessionTimings {
+            Aleph::session_timings()
         }
 
         fn authorities() -> Vec<AlephId> {
@@ -1026,6 +1059,30 @@ impl_runtime_apis! {
             Aleph::next_session_finality_version()
         }
 
+        fn next_session_abft_config() -> AbftConfig {
+            Aleph::next_session_abft_config()
+        }
+
+        fn bls_keys() -> Vec<BlsPublicKey> {
+            Aleph::bls_keys()
+        }
+
+        fn next_session_bls_keys() -> Vec<BlsPublicKey> {
+            Aleph::next_session_bls_keys()
+        }
+
+        fn emergency_finalizers() -> Option<EmergencyFinalizers> {
+            Aleph::emergency_finalizers()
+        }
+
+        fn next_session_emergency_finalizers() -> Option<EmergencyFinalizers> {
+            Aleph::queued_emergency_finalizers()
+        }
+
+        fn authoring_backoff_config() -> Option<AuthoringBackoffConfig> {
+            Aleph::authoring_backoff_config()
+        }
+
         fn predict_session_committee(
             session: SessionIndex,
         ) -> Result<SessionCommittee<AccountId>, SessionValidatorError> {
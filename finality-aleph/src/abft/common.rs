@@ -1,65 +1,74 @@
 use std::{sync::Arc, time::Duration};
 
-use crate::UnitCreationDelay;
-
-pub const MAX_ROUNDS: u16 = 7000;
-
-fn exponential_slowdown(
-    t: usize,
-    base_delay: f64,
-    start_exp_delay: usize,
-    exp_base: f64,
-) -> Duration {
-    // This gives:
-    // base_delay, for t <= start_exp_delay,
-    // base_delay * exp_base^(t - start_exp_delay), for t > start_exp_delay.
-    let delay = if t < start_exp_delay {
-        base_delay
-    } else {
-        let power = t - start_exp_delay;
-        base_delay * exp_base.powf(power as f64)
-    };
-    let delay = delay.round() as u64;
-    // the above will make it u64::MAX if it exceeds u64
-    Duration::from_millis(delay)
-}
+use crate::aleph_primitives::AbftConfig;
 
 pub type DelaySchedule = Arc<dyn Fn(usize) -> Duration + Sync + Send + 'static>;
 
-pub fn unit_creation_delay_fn(unit_creation_delay: UnitCreationDelay) -> DelaySchedule {
-    Arc::new(move |t| match t {
-        0 => Duration::from_millis(2000),
-        _ => exponential_slowdown(t, unit_creation_delay.0 as f64, 5000, 1.005),
-    })
+pub fn unit_creation_delay_fn(abft_config: AbftConfig) -> DelaySchedule {
+    Arc::new(move |t| Duration::from_millis(abft_config.round_delay_ms(t)))
 }
 
-// 7 days (as milliseconds)
-pub const SESSION_LEN_LOWER_BOUND_MS: u128 = 1000 * 60 * 60 * 24 * 7;
-
-pub fn sanity_check_round_delays(max_rounds: u16, round_delays: DelaySchedule) {
-    let delays_ok = sanity_check_round_delays_inner(max_rounds, round_delays);
+pub fn sanity_check_round_delays(
+    max_rounds: u16,
+    round_delays: DelaySchedule,
+    session_len_lower_bound_ms: u64,
+) {
+    let delays_ok =
+        sanity_check_round_delays_inner(max_rounds, round_delays, session_len_lower_bound_ms);
     assert!(
         delays_ok,
-        "Incorrect setting of delays. Make sure the total AlephBFT session time is at least {SESSION_LEN_LOWER_BOUND_MS}ms."
+        "Incorrect setting of delays. Make sure the total AlephBFT session time is at least {session_len_lower_bound_ms}ms."
     );
 }
 
-fn sanity_check_round_delays_inner(max_rounds: u16, round_delays: DelaySchedule) -> bool {
+fn sanity_check_round_delays_inner(
+    max_rounds: u16,
+    round_delays: DelaySchedule,
+    session_len_lower_bound_ms: u64,
+) -> bool {
     let mut total_delay = Duration::from_millis(0);
     for t in 0..=max_rounds {
         total_delay += round_delays(t as usize);
     }
-    total_delay.as_millis() > SESSION_LEN_LOWER_BOUND_MS
+    total_delay.as_millis() > session_len_lower_bound_ms as u128
 }
 
 #[test]
 fn sanity_check_fails_on_bad_config() {
-    let round_delays = unit_creation_delay_fn(UnitCreationDelay(300));
-    assert!(!sanity_check_round_delays_inner(5000, round_delays));
+    let abft_config = AbftConfig::default();
+    let round_delays = unit_creation_delay_fn(abft_config);
+    assert!(!sanity_check_round_delays_inner(
+        5000,
+        round_delays,
+        abft_config.session_len_lower_bound_ms
+    ));
 }
 
 #[test]
 fn sanity_check_passes_on_good_config() {
-    let round_delays = unit_creation_delay_fn(UnitCreationDelay(300));
-    assert!(sanity_check_round_delays_inner(7000, round_delays));
+    let abft_config = AbftConfig::default();
+    let round_delays = unit_creation_delay_fn(abft_config);
+    assert!(sanity_check_round_delays_inner(
+        7000,
+        round_delays,
+        abft_config.session_len_lower_bound_ms
+    ));
+}
+
+#[test]
+fn sanity_check_agrees_with_runtime_validation() {
+    for max_rounds in [5000, 6000, 6500, 7000] {
+        let abft_config = AbftConfig {
+            max_rounds,
+            ..AbftConfig::default()
+        };
+        assert_eq!(
+            sanity_check_round_delays_inner(
+                max_rounds,
+                unit_creation_delay_fn(abft_config),
+                abft_config.session_len_lower_bound_ms
+            ),
+            abft_config.is_valid()
+        );
+    }
 }
@@ -1,4 +1,11 @@
+use std::sync::Arc;
+
+use log::warn;
+
 use crate::{
+    aleph_primitives::{
+        BlsMultisignature, BlsSecretKey, BlsSignature, BlsVerifier, BLS_SIGNATURE_LEN,
+    },
     crypto::{AuthorityPen, AuthorityVerifier, Signature},
     NodeCount, NodeIndex, SignatureSet,
 };
@@ -141,3 +148,150 @@ impl legacy_aleph_bft::MultiKeychain for Keychain {
         Keychain::is_complete(self, msg, partial)
     }
 }
+
+/// BlsKeychain signs with the BLS session key of the node and completes multisignatures by
+/// aggregating BLS signatures, so that the resulting justifications are constant size.
+#[derive(Clone)]
+pub struct BlsKeychain {
+    id: NodeIndex,
+    secret_key: Option<Arc<BlsSecretKey>>,
+    verifier: BlsVerifier,
+}
+
+impl BlsKeychain {
+    /// Constructs a new keychain from the BLS keys of the committee. A node without its secret key
+    /// still takes part in the aggregation, but its signatures will be rejected.
+    pub fn new(id: NodeIndex, verifier: BlsVerifier, secret_key: Option<BlsSecretKey>) -> Self {
+        BlsKeychain {
+            id,
+            secret_key: secret_key.map(Arc::new),
+            verifier,
+        }
+    }
+
+    /// Aggregates the signatures into a multisignature that can be put into a justification.
+    pub fn multisignature(
+        &self,
+        partial: &SignatureSet<BlsSignature>,
+    ) -> Option<BlsMultisignature> {
+        self.verifier.aggregate(
+            partial
+                .iter()
+                .map(|(index, signature)| (index.0, *signature)),
+        )
+    }
+
+    fn node_count(&self) -> NodeCount {
+        NodeCount(self.verifier.node_count())
+    }
+
+    fn sign(&self, msg: &[u8]) -> BlsSignature {
+        match &self.secret_key {
+            Some(secret_key) => secret_key.sign(msg),
+            None => {
+                warn!(target: "aleph-party", "Missing the BLS secret key, producing an invalid signature.");
+                BlsSignature([0; BLS_SIGNATURE_LEN])
+            }
+        }
+    }
+
+    fn is_complete(&self, msg: &[u8], partial: &SignatureSet<BlsSignature>) -> bool {
+        self.multisignature(partial)
+            .map(|multisignature| self.verifier.is_complete(msg, &multisignature))
+            .unwrap_or(false)
+    }
+}
+
+impl current_aleph_bft::Index for BlsKeychain {
+    fn index(&self) -> current_aleph_bft::NodeIndex {
+        self.id.into()
+    }
+}
+
+impl current_aleph_bft::Keychain for BlsKeychain {
+    type Signature = BlsSignature;
+
+    fn node_count(&self) -> current_aleph_bft::NodeCount {
+        BlsKeychain::node_count(self).into()
+    }
+
+    fn sign(&self, msg: &[u8]) -> BlsSignature {
+        BlsKeychain::sign(self, msg)
+    }
+
+    fn verify(&self, msg: &[u8], sgn: &BlsSignature, index: current_aleph_bft::NodeIndex) -> bool {
+        self.verifier.verify(msg, sgn, index.0)
+    }
+}
+
+impl current_aleph_bft::MultiKeychain for BlsKeychain {
+    type PartialMultisignature = SignatureSet<BlsSignature>;
+
+    fn bootstrap_multi(
+        &self,
+        signature: &BlsSignature,
+        index: current_aleph_bft::NodeIndex,
+    ) -> Self::PartialMultisignature {
+        SignatureSet::with_size(BlsKeychain::node_count(self))
+            .add_signature(signature, index.into())
+    }
+
+    fn is_complete(&self, msg: &[u8], partial: &Self::PartialMultisignature) -> bool {
+        BlsKeychain::is_complete(self, msg, partial)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use current_aleph_bft::{Index as _, Keychain as _, MultiKeychain as _};
+
+    use super::BlsKeychain;
+    use crate::{
+        aleph_primitives::{BlsSecretKey, BlsVerifier},
+        NodeIndex,
+    };
+
+    fn keychains(n_members: usize) -> Vec<BlsKeychain> {
+        let secret_keys: Vec<_> = (0..n_members as u64)
+            .map(|id| BlsSecretKey::from_seed(&id.to_le_bytes()))
+            .collect();
+        let verifier = BlsVerifier::new(
+            &secret_keys
+                .iter()
+                .map(|key| key.public())
+                .collect::<Vec<_>>(),
+        );
+        secret_keys
+            .into_iter()
+            .enumerate()
+            .map(|(id, key)| BlsKeychain::new(NodeIndex(id), verifier.clone(), Some(key)))
+            .collect()
+    }
+
+    #[test]
+    fn completes_multisignature_with_a_quorum() {
+        let keychains = keychains(4);
+        let message = b"block hash";
+        let mut partial = keychains[0].bootstrap_multi(&keychains[0].sign(message), 0.into());
+        for keychain in &keychains[1..3] {
+            assert!(!keychains[0].is_complete(message, &partial));
+            let signature = keychain.sign(message);
+            assert!(keychains[0].verify(message, &signature, keychain.index()));
+            partial = partial.add_signature(&signature, keychain.index().into());
+        }
+        assert!(keychains[0].is_complete(message, &partial));
+        assert!(!keychains[0].is_complete(b"other block hash", &partial));
+        let multisignature = keychains[0]
+            .multisignature(&partial)
+            .expect("signatures are well formed");
+        assert_eq!(multisignature.signers().collect::<Vec<_>>(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn signatures_without_secret_key_are_rejected() {
+        let keychains = keychains(4);
+        let keychain = BlsKeychain::new(NodeIndex(3), keychains[0].verifier.clone(), None);
+        let message = b"block hash";
+        assert!(!keychains[0].verify(message, &keychain.sign(message), 3.into()));
+    }
+}
@@ -0,0 +1,257 @@
+//! Wrappers around the AlephBFT IO reporting what passes through them to the session metrics.
+use std::{
+    collections::HashMap,
+    io::{Result as IoResult, Write},
+    time::{Duration, Instant},
+};
+
+use parity_scale_codec::{Decode, Encode};
+
+use crate::{
+    metrics::SessionAbftMetrics,
+    network::{
+        data::{Network, SendError},
+        Data,
+    },
+    Recipient,
+};
+
+/// Members whose first data is ordered this long after the first data of the session are late.
+const LATE_MEMBER_THRESHOLD: Duration = Duration::from_secs(10);
+
+/// Index of the alert variant in the encoding of the AlephBFT network data. The data is an enum
+/// of unit and alert subprotocol messages, in that order, and its internals are not public, so we
+/// peek at the variant byte instead. This cannot tell fork alerts from their multicast messages.
+const ALERT_VARIANT_INDEX: u8 = 1;
+
+/// Length of the prefix of an encoded unit that holds its creator and round. Units are saved to the
+/// backup as signed full units, whose encoding starts with the creator index, encoded as `u64`,
+/// followed by the `u16` round. As with the network data, the unit internals are not public.
+const UNIT_PREFIX_LEN: usize = 10;
+
+/// Counts the units created, as AlephBFT asks for data exactly once per created unit.
+pub struct MetricsDataProvider<DP> {
+    inner: DP,
+    metrics: SessionAbftMetrics,
+}
+
+impl<DP> MetricsDataProvider<DP> {
+    pub fn new(inner: DP, metrics: SessionAbftMetrics) -> Self {
+        MetricsDataProvider { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl<D, DP> current_aleph_bft::DataProvider<D> for MetricsDataProvider<DP>
+where
+    D: current_aleph_bft::Data,
+    DP: current_aleph_bft::DataProvider<D>,
+{
+    async fn get_data(&mut self) -> Option<D> {
+        let data = self.inner.get_data().await;
+        self.metrics.report_unit_created();
+        data
+    }
+}
+
+/// Tracks the ordered data and which members it came from.
+pub struct MetricsFinalizationHandler<FH> {
+    inner: FH,
+    metrics: SessionAbftMetrics,
+    n_members: usize,
+    session_start: Instant,
+    first_ordered: Option<Instant>,
+    first_ordered_by_creator: HashMap<current_aleph_bft::NodeIndex, Instant>,
+}
+
+impl<FH> MetricsFinalizationHandler<FH> {
+    pub fn new(inner: FH, metrics: SessionAbftMetrics, n_members: usize) -> Self {
+        metrics.report_members(n_members, 0);
+        MetricsFinalizationHandler {
+            inner,
+            metrics,
+            n_members,
+            session_start: Instant::now(),
+            first_ordered: None,
+            first_ordered_by_creator: HashMap::new(),
+        }
+    }
+
+    fn report(&mut self, creator: current_aleph_bft::NodeIndex) {
+        let now = Instant::now();
+        self.metrics.report_ordered_data();
+        let first_ordered = *self.first_ordered.get_or_insert_with(|| {
+            self.metrics
+                .report_first_ordered(now.duration_since(self.session_start));
+            now
+        });
+        if self.first_ordered_by_creator.contains_key(&creator) {
+            return;
+        }
+        self.first_ordered_by_creator.insert(creator, now);
+        let missing = self
+            .n_members
+            .saturating_sub(self.first_ordered_by_creator.len());
+        let late = self
+            .first_ordered_by_creator
+            .values()
+            .filter(|time| time.duration_since(first_ordered) > LATE_MEMBER_THRESHOLD)
+            .count();
+        self.metrics.report_members(missing, late);
+    }
+}
+
+impl<D, FH> current_aleph_bft::FinalizationHandler<D> for MetricsFinalizationHandler<FH>
+where
+    D: current_aleph_bft::Data,
+    FH: current_aleph_bft::FinalizationHandler<D>,
+{
+    fn data_finalized(&mut self, data: D, creator: current_aleph_bft::NodeIndex) {
+        self.report(creator);
+        self.inner.data_finalized(data, creator)
+    }
+}
+
+/// Counts the bytes written to the backup and tracks the round of the units this node created.
+/// AlephBFT writes every unit with a single `write_all` followed by a flush, so the unit prefix is
+/// collected from the bytes written since the last flush.
+pub struct MetricsSaver<W: Write> {
+    inner: W,
+    metrics: SessionAbftMetrics,
+    own_index: current_aleph_bft::NodeIndex,
+    unit_prefix: Vec<u8>,
+}
+
+impl<W: Write> MetricsSaver<W> {
+    pub fn new(
+        inner: W,
+        metrics: SessionAbftMetrics,
+        own_index: current_aleph_bft::NodeIndex,
+    ) -> Self {
+        MetricsSaver {
+            inner,
+            metrics,
+            own_index,
+            unit_prefix: Vec::with_capacity(UNIT_PREFIX_LEN),
+        }
+    }
+
+    fn report_unit(&mut self) {
+        if let Ok((creator, round)) =
+            <(current_aleph_bft::NodeIndex, u16)>::decode(&mut &self.unit_prefix[..])
+        {
+            if creator == self.own_index {
+                self.metrics.report_round(round);
+            }
+        }
+        self.unit_prefix.clear();
+    }
+}
+
+impl<W: Write> Write for MetricsSaver<W> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        let written = self.inner.write(buf)?;
+        let missing = UNIT_PREFIX_LEN.saturating_sub(self.unit_prefix.len());
+        self.unit_prefix
+            .extend_from_slice(&buf[..written.min(missing)]);
+        self.metrics.report_backup_written(written);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.inner.flush()?;
+        self.report_unit();
+        Ok(())
+    }
+}
+
+/// Counts the messages of the alert subprotocol received from the network.
+pub struct MetricsNetwork<N> {
+    inner: N,
+    metrics: SessionAbftMetrics,
+}
+
+impl<N> MetricsNetwork<N> {
+    pub fn new(inner: N, metrics: SessionAbftMetrics) -> Self {
+        MetricsNetwork { inner, metrics }
+    }
+}
+
+#[async_trait::async_trait]
+impl<D: Data, N: Network<D>> Network<D> for MetricsNetwork<N> {
+    fn send(&self, data: D, recipient: Recipient) -> Result<(), SendError> {
+        self.inner.send(data, recipient)
+    }
+
+    async fn next(&mut self) -> Option<D> {
+        let data = self.inner.next().await?;
+        if data.using_encoded(|bytes| bytes.first() == Some(&ALERT_VARIANT_INDEX)) {
+            self.metrics.report_alert_message();
+        }
+        Some(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use parity_scale_codec::Encode;
+    use substrate_prometheus_endpoint::Registry;
+
+    use super::{MetricsFinalizationHandler, MetricsSaver};
+    use crate::{
+        metrics::{AbftMetrics, SessionAbftMetrics},
+        SessionId,
+    };
+
+    struct NoopHandler;
+
+    impl current_aleph_bft::FinalizationHandler<u32> for NoopHandler {
+        fn data_finalized(&mut self, _data: u32, _creator: current_aleph_bft::NodeIndex) {}
+    }
+
+    #[test]
+    fn saver_passes_writes_through() {
+        let mut saver = MetricsSaver::new(Vec::new(), SessionAbftMetrics::noop(), 0.into());
+        saver.write_all(&[1, 2, 3]).expect("writing to a vec works");
+        assert_eq!(saver.inner, vec![1, 2, 3]);
+    }
+
+    fn save_unit(saver: &mut MetricsSaver<Vec<u8>>, creator: u64, round: u16) {
+        let mut unit = (creator, round).encode();
+        // The rest of the unit: control hash, data, session and signature.
+        unit.extend_from_slice(&[7; 50]);
+        // Split the write to check that the prefix is collected across writes.
+        saver.write_all(&unit[..5]).expect("writing to a vec works");
+        saver.write_all(&unit[5..]).expect("writing to a vec works");
+        saver.flush().expect("flushing a vec works");
+    }
+
+    #[test]
+    fn saver_reports_round_of_own_units() {
+        let metrics = AbftMetrics::new(Some(&Registry::new()))
+            .unwrap()
+            .session(SessionId(0));
+        let mut saver = MetricsSaver::new(Vec::new(), metrics.clone(), 1.into());
+        save_unit(&mut saver, 1, 0);
+        save_unit(&mut saver, 1, 1);
+        save_unit(&mut saver, 0, 5);
+        save_unit(&mut saver, 2, 4);
+        match metrics {
+            SessionAbftMetrics::Prometheus { round, .. } => assert_eq!(round.get(), 1),
+            SessionAbftMetrics::Noop => panic!("metrics should be registered"),
+        }
+    }
+
+    #[test]
+    fn tracks_creators_of_ordered_data() {
+        let mut handler =
+            MetricsFinalizationHandler::new(NoopHandler, SessionAbftMetrics::noop(), 4);
+        handler.report(0.into());
+        handler.report(2.into());
+        handler.report(0.into());
+        assert!(handler.first_ordered.is_some());
+        assert_eq!(handler.first_ordered_by_creator.len(), 2);
+    }
+}
@@ -6,39 +6,45 @@ use network_clique::SpawnHandleT;
 use sp_blockchain::HeaderBackend;
 use sp_runtime::traits::{Block, Header};
 
+mod metrics;
 mod network;
+mod replay;
 mod traits;
 
 pub use network::NetworkData;
+pub use replay::{replay_member, OrderedData};
 
+use self::metrics::{
+    MetricsDataProvider, MetricsFinalizationHandler, MetricsNetwork, MetricsSaver,
+};
 pub use crate::aleph_primitives::{BlockHash, BlockNumber, CURRENT_FINALITY_VERSION as VERSION};
 use crate::{
-    abft::{
-        common::{unit_creation_delay_fn, MAX_ROUNDS, SESSION_LEN_LOWER_BOUND_MS},
-        NetworkWrapper,
-    },
+    abft::{common::unit_creation_delay_fn, NetworkWrapper},
+    aleph_primitives::AbftConfig,
     block::{Header as BlockHeader, HeaderVerifier, UnverifiedHeader},
     crypto::Signature,
     data_io::{AlephData, OrderedDataInterpreter, SubstrateChainInfoProvider},
+    metrics::SessionAbftMetrics,
     network::data::Network,
     oneshot,
     party::{
         backup::ABFTBackup,
         manager::{Task, TaskCommon},
     },
-    CurrentNetworkData, Hasher, Keychain, NodeIndex, SessionId, SignatureSet, UnitCreationDelay,
+    CurrentNetworkData, Hasher, Keychain, NodeIndex, SessionId, SignatureSet,
 };
 
 type WrappedNetwork<H, ADN> = NetworkWrapper<
     current_aleph_bft::NetworkData<Hasher, AlephData<H>, Signature, SignatureSet<Signature>>,
-    ADN,
+    MetricsNetwork<ADN>,
 >;
 
+#[allow(clippy::too_many_arguments)]
 pub fn run_member<B, C, ADN, V>(
     subtask_common: TaskCommon,
     multikeychain: Keychain,
     config: Config,
-    network: WrappedNetwork<B::Header, ADN>,
+    network: ADN,
     data_provider: impl current_aleph_bft::DataProvider<AlephData<B::Header>> + Send + 'static,
     ordered_data_interpreter: OrderedDataInterpreter<
         SubstrateChainInfoProvider<B, C>,
@@ -46,6 +52,8 @@ pub fn run_member<B, C, ADN, V>(
         V,
     >,
     backup: ABFTBackup,
+    metrics: SessionAbftMetrics,
+    n_members: usize,
 ) -> Task
 where
     B: Block<Hash = BlockHash>,
@@ -60,8 +68,16 @@ where
         session_id,
     } = subtask_common;
     let (stop, exit) = oneshot::channel();
+    let own_index = current_aleph_bft::Index::index(&multikeychain);
     let member_terminator = Terminator::create_root(exit, "member");
-    let local_io = LocalIO::new(data_provider, ordered_data_interpreter, backup.0, backup.1);
+    let network: WrappedNetwork<B::Header, ADN> =
+        MetricsNetwork::new(network, metrics.clone()).into();
+    let local_io = LocalIO::new(
+        MetricsDataProvider::new(data_provider, metrics.clone()),
+        MetricsFinalizationHandler::new(ordered_data_interpreter, metrics.clone(), n_members),
+        MetricsSaver::new(backup.0, metrics, own_index),
+        backup.1,
+    );
 
     let task = {
         let spawn_handle = spawn_handle.clone();
@@ -88,12 +104,12 @@ pub fn create_aleph_config(
     n_members: usize,
     node_id: NodeIndex,
     session_id: SessionId,
-    unit_creation_delay: UnitCreationDelay,
+    abft_config: AbftConfig,
 ) -> Config {
     let mut delay_config = default_delay_config();
-    delay_config.unit_creation_delay = unit_creation_delay_fn(unit_creation_delay);
-    match create_config(n_members.into(), node_id.into(), session_id.0 as u64, MAX_ROUNDS, delay_config, Duration::from_millis(SESSION_LEN_LOWER_BOUND_MS as u64)) {
+    delay_config.unit_creation_delay = unit_creation_delay_fn(abft_config);
+    match create_config(n_members.into(), node_id.into(), session_id.0 as u64, abft_config.max_rounds, delay_config, Duration::from_millis(abft_config.session_len_lower_bound_ms)) {
         Ok(config) => config,
-        Err(_) => panic!("Incorrect setting of delays. Make sure the total AlephBFT session time is at least {} ms.", SESSION_LEN_LOWER_BOUND_MS),
+        Err(_) => panic!("Incorrect setting of delays. Make sure the total AlephBFT session time is at least {} ms.", abft_config.session_len_lower_bound_ms),
     }
 }
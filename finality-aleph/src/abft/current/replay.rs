@@ -0,0 +1,140 @@
+//! Offline replay of a session from the backup of a single committee member.
+use std::{sync::Arc, time::Duration};
+
+use current_aleph_bft::{create_config, default_delay_config, LocalIO, Terminator};
+use futures::{
+    channel::{mpsc, oneshot},
+    future::pending,
+    pin_mut, StreamExt,
+};
+use log::{debug, warn};
+
+use crate::{
+    abft::{NetworkWrapper, SpawnHandle},
+    aleph_primitives::AbftConfig,
+    block::UnverifiedHeader,
+    data_io::AlephData,
+    network::{
+        data::{Network, SendError},
+        Data,
+    },
+    party::backup::Loader,
+    CurrentNetworkData, Keychain, NodeIndex, Recipient, SessionId,
+};
+
+// Long enough that the replaying member never creates a unit of its own,
+// and all the units in its DAG come from the backup.
+const REPLAY_UNIT_CREATION_DELAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The data ordered in a session, together with the creators of the units it came in.
+pub type OrderedData<UH> = Vec<(AlephData<UH>, NodeIndex)>;
+
+/// A network that drops everything sent and never receives anything.
+struct SilentNetwork;
+
+#[async_trait::async_trait]
+impl<D: Data> Network<D> for SilentNetwork {
+    fn send(&self, _data: D, _recipient: Recipient) -> Result<(), SendError> {
+        Ok(())
+    }
+
+    async fn next(&mut self) -> Option<D> {
+        pending().await
+    }
+}
+
+/// Never has anything to propose.
+struct NoData;
+
+#[async_trait::async_trait]
+impl<UH: UnverifiedHeader> current_aleph_bft::DataProvider<AlephData<UH>> for NoData {
+    async fn get_data(&mut self) -> Option<AlephData<UH>> {
+        None
+    }
+}
+
+/// Passes the ordered data on, instead of interpreting it.
+struct OrderedDataCollector<UH: UnverifiedHeader>(
+    mpsc::UnboundedSender<(AlephData<UH>, NodeIndex)>,
+);
+
+impl<UH: UnverifiedHeader> current_aleph_bft::FinalizationHandler<AlephData<UH>>
+    for OrderedDataCollector<UH>
+{
+    fn data_finalized(&mut self, data: AlephData<UH>, creator: current_aleph_bft::NodeIndex) {
+        if self.0.unbounded_send((data, creator.into())).is_err() {
+            warn!(target: "aleph-party", "Ordered data produced after the replay has finished.");
+        }
+    }
+}
+
+/// Replays the ordering of a session using only the units stored in the backup of a single member.
+///
+/// The member neither creates units nor talks to anybody, so the result only depends on the
+/// contents of the backup. As AlephBFT does not signal that it is done with a backup, the replay
+/// stops once no new data was ordered for `idle_timeout`.
+pub async fn replay_member<UH: UnverifiedHeader>(
+    keychain: Keychain,
+    n_members: usize,
+    node_id: NodeIndex,
+    session_id: SessionId,
+    backup: Loader,
+    abft_config: AbftConfig,
+    spawn_handle: SpawnHandle,
+    idle_timeout: Duration,
+) -> OrderedData<UH> {
+    let mut delay_config = default_delay_config();
+    delay_config.unit_creation_delay = Arc::new(|_| REPLAY_UNIT_CREATION_DELAY);
+    let config = match create_config(
+        n_members.into(),
+        node_id.into(),
+        session_id.0 as u64,
+        abft_config.max_rounds,
+        delay_config,
+        Duration::from_millis(abft_config.session_len_lower_bound_ms),
+    ) {
+        Ok(config) => config,
+        Err(_) => panic!("the replay unit creation delay is long enough for any session"),
+    };
+
+    let (ordered_tx, mut ordered_rx) = mpsc::unbounded();
+    let local_io = LocalIO::new(
+        NoData,
+        OrderedDataCollector(ordered_tx),
+        std::io::sink(),
+        backup,
+    );
+    let network: NetworkWrapper<CurrentNetworkData<UH>, _> = SilentNetwork.into();
+    let (stop, exit) = oneshot::channel();
+    let terminator = Terminator::create_root(exit, "replay member");
+
+    debug!(target: "aleph-party", "Replaying backup of {:?} for {:?}", node_id, session_id);
+    let session = current_aleph_bft::run_session(
+        config,
+        local_io,
+        network,
+        keychain,
+        spawn_handle,
+        terminator,
+    );
+    pin_mut!(session);
+    let mut ordered = Vec::new();
+    loop {
+        tokio::select! {
+            _ = &mut session => {
+                warn!(target: "aleph-party", "Replay of backup of {:?} stopped unexpectedly.", node_id);
+                return ordered;
+            },
+            next = tokio::time::timeout(idle_timeout, ordered_rx.next()) => match next {
+                Ok(Some(data)) => ordered.push(data),
+                _ => break,
+            },
+        }
+    }
+    if stop.send(()).is_err() {
+        warn!(target: "aleph-party", "Replaying member exited before being asked to.");
+    }
+    session.await;
+    debug!(target: "aleph-party", "Replay of backup of {:?} ordered {} items.", node_id, ordered.len());
+    ordered
+}
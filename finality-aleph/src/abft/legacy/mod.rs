@@ -9,10 +9,11 @@ mod traits;
 
 pub use network::NetworkData;
 
-use super::common::{sanity_check_round_delays, unit_creation_delay_fn, MAX_ROUNDS};
+use super::common::{sanity_check_round_delays, unit_creation_delay_fn};
 pub use crate::aleph_primitives::{BlockHash, BlockNumber, LEGACY_FINALITY_VERSION as VERSION};
 use crate::{
     abft::NetworkWrapper,
+    aleph_primitives::AbftConfig,
     data_io::{
         legacy::{AlephData, OrderedDataInterpreter},
         SubstrateChainInfoProvider,
@@ -23,7 +24,7 @@ use crate::{
         backup::ABFTBackup,
         manager::{Task, TaskCommon},
     },
-    Keychain, LegacyNetworkData, NodeIndex, SessionId, UnitCreationDelay,
+    Keychain, LegacyNetworkData, NodeIndex, SessionId,
 };
 
 pub fn run_member<B, C, ADN>(
@@ -41,12 +42,6 @@ where
     C: HeaderBackend<B> + Send + 'static,
     ADN: Network<LegacyNetworkData> + 'static,
 {
-    // Remove this check once we implement one on the AlephBFT side (A0-2583).
-    // Checks that the total time of a session is at least 7 days.
-    sanity_check_round_delays(
-        config.max_round,
-        config.delay_config.unit_creation_delay.clone(),
-    );
     let TaskCommon {
         spawn_handle,
         session_id,
@@ -80,10 +75,17 @@ pub fn create_aleph_config(
     n_members: usize,
     node_id: NodeIndex,
     session_id: SessionId,
-    unit_creation_delay: UnitCreationDelay,
+    abft_config: AbftConfig,
 ) -> Config {
     let mut config = default_config(n_members.into(), node_id.into(), session_id.0 as u64);
-    config.delay_config.unit_creation_delay = unit_creation_delay_fn(unit_creation_delay);
-    config.max_round = MAX_ROUNDS;
+    config.delay_config.unit_creation_delay = unit_creation_delay_fn(abft_config);
+    config.max_round = abft_config.max_rounds;
+    // Remove this check once we implement one on the AlephBFT side (A0-2583).
+    // Checks that the total time of a session is at least the configured lower bound.
+    sanity_check_round_delays(
+        config.max_round,
+        config.delay_config.unit_creation_delay.clone(),
+        abft_config.session_len_lower_bound_ms,
+    );
     config
 }
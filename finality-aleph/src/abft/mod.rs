@@ -17,10 +17,11 @@ mod types;
 use std::fmt::Debug;
 
 use aleph_bft_crypto::{PartialMultisignature, Signature};
-pub use crypto::Keychain;
+pub use crypto::{BlsKeychain, Keychain};
 pub use current::{
-    create_aleph_config as current_create_aleph_config, run_member as run_current_member,
-    NetworkData as CurrentNetworkData, VERSION as CURRENT_VERSION,
+    create_aleph_config as current_create_aleph_config, replay_member as replay_current_member,
+    run_member as run_current_member, NetworkData as CurrentNetworkData,
+    OrderedData as CurrentOrderedData, VERSION as CURRENT_VERSION,
 };
 pub use legacy::{
     create_aleph_config as legacy_create_aleph_config, run_member as run_legacy_member,
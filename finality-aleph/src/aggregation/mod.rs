@@ -1,14 +1,22 @@
 //! Module to glue legacy and current version of the aggregator;
 
-use std::marker::PhantomData;
+use std::{
+    fmt::{Display, Error as FmtError, Formatter},
+    marker::PhantomData,
+    time::Duration,
+};
 
 use current_aleph_aggregator::NetworkError as CurrentNetworkError;
 use legacy_aleph_aggregator::NetworkError as LegacyNetworkError;
+use log::error;
+use parity_scale_codec::{Decode, Encode, Error as CodecError, Input, Output};
 
 use crate::{
-    abft::SignatureSet,
-    aleph_primitives::BlockHash,
+    abft::{BlsKeychain, SignatureSet},
+    aleph_primitives::{BlockHash, BlsSignature},
     crypto::Signature,
+    justification::AlephJustification,
+    metrics::AggregatorMetrics,
     mpsc,
     network::{
         data::{Network, SendError},
@@ -21,6 +29,124 @@ pub type LegacyRmcNetworkData =
     legacy_aleph_aggregator::RmcNetworkData<BlockHash, Signature, SignatureSet<Signature>>;
 pub type CurrentRmcNetworkData =
     current_aleph_aggregator::RmcNetworkData<BlockHash, Signature, SignatureSet<Signature>>;
+pub type BlsRmcNetworkData =
+    current_aleph_aggregator::RmcNetworkData<BlockHash, BlsSignature, SignatureSet<BlsSignature>>;
+
+/// The byte prefixing BLS aggregation messages. The messages of the reliable multicast are enums
+/// with two variants, so their encoding never starts with it.
+const BLS_RMC_DATA_PREFIX: u8 = 2;
+
+/// Data of the current version of the aggregator, which aggregates either the ed25519 signatures
+/// of the committee or its BLS signatures. Messages of the former are encoded exactly as before
+/// BLS was introduced, so that nodes that do not know BLS still understand them.
+#[derive(Clone, Debug)]
+pub enum CurrentRmcData {
+    Signatures(CurrentRmcNetworkData),
+    Bls(BlsRmcNetworkData),
+}
+
+/// Allows decoding data with a byte that was already read from the input put back in front.
+struct Prepended<'a, I: Input> {
+    byte: Option<u8>,
+    input: &'a mut I,
+}
+
+impl<I: Input> Input for Prepended<'_, I> {
+    fn remaining_len(&mut self) -> Result<Option<usize>, CodecError> {
+        let prepended = self.byte.map_or(0, |_| 1);
+        Ok(self.input.remaining_len()?.map(|len| len + prepended))
+    }
+
+    fn read(&mut self, into: &mut [u8]) -> Result<(), CodecError> {
+        match (self.byte.take(), into.split_first_mut()) {
+            (Some(byte), Some((first, rest))) => {
+                *first = byte;
+                self.input.read(rest)
+            }
+            (byte, _) => {
+                self.byte = byte;
+                self.input.read(into)
+            }
+        }
+    }
+}
+
+impl Encode for CurrentRmcData {
+    fn size_hint(&self) -> usize {
+        match self {
+            CurrentRmcData::Signatures(data) => data.size_hint(),
+            CurrentRmcData::Bls(data) => BLS_RMC_DATA_PREFIX.size_hint() + data.size_hint(),
+        }
+    }
+
+    fn encode_to<T: Output + ?Sized>(&self, dest: &mut T) {
+        match self {
+            CurrentRmcData::Signatures(data) => data.encode_to(dest),
+            CurrentRmcData::Bls(data) => {
+                BLS_RMC_DATA_PREFIX.encode_to(dest);
+                data.encode_to(dest);
+            }
+        }
+    }
+}
+
+impl Decode for CurrentRmcData {
+    fn decode<I: Input>(input: &mut I) -> Result<Self, CodecError> {
+        match input.read_byte()? {
+            BLS_RMC_DATA_PREFIX => Ok(CurrentRmcData::Bls(BlsRmcNetworkData::decode(input)?)),
+            byte => Ok(CurrentRmcData::Signatures(CurrentRmcNetworkData::decode(
+                &mut Prepended {
+                    byte: Some(byte),
+                    input,
+                },
+            )?)),
+        }
+    }
+}
+
+/// Returned when aggregation data of the other kind was received.
+#[derive(Debug)]
+pub struct UnexpectedRmcData;
+
+impl Display for UnexpectedRmcData {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        write!(f, "aggregation data of an unexpected signature kind")
+    }
+}
+
+impl From<CurrentRmcNetworkData> for CurrentRmcData {
+    fn from(data: CurrentRmcNetworkData) -> Self {
+        CurrentRmcData::Signatures(data)
+    }
+}
+
+impl From<BlsRmcNetworkData> for CurrentRmcData {
+    fn from(data: BlsRmcNetworkData) -> Self {
+        CurrentRmcData::Bls(data)
+    }
+}
+
+impl TryFrom<CurrentRmcData> for CurrentRmcNetworkData {
+    type Error = UnexpectedRmcData;
+
+    fn try_from(data: CurrentRmcData) -> Result<Self, Self::Error> {
+        match data {
+            CurrentRmcData::Signatures(data) => Ok(data),
+            CurrentRmcData::Bls(_) => Err(UnexpectedRmcData),
+        }
+    }
+}
+
+impl TryFrom<CurrentRmcData> for BlsRmcNetworkData {
+    type Error = UnexpectedRmcData;
+
+    fn try_from(data: CurrentRmcData) -> Result<Self, Self::Error> {
+        match data {
+            CurrentRmcData::Bls(data) => Ok(data),
+            CurrentRmcData::Signatures(_) => Err(UnexpectedRmcData),
+        }
+    }
+}
 
 pub type LegacySignableBlockHash = legacy_aleph_aggregator::SignableHash<BlockHash>;
 pub type LegacyRmc<'a> =
@@ -41,32 +167,56 @@ pub type LegacyAggregator<'a, N> = legacy_aleph_aggregator::IO<
     NoopMetrics,
 >;
 
-pub type CurrentAggregator<N> =
-    current_aleph_aggregator::IO<BlockHash, NetworkWrapper<CurrentRmcNetworkData, N>, Keychain>;
+pub type CurrentAggregator<N> = current_aleph_aggregator::IO<
+    BlockHash,
+    NetworkWrapper<CurrentRmcNetworkData, N>,
+    Keychain,
+    AggregatorMetrics,
+>;
 
-enum EitherAggregator<'a, CN, LN>
+pub type BlsAggregator<N> = current_aleph_aggregator::IO<
+    BlockHash,
+    NetworkWrapper<BlsRmcNetworkData, N>,
+    BlsKeychain,
+    AggregatorMetrics,
+>;
+
+/// Configuration of the current version of the aggregator.
+#[derive(Clone)]
+pub struct CurrentAggregatorConfig {
+    pub metrics: AggregatorMetrics,
+    /// After how long without a multisignature a block hash is multicast again.
+    pub remulticast_timeout: Duration,
+}
+
+enum EitherAggregator<'a, CN, BN, LN>
 where
     LN: Network<LegacyRmcNetworkData>,
     CN: Network<CurrentRmcNetworkData>,
+    BN: Network<BlsRmcNetworkData>,
 {
     Current(Box<CurrentAggregator<CN>>),
+    Bls(Box<BlsAggregator<BN>>, BlsKeychain),
     Legacy(LegacyAggregator<'a, LN>),
 }
 
 /// Wrapper on the aggregator, which is either current or legacy one. Depending on the inner variant
-/// it behaves runs the legacy one or the current.
-pub struct Aggregator<'a, CN, LN>
+/// it behaves runs the legacy one or the current, the latter aggregating either ed25519 or BLS
+/// signatures.
+pub struct Aggregator<'a, CN, BN, LN>
 where
     LN: Network<LegacyRmcNetworkData>,
     CN: Network<CurrentRmcNetworkData>,
+    BN: Network<BlsRmcNetworkData>,
 {
-    agg: EitherAggregator<'a, CN, LN>,
+    agg: EitherAggregator<'a, CN, BN, LN>,
 }
 
-impl<'a, CN, LN> Aggregator<'a, CN, LN>
+impl<'a, CN, BN, LN> Aggregator<'a, CN, BN, LN>
 where
     LN: Network<LegacyRmcNetworkData>,
     CN: Network<CurrentRmcNetworkData>,
+    BN: Network<BlsRmcNetworkData>,
 {
     pub fn new_legacy(multikeychain: &'a Keychain, rmc_network: LN) -> Self {
         let (messages_for_rmc, messages_from_network) = mpsc::unbounded();
@@ -96,38 +246,114 @@ where
         }
     }
 
-    pub fn new_current(multikeychain: &'a Keychain, rmc_network: CN) -> Self {
+    pub fn new_current(
+        multikeychain: &'a Keychain,
+        rmc_network: CN,
+        config: CurrentAggregatorConfig,
+    ) -> Self {
+        let CurrentAggregatorConfig {
+            metrics,
+            remulticast_timeout,
+        } = config;
         let scheduler = current_aleph_bft_rmc::DoublingDelayScheduler::new(
             tokio::time::Duration::from_millis(500),
         );
         let rmc_handler = current_aleph_bft_rmc::Handler::new(multikeychain.clone());
         let rmc_service = current_aleph_bft_rmc::Service::new(scheduler, rmc_handler);
-        let aggregator = current_aleph_aggregator::BlockSignatureAggregator::new();
-        let aggregator_io =
-            CurrentAggregator::<CN>::new(NetworkWrapper::new(rmc_network), rmc_service, aggregator);
+        let aggregator = current_aleph_aggregator::BlockSignatureAggregator::new(metrics);
+        let aggregator_io = CurrentAggregator::<CN>::new(
+            NetworkWrapper::new(rmc_network),
+            rmc_service,
+            aggregator,
+            remulticast_timeout,
+        );
 
         Self {
             agg: EitherAggregator::Current(Box::new(aggregator_io)),
         }
     }
 
+    pub fn new_bls(
+        keychain: BlsKeychain,
+        rmc_network: BN,
+        config: CurrentAggregatorConfig,
+    ) -> Self {
+        let CurrentAggregatorConfig {
+            metrics,
+            remulticast_timeout,
+        } = config;
+        let scheduler = current_aleph_bft_rmc::DoublingDelayScheduler::new(
+            tokio::time::Duration::from_millis(500),
+        );
+        let rmc_handler = current_aleph_bft_rmc::Handler::new(keychain.clone());
+        let rmc_service = current_aleph_bft_rmc::Service::new(scheduler, rmc_handler);
+        let aggregator = current_aleph_aggregator::BlockSignatureAggregator::new(metrics);
+        let aggregator_io = BlsAggregator::<BN>::new(
+            NetworkWrapper::new(rmc_network),
+            rmc_service,
+            aggregator,
+            remulticast_timeout,
+        );
+
+        Self {
+            agg: EitherAggregator::Bls(Box::new(aggregator_io), keychain),
+        }
+    }
+
     pub async fn start_aggregation(&mut self, h: BlockHash) {
         match &mut self.agg {
             EitherAggregator::Current(agg) => agg.start_aggregation(h).await,
+            EitherAggregator::Bls(agg, _) => agg.start_aggregation(h).await,
             EitherAggregator::Legacy(agg) => agg.start_aggregation(h).await,
         }
     }
 
-    pub async fn next_multisigned_hash(&mut self) -> Option<(BlockHash, SignatureSet<Signature>)> {
+    /// The next hash for which aggregation finished, together with the resulting justification.
+    pub async fn next_multisigned_hash(&mut self) -> Option<(BlockHash, AlephJustification)> {
         match &mut self.agg {
-            EitherAggregator::Current(agg) => agg.next_multisigned_hash().await,
-            EitherAggregator::Legacy(agg) => agg.next_multisigned_hash().await,
+            EitherAggregator::Current(agg) => {
+                agg.next_multisigned_hash()
+                    .await
+                    .map(|(hash, multisignature)| {
+                        (
+                            hash,
+                            AlephJustification::CommitteeMultisignature(multisignature),
+                        )
+                    })
+            }
+            EitherAggregator::Bls(agg, keychain) => loop {
+                let (hash, signatures) = agg.next_multisigned_hash().await?;
+                // Complete signature sets always aggregate, as completeness is checked on the
+                // aggregate.
+                match keychain.multisignature(&signatures) {
+                    Some(multisignature) => {
+                        return Some((
+                            hash,
+                            AlephJustification::CommitteeBlsMultisignature(multisignature),
+                        ))
+                    }
+                    None => {
+                        error!(target: "aleph-party", "Failed to aggregate BLS signatures for {:?}.", hash)
+                    }
+                }
+            },
+            EitherAggregator::Legacy(agg) => {
+                agg.next_multisigned_hash()
+                    .await
+                    .map(|(hash, multisignature)| {
+                        (
+                            hash,
+                            AlephJustification::CommitteeMultisignature(multisignature),
+                        )
+                    })
+            }
         }
     }
 
     pub fn status_report(&self) {
         match &self.agg {
             EitherAggregator::Current(agg) => agg.status_report(),
+            EitherAggregator::Bls(agg, _) => agg.status_report(),
             EitherAggregator::Legacy(agg) => agg.status_report(),
         }
     }
@@ -182,3 +408,66 @@ where
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use aleph_bft_crypto::{Indexed, Signed};
+    use current_aleph_aggregator::SignableHash;
+    use current_aleph_bft_rmc::Message;
+    use parity_scale_codec::{Decode, Encode};
+
+    use super::{BlsRmcNetworkData, CurrentRmcData, CurrentRmcNetworkData};
+    use crate::{
+        abft::BlsKeychain,
+        aleph_primitives::{BlockHash, BlsSecretKey, BlsVerifier},
+        network::mock::crypto_basics,
+        Keychain, NodeIndex,
+    };
+
+    fn signatures_data() -> CurrentRmcNetworkData {
+        let (mut pens, verifier) = crypto_basics(1);
+        let (node_id, pen) = pens.pop().expect("we generated one pen");
+        let keychain = Keychain::new(node_id, verifier, pen);
+        let hash = SignableHash::new(BlockHash::random());
+        Message::SignedHash(Signed::sign(Indexed::new(hash, 0.into()), &keychain).into_unchecked())
+    }
+
+    fn bls_data() -> BlsRmcNetworkData {
+        let secret_key = BlsSecretKey::from_seed(&[7; 32]);
+        let keychain = BlsKeychain::new(
+            NodeIndex(0),
+            BlsVerifier::new(&[secret_key.public()]),
+            Some(secret_key),
+        );
+        let hash = SignableHash::new(BlockHash::random());
+        Message::SignedHash(Signed::sign(Indexed::new(hash, 0.into()), &keychain).into_unchecked())
+    }
+
+    #[test]
+    fn signatures_data_is_encoded_as_before() {
+        let data = signatures_data();
+        let encoded = CurrentRmcData::from(data.clone()).encode();
+        assert_eq!(encoded, data.encode());
+        match CurrentRmcData::decode(&mut encoded.as_slice()) {
+            Ok(CurrentRmcData::Signatures(decoded)) => assert_eq!(decoded.encode(), encoded),
+            other => panic!("unexpected decoding result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn decodes_bls_data() {
+        let data = bls_data();
+        let encoded = CurrentRmcData::from(data.clone()).encode();
+        assert_eq!(encoded[1..], data.encode());
+        match CurrentRmcData::decode(&mut encoded.as_slice()) {
+            Ok(CurrentRmcData::Bls(decoded)) => assert_eq!(decoded.encode(), data.encode()),
+            other => panic!("unexpected decoding result {other:?}"),
+        }
+    }
+
+    #[test]
+    fn does_not_mistake_data_kinds() {
+        assert!(CurrentRmcNetworkData::try_from(CurrentRmcData::from(bls_data())).is_err());
+        assert!(BlsRmcNetworkData::try_from(CurrentRmcData::from(signatures_data())).is_err());
+    }
+}
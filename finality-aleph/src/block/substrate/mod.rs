@@ -22,7 +22,9 @@ pub use justification::{
     InnerJustification, Justification, JustificationTranslator, TranslateError,
 };
 pub use status_notifier::SubstrateChainStatusNotifier;
-pub use verification::{SessionVerifier, SubstrateFinalizationInfo, VerifierCache};
+pub use verification::{
+    SessionVerificationError, SessionVerifier, SubstrateFinalizationInfo, VerifierCache,
+};
 
 const LOG_TARGET: &str = "aleph-substrate";
 
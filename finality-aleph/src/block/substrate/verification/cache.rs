@@ -14,9 +14,7 @@ use sp_runtime::{
 };
 
 use crate::{
-    aleph_primitives::{
-        AccountId, AuraId, AuthoritySignature, Block, BlockNumber, Header, MILLISECS_PER_BLOCK,
-    },
+    aleph_primitives::{AccountId, AuraId, AuthoritySignature, Block, BlockNumber, Header},
     block::{
         substrate::{
             verification::{
@@ -92,10 +90,13 @@ fn download_data<AP: AuthorityProvider>(
 ) -> Result<CachedData, CacheError> {
     Ok(match session_id {
         SessionId(0) => CachedData {
-            session_verifier: authority_provider
-                .authority_data(0)
-                .ok_or(CacheError::UnknownAuthorities(session_id))?
-                .into(),
+            session_verifier: SessionVerifier::new(
+                authority_provider
+                    .authority_data(0)
+                    .ok_or(CacheError::UnknownAuthorities(session_id))?,
+                authority_provider.bls_keys(0),
+                authority_provider.emergency_finalizers(0),
+            ),
             aura_authorities: authority_provider
                 .aura_authorities(0)
                 .ok_or(CacheError::UnknownAuraAuthorities(session_id))?
@@ -104,14 +105,28 @@ fn download_data<AP: AuthorityProvider>(
                 .collect(),
         },
         SessionId(id) => {
-            let prev_first = session_info.first_block_of_session(SessionId(id - 1));
+            // The data of the next session does not change during a session, so when the state
+            // of its first block is unavailable, e.g. after importing a snapshot, the state of
+            // its last block is just as good.
+            let prev_session = SessionId(id - 1);
+            let mut prev_block = session_info.first_block_of_session(prev_session);
+            let authority_data = match authority_provider.next_authority_data(prev_block) {
+                Some(authority_data) => authority_data,
+                None => {
+                    prev_block = session_info.last_block_of_session(prev_session);
+                    authority_provider
+                        .next_authority_data(prev_block)
+                        .ok_or(CacheError::UnknownAuthorities(session_id))?
+                }
+            };
             CachedData {
-                session_verifier: authority_provider
-                    .next_authority_data(prev_first)
-                    .ok_or(CacheError::UnknownAuthorities(session_id))?
-                    .into(),
+                session_verifier: SessionVerifier::new(
+                    authority_data,
+                    authority_provider.next_bls_keys(prev_block),
+                    authority_provider.next_emergency_finalizers(prev_block),
+                ),
                 aura_authorities: authority_provider
-                    .next_aura_authorities(prev_first)
+                    .next_aura_authorities(prev_block)
                     .ok_or(CacheError::UnknownAuraAuthorities(session_id))?
                     .into_iter()
                     .map(|(acc, auth)| (Some(acc), auth))
@@ -290,13 +305,16 @@ where
         sig: &AuraSignature,
         pre_hash: H256,
         author: &AuraId,
+        number: BlockNumber,
     ) -> Result<(), VerificationError> {
         use HeaderVerificationError::*;
-        // Aura: slot number is calculated using the system time.
-        // This code duplicates one of the parameters that we pass to Aura when starting the node!
+        // Aura: slot number is calculated using the system time and the block time in force
+        // at the block, the same as in the inherent data providers we pass to Aura.
         let slot_now = Slot::from_timestamp(
             sp_timestamp::Timestamp::current(),
-            sp_consensus_slots::SlotDuration::from_millis(MILLISECS_PER_BLOCK),
+            sp_consensus_slots::SlotDuration::from_millis(
+                self.session_info.millisecs_per_block(number).0,
+            ),
         );
         if *slot > slot_now + HEADER_VERIFICATION_SLOT_OFFSET {
             return Err(VerificationError::HeaderVerification(HeaderTooNew(*slot)));
@@ -395,7 +413,7 @@ where
         let (slot, sig, pre_hash, author, maybe_account_id) =
             self.parse_aura_header(&mut header)
                 .map_err(VerificationError::HeaderVerification)?;
-        self.verify_aura_header(&slot, &sig, pre_hash, &author)?;
+        self.verify_aura_header(&slot, &sig, pre_hash, &author, *header.number())?;
         let maybe_equivocation_proof =
             self.check_for_equivocation(&header, slot, author, maybe_account_id, just_created)?;
         Ok(VerifiedHeader {
@@ -419,7 +437,9 @@ mod tests {
         SessionVerifier, VerifierCache,
     };
     use crate::{
-        aleph_primitives::SessionAuthorityData,
+        aleph_primitives::{
+            BlsPublicKey, EmergencyFinalizers, SessionAuthorityData, SessionTimings,
+        },
         block::mock::MockHeader,
         session::{testing::authority_data, SessionBoundaryInfo, SessionId},
         SessionPeriod,
@@ -511,6 +531,29 @@ mod tests {
                         .collect()
                 })
         }
+
+        fn bls_keys(&self, _block_number: BlockNumber) -> Option<Vec<BlsPublicKey>> {
+            None
+        }
+
+        fn next_bls_keys(&self, _block_number: BlockNumber) -> Option<Vec<BlsPublicKey>> {
+            None
+        }
+
+        fn emergency_finalizers(&self, _block_number: BlockNumber) -> Option<EmergencyFinalizers> {
+            None
+        }
+
+        fn next_emergency_finalizers(
+            &self,
+            _block_number: BlockNumber,
+        ) -> Option<EmergencyFinalizers> {
+            None
+        }
+
+        fn session_timings(&self, _block_number: BlockNumber) -> Option<SessionTimings> {
+            None
+        }
     }
 
     fn setup_test(max_session_n: u32, finalized_number: Arc<Mutex<u32>>) -> TestVerifierCache {
@@ -11,8 +11,8 @@ use sp_consensus_slots::Slot;
 use crate::{
     aleph_primitives::{AccountId, AuraId, Block, BlockNumber, Header},
     block::{
-        substrate::verification::{cache::CacheError, verifier::SessionVerificationError},
-        EquivocationProof as EquivocationProofT, Header as HeaderT,
+        substrate::verification::cache::CacheError, EquivocationProof as EquivocationProofT,
+        Header as HeaderT,
     },
 };
 
@@ -20,7 +20,7 @@ mod cache;
 mod verifier;
 
 pub use cache::VerifierCache;
-pub use verifier::SessionVerifier;
+pub use verifier::{SessionVerificationError, SessionVerifier};
 
 /// Supplies finalized number. Will be unified together with other traits we used in A0-1839.
 pub trait FinalizationInfo: Clone + Send + Sync + 'static {
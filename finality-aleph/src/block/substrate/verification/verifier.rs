@@ -1,10 +1,17 @@
-use std::fmt::{Display, Error as FmtError, Formatter};
+use std::{
+    collections::HashSet,
+    fmt::{Display, Error as FmtError, Formatter},
+};
 
 use sp_runtime::RuntimeAppPublic;
 
 use crate::{
-    aleph_primitives::SessionAuthorityData, crypto::AuthorityVerifier,
-    justification::AlephJustification, AuthorityId,
+    aleph_primitives::{
+        AuthoritySignature, BlsPublicKey, BlsVerifier, EmergencyFinalizers, SessionAuthorityData,
+    },
+    crypto::AuthorityVerifier,
+    justification::AlephJustification,
+    AuthorityId,
 };
 
 /// A justification verifier within a single session.
@@ -12,14 +19,13 @@ use crate::{
 pub struct SessionVerifier {
     authority_verifier: AuthorityVerifier,
     emergency_signer: Option<AuthorityId>,
+    emergency_finalizers: Option<EmergencyFinalizers>,
+    bls_verifier: Option<BlsVerifier>,
 }
 
 impl From<SessionAuthorityData> for SessionVerifier {
     fn from(authority_data: SessionAuthorityData) -> Self {
-        SessionVerifier {
-            authority_verifier: AuthorityVerifier::new(authority_data.authorities().to_vec()),
-            emergency_signer: authority_data.emergency_finalizer().clone(),
-        }
+        SessionVerifier::new(authority_data, None, None)
     }
 }
 
@@ -29,6 +35,10 @@ pub enum SessionVerificationError {
     BadMultisignature,
     BadEmergencySignature,
     NoEmergencySigner,
+    BadBlsMultisignature,
+    NoBlsKeys,
+    BadEmergencyMultisignature,
+    NoEmergencyFinalizers,
 }
 
 impl Display for SessionVerificationError {
@@ -38,11 +48,61 @@ impl Display for SessionVerificationError {
             BadMultisignature => write!(f, "bad multisignature"),
             BadEmergencySignature => write!(f, "bad emergency signature"),
             NoEmergencySigner => write!(f, "no emergency signer defined"),
+            BadBlsMultisignature => write!(f, "bad BLS multisignature"),
+            NoBlsKeys => write!(f, "no BLS keys of the committee known"),
+            BadEmergencyMultisignature => write!(f, "bad emergency multisignature"),
+            NoEmergencyFinalizers => write!(f, "no emergency finalizer set defined"),
         }
     }
 }
 
 impl SessionVerifier {
+    /// Creates a verifier for the committee described by the authority data. BLS multisignatures
+    /// are only accepted if the BLS keys of the committee, in the same order, are provided, and
+    /// emergency multisignatures only if the emergency finalizer set is.
+    pub fn new(
+        authority_data: SessionAuthorityData,
+        bls_keys: Option<Vec<BlsPublicKey>>,
+        emergency_finalizers: Option<EmergencyFinalizers>,
+    ) -> Self {
+        let authorities = authority_data.authorities().to_vec();
+        let bls_verifier = bls_keys
+            .filter(|bls_keys| bls_keys.len() == authorities.len())
+            .map(|bls_keys| BlsVerifier::new(&bls_keys));
+        SessionVerifier {
+            authority_verifier: AuthorityVerifier::new(authorities),
+            emergency_signer: authority_data.emergency_finalizer().clone(),
+            emergency_finalizers,
+            bls_verifier,
+        }
+    }
+
+    fn verify_emergency_multisignature(
+        &self,
+        bytes: &[u8],
+        signatures: &[(u32, AuthoritySignature)],
+    ) -> Result<(), SessionVerificationError> {
+        use SessionVerificationError::*;
+        let emergency_finalizers = self
+            .emergency_finalizers
+            .as_ref()
+            .ok_or(NoEmergencyFinalizers)?;
+        let mut signers = HashSet::new();
+        for (index, signature) in signatures {
+            let key = emergency_finalizers
+                .keys
+                .get(*index as usize)
+                .ok_or(BadEmergencyMultisignature)?;
+            if !signers.insert(index) || !key.verify(&bytes, signature) {
+                return Err(BadEmergencyMultisignature);
+            }
+        }
+        match signers.len() >= emergency_finalizers.threshold as usize {
+            true => Ok(()),
+            false => Err(BadEmergencyMultisignature),
+        }
+    }
+
     /// Verifies the correctness of a justification for supplied bytes.
     pub fn verify_bytes(
         &self,
@@ -67,6 +127,178 @@ impl SessionVerifier {
                 true => Ok(()),
                 false => Err(BadEmergencySignature),
             },
+            CommitteeBlsMultisignature(multisignature) => match self
+                .bls_verifier
+                .as_ref()
+                .ok_or(NoBlsKeys)?
+                .is_complete(&bytes, multisignature)
+            {
+                true => Ok(()),
+                false => Err(BadBlsMultisignature),
+            },
+            EmergencyMultisignature(signatures) => {
+                self.verify_emergency_multisignature(&bytes, signatures)
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use sp_core::Pair;
+
+    use super::{SessionVerificationError, SessionVerifier};
+    use crate::{
+        aleph_primitives::{AuthorityPair, BlsSecretKey, BlsVerifier, EmergencyFinalizers},
+        justification::AlephJustification,
+        session::testing::authority_data,
+    };
+
+    const MESSAGE: [u8; 4] = [0u8, 1u8, 2u8, 3u8];
+
+    fn emergency_pairs() -> Vec<AuthorityPair> {
+        (0..3).map(|i| AuthorityPair::from_seed(&[i; 32])).collect()
+    }
+
+    fn verifier(pairs: &[AuthorityPair], threshold: u32) -> SessionVerifier {
+        SessionVerifier::new(
+            authority_data(0, 4),
+            None,
+            Some(EmergencyFinalizers {
+                keys: pairs.iter().map(|pair| pair.public()).collect(),
+                threshold,
+            }),
+        )
+    }
+
+    fn multisignature(pairs: &[AuthorityPair], indices: &[u32]) -> AlephJustification {
+        AlephJustification::EmergencyMultisignature(
+            indices
+                .iter()
+                .map(|index| (*index, pairs[*index as usize].sign(&MESSAGE)))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn accepts_emergency_multisignature_with_threshold_signatures() {
+        let pairs = emergency_pairs();
+        let verifier = verifier(&pairs, 2);
+
+        assert_eq!(
+            verifier.verify_bytes(&multisignature(&pairs, &[0, 2]), MESSAGE.to_vec()),
+            Ok(())
+        );
+        assert_eq!(
+            verifier.verify_bytes(&multisignature(&pairs, &[0, 1, 2]), MESSAGE.to_vec()),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn rejects_emergency_multisignature_below_threshold() {
+        let pairs = emergency_pairs();
+        let verifier = verifier(&pairs, 2);
+
+        assert_eq!(
+            verifier.verify_bytes(&multisignature(&pairs, &[1]), MESSAGE.to_vec()),
+            Err(SessionVerificationError::BadEmergencyMultisignature)
+        );
+        // The same signature counted twice does not reach the threshold.
+        assert_eq!(
+            verifier.verify_bytes(&multisignature(&pairs, &[1, 1]), MESSAGE.to_vec()),
+            Err(SessionVerificationError::BadEmergencyMultisignature)
+        );
+    }
+
+    #[test]
+    fn rejects_emergency_multisignature_with_wrong_signatures() {
+        let pairs = emergency_pairs();
+        let verifier = verifier(&pairs, 2);
+
+        let wrong_signer = AlephJustification::EmergencyMultisignature(vec![
+            (0, pairs[0].sign(&MESSAGE)),
+            (1, pairs[2].sign(&MESSAGE)),
+        ]);
+        assert_eq!(
+            verifier.verify_bytes(&wrong_signer, MESSAGE.to_vec()),
+            Err(SessionVerificationError::BadEmergencyMultisignature)
+        );
+        let unknown_index = AlephJustification::EmergencyMultisignature(vec![
+            (0, pairs[0].sign(&MESSAGE)),
+            (3, pairs[1].sign(&MESSAGE)),
+        ]);
+        assert_eq!(
+            verifier.verify_bytes(&unknown_index, MESSAGE.to_vec()),
+            Err(SessionVerificationError::BadEmergencyMultisignature)
+        );
+    }
+
+    #[test]
+    fn rejects_emergency_multisignature_without_emergency_finalizers() {
+        let pairs = emergency_pairs();
+        let verifier: SessionVerifier = authority_data(0, 4).into();
+
+        assert_eq!(
+            verifier.verify_bytes(&multisignature(&pairs, &[0, 1]), MESSAGE.to_vec()),
+            Err(SessionVerificationError::NoEmergencyFinalizers)
+        );
+    }
+
+    fn bls_secret_keys() -> Vec<BlsSecretKey> {
+        (0..4u8)
+            .map(|i| BlsSecretKey::from_seed(&[i; 32]))
+            .collect()
+    }
+
+    fn bls_multisignature(secret_keys: &[BlsSecretKey], indices: &[usize]) -> AlephJustification {
+        let public_keys: Vec<_> = secret_keys.iter().map(|key| key.public()).collect();
+        AlephJustification::CommitteeBlsMultisignature(
+            BlsVerifier::new(&public_keys)
+                .aggregate(
+                    indices
+                        .iter()
+                        .map(|index| (*index, secret_keys[*index].sign(&MESSAGE))),
+                )
+                .expect("signatures are well formed"),
+        )
+    }
+
+    #[test]
+    fn verifies_bls_multisignature_with_committee_keys() {
+        let secret_keys = bls_secret_keys();
+        let public_keys = secret_keys.iter().map(|key| key.public()).collect();
+        let verifier = SessionVerifier::new(authority_data(0, 4), Some(public_keys), None);
+
+        assert_eq!(
+            verifier.verify_bytes(
+                &bls_multisignature(&secret_keys, &[0, 1, 3]),
+                MESSAGE.to_vec()
+            ),
+            Ok(())
+        );
+        assert_eq!(
+            verifier.verify_bytes(&bls_multisignature(&secret_keys, &[0, 3]), MESSAGE.to_vec()),
+            Err(SessionVerificationError::BadBlsMultisignature)
+        );
+    }
+
+    #[test]
+    fn rejects_bls_multisignature_without_matching_keys() {
+        let secret_keys = bls_secret_keys();
+        let justification = bls_multisignature(&secret_keys, &[0, 1, 2]);
+
+        let verifier: SessionVerifier = authority_data(0, 4).into();
+        assert_eq!(
+            verifier.verify_bytes(&justification, MESSAGE.to_vec()),
+            Err(SessionVerificationError::NoBlsKeys)
+        );
+        // Keys of a different committee are ignored.
+        let public_keys = secret_keys[..3].iter().map(|key| key.public()).collect();
+        let verifier = SessionVerifier::new(authority_data(0, 4), Some(public_keys), None);
+        assert_eq!(
+            verifier.verify_bytes(&justification, MESSAGE.to_vec()),
+            Err(SessionVerificationError::NoBlsKeys)
+        );
+    }
+}
@@ -0,0 +1,57 @@
+//! Recovering BLS secret keys from the keystore, which holds ed25519 seed keys in their place. See
+//! `aleph_primitives::BlsPublicKey` for how the secret keys are derived.
+
+use sp_core::ed25519;
+use sp_keystore::Keystore;
+
+use crate::aleph_primitives::{BlsPublicKey, BlsSecretKey, BLS_KEY_TYPE, BLS_SEED_MESSAGE};
+
+/// Derives the BLS secret key of the given seed key, if the keystore holds it.
+pub fn bls_secret_key(keystore: &dyn Keystore, seed_key: &ed25519::Public) -> Option<BlsSecretKey> {
+    keystore
+        .ed25519_sign(BLS_KEY_TYPE, seed_key, BLS_SEED_MESSAGE)
+        .ok()
+        .flatten()
+        .map(|seed| BlsSecretKey::from_seed(seed.as_ref()))
+}
+
+/// Finds the secret key of the given BLS public key among the ones derived from the keystore.
+pub fn find_bls_secret_key(keystore: &dyn Keystore, key: &BlsPublicKey) -> Option<BlsSecretKey> {
+    keystore
+        .ed25519_public_keys(BLS_KEY_TYPE)
+        .iter()
+        .filter_map(|seed_key| bls_secret_key(keystore, seed_key))
+        .find(|secret_key| secret_key.public() == *key)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use sp_keystore::{testing::MemoryKeystore, KeystoreExt, KeystorePtr};
+    use sp_runtime::RuntimeAppPublic;
+
+    use super::find_bls_secret_key;
+    use crate::aleph_primitives::BlsPublicKey;
+
+    #[test]
+    fn finds_keys_generated_by_the_runtime() {
+        let keystore = Arc::new(MemoryKeystore::new());
+        let mut externalities = sp_io::TestExternalities::default();
+        externalities.register_extension(KeystoreExt::from(keystore.clone() as KeystorePtr));
+        let key = externalities.execute_with(|| BlsPublicKey::generate_pair(None));
+
+        let secret_key =
+            find_bls_secret_key(&*keystore, &key).expect("the runtime generated the key");
+        assert_eq!(secret_key.public(), key);
+        let signature = secret_key.sign(b"message");
+        assert!(key.verify(b"message", &signature));
+        assert_eq!(
+            externalities.execute_with(|| key.sign(b"message")),
+            Some(signature)
+        );
+
+        let other_key = BlsPublicKey::placeholder(b"other");
+        assert!(find_bls_secret_key(&*keystore, &other_key).is_none());
+    }
+}
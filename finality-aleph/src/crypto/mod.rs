@@ -10,6 +10,10 @@ use crate::{
     aleph_primitives::{AuthorityId, AuthoritySignature, KEY_TYPE},
 };
 
+mod bls;
+
+pub use bls::{bls_secret_key, find_bls_secret_key};
+
 #[derive(Debug)]
 pub enum Error {
     KeyMissing(AuthorityId),
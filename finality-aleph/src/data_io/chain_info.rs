@@ -244,3 +244,103 @@ where
         }
     }
 }
+
+// A wrapper around any ChainInfoProvider that pretends nothing above `limit` is finalized,
+// i.e. shows finalization as it was when the block at `limit` was the highest finalized one.
+// Useful for interpreting data ordered in the past, when the chain has since been finalized further.
+pub struct HistoricalChainInfoProvider<CIP>
+where
+    CIP: ChainInfoProvider,
+{
+    limit: BlockNumber,
+    chain_info_provider: CIP,
+}
+
+impl<CIP> HistoricalChainInfoProvider<CIP>
+where
+    CIP: ChainInfoProvider,
+{
+    pub fn new(chain_info_provider: CIP, limit: BlockNumber) -> Self {
+        HistoricalChainInfoProvider {
+            limit,
+            chain_info_provider,
+        }
+    }
+}
+
+impl<CIP> ChainInfoProvider for HistoricalChainInfoProvider<CIP>
+where
+    CIP: ChainInfoProvider,
+{
+    fn is_block_imported(&mut self, block: &BlockId) -> bool {
+        self.chain_info_provider.is_block_imported(block)
+    }
+
+    fn get_finalized_at(&mut self, num: BlockNumber) -> Result<BlockId, ()> {
+        if num > self.limit {
+            return Err(());
+        }
+        self.chain_info_provider.get_finalized_at(num)
+    }
+
+    fn get_parent_hash(&mut self, block: &BlockId) -> Result<BlockHash, ()> {
+        self.chain_info_provider.get_parent_hash(block)
+    }
+
+    fn get_highest_finalized(&mut self) -> BlockId {
+        let highest_finalized_inner = self.chain_info_provider.get_highest_finalized();
+        if highest_finalized_inner.number() <= self.limit {
+            return highest_finalized_inner;
+        }
+        self.chain_info_provider
+            .get_finalized_at(self.limit)
+            .expect("blocks below the highest finalized one are finalized")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use sp_runtime::traits::Header as _;
+
+    use super::{ChainInfoProvider, HistoricalChainInfoProvider, SubstrateChainInfoProvider};
+    use crate::{
+        testing::{
+            client_chain_builder::ClientChainBuilder,
+            mocks::{TBlock, TestClientBuilder, TestClientBuilderExt},
+        },
+        BlockId,
+    };
+
+    fn id(block: &TBlock) -> BlockId {
+        (block.header.hash(), block.header.number).into()
+    }
+
+    #[tokio::test]
+    async fn historical_chain_info_hides_later_finalization() {
+        let client = Arc::new(TestClientBuilder::new().build());
+        let mut chain_builder =
+            ClientChainBuilder::new(client.clone(), Arc::new(TestClientBuilder::new().build()));
+        let blocks = chain_builder.initialize_single_branch_and_import(5).await;
+        chain_builder.finalize_block(&blocks[3].header.hash());
+
+        let mut chain_info =
+            HistoricalChainInfoProvider::new(SubstrateChainInfoProvider::new(client.clone()), 2);
+        assert_eq!(chain_info.get_highest_finalized(), id(&blocks[1]));
+        assert_eq!(chain_info.get_finalized_at(2), Ok(id(&blocks[1])));
+        assert_eq!(chain_info.get_finalized_at(3), Err(()));
+        // Everything else is just passed through.
+        assert!(chain_info.is_block_imported(&id(&blocks[4])));
+        assert_eq!(
+            chain_info.get_parent_hash(&id(&blocks[4])),
+            Ok(blocks[3].header.hash())
+        );
+
+        // Below the limit the finalization is shown as it is.
+        let mut chain_info =
+            HistoricalChainInfoProvider::new(SubstrateChainInfoProvider::new(client), 10);
+        assert_eq!(chain_info.get_highest_finalized(), id(&blocks[3]));
+        assert_eq!(chain_info.get_finalized_at(5), Err(()));
+    }
+}
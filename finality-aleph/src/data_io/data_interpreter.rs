@@ -7,7 +7,7 @@ use crate::{
     block::{Header, HeaderVerifier},
     data_io::{
         chain_info::{AuxFinalizationChainInfoProvider, CachedChainInfoProvider},
-        proposal::ProposalStatus,
+        proposal::{PendingProposalStatus, ProposalStatus},
         status_provider::get_proposal_status,
         AlephData, ChainInfoProvider,
     },
@@ -97,16 +97,18 @@ where
         self.blocks_to_finalize_tx.unbounded_send(block)
     }
 
-    pub fn blocks_to_finalize_from_data(
+    /// Returns the blocks finalized by the data, or the status of the proposal if it cannot be judged
+    /// yet because some of its blocks are not available locally.
+    pub fn try_blocks_to_finalize_from_data(
         &mut self,
         new_data: AlephData<H::Unverified>,
-    ) -> Vec<BlockId> {
+    ) -> Result<Vec<BlockId>, PendingProposalStatus> {
         let unvalidated_proposal = new_data.head_proposal;
         let proposal = match unvalidated_proposal.validate_bounds(&self.session_boundaries) {
             Ok(proposal) => proposal,
             Err(error) => {
                 warn!(target: "aleph-finality", "Incorrect proposal {:?} passed through data availability, session bounds: {:?}, error: {:?}", unvalidated_proposal, self.session_boundaries, error);
-                return Vec::new();
+                return Ok(Vec::new());
             }
         };
 
@@ -121,12 +123,23 @@ where
             None,
         );
         match status {
-            Finalize(blocks) => blocks,
+            Finalize(blocks) => Ok(blocks),
             Ignore => {
                 debug!(target: "aleph-finality", "Ignoring proposal {:?} in interpreter.", proposal);
-                Vec::new()
+                Ok(Vec::new())
             }
-            Pending(pending_status) => {
+            Pending(pending_status) => Err(pending_status),
+        }
+    }
+
+    pub fn blocks_to_finalize_from_data(
+        &mut self,
+        new_data: AlephData<H::Unverified>,
+    ) -> Vec<BlockId> {
+        let proposal = new_data.head_proposal.clone();
+        match self.try_blocks_to_finalize_from_data(new_data) {
+            Ok(blocks) => blocks,
+            Err(pending_status) => {
                 panic!(
                     "Pending proposal {proposal:?} with status {pending_status:?} encountered in Data."
                 );
@@ -134,12 +147,17 @@ where
         }
     }
 
+    /// Makes the interpreter treat the block as finalized when judging further data.
+    pub fn mark_finalized(&mut self, block: BlockId) {
+        self.set_last_finalized(block.clone());
+        self.chain_info_provider()
+            .inner()
+            .update_aux_finalized(block);
+    }
+
     pub fn data_finalized(&mut self, data: AlephData<H::Unverified>) {
         for block in self.blocks_to_finalize_from_data(data) {
-            self.set_last_finalized(block.clone());
-            self.chain_info_provider()
-                .inner()
-                .update_aux_finalized(block.clone());
+            self.mark_finalized(block.clone());
             if let Err(err) = self.send_block_to_finalize(block) {
                 error!(target: "aleph-finality", "Error in sending a block from FinalizationHandler, {}", err);
             }
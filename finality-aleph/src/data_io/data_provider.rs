@@ -1,19 +1,23 @@
 use std::{marker::PhantomData, sync::Arc, time::Duration};
 
-use futures::channel::oneshot;
-use log::{debug, warn};
+use futures::{channel::oneshot, StreamExt};
+use log::{debug, trace, warn};
 use parking_lot::Mutex;
-use sc_client_api::HeaderBackend;
+use sc_client_api::{BlockchainEvents, HeaderBackend};
 use sp_consensus::SelectChain;
 use sp_runtime::{
     traits::{Block as BlockT, Header as HeaderT, Zero},
     SaturatedConversion,
 };
+use tokio::{sync::Notify, time::timeout};
 
 use crate::{
     aleph_primitives::{BlockHash, BlockNumber},
     block::UnverifiedHeader,
-    data_io::{proposal::UnvalidatedAlephProposal, AlephData, MAX_DATA_BRANCH_LEN},
+    data_io::{
+        proposal::UnvalidatedAlephProposal, proposal_strategy::ProposalStrategy, AlephData,
+        MAX_DATA_BRANCH_LEN,
+    },
     metrics::Checkpoint,
     party::manager::Runnable,
     BlockId, SessionBoundaries, TimingBlockMetrics,
@@ -105,16 +109,25 @@ where
     }
 }
 
-const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+// The data is refreshed on every import and finality notification, polling is only a fallback.
+const DEFAULT_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+// How long `get_data` waits for data when there is none, so that a block whose import is being
+// processed right now still makes it into the proposal.
+const DATA_READY_TIMEOUT: Duration = Duration::from_millis(20);
 
 pub struct ChainTrackerConfig {
+    /// How often to refresh the data in the absence of any chain notifications.
     pub refresh_interval: Duration,
+    /// Whether to refresh the data on import and finality notifications, or only periodically.
+    pub refresh_on_notifications: bool,
 }
 
 impl Default for ChainTrackerConfig {
     fn default() -> ChainTrackerConfig {
         ChainTrackerConfig {
             refresh_interval: DEFAULT_REFRESH_INTERVAL,
+            refresh_on_notifications: true,
         }
     }
 }
@@ -126,21 +139,24 @@ struct ChainInfo {
 }
 
 /// ChainTracker keeps track of the best_block in a given session and allows to generate `AlephData`.
-/// Internally it frequently updates a `data_to_propose` field that is shared with a `DataProvider`, which
+/// Internally it updates a `data_to_propose` field that is shared with a `DataProvider`, which
 /// in turn is a tiny wrapper around this single shared resource that takes out `data_to_propose` whenever
-/// `get_data` is called.
+/// `get_data` is called. The updates are triggered by block import and finality notifications from the
+/// client, with periodic refreshing only as a fallback.
 pub struct ChainTracker<B, SC, C>
 where
     B: BlockT<Hash = BlockHash>,
     B::Header: HeaderT<Number = BlockNumber> + UnverifiedHeader,
-    C: HeaderBackend<B> + 'static,
+    C: HeaderBackend<B> + BlockchainEvents<B> + 'static,
     SC: SelectChain<B> + 'static,
 {
     select_chain: SC,
     client: Arc<C>,
     data_to_propose: Arc<Mutex<Option<AlephData<B::Header>>>>,
+    data_ready: Arc<Notify>,
     session_boundaries: SessionBoundaries,
     prev_chain_info: Option<ChainInfo>,
+    proposal_strategy: Box<dyn ProposalStrategy>,
     config: ChainTrackerConfig,
     _phantom: PhantomData<B>,
 }
@@ -149,43 +165,51 @@ impl<B, SC, C> ChainTracker<B, SC, C>
 where
     B: BlockT<Hash = BlockHash>,
     B::Header: HeaderT<Number = BlockNumber> + UnverifiedHeader,
-    C: HeaderBackend<B> + 'static,
+    C: HeaderBackend<B> + BlockchainEvents<B> + 'static,
     SC: SelectChain<B> + 'static,
 {
     pub fn new(
         select_chain: SC,
         client: Arc<C>,
         session_boundaries: SessionBoundaries,
+        proposal_strategy: Box<dyn ProposalStrategy>,
         config: ChainTrackerConfig,
         metrics: TimingBlockMetrics,
     ) -> (Self, DataProvider<B::Header>) {
         let data_to_propose = Arc::new(Mutex::new(None));
+        let data_ready = Arc::new(Notify::new());
         (
             ChainTracker {
                 select_chain,
                 client,
                 data_to_propose: data_to_propose.clone(),
+                data_ready: data_ready.clone(),
                 session_boundaries,
                 prev_chain_info: None,
+                proposal_strategy,
                 config,
                 _phantom: PhantomData,
             },
             DataProvider {
                 data_to_propose,
+                data_ready,
                 metrics,
             },
         )
     }
 
+    fn finalized_block(&self) -> BlockId {
+        let client_info = self.client.info();
+        (client_info.finalized_hash, client_info.finalized_number).into()
+    }
+
     fn update_data(&mut self, best_block_in_session: &BlockId) {
         // We use best_block_in_session argument and the highest_finalized block from the client and compute
         // the corresponding `AlephData<B>` in `data_to_propose` for AlephBFT. To not recompute this many
         // times we remember these "inputs" in `prev_chain_info` and upon match we leave the old value
         // of `data_to_propose` unaffected.
 
-        let client_info = self.client.info();
-        let finalized_block: BlockId =
-            (client_info.finalized_hash, client_info.finalized_number).into();
+        let finalized_block = self.finalized_block();
 
         if finalized_block.number() >= self.session_boundaries.last_block() {
             // This session is already finished, but this instance of ChainTracker has not been terminated yet.
@@ -194,6 +218,11 @@ where
             return;
         }
 
+        // The proposal strategy might prefer a different branch than the one of our best block.
+        let best_block_in_session = &self
+            .proposal_strategy
+            .proposal_top(best_block_in_session.clone(), &finalized_block);
+
         if let Some(prev) = &self.prev_chain_info {
             if prev.best_block_in_session == *best_block_in_session
                 && prev.highest_finalized == finalized_block
@@ -220,7 +249,11 @@ where
             best_block_in_session.clone(),
             finalized_block,
         ) {
+            let ready = proposal.is_some();
             *self.data_to_propose.lock() = proposal;
+            if ready {
+                self.data_ready.notify_one();
+            }
         }
     }
 
@@ -280,17 +313,35 @@ where
         }
     }
 
+    async fn refresh(&mut self, best_block_in_session: Option<BlockId>) -> Option<BlockId> {
+        let best_block_in_session = self.get_best_block_in_session(best_block_in_session).await;
+        if let Some(best_block) = &best_block_in_session {
+            self.update_data(best_block);
+        }
+        best_block_in_session
+    }
+
     pub async fn run(mut self, mut exit: oneshot::Receiver<()>) {
-        let mut best_block_in_session: Option<BlockId> = None;
+        let mut import_notifications = self.client.import_notification_stream();
+        let mut finality_notifications = self.client.finality_notification_stream();
+        let refresh_on_notifications = self.config.refresh_on_notifications;
+        let mut best_block_in_session = self.refresh(None).await;
         loop {
             let delay = futures_timer::Delay::new(self.config.refresh_interval);
+            // Closed notification streams disable their branches, leaving only the periodic refresh.
             tokio::select! {
                 _ = delay => {
-                    best_block_in_session = self.get_best_block_in_session(best_block_in_session).await;
-                    if let Some(best_block) = &best_block_in_session {
-                        self.update_data(best_block);
+                    best_block_in_session = self.refresh(best_block_in_session).await;
+                }
+                Some(notification) = import_notifications.next(), if refresh_on_notifications => {
+                    if notification.is_new_best {
+                        trace!(target: "aleph-data-store", "New best block {:?} imported, refreshing proposal.", notification.hash);
+                        best_block_in_session = self.refresh(best_block_in_session).await;
                     }
-
+                }
+                Some(notification) = finality_notifications.next(), if refresh_on_notifications => {
+                    trace!(target: "aleph-data-store", "Block {:?} finalized, refreshing proposal.", notification.hash);
+                    best_block_in_session = self.refresh(best_block_in_session).await;
                 }
                 _ = &mut exit => {
                     debug!(target: "aleph-data-store", "Task for refreshing best chain received exit signal. Terminating.");
@@ -306,7 +357,7 @@ impl<B, SC, C> Runnable for ChainTracker<B, SC, C>
 where
     B: BlockT<Hash = BlockHash>,
     B::Header: HeaderT<Number = BlockNumber> + UnverifiedHeader,
-    C: HeaderBackend<B> + 'static,
+    C: HeaderBackend<B> + BlockchainEvents<B> + 'static,
     SC: SelectChain<B> + 'static,
 {
     async fn run(mut self, exit: oneshot::Receiver<()>) {
@@ -318,6 +369,8 @@ where
 #[derive(Clone)]
 pub struct DataProvider<UH: UnverifiedHeader> {
     data_to_propose: Arc<Mutex<Option<AlephData<UH>>>>,
+    /// Notified whenever new data to propose appears.
+    data_ready: Arc<Notify>,
     metrics: TimingBlockMetrics,
 }
 
@@ -331,7 +384,14 @@ pub struct DataProvider<UH: UnverifiedHeader> {
 //    at most MAX_DATA_BRANCH_LEN.
 impl<UH: UnverifiedHeader> DataProvider<UH> {
     pub async fn get_data(&mut self) -> Option<AlephData<UH>> {
-        let data_to_propose = (*self.data_to_propose.lock()).take();
+        let mut data_to_propose = (*self.data_to_propose.lock()).take();
+        if data_to_propose.is_none()
+            && timeout(DATA_READY_TIMEOUT, self.data_ready.notified())
+                .await
+                .is_ok()
+        {
+            data_to_propose = (*self.data_to_propose.lock()).take();
+        }
 
         if let Some(data) = &data_to_propose {
             self.metrics.report_block_if_not_present(
@@ -348,15 +408,20 @@ impl<UH: UnverifiedHeader> DataProvider<UH> {
 
 #[cfg(test)]
 mod tests {
-    use std::{future::Future, sync::Arc, time::Duration};
+    use std::{
+        future::Future,
+        sync::Arc,
+        time::{Duration, Instant},
+    };
 
     use futures::channel::oneshot;
-    use tokio::time::sleep;
+    use tokio::time::{sleep, timeout};
 
     use crate::{
         data_io::{
             data_provider::{ChainTracker, ChainTrackerConfig},
-            DataProvider, MAX_DATA_BRANCH_LEN,
+            proposal_strategy::LocalBestStrategy,
+            AlephData, DataProvider, MAX_DATA_BRANCH_LEN,
         },
         testing::{
             client_chain_builder::ClientChainBuilder,
@@ -370,7 +435,10 @@ mod tests {
     // the tests to fail. Even though 1ms works with no issues, we set it to 5ms for safety.
     const REFRESH_INTERVAL: Duration = Duration::from_millis(5);
 
-    fn prepare_chain_tracker_test() -> (
+    fn prepare_chain_tracker_test(
+        refresh_interval: Duration,
+        refresh_on_notifications: bool,
+    ) -> (
         impl Future<Output = ()>,
         oneshot::Sender<()>,
         ClientChainBuilder,
@@ -385,13 +453,15 @@ mod tests {
             .boundaries_for_session(SessionId(0));
 
         let config = ChainTrackerConfig {
-            refresh_interval: REFRESH_INTERVAL,
+            refresh_interval,
+            refresh_on_notifications,
         };
 
         let (chain_tracker, data_provider) = ChainTracker::new(
             select_chain,
             client,
             session_boundaries,
+            Box::new(LocalBestStrategy),
             config,
             TimingBlockMetrics::noop(),
         );
@@ -417,7 +487,27 @@ mod tests {
         F: Future,
         S: FnOnce(ClientChainBuilder, DataProvider<THeader>) -> F,
     {
-        let (task_handle, exit, chain_builder, data_provider) = prepare_chain_tracker_test();
+        run_test_with_refresh_interval(REFRESH_INTERVAL, scenario).await
+    }
+
+    async fn run_test_with_refresh_interval<F, S>(refresh_interval: Duration, scenario: S)
+    where
+        F: Future,
+        S: FnOnce(ClientChainBuilder, DataProvider<THeader>) -> F,
+    {
+        run_test_with_config(refresh_interval, true, scenario).await
+    }
+
+    async fn run_test_with_config<F, S>(
+        refresh_interval: Duration,
+        refresh_on_notifications: bool,
+        scenario: S,
+    ) where
+        F: Future,
+        S: FnOnce(ClientChainBuilder, DataProvider<THeader>) -> F,
+    {
+        let (task_handle, exit, chain_builder, data_provider) =
+            prepare_chain_tracker_test(refresh_interval, refresh_on_notifications);
         let chain_tracker_handle = tokio::spawn(task_handle);
 
         scenario(chain_builder, data_provider).await;
@@ -501,4 +591,116 @@ mod tests {
         })
         .await;
     }
+
+    // Long enough for the fallback refreshing to never happen during a test.
+    const NO_REFRESH_INTERVAL: Duration = Duration::from_secs(3600);
+
+    // Getting data waits for it to become ready, so this does not spin.
+    async fn wait_for_data(data_provider: &mut DataProvider<THeader>) -> AlephData<THeader> {
+        loop {
+            if let Some(data) = data_provider.get_data().await {
+                return data;
+            }
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn proposal_follows_notifications_without_polling() {
+        run_test_with_refresh_interval(
+            NO_REFRESH_INTERVAL,
+            |mut chain_builder, mut data_provider| async move {
+                let blocks = chain_builder
+                    .initialize_single_branch_and_import(2 * MAX_DATA_BRANCH_LEN)
+                    .await;
+                let data = timeout(Duration::from_secs(5), async {
+                    loop {
+                        let data = wait_for_data(&mut data_provider).await;
+                        if data.head_proposal.top_block().hash()
+                            == blocks[MAX_DATA_BRANCH_LEN - 1].header.hash()
+                        {
+                            return data;
+                        }
+                    }
+                })
+                .await
+                .expect("proposal should follow imports");
+                let expected_data = aleph_data_from_blocks(blocks[..MAX_DATA_BRANCH_LEN].to_vec());
+                assert_eq!(data, expected_data);
+
+                chain_builder.finalize_block(&blocks[0].header.hash());
+                let data = timeout(Duration::from_secs(5), wait_for_data(&mut data_provider))
+                    .await
+                    .expect("proposal should follow finalization");
+                let expected_data =
+                    aleph_data_from_blocks(blocks[1..(MAX_DATA_BRANCH_LEN + 1)].to_vec());
+                assert_eq!(data, expected_data);
+            },
+        )
+        .await;
+    }
+
+    // The interval with which the chain tracker used to poll before it followed notifications.
+    const POLLING_REFRESH_INTERVAL: Duration = Duration::from_millis(100);
+
+    async fn measure_proposal_latency(
+        refresh_interval: Duration,
+        refresh_on_notifications: bool,
+    ) -> Vec<Duration> {
+        // Stay within the session, so every imported block ends up in a proposal.
+        const ROUNDS: usize = SESSION_LEN as usize - 10;
+        let mut latencies = Vec::with_capacity(ROUNDS);
+        let latencies_ref = &mut latencies;
+        run_test_with_config(
+            refresh_interval,
+            refresh_on_notifications,
+            |mut chain_builder, mut data_provider| async move {
+                let mut parent = chain_builder.genesis_hash();
+                for _ in 0..ROUNDS {
+                    // Finalizing the parent keeps the new block within the proposal length limit.
+                    chain_builder.finalize_block(&parent);
+                    let block = chain_builder.build_block_above(&parent).await;
+                    parent = block.header.hash();
+                    let start = Instant::now();
+                    chain_builder.import_block(block).await;
+                    loop {
+                        let data = wait_for_data(&mut data_provider).await;
+                        if data.head_proposal.top_block().hash() == parent {
+                            break;
+                        }
+                    }
+                    latencies_ref.push(start.elapsed());
+                }
+            },
+        )
+        .await;
+        latencies.sort();
+        latencies
+    }
+
+    fn report_latencies(name: &str, latencies: &[Duration]) {
+        let rounds = latencies.len();
+        let total: Duration = latencies.iter().sum();
+        println!(
+            "Proposal latency with {} over {} imports: mean {:?}, median {:?}, p99 {:?}, max {:?}",
+            name,
+            rounds,
+            total / rounds as u32,
+            latencies[rounds / 2],
+            latencies[rounds * 99 / 100],
+            latencies[rounds - 1],
+        );
+    }
+
+    // Measures the latency between importing a new best block and getting it from `get_data`, when
+    // following notifications and when only polling.
+    // Run with `cargo test --release -p finality-aleph proposal_latency -- --ignored --nocapture`.
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn proposal_latency_benchmark() {
+        let notified = measure_proposal_latency(NO_REFRESH_INTERVAL, true).await;
+        report_latencies("notifications", &notified);
+        let polled = measure_proposal_latency(POLLING_REFRESH_INTERVAL, false).await;
+        report_latencies("polling", &polled);
+        assert!(notified[notified.len() / 2] < polled[polled.len() / 2]);
+    }
 }
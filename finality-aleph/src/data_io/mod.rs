@@ -13,16 +13,18 @@ mod data_interpreter;
 mod data_provider;
 mod data_store;
 mod proposal;
+mod proposal_strategy;
 mod status_provider;
 
 /// TODO(A0-3461): This is only temporary so we can change the proposal type once. Should be removed after that is done, and only the current version should be used.
 pub mod legacy;
 
-pub use chain_info::{ChainInfoProvider, SubstrateChainInfoProvider};
+pub use chain_info::{ChainInfoProvider, HistoricalChainInfoProvider, SubstrateChainInfoProvider};
 pub use data_interpreter::OrderedDataInterpreter;
 pub use data_provider::{ChainTracker, DataProvider};
 pub use data_store::{DataStore, DataStoreConfig};
-pub use proposal::UnvalidatedAlephProposal;
+pub use proposal::{PendingProposalStatus, UnvalidatedAlephProposal};
+pub use proposal_strategy::{FavouriteBlocks, ProposalStrategyConfig, ProposalStrategyKind};
 
 // Maximum number of blocks above the last finalized allowed in an AlephBFT proposal.
 pub const MAX_DATA_BRANCH_LEN: usize = 7;
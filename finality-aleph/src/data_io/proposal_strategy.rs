@@ -0,0 +1,363 @@
+use std::{
+    cmp::min,
+    collections::HashMap,
+    fmt::{Display, Error as FmtError, Formatter},
+    marker::PhantomData,
+    sync::Arc,
+};
+
+use log::debug;
+use sc_client_api::HeaderBackend;
+use sp_runtime::{
+    traits::{Block as BlockT, Header as HeaderT},
+    SaturatedConversion,
+};
+
+use crate::{
+    aleph_primitives::{BlockHash, BlockNumber},
+    block::UnverifiedHeader,
+    data_io::MAX_DATA_BRANCH_LEN,
+    BlockId, SessionBoundaries,
+};
+
+/// Decides which block the proposals of this node should lead up to.
+pub trait ProposalStrategy: Send + 'static {
+    /// Returns the top of the branch to propose, given the highest ancestor of the local best
+    /// block belonging to the session and the highest finalized block.
+    fn proposal_top(&mut self, best_block_in_session: BlockId, finalized: &BlockId) -> BlockId;
+}
+
+/// Always proposes the branch leading to the local best block.
+pub struct LocalBestStrategy;
+
+impl ProposalStrategy for LocalBestStrategy {
+    fn proposal_top(&mut self, best_block_in_session: BlockId, _finalized: &BlockId) -> BlockId {
+        best_block_in_session
+    }
+}
+
+/// Knowledge about the blocks other nodes would like to build on.
+pub trait FavouriteBlocks: Send + Sync + 'static {
+    /// The favourite blocks of all the nodes we recently heard from.
+    fn favourite_blocks(&self) -> Vec<BlockId>;
+}
+
+/// Prefers the branch most of the other nodes already have, according to their favourite blocks.
+///
+/// It finds the highest block that is an ancestor of the favourite blocks of a majority of the
+/// nodes, our own best block included. If our best block does not descend from it, we are most
+/// likely on a minority fork, so we propose the branch leading to that block instead.
+///
+/// The parents of the visited blocks are cached until they get finalized, so every refresh only
+/// reads the headers of blocks it has not seen before.
+pub struct MajorityForkStrategy<B, C>
+where
+    B: BlockT<Hash = BlockHash>,
+    B::Header: HeaderT<Number = BlockNumber>,
+    C: HeaderBackend<B>,
+{
+    client: Arc<C>,
+    favourite_blocks: Arc<dyn FavouriteBlocks>,
+    session_boundaries: SessionBoundaries,
+    parents: HashMap<BlockHash, BlockId>,
+    _phantom: PhantomData<B>,
+}
+
+impl<B, C> MajorityForkStrategy<B, C>
+where
+    B: BlockT<Hash = BlockHash>,
+    B::Header: HeaderT<Number = BlockNumber>,
+    C: HeaderBackend<B>,
+{
+    pub fn new(
+        client: Arc<C>,
+        favourite_blocks: Arc<dyn FavouriteBlocks>,
+        session_boundaries: SessionBoundaries,
+    ) -> Self {
+        MajorityForkStrategy {
+            client,
+            favourite_blocks,
+            session_boundaries,
+            parents: HashMap::new(),
+            _phantom: PhantomData,
+        }
+    }
+
+    // Returns `None` for the genesis block and for blocks we do not know.
+    fn parent(&mut self, block: &BlockId) -> Option<BlockId> {
+        if let Some(parent) = self.parents.get(&block.hash()) {
+            return Some(parent.clone());
+        }
+        if block.number() == 0 {
+            return None;
+        }
+        let header = self
+            .client
+            .header(block.hash())
+            .expect("client must respond")?;
+        let parent: BlockId = (*header.parent_hash(), block.number() - 1).into();
+        self.parents.insert(block.hash(), parent.clone());
+        Some(parent)
+    }
+
+    // The blocks above `finalized` leading to `block`, but not higher than `max_number`, starting from the top.
+    // Returns `None` if we do not know the block, or it does not descend from `finalized`.
+    fn branch(
+        &mut self,
+        block: BlockId,
+        finalized: &BlockId,
+        max_number: BlockNumber,
+    ) -> Option<Vec<BlockId>> {
+        let mut current = block;
+        while current.number() > max_number {
+            current = self.parent(&current)?;
+        }
+        let mut branch = Vec::new();
+        while current.number() > finalized.number() {
+            let parent = self.parent(&current)?;
+            branch.push(current);
+            current = parent;
+        }
+        (current == *finalized).then_some(branch)
+    }
+
+    fn descends_from(&mut self, block: &BlockId, ancestor: &BlockId) -> bool {
+        let mut current = block.clone();
+        while current.number() > ancestor.number() {
+            current = match self.parent(&current) {
+                Some(parent) => parent,
+                None => return false,
+            };
+        }
+        current == *ancestor
+    }
+}
+
+impl<B, C> ProposalStrategy for MajorityForkStrategy<B, C>
+where
+    B: BlockT<Hash = BlockHash>,
+    B::Header: HeaderT<Number = BlockNumber> + UnverifiedHeader,
+    C: HeaderBackend<B> + Send + Sync + 'static,
+{
+    fn proposal_top(&mut self, best_block_in_session: BlockId, finalized: &BlockId) -> BlockId {
+        // Walks never go below the finalized block, so parents below it are not needed anymore.
+        self.parents
+            .retain(|_, parent| parent.number() >= finalized.number());
+        // Only the blocks that can end up in a proposal matter.
+        let max_number = min(
+            finalized.number() + <BlockNumber>::saturated_from(MAX_DATA_BRANCH_LEN),
+            self.session_boundaries.last_block(),
+        );
+        let favourite_blocks = self.favourite_blocks.favourite_blocks();
+        let branches: Vec<_> = favourite_blocks
+            .into_iter()
+            .chain(Some(best_block_in_session.clone()))
+            .filter(|block| block.number() > finalized.number())
+            .filter_map(|block| self.branch(block, finalized, max_number))
+            .collect();
+        let mut support = HashMap::new();
+        for block in branches.iter().flatten() {
+            *support.entry(block).or_insert(0usize) += 1;
+        }
+        let majority = branches.len() / 2 + 1;
+        let agreed = match support
+            .into_iter()
+            .filter(|(_, count)| *count >= majority)
+            .map(|(block, _)| block)
+            .max_by_key(|block| block.number())
+        {
+            Some(agreed) => agreed.clone(),
+            None => return best_block_in_session,
+        };
+        if self.descends_from(&best_block_in_session, &agreed) {
+            return best_block_in_session;
+        }
+        debug!(target: "aleph-data-store", "Best block {:?} is not on the branch most nodes have, proposing {:?} instead.", best_block_in_session, agreed);
+        agreed
+    }
+}
+
+/// Which proposal strategy a node should use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProposalStrategyKind {
+    /// Propose the branch leading to the local best block.
+    #[default]
+    LocalBest,
+    /// Prefer the branch most of the other nodes have.
+    MajorityFork,
+}
+
+impl Display for ProposalStrategyKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        use ProposalStrategyKind::*;
+        match self {
+            LocalBest => write!(f, "local best"),
+            MajorityFork => write!(f, "majority fork"),
+        }
+    }
+}
+
+/// Everything needed to create the proposal strategy for a session.
+#[derive(Clone)]
+pub enum ProposalStrategyConfig {
+    LocalBest,
+    MajorityFork(Arc<dyn FavouriteBlocks>),
+}
+
+impl ProposalStrategyConfig {
+    pub fn new(kind: ProposalStrategyKind, favourite_blocks: Arc<dyn FavouriteBlocks>) -> Self {
+        match kind {
+            ProposalStrategyKind::LocalBest => ProposalStrategyConfig::LocalBest,
+            ProposalStrategyKind::MajorityFork => {
+                ProposalStrategyConfig::MajorityFork(favourite_blocks)
+            }
+        }
+    }
+
+    pub fn build<B, C>(
+        &self,
+        client: Arc<C>,
+        session_boundaries: SessionBoundaries,
+    ) -> Box<dyn ProposalStrategy>
+    where
+        B: BlockT<Hash = BlockHash>,
+        B::Header: HeaderT<Number = BlockNumber> + UnverifiedHeader,
+        C: HeaderBackend<B> + Send + Sync + 'static,
+    {
+        match self {
+            ProposalStrategyConfig::LocalBest => Box::new(LocalBestStrategy),
+            ProposalStrategyConfig::MajorityFork(favourite_blocks) => Box::new(
+                MajorityForkStrategy::new(client, favourite_blocks.clone(), session_boundaries),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use super::{FavouriteBlocks, MajorityForkStrategy, ProposalStrategy};
+    use crate::{
+        testing::{
+            client_chain_builder::ClientChainBuilder,
+            mocks::{TBlock, TestClient, TestClientBuilder, TestClientBuilderExt},
+        },
+        BlockId, SessionBoundaryInfo, SessionId, SessionPeriod,
+    };
+
+    const SESSION_LEN: u32 = 100;
+
+    #[derive(Clone, Default)]
+    struct MockFavouriteBlocks(Arc<Mutex<Vec<BlockId>>>);
+
+    impl FavouriteBlocks for MockFavouriteBlocks {
+        fn favourite_blocks(&self) -> Vec<BlockId> {
+            self.0.lock().clone()
+        }
+    }
+
+    fn id(block: &TBlock) -> BlockId {
+        (block.header.hash(), block.header.number).into()
+    }
+
+    fn prepare() -> (
+        ClientChainBuilder,
+        MockFavouriteBlocks,
+        MajorityForkStrategy<TBlock, TestClient>,
+    ) {
+        let client = Arc::new(TestClientBuilder::new().build());
+        let chain_builder =
+            ClientChainBuilder::new(client.clone(), Arc::new(TestClientBuilder::new().build()));
+        let favourite_blocks = MockFavouriteBlocks::default();
+        let session_boundaries = SessionBoundaryInfo::new(SessionPeriod(SESSION_LEN))
+            .boundaries_for_session(SessionId(0));
+        let strategy = MajorityForkStrategy::new(
+            client,
+            Arc::new(favourite_blocks.clone()),
+            session_boundaries,
+        );
+        (chain_builder, favourite_blocks, strategy)
+    }
+
+    #[tokio::test]
+    async fn proposes_local_best_without_peer_knowledge() {
+        let (mut chain_builder, _, mut strategy) = prepare();
+        let blocks = chain_builder.initialize_single_branch_and_import(3).await;
+        let genesis = chain_builder.genesis_id();
+
+        assert_eq!(
+            strategy.proposal_top(id(&blocks[2]), &genesis),
+            id(&blocks[2])
+        );
+    }
+
+    #[tokio::test]
+    async fn proposes_local_best_when_on_majority_branch() {
+        let (mut chain_builder, favourite_blocks, mut strategy) = prepare();
+        let blocks = chain_builder.initialize_single_branch_and_import(3).await;
+        let genesis = chain_builder.genesis_id();
+        *favourite_blocks.0.lock() = vec![id(&blocks[1]), id(&blocks[1]), id(&blocks[0])];
+
+        assert_eq!(
+            strategy.proposal_top(id(&blocks[2]), &genesis),
+            id(&blocks[2])
+        );
+    }
+
+    #[tokio::test]
+    async fn switches_to_majority_fork() {
+        let (mut chain_builder, favourite_blocks, mut strategy) = prepare();
+        let trunk = chain_builder.initialize_single_branch_and_import(2).await;
+        let minority = chain_builder
+            .build_and_import_branch_above(&trunk[1].header.hash(), 3)
+            .await;
+        let majority = chain_builder
+            .build_and_import_branch_above(&trunk[1].header.hash(), 2)
+            .await;
+        let genesis = chain_builder.genesis_id();
+        *favourite_blocks.0.lock() = vec![
+            id(&majority[1]),
+            id(&majority[1]),
+            id(&majority[0]),
+            id(&minority[0]),
+        ];
+
+        assert_eq!(
+            strategy.proposal_top(id(&minority[2]), &genesis),
+            id(&majority[0])
+        );
+    }
+
+    #[tokio::test]
+    async fn ignores_unknown_favourite_blocks() {
+        let (mut chain_builder, favourite_blocks, mut strategy) = prepare();
+        let blocks = chain_builder.initialize_single_branch_and_import(2).await;
+        let unknown = chain_builder
+            .build_branch_above(&blocks[1].header.hash(), 1)
+            .await;
+        let genesis = chain_builder.genesis_id();
+        *favourite_blocks.0.lock() = vec![id(&unknown[0]), id(&unknown[0])];
+
+        assert_eq!(
+            strategy.proposal_top(id(&blocks[1]), &genesis),
+            id(&blocks[1])
+        );
+    }
+
+    #[tokio::test]
+    async fn forgets_parents_of_finalized_blocks() {
+        let (mut chain_builder, favourite_blocks, mut strategy) = prepare();
+        let blocks = chain_builder.initialize_single_branch_and_import(4).await;
+        let genesis = chain_builder.genesis_id();
+        *favourite_blocks.0.lock() = vec![id(&blocks[3])];
+
+        strategy.proposal_top(id(&blocks[3]), &genesis);
+        assert_eq!(strategy.parents.len(), 4);
+
+        strategy.proposal_top(id(&blocks[3]), &id(&blocks[1]));
+        assert_eq!(strategy.parents.len(), 2);
+    }
+}
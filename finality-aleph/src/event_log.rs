@@ -0,0 +1,303 @@
+//! An optional log of consensus-significant events, written as newline-delimited JSON with
+//! a stable schema, so that the events can be processed without parsing free-form log lines.
+use std::{
+    fs::{File, OpenOptions},
+    io::{Error as IoError, Write},
+    path::Path,
+    sync::Arc,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use log::warn;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aleph_primitives::{AccountId, BanConfig, BlockHash, BlockNumber},
+    justification::AlephJustification,
+};
+
+const LOG_TARGET: &str = "aleph-event-log";
+
+/// Version of the schema of the written records. Bumped whenever an existing field changes its
+/// meaning or disappears, adding new events or fields does not change it.
+pub const EVENT_LOG_SCHEMA_VERSION: u32 = 1;
+
+/// The kind of a committee justification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitteeJustificationKind {
+    /// Signatures of the committee.
+    Multisignature,
+    /// An aggregated BLS signature of the committee.
+    BlsMultisignature,
+}
+
+/// The kind of an emergency justification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmergencyJustificationKind {
+    /// A signature of the emergency finalizer.
+    Signature,
+    /// Signatures of a threshold of the emergency finalizer set.
+    Multisignature,
+}
+
+/// A validator banned from the committee.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BannedValidator {
+    /// The stash account of the validator.
+    pub account: AccountId,
+    /// The reason for the ban, in a human readable form.
+    pub reason: String,
+    /// The first era of the ban.
+    pub start_era: u32,
+}
+
+/// An event significant for consensus.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ConsensusEvent {
+    /// The node started taking part in a session, as the member with the given index, or as
+    /// a non-member if there is none.
+    SessionStarted {
+        session: u32,
+        node_index: Option<u32>,
+    },
+    /// The node stopped taking part in a session.
+    SessionStopped { session: u32 },
+    /// The AlephBFT version the node runs a session with. A change of the version between
+    /// consecutive sessions is a version switch.
+    AbftVersion {
+        session: u32,
+        version: u32,
+        legacy: bool,
+    },
+    /// A block producer created two different blocks for the same slot.
+    Equivocation { ours: bool, proof: String },
+    /// A block was finalized with a committee justification.
+    JustificationImported {
+        hash: BlockHash,
+        number: BlockNumber,
+        kind: CommitteeJustificationKind,
+        signers: Vec<u32>,
+    },
+    /// A block was finalized with an emergency justification.
+    EmergencyFinalization {
+        hash: BlockHash,
+        number: BlockNumber,
+        kind: EmergencyJustificationKind,
+        signers: Vec<u32>,
+    },
+    /// The ban configuration of the committee changed in a finalized block.
+    BanConfigChanged {
+        hash: BlockHash,
+        number: BlockNumber,
+        config: BanConfig,
+    },
+    /// Validators were banned from the committee in a finalized block.
+    ValidatorsBanned {
+        hash: BlockHash,
+        number: BlockNumber,
+        validators: Vec<BannedValidator>,
+    },
+}
+
+impl ConsensusEvent {
+    /// The event describing the finalization of a block with the given justification.
+    pub fn finalization(
+        hash: BlockHash,
+        number: BlockNumber,
+        justification: &AlephJustification,
+    ) -> Self {
+        use AlephJustification::*;
+        let signers = justification
+            .signers()
+            .into_iter()
+            .map(|index| index.0 as u32)
+            .collect();
+        match justification {
+            CommitteeMultisignature(_) => ConsensusEvent::JustificationImported {
+                hash,
+                number,
+                kind: CommitteeJustificationKind::Multisignature,
+                signers,
+            },
+            CommitteeBlsMultisignature(_) => ConsensusEvent::JustificationImported {
+                hash,
+                number,
+                kind: CommitteeJustificationKind::BlsMultisignature,
+                signers,
+            },
+            EmergencySignature(_) => ConsensusEvent::EmergencyFinalization {
+                hash,
+                number,
+                kind: EmergencyJustificationKind::Signature,
+                signers,
+            },
+            EmergencyMultisignature(_) => ConsensusEvent::EmergencyFinalization {
+                hash,
+                number,
+                kind: EmergencyJustificationKind::Multisignature,
+                signers,
+            },
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    schema_version: u32,
+    timestamp_ms: u64,
+    #[serde(flatten)]
+    event: &'a ConsensusEvent,
+}
+
+/// Where consensus events are written, if anywhere. Every event takes a single line.
+#[derive(Clone, Default)]
+pub struct EventLog {
+    file: Option<Arc<Mutex<File>>>,
+}
+
+impl EventLog {
+    /// A log that drops all the events.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    /// A log appending the events to the file at `path`, which is created if needed.
+    pub fn new(path: &Path) -> Result<Self, IoError> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(EventLog {
+            file: Some(Arc::new(Mutex::new(file))),
+        })
+    }
+
+    /// Whether the events are written anywhere, so that preparing them can be skipped otherwise.
+    pub fn is_enabled(&self) -> bool {
+        self.file.is_some()
+    }
+
+    pub fn emit(&self, event: ConsensusEvent) {
+        let file = match &self.file {
+            Some(file) => file,
+            None => return,
+        };
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_millis() as u64)
+            .unwrap_or_default();
+        let mut line = match serde_json::to_vec(&Record {
+            schema_version: EVENT_LOG_SCHEMA_VERSION,
+            timestamp_ms,
+            event: &event,
+        }) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!(
+                    target: LOG_TARGET,
+                    "Failed to serialize event {:?}: {}", event, e
+                );
+                return;
+            }
+        };
+        line.push(b'\n');
+        // A single write, so that lines of concurrently emitted events do not interleave.
+        if let Err(e) = file.lock().write_all(&line) {
+            warn!(
+                target: LOG_TARGET,
+                "Failed to write event {:?}: {}", event, e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use serde_json::{json, Value};
+
+    use super::{ConsensusEvent, EventLog};
+    use crate::{
+        aleph_primitives::{AuthoritySignature, BlockHash},
+        justification::AlephJustification,
+    };
+
+    fn read_lines(path: &std::path::Path) -> Vec<Value> {
+        fs::read_to_string(path)
+            .expect("the log should have been written")
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("every line should be json"))
+            .collect()
+    }
+
+    #[test]
+    fn writes_one_line_per_event() {
+        let path = env::temp_dir().join(format!("aleph-event-log-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let log = EventLog::new(&path).expect("the file should be created");
+        assert!(log.is_enabled());
+        log.emit(ConsensusEvent::SessionStarted {
+            session: 7,
+            node_index: Some(2),
+        });
+        log.emit(ConsensusEvent::AbftVersion {
+            session: 7,
+            version: 3,
+            legacy: false,
+        });
+        log.emit(ConsensusEvent::SessionStopped { session: 7 });
+
+        // A reopened log appends instead of truncating.
+        EventLog::new(&path)
+            .expect("the file should be opened")
+            .emit(ConsensusEvent::Equivocation {
+                ours: false,
+                proof: "two blocks".to_string(),
+            });
+
+        let lines = read_lines(&path);
+        fs::remove_file(&path).expect("the file should exist");
+        assert_eq!(lines.len(), 4);
+        for line in &lines {
+            assert_eq!(line["schema_version"], json!(1));
+            assert!(line["timestamp_ms"].is_u64());
+        }
+        assert_eq!(lines[0]["event"], json!("session_started"));
+        assert_eq!(lines[0]["session"], json!(7));
+        assert_eq!(lines[0]["node_index"], json!(2));
+        assert_eq!(lines[1]["event"], json!("abft_version"));
+        assert_eq!(lines[1]["legacy"], json!(false));
+        assert_eq!(lines[2]["event"], json!("session_stopped"));
+        assert_eq!(lines[3]["event"], json!("equivocation"));
+        assert_eq!(lines[3]["proof"], json!("two blocks"));
+    }
+
+    #[test]
+    fn disabled_log_writes_nothing() {
+        let log = EventLog::disabled();
+        assert!(!log.is_enabled());
+        log.emit(ConsensusEvent::SessionStopped { session: 0 });
+    }
+
+    #[test]
+    fn emergency_justifications_are_emergency_finalizations() {
+        let justification = AlephJustification::EmergencyMultisignature(vec![
+            (
+                0,
+                AuthoritySignature::from(sp_core::ed25519::Signature::from_raw([0; 64])),
+            ),
+            (
+                3,
+                AuthoritySignature::from(sp_core::ed25519::Signature::from_raw([1; 64])),
+            ),
+        ]);
+        let event = ConsensusEvent::finalization(BlockHash::repeat_byte(1), 5, &justification);
+        let event = serde_json::to_value(event).expect("events should serialize");
+        assert_eq!(event["event"], json!("emergency_finalization"));
+        assert_eq!(event["kind"], json!("multisignature"));
+        assert_eq!(event["signers"], json!([0, 3]));
+        assert_eq!(event["number"], json!(5));
+    }
+}
@@ -10,7 +10,9 @@ use sp_runtime::{
 };
 
 use crate::{
-    aleph_primitives::{BlockHash, BlockNumber},
+    aleph_primitives::{BlockHash, BlockNumber, ALEPH_ENGINE_ID},
+    event_log::{ConsensusEvent, EventLog},
+    justification::backwards_compatible_decode,
     metrics::Checkpoint,
     BlockId, TimingBlockMetrics,
 };
@@ -27,6 +29,7 @@ where
 {
     client: Arc<C>,
     metrics: TimingBlockMetrics,
+    event_log: EventLog,
     phantom: PhantomData<(B, BE)>,
 }
 
@@ -36,10 +39,11 @@ where
     BE: Backend<B>,
     C: HeaderBackend<B> + LockImportRun<B, BE> + Finalizer<B, BE>,
 {
-    pub(crate) fn new(client: Arc<C>, metrics: TimingBlockMetrics) -> Self {
+    pub(crate) fn new(client: Arc<C>, metrics: TimingBlockMetrics, event_log: EventLog) -> Self {
         AlephFinalizer {
             client,
             metrics,
+            event_log,
             phantom: PhantomData,
         }
     }
@@ -64,6 +68,20 @@ where
 
         debug!(target: "aleph-finality", "Finalizing block with hash {:?} and number {:?}. Previous best: #{:?}.", hash, number, status.finalized_number);
 
+        let event = match &justification {
+            _ if !self.event_log.is_enabled() => None,
+            (ALEPH_ENGINE_ID, encoded) => match backwards_compatible_decode(encoded.clone()) {
+                Ok(justification) => {
+                    Some(ConsensusEvent::finalization(hash, number, &justification))
+                }
+                Err(e) => {
+                    warn!(target: "aleph-finality", "Failed to decode justification of block {:?} for the event log: {:?}", hash, e);
+                    None
+                }
+            },
+            _ => None,
+        };
+
         let update_res = self.client.lock_import_and_run(|import_op| {
             // NOTE: all other finalization logic should come here, inside the lock
             self.client
@@ -76,6 +94,9 @@ where
                 debug!(target: "aleph-finality", "Successfully finalized block with hash {:?} and number {:?}. Current best: #{:?}.", hash, number, status.best_number);
                 self.metrics
                     .report_block(hash, Instant::now(), Checkpoint::Finalized);
+                if let Some(event) = event {
+                    self.event_log.emit(event);
+                }
             }
             Err(_) => {
                 debug!(target: "aleph-finality", "Failed to finalize block with hash {:?} and number {:?}. Current best: #{:?}.", hash, number, status.best_number)
@@ -55,6 +55,8 @@ enum VersionedAlephJustification {
     V1(AlephJustificationV1),
     V2(AlephJustificationV2),
     V3(AlephJustification),
+    // Same as V3, but also allows BLS multisignatures, so older nodes recognize them as unknown.
+    V4(AlephJustification),
 }
 
 fn encode_with_version(version: Version, payload: &[u8]) -> Vec<u8> {
@@ -97,6 +99,7 @@ impl Encode for VersionedAlephJustification {
                 V1(justification) => justification.size_hint(),
                 V2(justification) => justification.size_hint(),
                 V3(justification) => justification.size_hint(),
+                V4(justification) => justification.size_hint(),
             }
     }
 
@@ -107,6 +110,7 @@ impl Encode for VersionedAlephJustification {
             V1(justification) => encode_with_version(Version(1), &justification.encode()),
             V2(justification) => encode_with_version(Version(2), &justification.encode()),
             V3(justification) => encode_with_version(Version(3), &justification.encode()),
+            V4(justification) => encode_with_version(Version(4), &justification.encode()),
         }
     }
 }
@@ -120,6 +124,7 @@ impl Decode for VersionedAlephJustification {
             Version(1) => Ok(V1(AlephJustificationV1::decode(input)?)),
             Version(2) => Ok(V2(AlephJustificationV2::decode(input)?)),
             Version(3) => Ok(V3(AlephJustification::decode(input)?)),
+            Version(4) => Ok(V4(AlephJustification::decode(input)?)),
             _ => {
                 let mut payload = vec![0; num_bytes.into()];
                 input.read(payload.as_mut_slice())?;
@@ -181,7 +186,7 @@ pub fn backwards_compatible_decode(
             match justification {
                 V1(justification) => Ok(justification.into()),
                 V2(justification) => Ok(justification.into()),
-                V3(justification) => Ok(justification),
+                V3(justification) | V4(justification) => Ok(justification),
                 Other(version, _) => {
                     // it is a coincidence that sometimes pre-compatibility legacy justification second word,
                     // which is in VersionedAlephJustification byte_count_size, can be small enough
@@ -198,7 +203,13 @@ pub fn backwards_compatible_decode(
 
 /// Encodes the justification in a way that is forwards compatible with future versions.
 pub fn versioned_encode(justification: AlephJustification) -> Vec<u8> {
-    VersionedAlephJustification::V3(justification).encode()
+    match justification {
+        AlephJustification::CommitteeBlsMultisignature(_)
+        | AlephJustification::EmergencyMultisignature(_) => {
+            VersionedAlephJustification::V4(justification).encode()
+        }
+        _ => VersionedAlephJustification::V3(justification).encode(),
+    }
 }
 
 #[cfg(test)]
@@ -211,7 +222,7 @@ mod test {
         VersionedAlephJustification,
     };
     use crate::{
-        aleph_primitives::{AuthorityPair, AuthoritySignature},
+        aleph_primitives::{AuthorityPair, AuthoritySignature, BlsSecretKey, BlsVerifier},
         crypto::{Signature, SignatureV1},
         justification::AlephJustification,
         NodeCount, SignatureSet, Version,
@@ -276,6 +287,54 @@ mod test {
         assert_eq!(decoded, Ok(just_v3));
     }
 
+    #[test]
+    fn correctly_decodes_v4_bls_committee() {
+        let secret_keys: Vec<_> = (0..4)
+            .map(|i| BlsSecretKey::from_seed(format!("//{i}").as_bytes()))
+            .collect();
+        let public_keys: Vec<_> = secret_keys.iter().map(|key| key.public()).collect();
+        let multisignature = BlsVerifier::new(&public_keys)
+            .aggregate(
+                secret_keys
+                    .iter()
+                    .enumerate()
+                    .map(|(i, key)| (i, key.sign(&[0u8, 0u8, 0u8, 0u8]))),
+            )
+            .expect("signatures are well formed");
+
+        let just_v4 = AlephJustification::CommitteeBlsMultisignature(multisignature);
+        let encoded_just = versioned_encode(just_v4.clone());
+        assert!(matches!(
+            VersionedAlephJustification::decode(&mut encoded_just.as_slice()),
+            Ok(VersionedAlephJustification::V4(_))
+        ));
+        let decoded = backwards_compatible_decode(encoded_just);
+        assert_eq!(decoded, Ok(just_v4));
+    }
+
+    #[test]
+    fn correctly_decodes_v4_emergency_multisignature() {
+        let signatures = (0..3)
+            .map(|i| {
+                (
+                    i,
+                    AuthorityPair::generate()
+                        .0
+                        .sign(vec![0u8, 0u8, 0u8, 0u8].as_slice()),
+                )
+            })
+            .collect();
+
+        let just_v4 = AlephJustification::EmergencyMultisignature(signatures);
+        let encoded_just = versioned_encode(just_v4.clone());
+        assert!(matches!(
+            VersionedAlephJustification::decode(&mut encoded_just.as_slice()),
+            Ok(VersionedAlephJustification::V4(_))
+        ));
+        let decoded = backwards_compatible_decode(encoded_just);
+        assert_eq!(decoded, Ok(just_v4));
+    }
+
     #[test]
     fn correctly_decodes_other() {
         let other = VersionedAlephJustification::Other(Version(43), vec![21, 37]);
@@ -292,9 +351,7 @@ mod test {
             Ok(AlephJustification::CommitteeMultisignature(signature)) => {
                 assert_eq!(signature.size(), NodeCount(expected_node_count))
             }
-            Ok(AlephJustification::EmergencySignature(_)) => {
-                panic!("decoded V1 as emergency signature")
-            }
+            Ok(justification) => panic!("decoded V1 as {justification:?}"),
             Err(e) => panic!("decoding V1 failed: {e}"),
         }
     }
@@ -3,8 +3,9 @@ use sp_runtime::Justification;
 
 use crate::{
     abft::SignatureSet,
-    aleph_primitives::{AuthoritySignature, ALEPH_ENGINE_ID},
+    aleph_primitives::{AuthoritySignature, BlsMultisignature, ALEPH_ENGINE_ID},
     crypto::Signature,
+    NodeIndex,
 };
 
 mod compatibility;
@@ -13,12 +14,38 @@ pub use compatibility::{backwards_compatible_decode, versioned_encode, Error as
 
 const LOG_TARGET: &str = "aleph-justification";
 
-/// A proof of block finality, currently in the form of a sufficiently long list of signatures or a
-/// sudo signature of a block for emergency finalization.
+/// A proof of block finality, currently in the form of a sufficiently long list of signatures, an
+/// aggregated BLS signature of the committee, or for emergency finalization either a sudo
+/// signature of a block or signatures of a threshold of emergency keys, indexed by the position
+/// of the key in the emergency finalizer set.
 #[derive(Clone, Encode, Decode, Debug, PartialEq, Eq)]
 pub enum AlephJustification {
     CommitteeMultisignature(SignatureSet<Signature>),
     EmergencySignature(AuthoritySignature),
+    CommitteeBlsMultisignature(BlsMultisignature),
+    EmergencyMultisignature(Vec<(u32, AuthoritySignature)>),
+}
+
+impl AlephJustification {
+    /// Indices of the signers of the justification, within the committee for committee
+    /// justifications and within the emergency finalizer set for emergency multisignatures.
+    /// Empty for sudo signatures.
+    pub fn signers(&self) -> Vec<NodeIndex> {
+        use AlephJustification::*;
+        match self {
+            CommitteeMultisignature(multisignature) => {
+                multisignature.iter().map(|(index, _)| index).collect()
+            }
+            EmergencySignature(_) => Vec::new(),
+            CommitteeBlsMultisignature(multisignature) => {
+                multisignature.signers().map(NodeIndex).collect()
+            }
+            EmergencyMultisignature(signatures) => signatures
+                .iter()
+                .map(|(index, _)| NodeIndex(*index as usize))
+                .collect(),
+        }
+    }
 }
 
 impl From<AlephJustification> for Justification {
@@ -14,7 +14,7 @@ use parity_scale_codec::{Decode, Encode, Output};
 use primitives as aleph_primitives;
 use primitives::{AuthorityId, Block as AlephBlock, BlockHash, BlockNumber, Hash as AlephHash};
 use sc_client_api::{
-    Backend, BlockBackend, BlockchainEvents, Finalizer, LockImportRun, StorageProvider,
+    AuxStore, Backend, BlockBackend, BlockchainEvents, Finalizer, LockImportRun, StorageProvider,
 };
 use sc_consensus::BlockImport;
 use sc_network::NetworkService;
@@ -31,11 +31,11 @@ use crate::{
         CurrentNetworkData, Keychain, LegacyNetworkData, NodeCount, NodeIndex, Recipient,
         SignatureSet, SpawnHandle, CURRENT_VERSION, LEGACY_VERSION,
     },
-    aggregation::{CurrentRmcNetworkData, LegacyRmcNetworkData},
+    aggregation::{BlsRmcNetworkData, CurrentRmcData, CurrentRmcNetworkData, LegacyRmcNetworkData},
     block::UnverifiedHeader,
     compatibility::{Version, Versioned},
     network::{data::split::Split, session::MAX_MESSAGE_SIZE as MAX_AUTHENTICATION_MESSAGE_SIZE},
-    session::{SessionBoundaries, SessionBoundaryInfo, SessionId},
+    session::SessionBoundaries,
     sync::MAX_MESSAGE_SIZE as MAX_BLOCK_SYNC_MESSAGE_SIZE,
     VersionedTryFromError::{ExpectedNewGotOld, ExpectedOldGotNew},
 };
@@ -46,6 +46,7 @@ mod block;
 mod compatibility;
 mod crypto;
 mod data_io;
+mod event_log;
 mod finalization;
 mod idx_to_account;
 mod import;
@@ -53,7 +54,9 @@ mod justification;
 mod metrics;
 mod network;
 mod nodes;
+mod participation;
 mod party;
+mod replay;
 mod runtime_api;
 mod session;
 mod session_map;
@@ -64,18 +67,33 @@ pub mod testing;
 
 pub use crate::{
     block::{
-        substrate::{BlockImporter, Justification, JustificationTranslator, SubstrateChainStatus},
+        substrate::{
+            BlockImporter, Justification, JustificationTranslator, SessionVerificationError,
+            SessionVerifier, SubstrateChainStatus,
+        },
         BlockId,
     },
+    crypto::bls_secret_key,
+    data_io::ProposalStrategyKind,
+    event_log::{
+        BannedValidator, CommitteeJustificationKind, ConsensusEvent, EmergencyJustificationKind,
+        EventLog, EVENT_LOG_SCHEMA_VERSION,
+    },
     import::{AlephBlockImport, RedirectingBlockImport, TracingBlockImport},
-    justification::AlephJustification,
+    justification::{backwards_compatible_decode, versioned_encode, AlephJustification},
     metrics::TimingBlockMetrics,
     network::{
-        address_cache::{ValidatorAddressCache, ValidatorAddressingInfo},
+        address_cache::{ValidatorAddressCache, ValidatorAddressRecord, ValidatorAddressingInfo},
+        session::{FinalityVersions, SessionConnectivity, ValidatorNetworkStatus},
         Protocol, ProtocolNaming,
     },
     nodes::run_validator_node,
-    session::SessionPeriod,
+    participation::{FinalityParticipation, MemberParticipation, SessionParticipation},
+    replay::{
+        replay_session, Interpretation, MemberBackup, OrderedItem, ReplayError, SessionReplay,
+    },
+    session::{SessionBoundaryInfo, SessionId, SessionPeriod},
+    session_map::{SessionHistory, SessionHistoryError},
     sync_oracle::SyncOracle,
 };
 
@@ -110,8 +128,29 @@ pub struct MillisecsPerBlock(pub u64);
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, Encode, Decode)]
 pub struct UnitCreationDelay(pub u64);
 
+/// Milliseconds after which a block hash without a multisignature is multicast again.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, Encode, Decode)]
+pub struct RemulticastTimeout(pub u64);
+
+/// Only every block with a number divisible by this, and the last block of every session, gets
+/// a justification from the aggregator. Taken from the AlephBFT config of the session, so that it
+/// is the same for the whole committee.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Encode, Decode)]
+pub struct AggregationBatchLen(pub BlockNumber);
+
+impl Default for AggregationBatchLen {
+    fn default() -> Self {
+        AggregationBatchLen(1)
+    }
+}
+
+/// The number of the most recent sessions, including the upcoming one, for which authority data
+/// is kept in the session history.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash, Ord, PartialOrd, Encode, Decode)]
+pub struct SessionHistoryDepth(pub u32);
+
 type LegacySplitData = Split<LegacyNetworkData, LegacyRmcNetworkData>;
-type CurrentSplitData<UH> = Split<CurrentNetworkData<UH>, CurrentRmcNetworkData>;
+type CurrentSplitData<UH> = Split<CurrentNetworkData<UH>, CurrentRmcData>;
 
 impl Versioned for LegacyNetworkData {
     const VERSION: Version = Version(LEGACY_VERSION);
@@ -220,6 +259,7 @@ pub trait ClientForAleph<B, BE>:
     + BlockchainEvents<B>
     + BlockBackend<B>
     + StorageProvider<B, BE>
+    + AuxStore
 where
     BE: Backend<B>,
     B: Block,
@@ -238,7 +278,8 @@ where
         + BlockchainEvents<B>
         + BlockImport<B, Error = sp_consensus::Error>
         + BlockBackend<B>
-        + StorageProvider<B, BE>,
+        + StorageProvider<B, BE>
+        + AuxStore,
 {
 }
 
@@ -263,9 +304,11 @@ pub struct AlephConfig<C, SC> {
     pub block_rx: mpsc::UnboundedReceiver<AlephBlock>,
     pub metrics: TimingBlockMetrics,
     pub registry: Option<Registry>,
-    pub session_period: SessionPeriod,
+    pub session_info: SessionBoundaryInfo,
     pub millisecs_per_block: MillisecsPerBlock,
     pub unit_creation_delay: UnitCreationDelay,
+    pub remulticast_timeout: RemulticastTimeout,
+    pub session_history_depth: Option<SessionHistoryDepth>,
     pub backup_saving_path: Option<PathBuf>,
     pub external_addresses: Vec<String>,
     pub validator_port: u16,
@@ -273,4 +316,8 @@ pub struct AlephConfig<C, SC> {
     pub rate_limiter_config: RateLimiterConfig,
     pub sync_oracle: SyncOracle,
     pub validator_address_cache: Option<ValidatorAddressCache>,
+    pub validator_network_status: ValidatorNetworkStatus,
+    pub finality_participation: FinalityParticipation,
+    pub event_log: EventLog,
+    pub proposal_strategy: ProposalStrategyKind,
 }
@@ -0,0 +1,271 @@
+use std::{collections::BTreeSet, sync::Arc, time::Duration};
+
+use log::trace;
+use parking_lot::Mutex;
+use substrate_prometheus_endpoint::{
+    register, Counter, CounterVec, Gauge, GaugeVec, Opts, PrometheusError, Registry, U64,
+};
+
+use crate::{metrics::LOG_TARGET, SessionId};
+
+/// How many sessions are reported at once, older ones are removed from the metrics.
+const SESSIONS_KEPT: u32 = 10;
+
+/// Metrics of the AlephBFT sessions the node takes part in, labelled with the session.
+#[derive(Clone)]
+pub enum AbftMetrics {
+    Prometheus {
+        units_created: CounterVec<U64>,
+        round: GaugeVec<U64>,
+        ordered_data: CounterVec<U64>,
+        first_ordered_time: GaugeVec<U64>,
+        missing_members: GaugeVec<U64>,
+        late_members: GaugeVec<U64>,
+        backup_bytes: CounterVec<U64>,
+        alert_messages: CounterVec<U64>,
+        sessions: Arc<Mutex<BTreeSet<u32>>>,
+    },
+    Noop,
+}
+
+impl AbftMetrics {
+    pub fn new(registry: Option<&Registry>) -> Result<Self, PrometheusError> {
+        let registry = match registry {
+            Some(registry) => registry,
+            None => return Ok(AbftMetrics::Noop),
+        };
+        let counter = |name: &str, help: &str| -> Result<CounterVec<U64>, PrometheusError> {
+            register(
+                CounterVec::new(Opts::new(name, help), &["session"])?,
+                registry,
+            )
+        };
+        let gauge = |name: &str, help: &str| -> Result<GaugeVec<U64>, PrometheusError> {
+            register(
+                GaugeVec::new(Opts::new(name, help), &["session"])?,
+                registry,
+            )
+        };
+
+        Ok(AbftMetrics::Prometheus {
+            units_created: counter(
+                "aleph_abft_units_created",
+                "Number of units created by this node in the session",
+            )?,
+            round: gauge(
+                "aleph_abft_round",
+                "Highest round of the units created by this node in the session",
+            )?,
+            ordered_data: counter(
+                "aleph_abft_ordered_data",
+                "Number of data items ordered in the session",
+            )?,
+            first_ordered_time: gauge(
+                "aleph_abft_first_ordered_time",
+                "Time in milliseconds from the start of the session to the first ordered data",
+            )?,
+            missing_members: gauge(
+                "aleph_abft_missing_members",
+                "Number of committee members none of whose data was ordered in the session yet",
+            )?,
+            late_members: gauge(
+                "aleph_abft_late_members",
+                "Number of committee members whose first data was ordered late in the session",
+            )?,
+            backup_bytes: counter(
+                "aleph_abft_backup_bytes",
+                "Number of bytes written to the AlephBFT backup in the session",
+            )?,
+            alert_messages: counter(
+                "aleph_abft_alert_messages",
+                "Number of messages of the alert subprotocol, fork alerts and their multicast, received in the session",
+            )?,
+            sessions: Arc::new(Mutex::new(BTreeSet::new())),
+        })
+    }
+
+    pub fn noop() -> Self {
+        AbftMetrics::Noop
+    }
+
+    /// Metrics of the given session. Removes the metrics of all sessions that are old enough.
+    pub fn session(&self, session_id: SessionId) -> SessionAbftMetrics {
+        let AbftMetrics::Prometheus {
+            units_created,
+            round,
+            ordered_data,
+            first_ordered_time,
+            missing_members,
+            late_members,
+            backup_bytes,
+            alert_messages,
+            sessions,
+        } = self
+        else {
+            return SessionAbftMetrics::Noop;
+        };
+
+        let old_sessions = {
+            let mut sessions = sessions.lock();
+            sessions.insert(session_id.0);
+            // Sessions are not necessarily consecutive, the node might have skipped some.
+            let kept = sessions.split_off(&session_id.0.saturating_sub(SESSIONS_KEPT - 1));
+            std::mem::replace(&mut *sessions, kept)
+        };
+        for old_session in old_sessions {
+            let old_label = old_session.to_string();
+            let labels = [old_label.as_str()];
+            // Some of the metrics might have never been reported in that session.
+            let _ = units_created.remove_label_values(&labels);
+            let _ = round.remove_label_values(&labels);
+            let _ = ordered_data.remove_label_values(&labels);
+            let _ = first_ordered_time.remove_label_values(&labels);
+            let _ = missing_members.remove_label_values(&labels);
+            let _ = late_members.remove_label_values(&labels);
+            let _ = backup_bytes.remove_label_values(&labels);
+            let _ = alert_messages.remove_label_values(&labels);
+            trace!(
+                target: LOG_TARGET,
+                "Removed AlephBFT metrics of session {}.",
+                old_session
+            );
+        }
+
+        let label = session_id.0.to_string();
+        let labels = [label.as_str()];
+        SessionAbftMetrics::Prometheus {
+            units_created: units_created.with_label_values(&labels),
+            round: round.with_label_values(&labels),
+            ordered_data: ordered_data.with_label_values(&labels),
+            first_ordered_time: first_ordered_time.with_label_values(&labels),
+            missing_members: missing_members.with_label_values(&labels),
+            late_members: late_members.with_label_values(&labels),
+            backup_bytes: backup_bytes.with_label_values(&labels),
+            alert_messages: alert_messages.with_label_values(&labels),
+        }
+    }
+}
+
+/// Metrics of a single AlephBFT session.
+#[derive(Clone)]
+pub enum SessionAbftMetrics {
+    Prometheus {
+        units_created: Counter<U64>,
+        round: Gauge<U64>,
+        ordered_data: Counter<U64>,
+        first_ordered_time: Gauge<U64>,
+        missing_members: Gauge<U64>,
+        late_members: Gauge<U64>,
+        backup_bytes: Counter<U64>,
+        alert_messages: Counter<U64>,
+    },
+    Noop,
+}
+
+impl SessionAbftMetrics {
+    pub fn noop() -> Self {
+        SessionAbftMetrics::Noop
+    }
+
+    pub fn report_unit_created(&self) {
+        if let SessionAbftMetrics::Prometheus { units_created, .. } = self {
+            units_created.inc();
+        }
+    }
+
+    /// Reports a unit created by this node in the given round, the gauge only ever grows.
+    pub fn report_round(&self, unit_round: u16) {
+        if let SessionAbftMetrics::Prometheus { round, .. } = self {
+            if round.get() < unit_round as u64 {
+                round.set(unit_round as u64);
+            }
+        }
+    }
+
+    pub fn report_ordered_data(&self) {
+        if let SessionAbftMetrics::Prometheus { ordered_data, .. } = self {
+            ordered_data.inc();
+        }
+    }
+
+    pub fn report_first_ordered(&self, since_start: Duration) {
+        if let SessionAbftMetrics::Prometheus {
+            first_ordered_time, ..
+        } = self
+        {
+            first_ordered_time.set(since_start.as_millis() as u64);
+        }
+    }
+
+    pub fn report_members(&self, missing: usize, late: usize) {
+        if let SessionAbftMetrics::Prometheus {
+            missing_members,
+            late_members,
+            ..
+        } = self
+        {
+            missing_members.set(missing as u64);
+            late_members.set(late as u64);
+        }
+    }
+
+    pub fn report_backup_written(&self, bytes: usize) {
+        if let SessionAbftMetrics::Prometheus { backup_bytes, .. } = self {
+            backup_bytes.inc_by(bytes as u64);
+        }
+    }
+
+    pub fn report_alert_message(&self) {
+        if let SessionAbftMetrics::Prometheus { alert_messages, .. } = self {
+            alert_messages.inc();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use substrate_prometheus_endpoint::{prometheus::core::Collector, Registry};
+
+    use super::*;
+
+    fn labelled_sessions(metrics: &AbftMetrics) -> Vec<String> {
+        match metrics {
+            AbftMetrics::Prometheus { units_created, .. } => {
+                let mut labelled: Vec<_> = units_created
+                    .collect()
+                    .iter()
+                    .flat_map(|family| family.get_metric())
+                    .flat_map(|metric| metric.get_label())
+                    .map(|label| label.get_value().to_string())
+                    .collect();
+                labelled.sort();
+                labelled
+            }
+            AbftMetrics::Noop => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn removes_all_sessions_outside_of_window() {
+        let metrics = AbftMetrics::new(Some(&Registry::new())).unwrap();
+        for session in [1, 3, 4, 12] {
+            metrics.session(SessionId(session)).report_unit_created();
+        }
+        assert_eq!(labelled_sessions(&metrics), vec!["12", "3", "4"]);
+        metrics.session(SessionId(30)).report_unit_created();
+        assert_eq!(labelled_sessions(&metrics), vec!["30"]);
+    }
+
+    #[test]
+    fn round_only_grows() {
+        let metrics = AbftMetrics::new(Some(&Registry::new()))
+            .unwrap()
+            .session(SessionId(0));
+        metrics.report_round(3);
+        metrics.report_round(1);
+        match metrics {
+            SessionAbftMetrics::Prometheus { round, .. } => assert_eq!(round.get(), 3),
+            SessionAbftMetrics::Noop => panic!("metrics should be registered"),
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use substrate_prometheus_endpoint::{
+    exponential_buckets, register, Counter, Gauge, Histogram, HistogramOpts, PrometheusError,
+    Registry, U64,
+};
+
+use crate::{abft::SignatureSet, aleph_primitives::BlockHash};
+
+/// Metrics of the block signature aggregator, shared by the aggregators of all sessions.
+#[derive(Clone)]
+pub enum AggregatorMetrics {
+    Prometheus {
+        pending_hashes: Gauge<U64>,
+        multisigned_time: Histogram,
+        signatures_per_hash: Histogram,
+        remulticasts: Counter<U64>,
+    },
+    Noop,
+}
+
+impl AggregatorMetrics {
+    pub fn new(registry: Option<&Registry>) -> Result<Self, PrometheusError> {
+        let registry = match registry {
+            Some(registry) => registry,
+            None => return Ok(AggregatorMetrics::Noop),
+        };
+
+        Ok(AggregatorMetrics::Prometheus {
+            pending_hashes: register(
+                Gauge::new(
+                    "aleph_aggregator_pending_hashes",
+                    "Number of block hashes waiting for a multisignature",
+                )?,
+                registry,
+            )?,
+            multisigned_time: register(
+                Histogram::with_opts(
+                    HistogramOpts::new(
+                        "aleph_aggregator_multisigned_time",
+                        "Time in milliseconds from starting the aggregation to the multisignature",
+                    )
+                    .buckets(exponential_buckets(50.0, 2.0, 12)?),
+                )?,
+                registry,
+            )?,
+            signatures_per_hash: register(
+                Histogram::with_opts(
+                    HistogramOpts::new(
+                        "aleph_aggregator_signatures_per_hash",
+                        "Number of signatures collected for a multisigned block hash",
+                    )
+                    .buckets(exponential_buckets(1.0, 2.0, 10)?),
+                )?,
+                registry,
+            )?,
+            remulticasts: register(
+                Counter::new(
+                    "aleph_aggregator_remulticasts",
+                    "Number of block hashes multicast again after a timeout",
+                )?,
+                registry,
+            )?,
+        })
+    }
+
+    pub fn noop() -> Self {
+        AggregatorMetrics::Noop
+    }
+}
+
+impl<S: Clone> current_aleph_aggregator::Metrics<BlockHash, SignatureSet<S>> for AggregatorMetrics {
+    fn report_pending(&mut self, pending: usize) {
+        if let AggregatorMetrics::Prometheus { pending_hashes, .. } = self {
+            pending_hashes.set(pending as u64);
+        }
+    }
+
+    fn report_aggregation_complete(
+        &mut self,
+        _: BlockHash,
+        duration: Duration,
+        multisignature: &SignatureSet<S>,
+    ) {
+        if let AggregatorMetrics::Prometheus {
+            multisigned_time,
+            signatures_per_hash,
+            ..
+        } = self
+        {
+            multisigned_time.observe(duration.as_millis() as f64);
+            signatures_per_hash.observe(multisignature.iter().count() as f64);
+        }
+    }
+
+    fn report_remulticast(&mut self, _: BlockHash) {
+        if let AggregatorMetrics::Prometheus { remulticasts, .. } = self {
+            remulticasts.inc();
+        }
+    }
+}
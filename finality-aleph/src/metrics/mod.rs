@@ -1,6 +1,12 @@
+mod abft;
+mod aggregation;
 mod chain_state;
+mod participation;
 mod timing;
 
+pub use abft::{AbftMetrics, SessionAbftMetrics};
+pub use aggregation::AggregatorMetrics;
 pub use chain_state::run_chain_state_metrics;
+pub use participation::ParticipationMetrics;
 pub use timing::{Checkpoint, TimingBlockMetrics};
 const LOG_TARGET: &str = "aleph-metrics";
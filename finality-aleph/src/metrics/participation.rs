@@ -0,0 +1,85 @@
+use substrate_prometheus_endpoint::{
+    register, GaugeVec, Opts, PrometheusError, Registry, F64, U64,
+};
+
+use crate::SessionId;
+
+/// Per session finality participation of the committee members.
+#[derive(Clone)]
+pub enum ParticipationMetrics {
+    Prometheus {
+        justified_blocks: GaugeVec<U64>,
+        participation: GaugeVec<F64>,
+    },
+    Noop,
+}
+
+impl ParticipationMetrics {
+    pub fn new(registry: Option<&Registry>) -> Result<Self, PrometheusError> {
+        let registry = match registry {
+            Some(registry) => registry,
+            None => return Ok(ParticipationMetrics::Noop),
+        };
+
+        Ok(ParticipationMetrics::Prometheus {
+            justified_blocks: register(
+                GaugeVec::new(
+                    Opts::new(
+                        "aleph_finality_justified_blocks",
+                        "Number of finalized blocks with committee justifications in the session",
+                    ),
+                    &["session"],
+                )?,
+                registry,
+            )?,
+            participation: register(
+                GaugeVec::new(
+                    Opts::new(
+                        "aleph_finality_participation",
+                        "Fraction of committee justifications in the session signed by the member, justifications only hold signatures of the fastest members up to the threshold",
+                    ),
+                    &["session", "member"],
+                )?,
+                registry,
+            )?,
+        })
+    }
+
+    pub fn noop() -> Self {
+        ParticipationMetrics::Noop
+    }
+
+    pub fn report_session(&self, session: SessionId, justified_blocks: u32, ratios: &[f64]) {
+        if let ParticipationMetrics::Prometheus {
+            justified_blocks: justified_blocks_gauge,
+            participation,
+        } = self
+        {
+            let session = session.0.to_string();
+            justified_blocks_gauge
+                .with_label_values(&[session.as_str()])
+                .set(justified_blocks as u64);
+            for (member, ratio) in ratios.iter().enumerate() {
+                participation
+                    .with_label_values(&[session.as_str(), member.to_string().as_str()])
+                    .set(*ratio);
+            }
+        }
+    }
+
+    pub fn remove_session(&self, session: SessionId, n_members: usize) {
+        if let ParticipationMetrics::Prometheus {
+            justified_blocks,
+            participation,
+        } = self
+        {
+            let session = session.0.to_string();
+            // Failing to remove only means the session was never reported.
+            let _ = justified_blocks.remove_label_values(&[session.as_str()]);
+            for member in 0..n_members {
+                let _ = participation
+                    .remove_label_values(&[session.as_str(), member.to_string().as_str()]);
+            }
+        }
+    }
+}
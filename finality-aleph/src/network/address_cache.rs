@@ -1,12 +1,23 @@
-use std::{collections::HashMap, fmt::Debug, num::NonZeroUsize, sync::Arc};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Debug,
+    fs,
+    io::Error as IoError,
+    num::NonZeroUsize,
+    path::PathBuf,
+    sync::Arc,
+};
 
+use log::warn;
 use lru::LruCache;
 use parking_lot::Mutex;
 use primitives::AccountId;
 use serde::{Deserialize, Serialize};
+use tokio::{runtime::Handle, task::spawn_blocking};
 
 use crate::{
-    abft::NodeIndex, idx_to_account::ValidatorIndexToAccountIdConverter, session::SessionId,
+    abft::NodeIndex, idx_to_account::ValidatorIndexToAccountIdConverter,
+    network::session::FinalityVersions, session::SessionId,
 };
 
 /// Network details for a given validator in a given session.
@@ -18,15 +29,139 @@ pub struct ValidatorAddressingInfo {
     pub network_level_address: String,
     /// PeerId of the validator used in validator (clique) network
     pub validator_network_peer_id: String,
+    /// Advertised addresses of the validator that we recently failed to connect to.
+    #[serde(default)]
+    pub unreachable_addresses: Vec<String>,
+    /// Finality versions the validator advertised support for, if it did.
+    #[serde(default)]
+    pub finality_versions: Option<FinalityVersions>,
+}
+
+/// Addresses used by a validator during a contiguous range of sessions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ValidatorAddressRecord {
+    /// Network level address of the validator, i.e. IP address (for validator network)
+    pub network_level_address: String,
+    /// PeerId of the validator used in validator (clique) network
+    pub validator_network_peer_id: String,
+    /// The first session in which the validator was seen using these addresses.
+    pub first_session: SessionId,
+    /// The last session in which the validator was seen using these addresses.
+    pub last_session: SessionId,
 }
 
-/// Stores most recent information about validator addresses.
+impl ValidatorAddressRecord {
+    fn new(info: &ValidatorAddressingInfo) -> Self {
+        ValidatorAddressRecord {
+            network_level_address: info.network_level_address.clone(),
+            validator_network_peer_id: info.validator_network_peer_id.clone(),
+            first_session: info.session,
+            last_session: info.session,
+        }
+    }
+
+    fn same_addresses(&self, info: &ValidatorAddressingInfo) -> bool {
+        self.network_level_address == info.network_level_address
+            && self.validator_network_peer_id == info.validator_network_peer_id
+    }
+
+    fn contains(&self, session: SessionId) -> bool {
+        self.first_session <= session && session <= self.last_session
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ValidatorEntry {
+    latest: ValidatorAddressingInfo,
+    history: VecDeque<ValidatorAddressRecord>,
+}
+
+impl ValidatorEntry {
+    fn new(info: ValidatorAddressingInfo) -> Self {
+        ValidatorEntry {
+            history: VecDeque::from([ValidatorAddressRecord::new(&info)]),
+            latest: info,
+        }
+    }
+
+    /// Updates the entry with new information, returns whether the validator was seen with new
+    /// addresses.
+    fn update(&mut self, mut info: ValidatorAddressingInfo) -> bool {
+        let addresses_changed = self.update_history(&info);
+        if info.session >= self.latest.session {
+            if self.latest.validator_network_peer_id == info.validator_network_peer_id
+                && info.unreachable_addresses.is_empty()
+            {
+                info.unreachable_addresses = self.latest.unreachable_addresses.clone();
+            }
+            if self.latest.validator_network_peer_id == info.validator_network_peer_id
+                && info.finality_versions.is_none()
+            {
+                info.finality_versions = self.latest.finality_versions;
+            }
+            self.latest = info;
+        }
+        addresses_changed
+    }
+
+    /// Returns whether a new record was added, extending the last one does not count.
+    fn update_history(&mut self, info: &ValidatorAddressingInfo) -> bool {
+        if self
+            .history
+            .iter()
+            .any(|record| record.same_addresses(info) && record.contains(info.session))
+        {
+            return false;
+        }
+        match self.history.back_mut() {
+            Some(record) if record.same_addresses(info) && info.session > record.last_session => {
+                record.last_session = info.session;
+                return false;
+            }
+            _ => {
+                self.history.push_back(ValidatorAddressRecord::new(info));
+                if self.history.len() > VALIDATOR_ADDRESS_HISTORY_SIZE {
+                    self.history.pop_front();
+                }
+            }
+        }
+        true
+    }
+
+    fn at_session(&self, session: SessionId) -> Option<ValidatorAddressingInfo> {
+        if self.latest.session == session {
+            return Some(self.latest.clone());
+        }
+        self.history
+            .iter()
+            .rev()
+            .find(|record| record.contains(session))
+            .map(|record| ValidatorAddressingInfo {
+                session,
+                network_level_address: record.network_level_address.clone(),
+                validator_network_peer_id: record.validator_network_peer_id.clone(),
+                unreachable_addresses: Vec::new(),
+                finality_versions: None,
+            })
+    }
+}
+
+/// Stores most recent information about validator addresses, together with a bounded history of
+/// addresses used by every validator. If created with a path, the history is kept on disk and
+/// survives restarts. It is saved whenever some validator changes its addresses, so the sessions
+/// in which the addresses were last seen might be slightly outdated after a restart.
 #[derive(Clone)]
 pub struct ValidatorAddressCache {
-    data: Arc<Mutex<LruCache<AccountId, ValidatorAddressingInfo>>>,
+    data: Arc<Mutex<LruCache<AccountId, ValidatorEntry>>>,
+    path: Option<Arc<PathBuf>>,
+    /// The most recent encoded contents not yet written to the file.
+    pending: Arc<Mutex<Option<Vec<u8>>>>,
+    /// Held while writing the file, so that writes do not interleave.
+    writing: Arc<Mutex<()>>,
 }
 
 const VALIDATOR_ADDRESS_CACHE_SIZE: usize = 1000;
+const VALIDATOR_ADDRESS_HISTORY_SIZE: usize = 32;
 
 impl ValidatorAddressCache {
     pub fn new() -> Self {
@@ -35,15 +170,154 @@ impl ValidatorAddressCache {
                 NonZeroUsize::try_from(VALIDATOR_ADDRESS_CACHE_SIZE)
                     .expect("the cache size is a non-zero constant"),
             ))),
+            path: None,
+            pending: Arc::new(Mutex::new(None)),
+            writing: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Creates a cache persisted in the file at `path`, loading its previous contents if present.
+    /// A file that cannot be parsed is ignored and will be overwritten.
+    pub fn new_persistent(path: PathBuf) -> Self {
+        let cache = Self {
+            path: Some(Arc::new(path)),
+            ..Self::new()
+        };
+        if let Err(e) = cache.load() {
+            warn!(
+                target: "aleph-network",
+                "Failed to load validator address history from {:?}: {}, starting with an empty one.",
+                cache.path,
+                e
+            );
+        }
+        cache
+    }
+
+    fn load(&self) -> Result<(), IoError> {
+        let path = match &self.path {
+            Some(path) if path.exists() => path,
+            _ => return Ok(()),
+        };
+        let entries: Vec<(AccountId, ValidatorEntry)> =
+            serde_json::from_slice(&fs::read(path.as_ref())?)?;
+        let mut data = self.data.lock();
+        // Entries are saved from the most recently used one, so we put them in reverse.
+        for (validator_stash, entry) in entries.into_iter().rev() {
+            data.put(validator_stash, entry);
         }
+        Ok(())
     }
 
+    /// Schedules saving the contents of the cache. The file is written on a blocking thread if
+    /// we are inside a runtime, otherwise immediately.
+    fn save(&self, data: &LruCache<AccountId, ValidatorEntry>) {
+        if self.path.is_none() {
+            return;
+        }
+        let entries: Vec<_> = data.iter().collect();
+        match serde_json::to_vec(&entries) {
+            Ok(encoded) => *self.pending.lock() = Some(encoded),
+            Err(e) => {
+                warn!(
+                    target: "aleph-network",
+                    "Failed to encode validator address history: {}.", e
+                );
+                return;
+            }
+        }
+        let cache = self.clone();
+        match Handle::try_current() {
+            Ok(_) => {
+                spawn_blocking(move || cache.write_pending());
+            }
+            Err(_) => cache.write_pending(),
+        }
+    }
+
+    fn write_pending(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        let _writing = self.writing.lock();
+        // Taken only now, so that if several saves were scheduled, only the newest one is written.
+        let encoded = match self.pending.lock().take() {
+            Some(encoded) => encoded,
+            None => return,
+        };
+        let temporary_path = path.with_extension("tmp");
+        let result = fs::write(&temporary_path, encoded)
+            .and_then(|_| fs::rename(&temporary_path, path.as_ref()));
+        if let Err(e) = result {
+            warn!(
+                target: "aleph-network",
+                "Failed to save validator address history to {:?}: {}.", path, e
+            );
+        }
+    }
+
+    /// Stores the most recent information about the validator. Reachability reported for the same
+    /// validator network peer id is kept, as it is not part of the advertised information.
     pub fn insert(&self, validator_stash: AccountId, info: ValidatorAddressingInfo) {
-        self.data.lock().put(validator_stash, info);
+        let mut data = self.data.lock();
+        let addresses_changed = match data.get_mut(&validator_stash) {
+            Some(entry) => entry.update(info),
+            None => {
+                data.put(validator_stash, ValidatorEntry::new(info));
+                true
+            }
+        };
+        if addresses_changed {
+            self.save(&data);
+        }
+    }
+
+    /// Records the result of dialing the validator with the given validator network peer id.
+    /// `unreachable_addresses` replaces whatever was reported before, so reporting an empty
+    /// list marks all the addresses as working again.
+    pub fn report_reachability(
+        &self,
+        validator_network_peer_id: &str,
+        unreachable_addresses: Vec<String>,
+    ) {
+        if let Some((_, entry)) =
+            self.data.lock().iter_mut().find(|(_, entry)| {
+                entry.latest.validator_network_peer_id == validator_network_peer_id
+            })
+        {
+            entry.latest.unreachable_addresses = unreachable_addresses;
+        }
     }
 
+    /// The most recent information about every validator.
     pub fn snapshot(&self) -> HashMap<AccountId, ValidatorAddressingInfo> {
-        HashMap::from_iter(self.data.lock().iter().map(|(k, v)| (k.clone(), v.clone())))
+        HashMap::from_iter(
+            self.data
+                .lock()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.latest.clone())),
+        )
+    }
+
+    /// Information about the validators that were seen in the given session.
+    pub fn snapshot_at(&self, session: SessionId) -> HashMap<AccountId, ValidatorAddressingInfo> {
+        HashMap::from_iter(
+            self.data
+                .lock()
+                .iter()
+                .filter_map(|(k, v)| v.at_session(session).map(|info| (k.clone(), info))),
+        )
+    }
+
+    /// The history of addresses used by every validator, from the oldest to the newest.
+    pub fn history(&self) -> HashMap<AccountId, Vec<ValidatorAddressRecord>> {
+        HashMap::from_iter(
+            self.data
+                .lock()
+                .iter()
+                .map(|(k, v)| (k.clone(), v.history.iter().cloned().collect())),
+        )
     }
 }
 
@@ -101,12 +375,159 @@ impl<C: ValidatorIndexToAccountIdConverter> ValidatorAddressCacheUpdater
 
 #[cfg(test)]
 pub mod test {
+    use std::{env, fs};
+
+    use primitives::AccountId;
+
     use crate::{
         idx_to_account::MockConverter,
-        network::address_cache::{ValidatorAddressCacheUpdater, ValidatorAddressCacheUpdaterImpl},
+        network::address_cache::{
+            ValidatorAddressCache, ValidatorAddressCacheUpdater, ValidatorAddressCacheUpdaterImpl,
+            ValidatorAddressRecord, ValidatorAddressingInfo,
+        },
+        network::session::FinalityVersions,
+        session::SessionId,
     };
 
     pub fn noop_updater() -> impl ValidatorAddressCacheUpdater {
         ValidatorAddressCacheUpdaterImpl::<MockConverter>::Noop
     }
+
+    fn info(session: u32, address: &str, peer_id: &str) -> ValidatorAddressingInfo {
+        ValidatorAddressingInfo {
+            session: SessionId(session),
+            network_level_address: address.to_string(),
+            validator_network_peer_id: peer_id.to_string(),
+            unreachable_addresses: Vec::new(),
+            finality_versions: None,
+        }
+    }
+
+    fn record(first: u32, last: u32, address: &str, peer_id: &str) -> ValidatorAddressRecord {
+        ValidatorAddressRecord {
+            network_level_address: address.to_string(),
+            validator_network_peer_id: peer_id.to_string(),
+            first_session: SessionId(first),
+            last_session: SessionId(last),
+        }
+    }
+
+    #[test]
+    fn reachability_is_reported_for_matching_peer() {
+        let cache = ValidatorAddressCache::new();
+        let first = AccountId::new([0; 32]);
+        let second = AccountId::new([1; 32]);
+        cache.insert(first.clone(), info(7, "127.0.0.1:30343", "first"));
+        cache.insert(second.clone(), info(7, "127.0.0.1:30343", "second"));
+
+        cache.report_reachability("first", vec!["[::1]:30343".to_string()]);
+
+        let snapshot = cache.snapshot();
+        assert_eq!(
+            snapshot[&first].unreachable_addresses,
+            vec!["[::1]:30343".to_string()]
+        );
+        assert!(snapshot[&second].unreachable_addresses.is_empty());
+
+        cache.insert(first.clone(), info(7, "127.0.0.1:30343", "first"));
+        assert_eq!(
+            cache.snapshot()[&first].unreachable_addresses,
+            vec!["[::1]:30343".to_string()],
+            "reinserting the same peer should not forget its reachability"
+        );
+
+        cache.report_reachability("first", Vec::new());
+        assert!(cache.snapshot()[&first].unreachable_addresses.is_empty());
+    }
+
+    #[test]
+    fn finality_versions_are_kept_for_matching_peer() {
+        let cache = ValidatorAddressCache::new();
+        let validator = AccountId::new([0; 32]);
+        let mut with_versions = info(7, "127.0.0.1:30343", "first");
+        with_versions.finality_versions = Some(FinalityVersions::supported());
+        cache.insert(validator.clone(), with_versions);
+
+        cache.insert(validator.clone(), info(8, "127.0.0.1:30343", "first"));
+        assert_eq!(
+            cache.snapshot()[&validator].finality_versions,
+            Some(FinalityVersions::supported())
+        );
+
+        cache.insert(validator.clone(), info(9, "127.0.0.1:30343", "second"));
+        assert!(cache.snapshot()[&validator].finality_versions.is_none());
+    }
+
+    #[test]
+    fn history_tracks_address_changes() {
+        let cache = ValidatorAddressCache::new();
+        let validator = AccountId::new([0; 32]);
+        for session in 3..6 {
+            cache.insert(validator.clone(), info(session, "10.0.0.1:30343", "old"));
+        }
+        cache.insert(validator.clone(), info(6, "10.0.0.2:30343", "new"));
+        cache.insert(validator.clone(), info(7, "10.0.0.2:30343", "new"));
+        // late information about an already known session should not create new records
+        cache.insert(validator.clone(), info(4, "10.0.0.1:30343", "old"));
+
+        assert_eq!(
+            cache.history()[&validator],
+            vec![
+                record(3, 5, "10.0.0.1:30343", "old"),
+                record(6, 7, "10.0.0.2:30343", "new"),
+            ]
+        );
+        assert_eq!(cache.snapshot()[&validator].session, SessionId(7));
+        assert_eq!(
+            cache.snapshot_at(SessionId(4))[&validator].network_level_address,
+            "10.0.0.1:30343"
+        );
+        assert!(cache.snapshot_at(SessionId(2)).is_empty());
+    }
+
+    #[test]
+    fn history_survives_restart() {
+        let path = env::temp_dir().join(format!(
+            "aleph-validator-address-cache-{}.json",
+            std::process::id()
+        ));
+        let validator = AccountId::new([0; 32]);
+        {
+            let cache = ValidatorAddressCache::new_persistent(path.clone());
+            cache.insert(validator.clone(), info(3, "10.0.0.1:30343", "old"));
+            cache.insert(validator.clone(), info(4, "10.0.0.2:30343", "new"));
+        }
+
+        let cache = ValidatorAddressCache::new_persistent(path.clone());
+        fs::remove_file(&path).expect("the history should have been saved");
+        assert_eq!(
+            cache.history()[&validator],
+            vec![
+                record(3, 3, "10.0.0.1:30343", "old"),
+                record(4, 4, "10.0.0.2:30343", "new"),
+            ]
+        );
+        assert_eq!(cache.snapshot()[&validator].session, SessionId(4));
+    }
+
+    #[test]
+    fn history_is_saved_only_on_address_changes() {
+        let path = env::temp_dir().join(format!(
+            "aleph-validator-address-cache-changes-{}.json",
+            std::process::id()
+        ));
+        let validator = AccountId::new([0; 32]);
+        let cache = ValidatorAddressCache::new_persistent(path.clone());
+        cache.insert(validator.clone(), info(3, "10.0.0.1:30343", "old"));
+        fs::remove_file(&path).expect("the history should have been saved");
+
+        cache.insert(validator.clone(), info(4, "10.0.0.1:30343", "old"));
+        assert!(
+            !path.exists(),
+            "seeing the same addresses again should not rewrite the file"
+        );
+
+        cache.insert(validator.clone(), info(5, "10.0.0.2:30343", "new"));
+        fs::remove_file(&path).expect("the history should have been saved");
+    }
 }
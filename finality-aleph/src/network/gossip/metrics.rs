@@ -1,5 +1,11 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use parking_lot::Mutex;
 use substrate_prometheus_endpoint::{
     exponential_buckets, prometheus::HistogramTimer, register, CounterVec, Histogram,
     HistogramOpts, Opts, PrometheusError, Registry, U64,
@@ -19,7 +25,9 @@ fn protocol_name(protocol: Protocol) -> &'static str {
 pub enum Metrics {
     Prometheus {
         send_times: HashMap<Protocol, Histogram>,
+        message_latencies: HashMap<Protocol, Histogram>,
         peer_sender_queue_size: CounterVec<U64>,
+        peer_problems: CounterVec<U64>,
     },
     Noop,
 }
@@ -33,6 +41,7 @@ impl Metrics {
         };
 
         let mut send_times = HashMap::new();
+        let mut message_latencies = HashMap::new();
         for protocol in [Authentication, BlockSync] {
             send_times.insert(
                 protocol,
@@ -52,6 +61,24 @@ impl Metrics {
                     &registry,
                 )?,
             );
+            message_latencies.insert(
+                protocol,
+                register(
+                    Histogram::with_opts(HistogramOpts {
+                        common_opts: Opts {
+                            namespace: "gossip_network".to_string(),
+                            subsystem: protocol_name(protocol).to_string(),
+                            name: "message_latency".to_string(),
+                            help: "How long did it take from queueing a message for a peer to sending it."
+                                .to_string(),
+                            const_labels: Default::default(),
+                            variable_labels: Default::default(),
+                        },
+                        buckets: exponential_buckets(0.001, 1.26, 30)?,
+                    })?,
+                    &registry,
+                )?,
+            );
         }
 
         let peer_sender_queue_size = register(CounterVec::new(
@@ -62,9 +89,19 @@ impl Metrics {
             &["protocol", "action"],
         )?, &registry)?;
 
+        let peer_problems = register(CounterVec::new(
+            Opts::new(
+                "gossip_network_peer_problems",
+                "Total number of full peer sender queues and failed sends for all peers, for a given protocol",
+            ),
+            &["protocol", "problem"],
+        )?, &registry)?;
+
         Ok(Metrics::Prometheus {
             send_times,
+            message_latencies,
             peer_sender_queue_size,
+            peer_problems,
         })
     }
 
@@ -109,3 +146,258 @@ impl Metrics {
         }
     }
 }
+
+impl Metrics {
+    fn report_peer_problem(&self, protocol: Protocol, problem: &str) {
+        match self {
+            Metrics::Prometheus { peer_problems, .. } => {
+                peer_problems
+                    .with_label_values(&[protocol_name(protocol), problem])
+                    .inc();
+            }
+            Metrics::Noop => {}
+        }
+    }
+
+    fn report_message_latency(&self, protocol: Protocol, latency: Duration) {
+        match self {
+            Metrics::Prometheus {
+                message_latencies, ..
+            } => {
+                if let Some(histogram) = message_latencies.get(&protocol) {
+                    histogram.observe(latency.as_secs_f64());
+                }
+            }
+            Metrics::Noop => {}
+        }
+    }
+}
+
+/// Half-life of the penalty accumulated by a peer.
+const PENALTY_HALF_LIFE: Duration = Duration::from_secs(10);
+/// Peers with a penalty above this are considered unhealthy.
+const MAX_HEALTHY_PENALTY: f64 = 1.0;
+/// Peers with an average message latency above this are considered unhealthy.
+const MAX_HEALTHY_LATENCY: Duration = Duration::from_secs(1);
+/// Weight of the newest observation in the average message latency.
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+#[derive(Debug, Clone)]
+struct PeerQuality {
+    queued: usize,
+    penalty: f64,
+    latency: Option<Duration>,
+    last_update: Instant,
+}
+
+impl PeerQuality {
+    fn new(now: Instant) -> Self {
+        PeerQuality {
+            queued: 0,
+            penalty: 0.0,
+            latency: None,
+            last_update: now,
+        }
+    }
+
+    fn decay(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_update);
+        self.penalty *= 0.5f64.powf(elapsed.as_secs_f64() / PENALTY_HALF_LIFE.as_secs_f64());
+        self.last_update = std::cmp::max(now, self.last_update);
+    }
+
+    fn is_healthy(&self, max_queued: usize) -> bool {
+        self.queued < max_queued
+            && self.penalty <= MAX_HEALTHY_PENALTY
+            && self.latency.unwrap_or_default() <= MAX_HEALTHY_LATENCY
+    }
+}
+
+/// Keeps track of how well messages to particular peers are flowing: how many are waiting in their
+/// queues, how often the queues were full or sending failed, and how long messages take to be sent.
+/// Only peers with an open stream are tracked, reports about other peers are ignored apart from
+/// the [Metrics]. Also reports these events to the [Metrics].
+#[derive(Clone)]
+pub struct PeerQualities<P: Clone + Eq + Hash> {
+    qualities: Arc<Mutex<HashMap<(P, Protocol), PeerQuality>>>,
+    max_queued: usize,
+    metrics: Metrics,
+}
+
+impl<P: Clone + Eq + Hash> PeerQualities<P> {
+    /// Creates the tracker, peers with at least `max_queued` messages waiting are considered
+    /// unhealthy.
+    pub fn new(max_queued: usize, metrics: Metrics) -> Self {
+        PeerQualities {
+            qualities: Arc::new(Mutex::new(HashMap::new())),
+            max_queued,
+            metrics,
+        }
+    }
+
+    fn update<F: FnOnce(&mut PeerQuality)>(&self, peer: P, protocol: Protocol, now: Instant, f: F) {
+        // Senders of disconnected peers might still report, their peers should stay forgotten.
+        if let Some(quality) = self.qualities.lock().get_mut(&(peer, protocol)) {
+            quality.decay(now);
+            f(quality);
+        }
+    }
+
+    /// Starts tracking the peer, should be called when a stream to it opens.
+    pub fn add(&self, peer: P, protocol: Protocol) {
+        self.qualities
+            .lock()
+            .entry((peer, protocol))
+            .or_insert_with(|| PeerQuality::new(Instant::now()));
+    }
+
+    pub fn report_queued(&self, peer: P, protocol: Protocol) {
+        self.metrics
+            .report_message_pushed_to_peer_sender_queue(protocol);
+        self.update(peer, protocol, Instant::now(), |quality| {
+            quality.queued += 1
+        });
+    }
+
+    pub fn report_dequeued(&self, peer: P, protocol: Protocol) {
+        self.metrics
+            .report_message_popped_from_peer_sender_queue(protocol);
+        self.update(peer, protocol, Instant::now(), |quality| {
+            quality.queued = quality.queued.saturating_sub(1)
+        });
+    }
+
+    pub fn report_full_queue(&self, peer: P, protocol: Protocol) {
+        self.report_full_queue_at(peer, protocol, Instant::now());
+    }
+
+    fn report_full_queue_at(&self, peer: P, protocol: Protocol, now: Instant) {
+        self.metrics.report_peer_problem(protocol, "full_queue");
+        self.update(peer, protocol, now, |quality| quality.penalty += 1.0);
+    }
+
+    pub fn report_send_failure(&self, peer: P, protocol: Protocol) {
+        self.metrics.report_peer_problem(protocol, "send_failure");
+        self.update(peer, protocol, Instant::now(), |quality| {
+            quality.penalty += 1.0
+        });
+    }
+
+    /// Reports that a message spent `latency` between being queued and being sent.
+    pub fn report_latency(&self, peer: P, protocol: Protocol, latency: Duration) {
+        self.metrics.report_message_latency(protocol, latency);
+        self.update(peer, protocol, Instant::now(), |quality| {
+            quality.latency = Some(match quality.latency {
+                Some(average) => average
+                    .mul_f64(1.0 - LATENCY_SMOOTHING)
+                    .saturating_add(latency.mul_f64(LATENCY_SMOOTHING)),
+                None => latency,
+            })
+        });
+    }
+
+    pub fn remove(&self, peer: P, protocol: Protocol) {
+        self.qualities.lock().remove(&(peer, protocol));
+    }
+
+    pub fn is_healthy(&self, peer: &P, protocol: Protocol) -> bool {
+        self.is_healthy_at(peer, protocol, Instant::now())
+    }
+
+    fn is_healthy_at(&self, peer: &P, protocol: Protocol, now: Instant) -> bool {
+        match self.qualities.lock().get_mut(&(peer.clone(), protocol)) {
+            Some(quality) => {
+                quality.decay(now);
+                quality.is_healthy(self.max_queued)
+            }
+            None => true,
+        }
+    }
+
+    /// Number of tracked peers that are currently unhealthy, for the given protocol.
+    pub fn unhealthy_count(&self, protocol: Protocol) -> usize {
+        let now = Instant::now();
+        self.qualities
+            .lock()
+            .iter_mut()
+            .filter(|((_, peer_protocol), _)| *peer_protocol == protocol)
+            .map(|(_, quality)| {
+                quality.decay(now);
+                quality.is_healthy(self.max_queued)
+            })
+            .filter(|healthy| !healthy)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{Metrics, PeerQualities};
+    use crate::Protocol;
+
+    const PROTOCOL: Protocol = Protocol::BlockSync;
+
+    #[test]
+    fn unknown_peers_are_healthy() {
+        let qualities = PeerQualities::<u32>::new(4, Metrics::noop());
+        assert!(qualities.is_healthy(&0, PROTOCOL));
+    }
+
+    #[test]
+    fn full_queue_makes_peer_temporarily_unhealthy() {
+        let qualities = PeerQualities::new(4, Metrics::noop());
+        let now = Instant::now();
+        qualities.add(0, PROTOCOL);
+        qualities.add(1, PROTOCOL);
+        qualities.report_full_queue_at(0, PROTOCOL, now);
+        qualities.report_full_queue_at(0, PROTOCOL, now);
+
+        assert!(!qualities.is_healthy_at(&0, PROTOCOL, now));
+        assert!(qualities.is_healthy_at(&1, PROTOCOL, now));
+        assert!(qualities.is_healthy_at(&0, Protocol::Authentication, now));
+        assert!(qualities.is_healthy_at(&0, PROTOCOL, now + Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn long_queue_makes_peer_unhealthy() {
+        let qualities = PeerQualities::new(2, Metrics::noop());
+        qualities.add(0, PROTOCOL);
+        qualities.report_queued(0, PROTOCOL);
+        assert!(qualities.is_healthy(&0, PROTOCOL));
+        qualities.report_queued(0, PROTOCOL);
+        assert!(!qualities.is_healthy(&0, PROTOCOL));
+        assert_eq!(qualities.unhealthy_count(PROTOCOL), 1);
+        qualities.report_dequeued(0, PROTOCOL);
+        assert!(qualities.is_healthy(&0, PROTOCOL));
+    }
+
+    #[test]
+    fn high_latency_makes_peer_unhealthy() {
+        let qualities = PeerQualities::new(4, Metrics::noop());
+        qualities.add(0, PROTOCOL);
+        qualities.report_latency(0, PROTOCOL, Duration::from_millis(10));
+        assert!(qualities.is_healthy(&0, PROTOCOL));
+        for _ in 0..20 {
+            qualities.report_latency(0, PROTOCOL, Duration::from_secs(5));
+        }
+        assert!(!qualities.is_healthy(&0, PROTOCOL));
+        qualities.remove(0, PROTOCOL);
+        assert!(qualities.is_healthy(&0, PROTOCOL));
+    }
+
+    #[test]
+    fn reports_about_removed_peers_are_ignored() {
+        let qualities = PeerQualities::new(1, Metrics::noop());
+        qualities.add(0, PROTOCOL);
+        qualities.report_queued(0, PROTOCOL);
+        assert_eq!(qualities.unhealthy_count(PROTOCOL), 1);
+        qualities.remove(0, PROTOCOL);
+
+        qualities.report_queued(0, PROTOCOL);
+        qualities.report_latency(0, PROTOCOL, Duration::from_secs(5));
+        assert!(qualities.qualities.lock().is_empty());
+        assert_eq!(qualities.unhealthy_count(PROTOCOL), 0);
+    }
+}
@@ -9,7 +9,7 @@ use std::{
 use futures::{channel::mpsc, StreamExt};
 use log::{debug, error, info, trace, warn};
 use network_clique::SpawnHandleT;
-use rand::{seq::IteratorRandom, thread_rng};
+use rand::{seq::SliceRandom, thread_rng};
 use substrate_prometheus_endpoint::Registry;
 use tokio::time;
 
@@ -18,7 +18,8 @@ const MAX_QUEUE_SIZE: usize = 16;
 use crate::{
     network::{
         gossip::{
-            metrics::Metrics, Event, EventStream, Network, NetworkSender, Protocol, RawNetwork,
+            metrics::{Metrics, PeerQualities},
+            Event, EventStream, Network, NetworkSender, Protocol, RawNetwork,
         },
         Data,
     },
@@ -46,11 +47,12 @@ pub struct Service<N: RawNetwork, AD: Data, BSD: Data> {
     messages_for_authentication_user: mpsc::UnboundedSender<(AD, N::PeerId)>,
     messages_for_block_sync_user: mpsc::UnboundedSender<(BSD, N::PeerId)>,
     authentication_connected_peers: HashSet<N::PeerId>,
-    authentication_peer_senders: HashMap<N::PeerId, mpsc::Sender<AD>>,
+    authentication_peer_senders: HashMap<N::PeerId, mpsc::Sender<(AD, Instant)>>,
     block_sync_connected_peers: HashSet<N::PeerId>,
-    block_sync_peer_senders: HashMap<N::PeerId, mpsc::Sender<BSD>>,
+    block_sync_peer_senders: HashMap<N::PeerId, mpsc::Sender<(BSD, Instant)>>,
     spawn_handle: SpawnHandle,
     metrics: Metrics,
+    peer_qualities: PeerQualities<N::PeerId>,
     timestamp_of_last_log_that_channel_is_full: HashMap<(N::PeerId, Protocol), Instant>,
 }
 
@@ -140,6 +142,7 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
                 Metrics::noop()
             }
         };
+        let peer_qualities = PeerQualities::new(MAX_QUEUE_SIZE, metrics.clone());
         (
             Service {
                 network,
@@ -149,6 +152,7 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
                 messages_for_block_sync_user,
                 spawn_handle,
                 metrics,
+                peer_qualities,
                 authentication_connected_peers: HashSet::new(),
                 authentication_peer_senders: HashMap::new(),
                 block_sync_connected_peers: HashSet::new(),
@@ -166,27 +170,34 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
         )
     }
 
-    fn get_authentication_sender(&mut self, peer: &N::PeerId) -> Option<&mut mpsc::Sender<AD>> {
+    fn get_authentication_sender(
+        &mut self,
+        peer: &N::PeerId,
+    ) -> Option<&mut mpsc::Sender<(AD, Instant)>> {
         self.authentication_peer_senders.get_mut(peer)
     }
 
-    fn get_block_sync_sender(&mut self, peer: &N::PeerId) -> Option<&mut mpsc::Sender<BSD>> {
+    fn get_block_sync_sender(
+        &mut self,
+        peer: &N::PeerId,
+    ) -> Option<&mut mpsc::Sender<(BSD, Instant)>> {
         self.block_sync_peer_senders.get_mut(peer)
     }
 
     fn peer_sender<D: Data>(
         &self,
         peer_id: N::PeerId,
-        mut receiver: mpsc::Receiver<D>,
+        mut receiver: mpsc::Receiver<(D, Instant)>,
         protocol: Protocol,
     ) -> impl Future<Output = ()> + Send + 'static {
         let network = self.network.clone();
         let metrics = self.metrics.clone();
+        let peer_qualities = self.peer_qualities.clone();
         async move {
             let mut sender = None;
             loop {
-                if let Some(data) = receiver.next().await {
-                    metrics.report_message_popped_from_peer_sender_queue(protocol);
+                if let Some((data, queued_at)) = receiver.next().await {
+                    peer_qualities.report_dequeued(peer_id.clone(), protocol);
                     let s = if let Some(s) = sender.as_mut() {
                         s
                     } else {
@@ -197,17 +208,26 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
                                     target: LOG_TARGET,
                                     "Failed creating sender. Dropping message: {}", e
                                 );
+                                peer_qualities.report_send_failure(peer_id.clone(), protocol);
                                 continue;
                             }
                         }
                     };
                     let maybe_timer = metrics.start_sending_in(protocol);
-                    if let Err(e) = s.send(data.encode()).await {
-                        debug!(
-                            target: LOG_TARGET,
-                            "Failed sending data to peer. Dropping sender and message: {}", e
-                        );
-                        sender = None;
+                    match s.send(data.encode()).await {
+                        Ok(()) => peer_qualities.report_latency(
+                            peer_id.clone(),
+                            protocol,
+                            queued_at.elapsed(),
+                        ),
+                        Err(e) => {
+                            debug!(
+                                target: LOG_TARGET,
+                                "Failed sending data to peer. Dropping sender and message: {}", e
+                            );
+                            peer_qualities.report_send_failure(peer_id.clone(), protocol);
+                            sender = None;
+                        }
                     }
                     if let Some(timer) = maybe_timer {
                         timer.observe_duration();
@@ -245,9 +265,11 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
     fn send_to_authentication_peer(&mut self, data: AD, peer: N::PeerId) -> Result<(), SendError> {
         match self.get_authentication_sender(&peer) {
             Some(sender) => {
-                match sender.try_send(data) {
+                match sender.try_send((data, Instant::now())) {
                     Err(e) => {
                         if e.is_full() {
+                            self.peer_qualities
+                                .report_full_queue(peer.clone(), Protocol::Authentication);
                             self.possibly_log_that_channel_is_full(
                                 peer.clone(),
                                 Protocol::Authentication,
@@ -261,8 +283,8 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
                         Err(SendError::SendingFailed)
                     }
                     Ok(_) => {
-                        self.metrics
-                            .report_message_pushed_to_peer_sender_queue(Protocol::Authentication);
+                        self.peer_qualities
+                            .report_queued(peer, Protocol::Authentication);
                         Ok(())
                     }
                 }
@@ -274,9 +296,11 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
     fn send_to_block_sync_peer(&mut self, data: BSD, peer: N::PeerId) -> Result<(), SendError> {
         match self.get_block_sync_sender(&peer) {
             Some(sender) => {
-                match sender.try_send(data) {
+                match sender.try_send((data, Instant::now())) {
                     Err(e) => {
                         if e.is_full() {
+                            self.peer_qualities
+                                .report_full_queue(peer.clone(), Protocol::BlockSync);
                             self.possibly_log_that_channel_is_full(
                                 peer.clone(),
                                 Protocol::BlockSync,
@@ -290,8 +314,7 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
                         Err(SendError::SendingFailed)
                     }
                     Ok(_) => {
-                        self.metrics
-                            .report_message_pushed_to_peer_sender_queue(Protocol::BlockSync);
+                        self.peer_qualities.report_queued(peer, Protocol::BlockSync);
                         Ok(())
                     }
                 }
@@ -335,19 +358,20 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
         }
     }
 
-    fn random_peer<'a>(
-        &'a self,
-        peer_ids: &'a HashSet<N::PeerId>,
-        protocol: Protocol,
-    ) -> Option<&'a N::PeerId> {
-        peer_ids
+    /// Candidates for sending a message to a random peer, in the order they should be tried.
+    /// Connected peers among `peer_ids` are preferred, if there are none all connected peers are
+    /// considered. The candidates are shuffled, but the healthy ones always go first.
+    fn random_peers(&self, peer_ids: &HashSet<N::PeerId>, protocol: Protocol) -> Vec<N::PeerId> {
+        let mut candidates: Vec<_> = peer_ids
             .intersection(self.protocol_peers(protocol))
-            .choose(&mut thread_rng())
-            .or_else(|| {
-                self.protocol_peers(protocol)
-                    .iter()
-                    .choose(&mut thread_rng())
-            })
+            .cloned()
+            .collect();
+        if candidates.is_empty() {
+            candidates = self.protocol_peers(protocol).iter().cloned().collect();
+        }
+        candidates.shuffle(&mut thread_rng());
+        candidates.sort_by_key(|peer_id| !self.peer_qualities.is_healthy(peer_id, protocol));
+        candidates
     }
 
     fn send_to_random_authentication(&mut self, data: AD, peer_ids: HashSet<N::PeerId>) {
@@ -356,17 +380,29 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
             "Sending authentication data to random peer among {:?}.",
             peer_ids,
         );
-        let peer_id = match self.random_peer(&peer_ids, Protocol::Authentication) {
-            Some(peer_id) => peer_id.clone(),
-            None => {
-                debug!(
+        let candidates = self.random_peers(&peer_ids, Protocol::Authentication);
+        if candidates.is_empty() {
+            debug!(
+                target: LOG_TARGET,
+                "Failed to send authentication message to random peer, no peers are available."
+            );
+            return;
+        }
+        for peer_id in candidates {
+            match self.send_to_authentication_peer(data.clone(), peer_id.clone()) {
+                Ok(()) => return,
+                Err(e) => trace!(
                     target: LOG_TARGET,
-                    "Failed to send authentication message to random peer, no peers are available."
-                );
-                return;
+                    "Failed to send to random peer {:?}, trying another one: {:?}",
+                    peer_id,
+                    e
+                ),
             }
-        };
-        self.send_authentication_data(data, peer_id);
+        }
+        debug!(
+            target: LOG_TARGET,
+            "Failed to send authentication message to random peer, all peers failed."
+        );
     }
 
     fn send_to_random_block_sync(&mut self, data: BSD, peer_ids: HashSet<N::PeerId>) {
@@ -375,17 +411,29 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
             "Sending block sync data to random peer among {:?}.",
             peer_ids,
         );
-        let peer_id = match self.random_peer(&peer_ids, Protocol::BlockSync) {
-            Some(peer_id) => peer_id.clone(),
-            None => {
-                debug!(
+        let candidates = self.random_peers(&peer_ids, Protocol::BlockSync);
+        if candidates.is_empty() {
+            debug!(
+                target: LOG_TARGET,
+                "Failed to send block sync message to random peer, no peers are available."
+            );
+            return;
+        }
+        for peer_id in candidates {
+            match self.send_to_block_sync_peer(data.clone(), peer_id.clone()) {
+                Ok(()) => return,
+                Err(e) => trace!(
                     target: LOG_TARGET,
-                    "Failed to send block sync message to random peer, no peers are available."
-                );
-                return;
+                    "Failed to send to random peer {:?}, trying another one: {:?}",
+                    peer_id,
+                    e
+                ),
             }
-        };
-        self.send_block_sync_data(data, peer_id);
+        }
+        debug!(
+            target: LOG_TARGET,
+            "Failed to send block sync message to random peer, all peers failed."
+        );
     }
 
     fn broadcast_authentication(&mut self, data: AD) {
@@ -412,6 +460,7 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
                     peer,
                     protocol
                 );
+                self.peer_qualities.add(peer.clone(), protocol);
                 match protocol {
                     Protocol::Authentication => {
                         let (tx, rx) = mpsc::channel(MAX_QUEUE_SIZE);
@@ -450,6 +499,7 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
                         self.block_sync_peer_senders.remove(&peer);
                     }
                 }
+                self.peer_qualities.remove(peer, protocol);
             }
             Messages(peer_id, messages) => {
                 for (protocol, data) in messages.into_iter() {
@@ -496,6 +546,15 @@ impl<N: RawNetwork, AD: Data, BSD: Data> Service<N, AD, BSD> {
             "block sync connected peers - {:?}; ",
             self.block_sync_connected_peers.len()
         ));
+        status.push_str(&format!(
+            "unhealthy authentication peers - {:?}; ",
+            self.peer_qualities
+                .unhealthy_count(Protocol::Authentication)
+        ));
+        status.push_str(&format!(
+            "unhealthy block sync peers - {:?}; ",
+            self.peer_qualities.unhealthy_count(Protocol::BlockSync)
+        ));
 
         info!(target: LOG_TARGET, "{}", status);
     }
@@ -939,4 +998,50 @@ mod tests {
 
         test_data.cleanup().await
     }
+
+    #[tokio::test]
+    async fn test_send_to_random_prefers_healthy_peers() {
+        let mut test_data = TestData::prepare();
+
+        let unhealthy_peer_id = random_peer_id();
+        let healthy_peer_id = random_peer_id();
+
+        let message = message(1);
+
+        for peer_id in [&unhealthy_peer_id, &healthy_peer_id] {
+            test_data
+                .service
+                .handle_network_event(MockEvent::StreamOpened(peer_id.clone(), PROTOCOL))
+                .expect("Should handle");
+        }
+        for _ in 0..2 {
+            test_data
+                .service
+                .peer_qualities
+                .report_full_queue(unhealthy_peer_id.clone(), PROTOCOL);
+        }
+
+        let peer_ids: HashSet<_> = [unhealthy_peer_id, healthy_peer_id.clone()]
+            .into_iter()
+            .collect();
+        for _ in 0..5 {
+            test_data
+                .service
+                .send_to_random_authentication(message.clone(), peer_ids.clone());
+
+            let expected = (message.encode(), healthy_peer_id.clone(), PROTOCOL);
+
+            assert_eq!(
+                test_data
+                    .network
+                    .send_message
+                    .next()
+                    .await
+                    .expect("Should receive message"),
+                expected,
+            );
+        }
+
+        test_data.cleanup().await
+    }
 }
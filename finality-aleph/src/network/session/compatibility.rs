@@ -7,7 +7,10 @@ use log::warn;
 use parity_scale_codec::{Decode, Encode, Error as CodecError, Input as CodecInput};
 
 use crate::{
-    network::{session::Authentication, AddressingInformation},
+    network::{
+        session::{Authentication, VersionsAdvertisement},
+        AddressingInformation,
+    },
     SessionId, Version,
 };
 
@@ -21,6 +24,7 @@ pub enum VersionedAuthentication<A: AddressingInformation> {
     // Most likely from the future.
     Other(Version, Vec<u8>),
     V2(Authentication<A>),
+    V3(Authentication<A>, VersionsAdvertisement),
 }
 
 impl<A: AddressingInformation> From<Authentication<A>> for Vec<VersionedAuthentication<A>> {
@@ -29,12 +33,34 @@ impl<A: AddressingInformation> From<Authentication<A>> for Vec<VersionedAuthenti
     }
 }
 
-pub type DiscoveryMessage<A> = Authentication<A>;
+/// An authentication, together with the finality versions its creator advertises, if it does.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiscoveryMessage<A: AddressingInformation> {
+    pub authentication: Authentication<A>,
+    pub versions_advertisement: Option<VersionsAdvertisement>,
+}
 
 impl<A: AddressingInformation> DiscoveryMessage<A> {
     /// Session ID associated with this message.
     pub fn session_id(&self) -> SessionId {
-        self.0.session()
+        self.authentication.0.session()
+    }
+}
+
+impl<A: AddressingInformation> From<DiscoveryMessage<A>> for Vec<VersionedAuthentication<A>> {
+    fn from(message: DiscoveryMessage<A>) -> Self {
+        let DiscoveryMessage {
+            authentication,
+            versions_advertisement,
+        } = message;
+        // Nodes that do not understand V3 still need the V2 authentication to connect to us.
+        match versions_advertisement {
+            Some(versions_advertisement) => vec![
+                VersionedAuthentication::V2(authentication.clone()),
+                VersionedAuthentication::V3(authentication, versions_advertisement),
+            ],
+            None => vec![VersionedAuthentication::V2(authentication)],
+        }
     }
 }
 
@@ -44,7 +70,14 @@ impl<A: AddressingInformation> TryInto<DiscoveryMessage<A>> for VersionedAuthent
     fn try_into(self) -> Result<DiscoveryMessage<A>, Self::Error> {
         use VersionedAuthentication::*;
         match self {
-            V2(authentication) => Ok(authentication),
+            V2(authentication) => Ok(DiscoveryMessage {
+                authentication,
+                versions_advertisement: None,
+            }),
+            V3(authentication, versions_advertisement) => Ok(DiscoveryMessage {
+                authentication,
+                versions_advertisement: Some(versions_advertisement),
+            }),
             Other(v, _) => Err(Error::UnknownVersion(v)),
         }
     }
@@ -90,6 +123,9 @@ impl<A: AddressingInformation> Encode for VersionedAuthentication<A> {
             + match self {
                 Other(_, payload) => payload.len(),
                 V2(data) => data.size_hint(),
+                V3(data, versions_advertisement) => {
+                    data.size_hint() + versions_advertisement.size_hint()
+                }
             }
     }
 
@@ -98,6 +134,9 @@ impl<A: AddressingInformation> Encode for VersionedAuthentication<A> {
         match self {
             Other(version, payload) => encode_with_version(*version, payload),
             V2(data) => encode_with_version(Version(2), &data.encode()),
+            V3(data, versions_advertisement) => {
+                encode_with_version(Version(3), &(data, versions_advertisement).encode())
+            }
         }
     }
 }
@@ -109,6 +148,10 @@ impl<A: AddressingInformation> Decode for VersionedAuthentication<A> {
         let num_bytes = ByteCount::decode(input)?;
         match version {
             Version(2) => Ok(V2(Authentication::decode(input)?)),
+            Version(3) => Ok(V3(
+                Authentication::decode(input)?,
+                VersionsAdvertisement::decode(input)?,
+            )),
             _ => {
                 if num_bytes > MAX_AUTHENTICATION_SIZE {
                     Err("Authentication has unknown version and is encoded as more than 16KiB.")?;
@@ -145,7 +188,7 @@ mod test {
     use parity_scale_codec::{Decode, Encode};
     use sp_keystore::testing::MemoryKeystore as Keystore;
 
-    use super::VersionedAuthentication;
+    use super::{DiscoveryMessage, VersionedAuthentication};
     use crate::{
         crypto::AuthorityVerifier,
         network::{
@@ -239,6 +282,55 @@ mod test {
         assert_eq!(decoded, Ok(authentication_v2))
     }
 
+    fn discovery_message(
+        handler: SessionHandler<SignedTcpAddressingInformation>,
+    ) -> DiscoveryMessage<SignedTcpAddressingInformation> {
+        handler
+            .discovery_message()
+            .expect("should have discovery message")
+    }
+
+    #[test]
+    fn correctly_decodes_v3_roundtrip() {
+        let message = discovery_message(handler());
+        let authentication_v3 = VersionedAuthentication::V3(
+            message.authentication,
+            message
+                .versions_advertisement
+                .expect("should advertise versions"),
+        );
+
+        let encoded = authentication_v3.encode();
+        let decoded = VersionedAuthentication::decode(&mut encoded.as_slice());
+
+        assert_eq!(decoded, Ok(authentication_v3))
+    }
+
+    #[test]
+    fn sends_both_v2_and_v3_when_advertising_versions() {
+        let message = discovery_message(handler());
+        let authentications: Vec<VersionedAuthentication<_>> = message.clone().into();
+
+        assert_eq!(authentications.len(), 2);
+        for authentication in authentications {
+            let decoded: DiscoveryMessage<_> = authentication
+                .clone()
+                .try_into()
+                .expect("should be a known version");
+            assert_eq!(decoded.authentication, message.authentication);
+            match authentication {
+                VersionedAuthentication::V2(_) => assert!(decoded.versions_advertisement.is_none()),
+                VersionedAuthentication::V3(..) => {
+                    assert_eq!(
+                        decoded.versions_advertisement,
+                        message.versions_advertisement
+                    )
+                }
+                VersionedAuthentication::Other(..) => panic!("should not send other versions"),
+            }
+        }
+    }
+
     #[test]
     fn correctly_decodes_other() {
         let other =
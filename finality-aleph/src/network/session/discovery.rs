@@ -8,7 +8,7 @@ use log::{debug, info, trace};
 
 use crate::{
     network::{
-        session::{Authentication, SessionHandler},
+        session::{Authentication, DiscoveryMessage, SessionHandler},
         AddressingInformation,
     },
     NodeIndex,
@@ -35,16 +35,16 @@ impl<A: AddressingInformation> Discovery<A> {
     pub fn discover_authorities(
         &mut self,
         handler: &SessionHandler<A>,
-    ) -> Option<Authentication<A>> {
-        let authentication = match handler.authentication() {
-            Some(authentication) => authentication,
+    ) -> Option<DiscoveryMessage<A>> {
+        let message = match handler.discovery_message() {
+            Some(message) => message,
             None => return None,
         };
 
         let missing_authorities = handler.missing_nodes();
         let node_count = handler.node_count();
         info!(target: "aleph-network", "{}/{} authorities known for session {}.", node_count.0-missing_authorities.len(), node_count.0, handler.session_id().0);
-        Some(authentication)
+        Some(message)
     }
 
     fn should_rebroadcast(&self, node_id: &NodeIndex) -> bool {
@@ -138,12 +138,12 @@ mod tests {
         for num_nodes in 2..NUM_NODES {
             let (mut discovery, mut handlers, _) = build_number(num_nodes);
             let handler = &mut handlers[0];
-            let maybe_authentication = discovery.discover_authorities(handler);
+            let maybe_message = discovery.discover_authorities(handler);
             assert_eq!(
-                maybe_authentication.expect("there is an authentication"),
+                maybe_message.expect("there is a message"),
                 handler
-                    .authentication()
-                    .expect("the handler has an authentication"),
+                    .discovery_message()
+                    .expect("the handler has a message"),
             );
         }
     }
@@ -6,7 +6,9 @@ use crate::{
     abft::NodeCount,
     crypto::{AuthorityPen, AuthorityVerifier},
     network::{
-        session::{AuthData, Authentication},
+        session::{
+            AuthData, Authentication, DiscoveryMessage, FinalityVersions, VersionsAdvertisement,
+        },
         AddressingInformation,
     },
     NodeIndex, SessionId,
@@ -15,16 +17,14 @@ use crate::{
 #[derive(Debug)]
 pub enum SessionInfo<A: AddressingInformation> {
     SessionId(SessionId),
-    OwnAuthentication(Authentication<A>),
+    OwnAuthentication(Authentication<A>, VersionsAdvertisement),
 }
 
 impl<A: AddressingInformation> SessionInfo<A> {
     fn session_id(&self) -> SessionId {
         match self {
             SessionInfo::SessionId(session_id) => *session_id,
-            SessionInfo::OwnAuthentication(peer_authentications) => {
-                peer_authentications.session_id()
-            }
+            SessionInfo::OwnAuthentication(own_authentication, _) => own_authentication.0.session(),
         }
     }
 }
@@ -34,6 +34,8 @@ impl<A: AddressingInformation> SessionInfo<A> {
 pub struct Handler<A: AddressingInformation> {
     peers_by_node: HashMap<NodeIndex, A::PeerId>,
     authentications: HashMap<A::PeerId, Authentication<A>>,
+    versions_advertisements: HashMap<A::PeerId, VersionsAdvertisement>,
+    finality_versions: HashMap<NodeIndex, FinalityVersions>,
     session_info: SessionInfo<A>,
     own_peer_id: A::PeerId,
     authority_index_and_pen: Option<(NodeIndex, AuthorityPen)>,
@@ -61,8 +63,18 @@ fn construct_session_info<A: AddressingInformation>(
                 session_id,
             };
             let signature = authority_pen.sign(&auth_data.encode());
-            let authentications = Authentication(auth_data, signature);
-            (SessionInfo::OwnAuthentication(authentications), peer_id)
+            let finality_versions = FinalityVersions::supported();
+            let versions_signature = authority_pen.sign(&VersionsAdvertisement::payload(
+                &auth_data,
+                &finality_versions,
+            ));
+            let authentication = Authentication(auth_data, signature);
+            let versions_advertisement =
+                VersionsAdvertisement(finality_versions, versions_signature);
+            (
+                SessionInfo::OwnAuthentication(authentication, versions_advertisement),
+                peer_id,
+            )
         }
         None => (SessionInfo::SessionId(session_id), peer_id),
     }
@@ -82,6 +94,8 @@ impl<A: AddressingInformation> Handler<A> {
         Handler {
             peers_by_node: HashMap::new(),
             authentications: HashMap::new(),
+            versions_advertisements: HashMap::new(),
+            finality_versions: HashMap::new(),
             session_info,
             authority_index_and_pen,
             authority_verifier,
@@ -112,8 +126,21 @@ impl<A: AddressingInformation> Handler<A> {
     pub fn authentication(&self) -> Option<Authentication<A>> {
         match &self.session_info {
             SessionInfo::SessionId(_) => None,
-            SessionInfo::OwnAuthentication(own_authentications) => {
-                Some(own_authentications.clone())
+            SessionInfo::OwnAuthentication(own_authentication, _) => {
+                Some(own_authentication.clone())
+            }
+        }
+    }
+
+    /// Returns the discovery message advertising the node and session this handler is responsible for.
+    pub fn discovery_message(&self) -> Option<DiscoveryMessage<A>> {
+        match &self.session_info {
+            SessionInfo::SessionId(_) => None,
+            SessionInfo::OwnAuthentication(own_authentication, versions_advertisement) => {
+                Some(DiscoveryMessage {
+                    authentication: own_authentication.clone(),
+                    versions_advertisement: Some(versions_advertisement.clone()),
+                })
             }
         }
     }
@@ -156,10 +183,65 @@ impl<A: AddressingInformation> Handler<A> {
         }
         self.peers_by_node
             .insert(auth_data.creator(), peer_id.clone());
+        if self.authentications.get(&peer_id) != Some(&authentication) {
+            // The advertisement is only valid together with the authentication it was signed with.
+            self.versions_advertisements.remove(&peer_id);
+        }
         self.authentications.insert(peer_id, authentication);
         Some(address)
     }
 
+    /// Verifies the advertisement of finality versions attached to an authentication that was
+    /// already accepted, and remembers the versions if it is correct.
+    pub fn handle_versions_advertisement(
+        &mut self,
+        authentication: &Authentication<A>,
+        versions_advertisement: VersionsAdvertisement,
+    ) -> Option<FinalityVersions> {
+        let Authentication(auth_data, _) = authentication;
+        let peer_id = auth_data.address().peer_id();
+        if self.authentications.get(&peer_id) != Some(authentication) {
+            return None;
+        }
+        let VersionsAdvertisement(finality_versions, signature) = &versions_advertisement;
+        if !self.authority_verifier.verify(
+            &VersionsAdvertisement::payload(auth_data, finality_versions),
+            signature,
+            auth_data.creator(),
+        ) {
+            return None;
+        }
+        self.finality_versions
+            .insert(auth_data.creator(), *finality_versions);
+        self.versions_advertisements
+            .insert(peer_id, versions_advertisement);
+        Some(*finality_versions)
+    }
+
+    /// Returns the finality versions advertised by the node with the given NodeIndex, if known.
+    pub fn finality_versions(&self, node_id: &NodeIndex) -> Option<FinalityVersions> {
+        match (&self.session_info, self.index()) {
+            (SessionInfo::OwnAuthentication(_, versions_advertisement), Some(index))
+                if index == *node_id =>
+            {
+                Some(versions_advertisement.finality_versions())
+            }
+            _ => self.finality_versions.get(node_id).copied(),
+        }
+    }
+
+    /// Returns the accepted advertisement of finality versions attached to the given authentication, if any.
+    pub fn versions_advertisement(
+        &self,
+        authentication: &Authentication<A>,
+    ) -> Option<VersionsAdvertisement> {
+        let peer_id = authentication.0.address().peer_id();
+        if self.authentications.get(&peer_id) != Some(authentication) {
+            return None;
+        }
+        self.versions_advertisements.get(&peer_id).cloned()
+    }
+
     /// Returns the PeerId of the node with the given NodeIndex, if known.
     pub fn peer_id(&self, node_id: &NodeIndex) -> Option<A::PeerId> {
         self.peers_by_node.get(node_id).cloned()
@@ -186,6 +268,7 @@ impl<A: AddressingInformation> Handler<A> {
         }
 
         let authentications = self.authentications.clone();
+        let mut versions_advertisements = self.versions_advertisements.clone();
 
         *self = Handler::new(
             authority_index_and_pen,
@@ -194,8 +277,13 @@ impl<A: AddressingInformation> Handler<A> {
             address,
         );
 
-        for (_, authentication) in authentications {
-            self.handle_authentication(authentication);
+        for (peer_id, authentication) in authentications {
+            if self.handle_authentication(authentication.clone()).is_none() {
+                continue;
+            }
+            if let Some(versions_advertisement) = versions_advertisements.remove(&peer_id) {
+                self.handle_versions_advertisement(&authentication, versions_advertisement);
+            }
         }
         Ok(self
             .authentications
@@ -211,7 +299,11 @@ pub mod tests {
 
     use super::{Handler, HandlerError};
     use crate::{
-        network::{mock::crypto_basics, session::Authentication, AddressingInformation},
+        network::{
+            mock::crypto_basics,
+            session::{Authentication, FinalityVersions, VersionsAdvertisement},
+            AddressingInformation,
+        },
         NodeIndex, SessionId,
     };
 
@@ -487,4 +579,106 @@ pub mod tests {
         assert_eq!(missing_nodes, expected_missing);
         assert!(handler0.peer_id(&NodeIndex(1)).is_none());
     }
+
+    fn versions_advertisement(
+        handler: &Handler<MockAddressingInformation>,
+    ) -> VersionsAdvertisement {
+        handler
+            .discovery_message()
+            .and_then(|message| message.versions_advertisement)
+            .expect("this is a validator handler")
+    }
+
+    #[test]
+    fn accepts_correct_versions_advertisement() {
+        let crypto_basics = crypto_basics(NUM_NODES);
+        let mut handler0 = Handler::new(
+            Some(crypto_basics.0[0].clone()),
+            crypto_basics.1.clone(),
+            SessionId(43),
+            random_address(),
+        );
+        let handler1 = Handler::new(
+            Some(crypto_basics.0[1].clone()),
+            crypto_basics.1.clone(),
+            SessionId(43),
+            random_address(),
+        );
+        assert!(handler0.finality_versions(&NodeIndex(1)).is_none());
+        assert!(handler0
+            .handle_authentication(authentication(&handler1))
+            .is_some());
+        assert_eq!(
+            handler0.handle_versions_advertisement(
+                &authentication(&handler1),
+                versions_advertisement(&handler1)
+            ),
+            Some(FinalityVersions::supported())
+        );
+        assert_eq!(
+            handler0.finality_versions(&NodeIndex(1)),
+            Some(FinalityVersions::supported())
+        );
+        assert_eq!(
+            handler0.finality_versions(&NodeIndex(0)),
+            Some(FinalityVersions::supported())
+        );
+    }
+
+    #[test]
+    fn ignores_versions_advertisement_of_unknown_authentication() {
+        let crypto_basics = crypto_basics(NUM_NODES);
+        let mut handler0 = Handler::new(
+            Some(crypto_basics.0[0].clone()),
+            crypto_basics.1.clone(),
+            SessionId(43),
+            random_address(),
+        );
+        let handler1 = Handler::new(
+            Some(crypto_basics.0[1].clone()),
+            crypto_basics.1.clone(),
+            SessionId(43),
+            random_address(),
+        );
+        assert!(handler0
+            .handle_versions_advertisement(
+                &authentication(&handler1),
+                versions_advertisement(&handler1)
+            )
+            .is_none());
+        assert!(handler0.finality_versions(&NodeIndex(1)).is_none());
+    }
+
+    #[test]
+    fn ignores_versions_advertisement_of_someone_else() {
+        let crypto_basics = crypto_basics(NUM_NODES);
+        let mut handler0 = Handler::new(
+            Some(crypto_basics.0[0].clone()),
+            crypto_basics.1.clone(),
+            SessionId(43),
+            random_address(),
+        );
+        let handler1 = Handler::new(
+            Some(crypto_basics.0[1].clone()),
+            crypto_basics.1.clone(),
+            SessionId(43),
+            random_address(),
+        );
+        let handler2 = Handler::new(
+            Some(crypto_basics.0[2].clone()),
+            crypto_basics.1.clone(),
+            SessionId(43),
+            random_address(),
+        );
+        assert!(handler0
+            .handle_authentication(authentication(&handler1))
+            .is_some());
+        assert!(handler0
+            .handle_versions_advertisement(
+                &authentication(&handler1),
+                versions_advertisement(&handler2)
+            )
+            .is_none());
+        assert!(handler0.finality_versions(&NodeIndex(1)).is_none());
+    }
 }
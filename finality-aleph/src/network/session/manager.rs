@@ -13,8 +13,8 @@ use crate::{
     network::{
         address_cache::{ValidatorAddressCacheUpdater, ValidatorAddressingInfo},
         session::{
-            data::DataInSession, Authentication, Connections, Discovery, DiscoveryMessage,
-            SessionHandler, SessionHandlerError,
+            data::DataInSession, Connections, Discovery, DiscoveryMessage, FinalityVersions,
+            SessionConnectivity, SessionHandler, SessionHandlerError,
         },
         AddressingInformation, Data, NetworkIdentity, PeerId,
     },
@@ -58,7 +58,7 @@ pub struct PreNonvalidatorSession {
 /// discovery  purposes.
 pub struct ManagerActions<A: AddressingInformation> {
     pub maybe_command: Option<ConnectionCommand<A>>,
-    pub maybe_message: Option<Authentication<A>>,
+    pub maybe_message: Option<DiscoveryMessage<A>>,
 }
 
 impl<A: AddressingInformation> ManagerActions<A> {
@@ -134,7 +134,7 @@ impl<NI: NetworkIdentity, D: Data, VCU: ValidatorAddressCacheUpdater> Manager<NI
     fn discover_authorities(
         &mut self,
         session_id: &SessionId,
-    ) -> Option<Authentication<NI::AddressingInformation>> {
+    ) -> Option<DiscoveryMessage<NI::AddressingInformation>> {
         self.sessions.get_mut(session_id).and_then(
             |Session {
                  handler, discovery, ..
@@ -143,7 +143,7 @@ impl<NI: NetworkIdentity, D: Data, VCU: ValidatorAddressCacheUpdater> Manager<NI
     }
 
     /// Returns all the network messages that should be sent as part of discovery at this moment.
-    pub fn discovery(&mut self) -> Vec<Authentication<NI::AddressingInformation>> {
+    pub fn discovery(&mut self) -> Vec<DiscoveryMessage<NI::AddressingInformation>> {
         let sessions: Vec<_> = self.sessions.keys().cloned().collect();
         sessions
             .iter()
@@ -156,7 +156,7 @@ impl<NI: NetworkIdentity, D: Data, VCU: ValidatorAddressCacheUpdater> Manager<NI
         pre_session: PreValidatorSession,
         address: NI::AddressingInformation,
     ) -> (
-        Option<Authentication<NI::AddressingInformation>>,
+        Option<DiscoveryMessage<NI::AddressingInformation>>,
         mpsc::UnboundedReceiver<D>,
     ) {
         let PreValidatorSession {
@@ -218,6 +218,8 @@ impl<NI: NetworkIdentity, D: Data, VCU: ValidatorAddressCacheUpdater> Manager<NI
                 session: session_id,
                 network_level_address: address.address(),
                 validator_network_peer_id: address.peer_id().to_string(),
+                unreachable_addresses: Vec::new(),
+                finality_versions: Some(FinalityVersions::supported()),
             },
         );
 
@@ -324,13 +326,27 @@ impl<NI: NetworkIdentity, D: Data, VCU: ValidatorAddressCacheUpdater> Manager<NI
         message: DiscoveryMessage<NI::AddressingInformation>,
     ) -> ManagerActions<NI::AddressingInformation> {
         let session_id = message.session_id();
-        let creator = message.0.creator();
+        let creator = message.authentication.0.creator();
         match self.sessions.get_mut(&session_id) {
             Some(Session {
                 handler, discovery, ..
             }) => {
-                let (maybe_address, maybe_message) =
-                    discovery.handle_authentication(message, handler);
+                let DiscoveryMessage {
+                    authentication,
+                    versions_advertisement,
+                } = message;
+                let (maybe_address, maybe_authentication) =
+                    discovery.handle_authentication(authentication.clone(), handler);
+                if let (Some(_), Some(versions_advertisement)) =
+                    (&maybe_address, versions_advertisement)
+                {
+                    handler.handle_versions_advertisement(&authentication, versions_advertisement);
+                }
+                // Rebroadcast with the advertisement we know of, even if this copy came without it.
+                let maybe_message = maybe_authentication.map(|authentication| DiscoveryMessage {
+                    versions_advertisement: handler.versions_advertisement(&authentication),
+                    authentication,
+                });
                 let mut maybe_command = None;
                 if let Some(address) = maybe_address {
                     self.validator_address_cache_updater.update(
@@ -339,6 +355,8 @@ impl<NI: NetworkIdentity, D: Data, VCU: ValidatorAddressCacheUpdater> Manager<NI
                             session: session_id,
                             network_level_address: address.address(),
                             validator_network_peer_id: address.peer_id().to_string(),
+                            unreachable_addresses: Vec::new(),
+                            finality_versions: handler.finality_versions(&creator),
                         },
                     );
                     if handler.is_validator() {
@@ -373,6 +391,23 @@ impl<NI: NetworkIdentity, D: Data, VCU: ValidatorAddressCacheUpdater> Manager<NI
         }
     }
 
+    /// Returns the connectivity in all the sessions in which we authenticate ourselves.
+    pub fn connectivity(&self) -> HashMap<SessionId, SessionConnectivity> {
+        self.sessions
+            .iter()
+            .filter(|(_, session)| session.handler.authentication().is_some())
+            .map(|(session_id, session)| {
+                (
+                    *session_id,
+                    SessionConnectivity {
+                        authenticated: session.handler.peers().len() + 1,
+                        committee_size: session.handler.node_count().0,
+                    },
+                )
+            })
+            .collect()
+    }
+
     pub fn status_report(&self) {
         let mut status = String::from("Connection Manager status report: ");
 
@@ -467,7 +502,7 @@ mod tests {
         network::{
             address_cache::{test::noop_updater, ValidatorAddressCacheUpdater},
             mock::crypto_basics,
-            session::data::DataInSession,
+            session::{data::DataInSession, SessionConnectivity},
         },
         Recipient, SessionId,
     };
@@ -588,7 +623,7 @@ mod tests {
             })
             .unwrap();
         let message = maybe_message.expect("there should be a discovery message");
-        let (address, message) = (message.0.address(), message);
+        let (address, message) = (message.authentication.0.address(), message);
         let ManagerActions {
             maybe_command,
             maybe_message,
@@ -639,4 +674,47 @@ mod tests {
             }
         );
     }
+
+    #[test]
+    fn reports_connectivity() {
+        let mut manager = build();
+        let (validator_data, verifier) = crypto_basics(NUM_NODES);
+        let (node_id, pen) = validator_data[0].clone();
+        let session_id = SessionId(43);
+        manager
+            .update_nonvalidator_session(PreNonvalidatorSession {
+                session_id: SessionId(42),
+                verifier: verifier.clone(),
+            })
+            .unwrap();
+        manager
+            .update_validator_session(PreValidatorSession {
+                session_id,
+                verifier: verifier.clone(),
+                node_id,
+                pen,
+            })
+            .unwrap();
+        let mut other_manager = build();
+        let (node_id, pen) = validator_data[1].clone();
+        let (ManagerActions { maybe_message, .. }, _) = other_manager
+            .update_validator_session(PreValidatorSession {
+                session_id,
+                verifier,
+                node_id,
+                pen,
+            })
+            .unwrap();
+        let message = maybe_message.expect("there should be a discovery message");
+        manager.on_discovery_message(message);
+        let connectivity = manager.connectivity();
+        assert_eq!(connectivity.len(), 1);
+        assert_eq!(
+            connectivity.get(&session_id),
+            Some(&SessionConnectivity {
+                authenticated: 2,
+                committee_size: NUM_NODES,
+            })
+        );
+    }
 }
@@ -3,8 +3,10 @@ use std::fmt::Display;
 
 use futures::channel::mpsc;
 use parity_scale_codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
 
 use crate::{
+    abft::{CURRENT_VERSION, LEGACY_VERSION},
     crypto::{AuthorityPen, AuthorityVerifier, Signature},
     network::{
         data::{
@@ -23,6 +25,7 @@ mod discovery;
 mod handler;
 mod manager;
 mod service;
+mod status;
 
 pub use compatibility::{DiscoveryMessage, VersionedAuthentication};
 use connections::Connections;
@@ -33,6 +36,7 @@ pub use discovery::Discovery;
 pub use handler::tests::authentication;
 pub use handler::{Handler as SessionHandler, HandlerError as SessionHandlerError};
 pub use service::{Config as ConnectionManagerConfig, ManagerError, Service as ConnectionManager};
+pub use status::{SessionConnectivity, ValidatorNetworkStatus};
 
 /// The maximum size an authentication can have and be accepted.
 /// This leaves a generous margin of error, as the signature is 64 bytes,
@@ -67,6 +71,45 @@ impl<A: AddressingInformation> AuthData<A> {
 #[derive(Clone, Decode, Encode, Debug, Eq, PartialEq, Hash)]
 pub struct Authentication<A: AddressingInformation>(AuthData<A>, Signature);
 
+/// The range of finality versions a node is able to run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Encode, Decode, Serialize, Deserialize)]
+pub struct FinalityVersions {
+    pub lowest: u32,
+    pub highest: u32,
+}
+
+impl FinalityVersions {
+    /// The finality versions this node is able to run.
+    pub fn supported() -> Self {
+        FinalityVersions {
+            lowest: LEGACY_VERSION as u32,
+            highest: CURRENT_VERSION as u32,
+        }
+    }
+
+    pub fn contains(&self, version: u32) -> bool {
+        self.lowest <= version && version <= self.highest
+    }
+}
+
+/// The finality versions supported by the creator of an authentication, signed together with
+/// the authentication data, so that they cannot be attached to someone else's authentication.
+#[derive(Clone, Decode, Encode, Debug, Eq, PartialEq, Hash)]
+pub struct VersionsAdvertisement(FinalityVersions, Signature);
+
+impl VersionsAdvertisement {
+    fn payload<A: AddressingInformation>(
+        auth_data: &AuthData<A>,
+        finality_versions: &FinalityVersions,
+    ) -> Vec<u8> {
+        (auth_data, finality_versions).encode()
+    }
+
+    pub fn finality_versions(&self) -> FinalityVersions {
+        self.0
+    }
+}
+
 /// Sends data within a single session.
 #[derive(Clone)]
 pub struct SessionSender<D: Data> {
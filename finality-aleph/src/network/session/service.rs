@@ -23,7 +23,8 @@ use crate::{
                 AddressedData, ConnectionCommand, Manager, ManagerActions, PreNonvalidatorSession,
                 PreValidatorSession, SendError,
             },
-            Network, SessionHandlerError, SessionManager, SessionSender, VersionedAuthentication,
+            Network, SessionHandlerError, SessionManager, SessionSender, ValidatorNetworkStatus,
+            VersionedAuthentication,
         },
         AddressingInformation, Data, GossipNetwork, NetworkIdentity,
     },
@@ -186,6 +187,7 @@ pub struct Service<
     messages_from_user: mpsc::UnboundedReceiver<(D, SessionId, Recipient)>,
     validator_network: CN,
     gossip_network: GN,
+    network_status: ValidatorNetworkStatus,
     maintenance_period: Duration,
     initial_delay: Duration,
 }
@@ -226,6 +228,7 @@ where
         validator_network: CN,
         gossip_network: GN,
         validator_address_cache_updater: VCU,
+        network_status: ValidatorNetworkStatus,
         config: Config,
     ) -> (
         Service<D, NI, CN, GN, VCU>,
@@ -250,6 +253,7 @@ where
                 messages_from_user,
                 validator_network,
                 gossip_network,
+                network_status,
                 maintenance_period,
                 initial_delay,
             },
@@ -407,6 +411,7 @@ where
                 },
                 _ = status_ticker.tick() => {
                     self.manager.status_report();
+                    self.network_status.update(self.manager.connectivity());
                 }
             }
         }
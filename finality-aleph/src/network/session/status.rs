@@ -0,0 +1,37 @@
+use std::{collections::HashMap, sync::Arc};
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+use crate::SessionId;
+
+/// Connectivity of this node within the committee of a single session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionConnectivity {
+    /// The number of committee members this node is authenticated with, including itself.
+    pub authenticated: usize,
+    /// The size of the committee.
+    pub committee_size: usize,
+}
+
+/// Connectivity of this node in the sessions handled by the connection manager, refreshed with
+/// every status report of the manager.
+#[derive(Clone, Default)]
+pub struct ValidatorNetworkStatus {
+    sessions: Arc<Mutex<HashMap<SessionId, SessionConnectivity>>>,
+}
+
+impl ValidatorNetworkStatus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The connectivity in the given session, if this node is authenticating itself in it.
+    pub fn session(&self, session_id: SessionId) -> Option<SessionConnectivity> {
+        self.sessions.lock().get(&session_id).copied()
+    }
+
+    pub(crate) fn update(&self, sessions: HashMap<SessionId, SessionConnectivity>) {
+        *self.sessions.lock() = sessions;
+    }
+}
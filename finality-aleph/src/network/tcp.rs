@@ -1,16 +1,26 @@
-use std::{io::Error as IoError, iter, net::ToSocketAddrs as _};
+use std::{
+    collections::HashMap,
+    io::{Error as IoError, ErrorKind as IoErrorKind},
+    iter,
+    sync::Arc,
+    time::Duration,
+};
 
 use derive_more::{AsRef, Display};
-use log::info;
+use log::{debug, info};
 use network_clique::{Dialer, Listener, PeerId, PublicKey, SecretKey};
 use parity_scale_codec::{Decode, Encode};
+use parking_lot::Mutex;
 use sp_core::crypto::KeyTypeId;
-use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::{
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    time::timeout,
+};
 
 use crate::{
     aleph_primitives::AuthorityId,
     crypto::{verify, AuthorityPen, Signature},
-    network::{AddressingInformation, NetworkIdentity},
+    network::{address_cache::ValidatorAddressCache, AddressingInformation, NetworkIdentity},
 };
 
 const LOG_TARGET: &str = "tcp-network";
@@ -86,6 +96,10 @@ impl TcpAddressingInformation {
     fn peer_id(&self) -> AuthorityId {
         self.peer_id.clone()
     }
+
+    fn addresses(&self) -> impl Iterator<Item = &String> {
+        iter::once(&self.primary_address).chain(self.other_addresses.iter())
+    }
 }
 
 /// A representation of TCP addressing information with an associated peer ID, self-signed.
@@ -136,8 +150,73 @@ impl SignedTcpAddressingInformation {
     }
 }
 
+/// How long we wait for a single advertised address before trying the next one.
+const ADDRESS_DIAL_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Remembers which of the advertised addresses of every peer worked last time.
+#[derive(Clone, Default)]
+struct PreferredAddresses {
+    addresses: Arc<Mutex<HashMap<AuthorityId, String>>>,
+}
+
+impl PreferredAddresses {
+    /// Orders the advertised addresses so that the one that worked last time is tried first,
+    /// the rest are tried in the order in which they were advertised.
+    fn ordered(&self, addressing_information: &TcpAddressingInformation) -> Vec<String> {
+        let preferred = self
+            .addresses
+            .lock()
+            .get(&addressing_information.peer_id)
+            .cloned();
+        let mut addresses: Vec<String> = Vec::new();
+        for address in preferred
+            .iter()
+            .filter(|preferred| addressing_information.addresses().any(|a| a == *preferred))
+            .chain(addressing_information.addresses())
+        {
+            if !addresses.contains(address) {
+                addresses.push(address.clone());
+            }
+        }
+        addresses
+    }
+
+    fn set(&self, peer_id: AuthorityId, address: String) {
+        self.addresses.lock().insert(peer_id, address);
+    }
+}
+
 #[derive(Clone)]
-struct TcpDialer;
+struct TcpDialer {
+    preferred_addresses: PreferredAddresses,
+    validator_address_cache: Option<ValidatorAddressCache>,
+}
+
+impl TcpDialer {
+    fn new(validator_address_cache: Option<ValidatorAddressCache>) -> Self {
+        TcpDialer {
+            preferred_addresses: PreferredAddresses::default(),
+            validator_address_cache,
+        }
+    }
+
+    async fn connect_to(address: &str) -> Result<TcpStream, IoError> {
+        // Resolves DNS names and tries all the resulting IPv4 and IPv6 socket addresses.
+        match timeout(ADDRESS_DIAL_TIMEOUT, TcpStream::connect(address)).await {
+            Ok(result) => result,
+            Err(_) => Err(IoError::new(IoErrorKind::TimedOut, "dial timeout")),
+        }
+    }
+
+    fn report_reachability(&self, peer_id: AuthorityId, unreachable_addresses: Vec<String>) {
+        if let Some(validator_address_cache) = &self.validator_address_cache {
+            validator_address_cache.report_reachability(
+                &AuthorityIdWrapper::from(peer_id).to_string(),
+                unreachable_addresses,
+            );
+        }
+    }
+}
 
 #[async_trait::async_trait]
 impl Dialer<SignedTcpAddressingInformation> for TcpDialer {
@@ -152,21 +231,32 @@ impl Dialer<SignedTcpAddressingInformation> for TcpDialer {
             addressing_information,
             ..
         } = address;
-        let TcpAddressingInformation {
-            primary_address,
-            other_addresses,
-            ..
-        } = addressing_information;
-        let parsed_addresses: Vec<_> = iter::once(primary_address)
-            .chain(other_addresses)
-            .filter_map(|address| address.to_socket_addrs().ok())
-            .flatten()
-            .collect();
-        let stream = TcpStream::connect(&parsed_addresses[..]).await?;
-        if stream.set_linger(None).is_err() {
-            info!(target: LOG_TARGET, "stream.set_linger(None) failed.");
-        };
-        Ok(stream)
+        let peer_id = addressing_information.peer_id();
+        let mut unreachable_addresses = Vec::new();
+        let mut last_error = None;
+        for address in self.preferred_addresses.ordered(&addressing_information) {
+            match Self::connect_to(&address).await {
+                Ok(stream) => {
+                    if stream.set_linger(None).is_err() {
+                        info!(target: LOG_TARGET, "stream.set_linger(None) failed.");
+                    };
+                    self.preferred_addresses.set(peer_id.clone(), address);
+                    self.report_reachability(peer_id, unreachable_addresses);
+                    return Ok(stream);
+                }
+                Err(e) => {
+                    debug!(
+                        target: LOG_TARGET,
+                        "Failed to connect to {} using address {}: {}.", peer_id, address, e
+                    );
+                    unreachable_addresses.push(address);
+                    last_error = Some(e);
+                }
+            }
+        }
+        self.report_reachability(peer_id, unreachable_addresses);
+        Err(last_error
+            .unwrap_or_else(|| IoError::new(IoErrorKind::NotFound, "no addresses to connect to")))
     }
 }
 
@@ -190,11 +280,13 @@ impl From<AddressingInformationError> for Error {
 }
 
 /// Create a new tcp network, including an identity that can be used for constructing
-/// authentications for other peers.
+/// authentications for other peers. If `validator_address_cache` is provided, the addresses
+/// of other peers we fail to connect to are reported there.
 pub async fn new_tcp_network<A: ToSocketAddrs>(
     listening_addresses: A,
     external_addresses: Vec<String>,
     authority_pen: &AuthorityPen,
+    validator_address_cache: Option<ValidatorAddressCache>,
 ) -> Result<
     (
         impl Dialer<SignedTcpAddressingInformation>,
@@ -208,7 +300,7 @@ pub async fn new_tcp_network<A: ToSocketAddrs>(
 > {
     let listener = TcpListener::bind(listening_addresses).await?;
     let identity = SignedTcpAddressingInformation::new(external_addresses, authority_pen)?;
-    Ok((TcpDialer {}, listener, identity))
+    Ok((TcpDialer::new(validator_address_cache), listener, identity))
 }
 
 #[cfg(test)]
@@ -229,3 +321,81 @@ pub mod testing {
             .expect("the provided addresses are fine")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use network_clique::Dialer;
+    use tokio::net::TcpListener;
+
+    use super::{
+        PreferredAddresses, SignedTcpAddressingInformation, TcpAddressingInformation, TcpDialer,
+    };
+    use crate::{crypto::AuthorityPen, network::mock::crypto_basics};
+
+    fn pen() -> AuthorityPen {
+        crypto_basics(1).0.pop().expect("we asked for one pen").1
+    }
+
+    fn addressing_information(
+        addresses: Vec<String>,
+        pen: &AuthorityPen,
+    ) -> TcpAddressingInformation {
+        TcpAddressingInformation::new(addresses, pen.authority_id())
+            .expect("the provided addresses are fine")
+    }
+
+    #[test]
+    fn preferred_address_goes_first() {
+        let pen = pen();
+        let info = addressing_information(
+            vec!["a:1".to_string(), "b:2".to_string(), "c:3".to_string()],
+            &pen,
+        );
+        let preferred_addresses = PreferredAddresses::default();
+        assert_eq!(
+            preferred_addresses.ordered(&info),
+            vec!["a:1".to_string(), "b:2".to_string(), "c:3".to_string()]
+        );
+
+        preferred_addresses.set(pen.authority_id(), "c:3".to_string());
+        assert_eq!(
+            preferred_addresses.ordered(&info),
+            vec!["c:3".to_string(), "a:1".to_string(), "b:2".to_string()]
+        );
+
+        preferred_addresses.set(pen.authority_id(), "d:4".to_string());
+        assert_eq!(
+            preferred_addresses.ordered(&info),
+            vec!["a:1".to_string(), "b:2".to_string(), "c:3".to_string()],
+            "addresses that are no longer advertised should not be tried"
+        );
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_working_address_and_remembers_it() {
+        let pen = pen();
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("should bind to a free port");
+        let working = listener
+            .local_addr()
+            .expect("listener should have an address")
+            .to_string();
+        let unparsable = "definitely not an address".to_string();
+        let address =
+            SignedTcpAddressingInformation::new(vec![unparsable.clone(), working.clone()], &pen)
+                .expect("the provided addresses are fine");
+
+        let mut dialer = TcpDialer::new(None);
+        dialer
+            .connect(address.clone())
+            .await
+            .expect("should connect using the second address");
+        assert_eq!(
+            dialer
+                .preferred_addresses
+                .ordered(&address.addressing_information),
+            vec![working, unparsable]
+        );
+    }
+}
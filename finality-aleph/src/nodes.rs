@@ -1,16 +1,18 @@
-use std::{marker::PhantomData, sync::Arc};
+use std::{marker::PhantomData, sync::Arc, time::Duration};
 
 use bip39::{Language, Mnemonic, MnemonicType};
 use futures::channel::oneshot;
-use log::{debug, error};
+use log::{debug, error, warn};
 use network_clique::{RateLimitingDialer, RateLimitingListener, Service, SpawnHandleT};
 use rate_limiter::SleepingRateLimiter;
 use sc_client_api::Backend;
+use sp_blockchain::HeaderBackend;
 use sp_consensus::SelectChain;
 use sp_consensus_aura::AuraApi;
 use sp_keystore::Keystore;
 
 use crate::{
+    aggregation::CurrentAggregatorConfig,
     aleph_primitives::{AlephSessionApi, AuraId, Block},
     block::{
         substrate::{
@@ -20,22 +22,23 @@ use crate::{
         ChainStatus, FinalizationStatus, Justification,
     },
     crypto::AuthorityPen,
+    data_io::ProposalStrategyConfig,
     finalization::AlephFinalizer,
     idx_to_account::ValidatorIndexToAccountIdConverterImpl,
-    metrics::run_chain_state_metrics,
+    metrics::{run_chain_state_metrics, AbftMetrics, AggregatorMetrics, ParticipationMetrics},
     network::{
         address_cache::validator_address_cache_updater,
         session::{ConnectionManager, ConnectionManagerConfig},
         tcp::{new_tcp_network, KEY_TYPE},
         GossipService, SubstrateNetwork,
     },
+    participation::ParticipationTracker,
     party::{
         impls::ChainStateImpl, manager::NodeSessionManagerImpl, ConsensusParty,
         ConsensusPartyParams,
     },
     runtime_api::RuntimeApiImpl,
-    session::SessionBoundaryInfo,
-    session_map::{AuthorityProviderImpl, FinalityNotifierImpl, SessionMapUpdater},
+    session_map::{AuthorityProviderImpl, FinalityNotifierImpl, SessionHistory, SessionMapUpdater},
     sync::{DatabaseIO as SyncDatabaseIO, Service as SyncService, IO as SyncIO},
     AlephConfig,
 };
@@ -72,7 +75,9 @@ where
         metrics,
         registry,
         unit_creation_delay,
-        session_period,
+        remulticast_timeout,
+        session_history_depth,
+        session_info,
         millisecs_per_block,
         justification_rx,
         block_rx,
@@ -83,6 +88,10 @@ where
         rate_limiter_config,
         sync_oracle,
         validator_address_cache,
+        validator_network_status,
+        finality_participation,
+        event_log,
+        proposal_strategy,
     } = aleph_config;
 
     // We generate the phrase manually to only save the key in RAM, we don't want to have these
@@ -99,6 +108,7 @@ where
         ("0.0.0.0", validator_port),
         external_addresses,
         &network_authority_pen,
+        validator_address_cache.clone(),
     )
     .await
     .expect("we should have working networking");
@@ -131,7 +141,9 @@ where
     let map_updater = SessionMapUpdater::new(
         AuthorityProviderImpl::new(client.clone(), RuntimeApiImpl::new(client.clone())),
         FinalityNotifierImpl::new(client.clone()),
-        session_period,
+        session_info.clone(),
+        SessionHistory::new(client.clone()),
+        session_history_depth,
     );
     let session_authorities = map_updater.readonly_session_map();
     spawn_handle.spawn("aleph/updater", async move {
@@ -139,6 +151,25 @@ where
         map_updater.run().await
     });
 
+    let participation_metrics = match ParticipationMetrics::new(registry.as_ref()) {
+        Ok(metrics) => metrics,
+        Err(e) => {
+            warn!(target: "aleph-party", "Failed to register finality participation metrics: {:?}.", e);
+            ParticipationMetrics::noop()
+        }
+    };
+    let participation_tracker = ParticipationTracker::new(
+        client.clone(),
+        AuthorityProviderImpl::new(client.clone(), RuntimeApiImpl::new(client.clone())),
+        session_info.clone(),
+        finality_participation,
+        participation_metrics,
+    );
+    spawn_handle.spawn("aleph/participation", async move {
+        debug!(target: "aleph-party", "Finality participation tracker has started.");
+        participation_tracker.run().await
+    });
+
     let chain_events = SubstrateChainStatusNotifier::new(
         client.finality_notification_stream(),
         client.every_import_notification_stream(),
@@ -156,7 +187,6 @@ where
         .await;
     });
 
-    let session_info = SessionBoundaryInfo::new(session_period);
     let genesis_header = match chain_status.finalized_at(0) {
         Ok(FinalizationStatus::FinalizedWithJustification(justification)) => {
             justification.header().clone()
@@ -170,7 +200,7 @@ where
         VERIFIER_CACHE_SIZE,
         genesis_header,
     );
-    let finalizer = AlephFinalizer::new(client.clone(), metrics.clone());
+    let finalizer = AlephFinalizer::new(client.clone(), metrics.clone(), event_log.clone());
     import_queue_handle.attach_metrics(metrics.clone());
     let sync_io = SyncIO::new(
         SyncDatabaseIO::new(chain_status.clone(), finalizer, import_queue_handle),
@@ -180,15 +210,17 @@ where
         justification_rx,
         block_rx,
     );
-    let (sync_service, justifications_for_sync, request_block) = match SyncService::new(
-        verifier.clone(),
-        session_info.clone(),
-        sync_io,
-        registry.clone(),
-    ) {
-        Ok(x) => x,
-        Err(e) => panic!("Failed to initialize Sync service: {e}"),
-    };
+    let (sync_service, justifications_for_sync, request_block, peer_favourites) =
+        match SyncService::new(
+            verifier.clone(),
+            session_info.clone(),
+            sync_io,
+            registry.clone(),
+            event_log.clone(),
+        ) {
+            Ok(x) => x,
+            Err(e) => panic!("Failed to initialize Sync service: {e}"),
+        };
     let sync_task = async move { sync_service.run().await };
 
     let validator_address_cache_updater = validator_address_cache_updater(
@@ -200,12 +232,17 @@ where
         ),
     );
 
+    let current_session = session_info.session_id_from_block_num(client.info().finalized_number);
     let (connection_manager_service, connection_manager) = ConnectionManager::new(
         network_identity,
         validator_network,
         authentication_network,
         validator_address_cache_updater,
-        ConnectionManagerConfig::with_session_period(&session_period, &millisecs_per_block),
+        validator_network_status,
+        ConnectionManagerConfig::with_session_period(
+            &session_info.session_period(current_session),
+            &millisecs_per_block,
+        ),
     );
 
     let connection_manager_task = async move {
@@ -221,6 +258,22 @@ where
     spawn_handle.spawn("aleph/gossip_network", gossip_network_task);
     debug!(target: "aleph-party", "Gossip network has started.");
 
+    let aggregator_metrics = match AggregatorMetrics::new(registry.as_ref()) {
+        Ok(metrics) => metrics,
+        Err(e) => {
+            warn!(target: "aleph-party", "Failed to register aggregator metrics: {:?}.", e);
+            AggregatorMetrics::noop()
+        }
+    };
+    let abft_metrics = match AbftMetrics::new(registry.as_ref()) {
+        Ok(metrics) => metrics,
+        Err(e) => {
+            warn!(target: "aleph-party", "Failed to register AlephBFT metrics: {:?}.", e);
+            AbftMetrics::noop()
+        }
+    };
+
+    debug!(target: "aleph-party", "Using the {} proposal strategy.", proposal_strategy);
     let party = ConsensusParty::new(ConsensusPartyParams {
         session_authorities,
         sync_oracle,
@@ -233,17 +286,25 @@ where
             client,
             select_chain,
             verifier,
-            session_period,
+            session_info.clone(),
             unit_creation_delay,
             justifications_for_sync,
             JustificationTranslator::new(chain_status.clone()),
             request_block,
             metrics,
+            abft_metrics,
             spawn_handle,
             connection_manager,
             keystore,
+            ProposalStrategyConfig::new(proposal_strategy, Arc::new(peer_favourites)),
+            CurrentAggregatorConfig {
+                metrics: aggregator_metrics,
+                remulticast_timeout: Duration::from_millis(remulticast_timeout.0),
+            },
+            event_log.clone(),
         ),
         session_info,
+        event_log,
     });
 
     debug!(target: "aleph-party", "Consensus party has started.");
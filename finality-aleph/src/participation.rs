@@ -0,0 +1,375 @@
+//! Tracking of which committee members take part in finalization, based on the signatures
+//! present in the justifications of finalized blocks. Justifications only hold as many
+//! signatures as the threshold requires, usually those of the fastest members, so this measures
+//! how often a member is among them rather than whether it signs at all.
+use std::{collections::BTreeMap, iter, sync::Arc};
+
+use futures::StreamExt;
+use log::{debug, warn};
+use parking_lot::Mutex;
+use sc_client_api::{BlockBackend, BlockchainEvents};
+use serde::{Deserialize, Serialize};
+use sp_blockchain::HeaderBackend;
+
+use crate::{
+    aleph_primitives::{AuthorityId, Block, BlockHash, ALEPH_ENGINE_ID},
+    justification::{backwards_compatible_decode, AlephJustification},
+    metrics::ParticipationMetrics,
+    session_map::AuthorityProvider,
+    NodeIndex, SessionBoundaryInfo, SessionId,
+};
+
+const LOG_TARGET: &str = "aleph-participation";
+
+/// How many of the most recent sessions are kept.
+const SESSIONS_KEPT: u32 = 16;
+
+/// Finality participation of a single committee member.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemberParticipation {
+    /// The index of the member in the committee.
+    pub index: u32,
+    /// The Aleph key of the member.
+    pub authority: AuthorityId,
+    /// The number of justifications in the session containing a signature of the member.
+    pub signed: u32,
+    /// The fraction of the justifications in the session signed by the member.
+    pub ratio: f64,
+}
+
+/// Finality participation of the committee of a session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionParticipation {
+    /// The session.
+    pub session: SessionId,
+    /// The number of finalized blocks of the session with committee justifications seen so far.
+    /// Blocks finalized implicitly, or with emergency justifications, are not counted.
+    pub justified_blocks: u32,
+    /// The participation of every member, in the order of committee indices.
+    pub members: Vec<MemberParticipation>,
+}
+
+struct SessionRecord {
+    authorities: Vec<AuthorityId>,
+    justified_blocks: u32,
+    signed: Vec<u32>,
+}
+
+impl SessionRecord {
+    fn new(authorities: Vec<AuthorityId>) -> Self {
+        SessionRecord {
+            signed: vec![0; authorities.len()],
+            authorities,
+            justified_blocks: 0,
+        }
+    }
+
+    fn record(&mut self, signers: &[NodeIndex]) {
+        self.justified_blocks += 1;
+        for signer in signers {
+            match self.signed.get_mut(signer.0) {
+                Some(signed) => *signed += 1,
+                None => warn!(
+                    target: LOG_TARGET,
+                    "Justification signed by {:?}, who is not in the committee.", signer
+                ),
+            }
+        }
+    }
+
+    fn ratios(&self) -> Vec<f64> {
+        self.signed
+            .iter()
+            .map(|signed| match self.justified_blocks {
+                0 => 0.0,
+                justified_blocks => *signed as f64 / justified_blocks as f64,
+            })
+            .collect()
+    }
+
+    fn participation(&self, session: SessionId) -> SessionParticipation {
+        let members = self
+            .authorities
+            .iter()
+            .zip(self.signed.iter())
+            .zip(self.ratios())
+            .enumerate()
+            .map(
+                |(index, ((authority, signed), ratio))| MemberParticipation {
+                    index: index as u32,
+                    authority: authority.clone(),
+                    signed: *signed,
+                    ratio,
+                },
+            )
+            .collect();
+        SessionParticipation {
+            session,
+            justified_blocks: self.justified_blocks,
+            members,
+        }
+    }
+}
+
+/// Finality participation of committee members in the most recent sessions, shared between
+/// the tracker filling it and whoever wants to read it.
+#[derive(Clone, Default)]
+pub struct FinalityParticipation {
+    sessions: Arc<Mutex<BTreeMap<SessionId, SessionRecord>>>,
+}
+
+impl FinalityParticipation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The participation in the given session, if it is tracked.
+    pub fn session(&self, session: SessionId) -> Option<SessionParticipation> {
+        self.sessions
+            .lock()
+            .get(&session)
+            .map(|record| record.participation(session))
+    }
+
+    /// The participation in the most recent tracked session.
+    pub fn latest(&self) -> Option<SessionParticipation> {
+        self.sessions
+            .lock()
+            .iter()
+            .next_back()
+            .map(|(session, record)| record.participation(*session))
+    }
+
+    fn contains(&self, session: SessionId) -> bool {
+        self.sessions.lock().contains_key(&session)
+    }
+
+    fn start_session(&self, session: SessionId, authorities: Vec<AuthorityId>) {
+        self.sessions
+            .lock()
+            .entry(session)
+            .or_insert_with(|| SessionRecord::new(authorities));
+    }
+
+    /// Records a justification of a block of an already started session, returns the number
+    /// of justified blocks and participation ratios in the session.
+    fn record(&self, session: SessionId, signers: &[NodeIndex]) -> Option<(u32, Vec<f64>)> {
+        let mut sessions = self.sessions.lock();
+        let record = sessions.get_mut(&session)?;
+        record.record(signers);
+        Some((record.justified_blocks, record.ratios()))
+    }
+
+    /// Forgets sessions too old to be kept with respect to the given one, returns them together
+    /// with the sizes of their committees.
+    fn prune(&self, session: SessionId) -> Vec<(SessionId, usize)> {
+        let first_kept = SessionId(session.0.saturating_sub(SESSIONS_KEPT - 1));
+        let mut sessions = self.sessions.lock();
+        let kept = sessions.split_off(&first_kept);
+        let pruned = std::mem::replace(&mut *sessions, kept);
+        pruned
+            .into_iter()
+            .map(|(session, record)| (session, record.authorities.len()))
+            .collect()
+    }
+}
+
+/// Follows finalized blocks and records which committee members signed their justifications.
+pub struct ParticipationTracker<C, AP>
+where
+    C: HeaderBackend<Block> + BlockBackend<Block> + BlockchainEvents<Block>,
+    AP: AuthorityProvider,
+{
+    client: Arc<C>,
+    authority_provider: AP,
+    session_info: SessionBoundaryInfo,
+    participation: FinalityParticipation,
+    metrics: ParticipationMetrics,
+}
+
+impl<C, AP> ParticipationTracker<C, AP>
+where
+    C: HeaderBackend<Block> + BlockBackend<Block> + BlockchainEvents<Block>,
+    AP: AuthorityProvider,
+{
+    pub fn new(
+        client: Arc<C>,
+        authority_provider: AP,
+        session_info: SessionBoundaryInfo,
+        participation: FinalityParticipation,
+        metrics: ParticipationMetrics,
+    ) -> Self {
+        ParticipationTracker {
+            client,
+            authority_provider,
+            session_info,
+            participation,
+            metrics,
+        }
+    }
+
+    fn committee_signers(&self, hash: BlockHash) -> Option<Vec<NodeIndex>> {
+        let justification = match self.client.justifications(hash) {
+            Ok(justifications) => justifications?.into_justification(ALEPH_ENGINE_ID)?,
+            Err(e) => {
+                warn!(
+                    target: LOG_TARGET,
+                    "Failed to read justifications of block {}: {}", hash, e
+                );
+                return None;
+            }
+        };
+        match backwards_compatible_decode(justification) {
+            Ok(
+                justification @ (AlephJustification::CommitteeMultisignature(_)
+                | AlephJustification::CommitteeBlsMultisignature(_)),
+            ) => Some(justification.signers()),
+            // Emergency justifications say nothing about the committee.
+            Ok(_) => None,
+            Err(e) => {
+                warn!(
+                    target: LOG_TARGET,
+                    "Failed to decode justification of block {}: {:?}", hash, e
+                );
+                None
+            }
+        }
+    }
+
+    fn process_block(&self, hash: BlockHash) {
+        let signers = match self.committee_signers(hash) {
+            Some(signers) => signers,
+            None => return,
+        };
+        let number = match self.client.number(hash) {
+            Ok(Some(number)) => number,
+            _ => {
+                warn!(
+                    target: LOG_TARGET,
+                    "Failed to read the number of finalized block {}.", hash
+                );
+                return;
+            }
+        };
+        let session = self.session_info.session_id_from_block_num(number);
+        if !self.participation.contains(session) {
+            let first_block = self
+                .session_info
+                .boundaries_for_session(session)
+                .first_block();
+            match self.authority_provider.authority_data(first_block) {
+                Some(authority_data) => self
+                    .participation
+                    .start_session(session, authority_data.authorities().clone()),
+                None => {
+                    warn!(
+                        target: LOG_TARGET,
+                        "Failed to read the committee of {:?}.", session
+                    );
+                    return;
+                }
+            }
+            for (session, n_members) in self.participation.prune(session) {
+                debug!(
+                    target: LOG_TARGET,
+                    "Stopped tracking participation in {:?}.", session
+                );
+                self.metrics.remove_session(session, n_members);
+            }
+        }
+        if let Some((justified_blocks, ratios)) = self.participation.record(session, &signers) {
+            self.metrics
+                .report_session(session, justified_blocks, &ratios);
+        }
+    }
+
+    pub async fn run(self) {
+        let mut finality_notifications = self.client.finality_notification_stream();
+        while let Some(notification) = finality_notifications.next().await {
+            for hash in notification
+                .tree_route
+                .iter()
+                .cloned()
+                .chain(iter::once(notification.hash))
+            {
+                self.process_block(hash);
+            }
+        }
+        warn!(
+            target: LOG_TARGET,
+            "Finality notification stream ended unexpectedly."
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sp_core::ed25519::Public;
+
+    use super::FinalityParticipation;
+    use crate::{aleph_primitives::AuthorityId, NodeIndex, SessionId};
+
+    fn authorities(n_members: u8) -> Vec<AuthorityId> {
+        (0..n_members)
+            .map(|i| AuthorityId::from(Public::from_raw([i; 32])))
+            .collect()
+    }
+
+    #[test]
+    fn computes_participation_ratios() {
+        let participation = FinalityParticipation::new();
+        let session = SessionId(3);
+        participation.start_session(session, authorities(3));
+        participation.record(session, &[NodeIndex(0), NodeIndex(1)]);
+        let (justified_blocks, ratios) = participation
+            .record(session, &[NodeIndex(0), NodeIndex(2)])
+            .expect("the session was started");
+        assert_eq!(justified_blocks, 2);
+        assert_eq!(ratios, vec![1.0, 0.5, 0.5]);
+
+        let session_participation = participation.session(session).expect("session is tracked");
+        assert_eq!(session_participation.justified_blocks, 2);
+        assert_eq!(
+            session_participation
+                .members
+                .iter()
+                .map(|member| member.signed)
+                .collect::<Vec<_>>(),
+            vec![2, 1, 1]
+        );
+    }
+
+    #[test]
+    fn ignores_unknown_sessions_and_signers() {
+        let participation = FinalityParticipation::new();
+        assert!(participation
+            .record(SessionId(0), &[NodeIndex(0)])
+            .is_none());
+        participation.start_session(SessionId(0), authorities(2));
+        let (_, ratios) = participation
+            .record(SessionId(0), &[NodeIndex(1), NodeIndex(7)])
+            .expect("the session was started");
+        assert_eq!(ratios, vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn prunes_old_sessions() {
+        let participation = FinalityParticipation::new();
+        for session in 0..20 {
+            participation.start_session(SessionId(session), authorities(4));
+        }
+        let pruned = participation.prune(SessionId(19));
+        assert_eq!(
+            pruned,
+            (0..4)
+                .map(|session| (SessionId(session), 4))
+                .collect::<Vec<_>>()
+        );
+        assert!(participation.session(SessionId(3)).is_none());
+        assert!(participation.session(SessionId(4)).is_some());
+        assert_eq!(
+            participation.latest().map(|latest| latest.session),
+            Some(SessionId(19))
+        );
+    }
+}
@@ -118,6 +118,24 @@ pub fn rotate(
     Ok((backup_saver, backup_loader))
 }
 
+/// Loads the existing backups of a session without modifying the backup directory.
+///
+/// `backup_path` is the path to the backup directory of some node (i.e. the argument to `--backup-saving-path`).
+/// Meant for inspecting backups, e.g. copied from other nodes, so unlike [rotate] it creates neither
+/// the session directory nor a new backup file.
+pub fn load(backup_path: &Path, session_id: u32) -> Result<Loader, BackupLoadError> {
+    let session_path = backup_path.join(format!("{session_id}"));
+    debug!(target: "aleph-party", "Loading backup for session {:?} at path {:?} in read-only mode", session_id, session_path);
+    if !session_path.is_dir() {
+        return Err(BackupLoadError::IOError(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no backup directory at {}", session_path.display()),
+        )));
+    }
+    let session_backup_idxs = get_session_backup_idxs(&session_path)?;
+    load_backup(&session_path, &session_backup_idxs)
+}
+
 /// Removes the backup directory for all old sessions except the current session.
 ///
 /// `backup_path` is the path to the backup directory (i.e. the argument to `--backup-saving-path`).
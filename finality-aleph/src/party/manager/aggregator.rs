@@ -10,21 +10,20 @@ use sp_runtime::traits::{Block, Header};
 use tokio::time;
 
 use crate::{
-    abft::SignatureSet,
-    aggregation::Aggregator,
+    abft::BlsKeychain,
+    aggregation::{Aggregator, CurrentAggregatorConfig},
     aleph_primitives::{BlockHash, BlockNumber},
     block::substrate::{Justification, JustificationTranslator},
-    crypto::Signature,
     justification::AlephJustification,
     metrics::Checkpoint,
     network::data::Network,
     party::{
-        manager::aggregator::AggregatorVersion::{Current, Legacy},
+        manager::aggregator::AggregatorVersion::{Bls, Current, Legacy},
         AuthoritySubtaskCommon, Task,
     },
     sync::JustificationSubmissions,
-    BlockId, CurrentRmcNetworkData, Keychain, LegacyRmcNetworkData, SessionBoundaries,
-    TimingBlockMetrics, STATUS_REPORT_INTERVAL,
+    AggregationBatchLen, BlockId, BlsRmcNetworkData, CurrentRmcNetworkData, Keychain,
+    LegacyRmcNetworkData, SessionBoundaries, TimingBlockMetrics, STATUS_REPORT_INTERVAL,
 };
 
 /// IO channels used by the aggregator task.
@@ -37,23 +36,43 @@ where
     pub justification_translator: JustificationTranslator,
 }
 
-async fn process_new_block_data<CN, LN>(
-    aggregator: &mut Aggregator<'_, CN, LN>,
+/// Whether the block ends a batch, i.e. gets its own justification. The remaining blocks are
+/// justified implicitly by their descendants. The last block of a session always ends a batch.
+fn is_batch_end(
+    number: BlockNumber,
+    batch_len: AggregationBatchLen,
+    session_boundaries: &SessionBoundaries,
+) -> bool {
+    number % batch_len.0 == 0 || number == session_boundaries.last_block()
+}
+
+/// Returns whether the aggregation was started for the block.
+async fn process_new_block_data<CN, BN, LN>(
+    aggregator: &mut Aggregator<'_, CN, BN, LN>,
     block: BlockId,
+    batch_len: AggregationBatchLen,
+    session_boundaries: &SessionBoundaries,
     metrics: &TimingBlockMetrics,
-) where
+) -> bool
+where
     CN: Network<CurrentRmcNetworkData>,
+    BN: Network<BlsRmcNetworkData>,
     LN: Network<LegacyRmcNetworkData>,
 {
     trace!(target: "aleph-party", "Received unit {:?} in aggregator.", block);
     metrics.report_block(block.hash(), std::time::Instant::now(), Checkpoint::Ordered);
 
+    if !is_batch_end(block.number(), batch_len, session_boundaries) {
+        trace!(target: "aleph-party", "Block {:?} does not end a batch, not aggregating.", block);
+        return false;
+    }
     aggregator.start_aggregation(block.hash()).await;
+    true
 }
 
 fn process_hash<B, C, JS>(
     hash: BlockHash,
-    multisignature: SignatureSet<Signature>,
+    justification: AlephJustification,
     justifications_for_chain: &mut JS,
     justification_translator: &JustificationTranslator,
     client: &Arc<C>,
@@ -66,10 +85,9 @@ where
 {
     let number = client.number(hash).unwrap().unwrap();
     // The unwrap might actually fail if data availability is not implemented correctly.
-    let justification = match justification_translator.translate(
-        AlephJustification::CommitteeMultisignature(multisignature),
-        BlockId::new(hash, number),
-    ) {
+    let justification = match justification_translator
+        .translate(justification, BlockId::new(hash, number))
+    {
         Ok(justification) => justification,
         Err(e) => {
             error!(target: "aleph-party", "Issue with translating justification from Aggregator to Sync Justification: {}.", e);
@@ -83,11 +101,12 @@ where
     Ok(())
 }
 
-async fn run_aggregator<B, C, CN, LN, JS>(
-    mut aggregator: Aggregator<'_, CN, LN>,
+async fn run_aggregator<B, C, CN, BN, LN, JS>(
+    mut aggregator: Aggregator<'_, CN, BN, LN>,
     io: IO<JS>,
     client: Arc<C>,
     session_boundaries: &SessionBoundaries,
+    batch_len: AggregationBatchLen,
     metrics: TimingBlockMetrics,
     mut exit_rx: oneshot::Receiver<()>,
 ) -> Result<(), ()>
@@ -98,6 +117,7 @@ where
     C: HeaderBackend<B> + Send + Sync + 'static,
     LN: Network<LegacyRmcNetworkData>,
     CN: Network<CurrentRmcNetworkData>,
+    BN: Network<BlsRmcNetworkData>,
 {
     let IO {
         blocks_from_interpreter,
@@ -125,20 +145,24 @@ where
         tokio::select! {
             maybe_block = blocks_from_interpreter.next() => {
                 if let Some(block) = maybe_block {
-                    hash_of_last_block = Some(block.hash());
-                    process_new_block_data::<CN, LN>(
+                    let hash = block.hash();
+                    if process_new_block_data::<CN, BN, LN>(
                         &mut aggregator,
                         block,
+                        batch_len,
+                        session_boundaries,
                         &metrics
-                    ).await;
+                    ).await {
+                        hash_of_last_block = Some(hash);
+                    }
                 } else {
                     debug!(target: "aleph-party", "Blocks ended in aggregator.");
                     no_more_blocks = true;
                 }
             }
             multisigned_hash = aggregator.next_multisigned_hash() => {
-                if let Some((hash, multisignature)) = multisigned_hash {
-                    process_hash(hash, multisignature, &mut justifications_for_chain, &justification_translator, &client)?;
+                if let Some((hash, justification)) = multisigned_hash {
+                    process_hash(hash, justification, &mut justifications_for_chain, &justification_translator, &client)?;
                     if Some(hash) == hash_of_last_block {
                         hash_of_last_block = None;
                     }
@@ -164,20 +188,24 @@ where
     Ok(())
 }
 
-pub enum AggregatorVersion<CN, LN> {
-    Current(CN),
+pub enum AggregatorVersion<CN, BN, LN> {
+    Current(CN, CurrentAggregatorConfig),
+    /// The current version aggregating BLS signatures into constant size justifications.
+    Bls(BN, CurrentAggregatorConfig, BlsKeychain),
     Legacy(LN),
 }
 
 /// Runs the justification signature aggregator within a single session.
-pub fn task<B, C, CN, LN, JS>(
+#[allow(clippy::too_many_arguments)]
+pub fn task<B, C, CN, BN, LN, JS>(
     subtask_common: AuthoritySubtaskCommon,
     client: Arc<C>,
     io: IO<JS>,
     session_boundaries: SessionBoundaries,
+    batch_len: AggregationBatchLen,
     metrics: TimingBlockMetrics,
     multikeychain: Keychain,
-    version: AggregatorVersion<CN, LN>,
+    version: AggregatorVersion<CN, BN, LN>,
 ) -> Task
 where
     B: Block<Hash = BlockHash>,
@@ -186,6 +214,7 @@ where
     C: HeaderBackend<B> + Send + Sync + 'static,
     LN: Network<LegacyRmcNetworkData> + 'static,
     CN: Network<CurrentRmcNetworkData> + 'static,
+    BN: Network<BlsRmcNetworkData> + 'static,
 {
     let AuthoritySubtaskCommon {
         spawn_handle,
@@ -195,7 +224,12 @@ where
     let task = {
         async move {
             let aggregator_io = match version {
-                Current(rmc_network) => Aggregator::new_current(&multikeychain, rmc_network),
+                Current(rmc_network, config) => {
+                    Aggregator::new_current(&multikeychain, rmc_network, config)
+                }
+                Bls(rmc_network, config, keychain) => {
+                    Aggregator::new_bls(keychain, rmc_network, config)
+                }
                 Legacy(rmc_network) => Aggregator::new_legacy(&multikeychain, rmc_network),
             };
             debug!(target: "aleph-party", "Running the aggregator task for {:?}", session_id);
@@ -204,6 +238,7 @@ where
                 io,
                 client,
                 &session_boundaries,
+                batch_len,
                 metrics,
                 exit,
             )
@@ -217,3 +252,97 @@ where
         spawn_handle.spawn_essential_with_result("aleph/consensus_session_aggregator", task);
     Task::new(handle, stop)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::future::pending;
+
+    use super::{is_batch_end, process_new_block_data};
+    use crate::{
+        abft::Recipient,
+        aggregation::{Aggregator, CurrentAggregatorConfig},
+        metrics::AggregatorMetrics,
+        network::{
+            data::{Network, SendError},
+            mock::crypto_basics,
+            Data,
+        },
+        AggregationBatchLen, BlockId, Keychain, SessionBoundaries, SessionBoundaryInfo, SessionId,
+        SessionPeriod, TimingBlockMetrics,
+    };
+
+    struct SilentNetwork;
+
+    #[async_trait::async_trait]
+    impl<D: Data> Network<D> for SilentNetwork {
+        fn send(&self, _data: D, _recipient: Recipient) -> Result<(), SendError> {
+            Ok(())
+        }
+
+        async fn next(&mut self) -> Option<D> {
+            pending().await
+        }
+    }
+
+    // Session 1 spans blocks 18 to 35, so its last block is not divisible by the batch lengths used.
+    fn session_boundaries() -> SessionBoundaries {
+        SessionBoundaryInfo::new(SessionPeriod(18)).boundaries_for_session(SessionId(1))
+    }
+
+    #[test]
+    fn every_block_ends_a_batch_of_length_one() {
+        let boundaries = session_boundaries();
+        for number in boundaries.first_block()..=boundaries.last_block() {
+            assert!(is_batch_end(number, AggregationBatchLen(1), &boundaries));
+        }
+    }
+
+    #[test]
+    fn batches_end_at_multiples_of_their_length_and_at_session_end() {
+        let boundaries = session_boundaries();
+        let batch_ends: Vec<_> = (boundaries.first_block()..=boundaries.last_block())
+            .filter(|number| is_batch_end(*number, AggregationBatchLen(5), &boundaries))
+            .collect();
+        assert_eq!(batch_ends, vec![20, 25, 30, 35]);
+
+        let batch_ends: Vec<_> = (boundaries.first_block()..=boundaries.last_block())
+            .filter(|number| is_batch_end(*number, AggregationBatchLen(4), &boundaries))
+            .collect();
+        assert_eq!(batch_ends, vec![20, 24, 28, 32, 35]);
+    }
+
+    #[tokio::test]
+    async fn aggregates_only_batch_ends() {
+        let (mut pens, verifier) = crypto_basics(1);
+        let (node_id, pen) = pens.pop().expect("we generated one pen");
+        let keychain = Keychain::new(node_id, verifier, pen);
+        let mut aggregator = Aggregator::<SilentNetwork, SilentNetwork, SilentNetwork>::new_current(
+            &keychain,
+            SilentNetwork,
+            CurrentAggregatorConfig {
+                metrics: AggregatorMetrics::noop(),
+                remulticast_timeout: Duration::from_secs(30),
+            },
+        );
+        let boundaries = session_boundaries();
+        let metrics = TimingBlockMetrics::noop();
+
+        let mut aggregated = Vec::new();
+        for number in boundaries.first_block()..=boundaries.last_block() {
+            if process_new_block_data::<SilentNetwork, SilentNetwork, SilentNetwork>(
+                &mut aggregator,
+                BlockId::new_random(number),
+                AggregationBatchLen(4),
+                &boundaries,
+                &metrics,
+            )
+            .await
+            {
+                aggregated.push(number);
+            }
+        }
+        assert_eq!(aggregated, vec![20, 24, 28, 32, 35]);
+    }
+}
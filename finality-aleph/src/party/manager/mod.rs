@@ -13,21 +13,27 @@ use sp_runtime::traits::{Block as BlockT, Header as HeaderT};
 use crate::{
     abft::{
         current_create_aleph_config, legacy_create_aleph_config, run_current_member,
-        run_legacy_member, SpawnHandle,
+        run_legacy_member, BlsKeychain, SpawnHandle,
+    },
+    aggregation::CurrentAggregatorConfig,
+    aleph_primitives::{
+        AbftConfig, AlephSessionApi, BlockHash, BlockNumber, BlsVerifier, KEY_TYPE,
     },
-    aleph_primitives::{AlephSessionApi, BlockHash, BlockNumber, KEY_TYPE},
     block::{
         substrate::{Justification, JustificationTranslator},
         Header, HeaderVerifier, UnverifiedHeader,
     },
-    crypto::{AuthorityPen, AuthorityVerifier},
+    crypto::{find_bls_secret_key, AuthorityPen, AuthorityVerifier},
     data_io::{
         legacy::{
             ChainTracker as LegacyChainTracker, DataStore as LegacyDataStore,
             OrderedDataInterpreter as LegacyOrderedDataInterpreter,
         },
-        ChainTracker, DataStore, OrderedDataInterpreter, SubstrateChainInfoProvider,
+        ChainTracker, DataStore, OrderedDataInterpreter, ProposalStrategyConfig,
+        SubstrateChainInfoProvider,
     },
+    event_log::{ConsensusEvent, EventLog},
+    metrics::AbftMetrics,
     mpsc,
     network::{
         data::{
@@ -40,9 +46,9 @@ use crate::{
         backup::ABFTBackup, manager::aggregator::AggregatorVersion, traits::NodeSessionManager,
     },
     sync::JustificationSubmissions,
-    AuthorityId, BlockId, CurrentRmcNetworkData, Keychain, LegacyRmcNetworkData, NodeIndex,
-    SessionBoundaries, SessionBoundaryInfo, SessionId, SessionPeriod, TimingBlockMetrics,
-    UnitCreationDelay, VersionedNetworkData,
+    AggregationBatchLen, AuthorityId, BlockId, BlsRmcNetworkData, CurrentRmcNetworkData, Keychain,
+    LegacyRmcNetworkData, NodeIndex, SessionBoundaries, SessionBoundaryInfo, SessionId,
+    TimingBlockMetrics, UnitCreationDelay, VersionedNetworkData,
 };
 
 mod aggregator;
@@ -70,6 +76,11 @@ type CurrentNetworkType = SimpleNetwork<
     mpsc::UnboundedReceiver<CurrentRmcNetworkData>,
     SessionSender<CurrentRmcNetworkData>,
 >;
+type BlsNetworkType = SimpleNetwork<
+    BlsRmcNetworkData,
+    mpsc::UnboundedReceiver<BlsRmcNetworkData>,
+    SessionSender<BlsRmcNetworkData>,
+>;
 
 struct SubtasksParams<C, B, N, BE, JS>
 where
@@ -90,8 +101,10 @@ where
     chain_info: SubstrateChainInfoProvider<B, C>,
     aggregator_io: aggregator::IO<JS>,
     multikeychain: Keychain,
+    bls_keychain: Option<BlsKeychain>,
     exit_rx: oneshot::Receiver<()>,
     backup: ABFTBackup,
+    abft_config: AbftConfig,
     phantom: PhantomData<BE>,
 }
 
@@ -116,9 +129,13 @@ where
     justification_translator: JustificationTranslator,
     block_requester: RB,
     metrics: TimingBlockMetrics,
+    abft_metrics: AbftMetrics,
     spawn_handle: SpawnHandle,
     session_manager: SM,
     keystore: Arc<dyn Keystore>,
+    proposal_strategy: ProposalStrategyConfig,
+    aggregator_config: CurrentAggregatorConfig,
+    event_log: EventLog,
     _phantom: PhantomData<(B, BE)>,
 }
 
@@ -140,29 +157,37 @@ where
         client: Arc<C>,
         select_chain: SC,
         verifier: V,
-        session_period: SessionPeriod,
+        session_info: SessionBoundaryInfo,
         unit_creation_delay: UnitCreationDelay,
         justifications_for_sync: JS,
         justification_translator: JustificationTranslator,
         block_requester: RB,
         metrics: TimingBlockMetrics,
+        abft_metrics: AbftMetrics,
         spawn_handle: SpawnHandle,
         session_manager: SM,
         keystore: Arc<dyn Keystore>,
+        proposal_strategy: ProposalStrategyConfig,
+        aggregator_config: CurrentAggregatorConfig,
+        event_log: EventLog,
     ) -> Self {
         Self {
             client,
             select_chain,
             verifier,
-            session_info: SessionBoundaryInfo::new(session_period),
+            session_info,
             unit_creation_delay,
             justifications_for_sync,
             justification_translator,
             block_requester,
             metrics,
+            abft_metrics,
             spawn_handle,
             session_manager,
             keystore,
+            proposal_strategy,
+            aggregator_config,
+            event_log,
             _phantom: PhantomData,
         }
     }
@@ -184,8 +209,14 @@ where
             multikeychain,
             exit_rx,
             backup,
+            abft_config,
             ..
         } = params;
+        self.event_log.emit(ConsensusEvent::AbftVersion {
+            session: session_id.0,
+            version: LEGACY_VERSION as u32,
+            legacy: true,
+        });
         let (chain_tracker, data_provider) = LegacyChainTracker::new(
             self.select_chain.clone(),
             self.client.clone(),
@@ -199,7 +230,7 @@ where
             session_boundaries.clone(),
         );
         let consensus_config =
-            legacy_create_aleph_config(n_members, node_id, session_id, self.unit_creation_delay);
+            legacy_create_aleph_config(n_members, node_id, session_id, abft_config);
         let data_network = data_network.map();
 
         let (unfiltered_aleph_network, rmc_network) =
@@ -227,9 +258,10 @@ where
                 self.client.clone(),
                 aggregator_io,
                 session_boundaries,
+                AggregationBatchLen(abft_config.aggregation_batch_len),
                 self.metrics.clone(),
                 multikeychain,
-                AggregatorVersion::<CurrentNetworkType, _>::Legacy(rmc_network),
+                AggregatorVersion::<CurrentNetworkType, BlsNetworkType, _>::Legacy(rmc_network),
             ),
             task::task(subtask_common.clone(), chain_tracker, "chain tracker"),
             task::task(subtask_common, data_store, "data store"),
@@ -251,14 +283,23 @@ where
             chain_info,
             aggregator_io,
             multikeychain,
+            bls_keychain,
             exit_rx,
             backup,
+            abft_config,
             ..
         } = params;
+        self.event_log.emit(ConsensusEvent::AbftVersion {
+            session: session_id.0,
+            version: CURRENT_VERSION as u32,
+            legacy: false,
+        });
         let (chain_tracker, data_provider) = ChainTracker::new(
             self.select_chain.clone(),
             self.client.clone(),
             session_boundaries.clone(),
+            self.proposal_strategy
+                .build(self.client.clone(), session_boundaries.clone()),
             Default::default(),
             self.metrics.clone(),
         );
@@ -269,7 +310,7 @@ where
             session_boundaries.clone(),
         );
         let consensus_config =
-            current_create_aleph_config(n_members, node_id, session_id, self.unit_creation_delay);
+            current_create_aleph_config(n_members, node_id, session_id, abft_config);
         let data_network = data_network.map();
 
         let (unfiltered_aleph_network, rmc_network) =
@@ -282,25 +323,42 @@ where
             Default::default(),
             unfiltered_aleph_network,
         );
+        let aggregator_version = match bls_keychain {
+            Some(bls_keychain) => {
+                info!(target: "aleph-party", "Aggregating BLS signatures in session {:?}.", session_id);
+                AggregatorVersion::<_, _, LegacyNetworkType>::Bls(
+                    NetworkMap::<_, BlsRmcNetworkData>::map(rmc_network),
+                    self.aggregator_config.clone(),
+                    bls_keychain,
+                )
+            }
+            None => AggregatorVersion::Current(
+                NetworkMap::<_, CurrentRmcNetworkData>::map(rmc_network),
+                self.aggregator_config.clone(),
+            ),
+        };
         Subtasks::new(
             exit_rx,
             run_current_member(
                 subtask_common.clone(),
                 multikeychain.clone(),
                 consensus_config,
-                aleph_network.into(),
+                aleph_network,
                 data_provider,
                 ordered_data_interpreter,
                 backup,
+                self.abft_metrics.session(session_id),
+                n_members,
             ),
             aggregator::task(
                 subtask_common.clone(),
                 self.client.clone(),
                 aggregator_io,
                 session_boundaries,
+                AggregationBatchLen(abft_config.aggregation_batch_len),
                 self.metrics.clone(),
                 multikeychain,
-                AggregatorVersion::<_, LegacyNetworkType>::Current(rmc_network),
+                aggregator_version,
             ),
             task::task(subtask_common.clone(), chain_tracker, "chain tracker"),
             task::task(subtask_common, data_store, "data store"),
@@ -354,6 +412,14 @@ where
             .block_hash(last_block_of_previous_session)
             .expect("Previous session ended, the block should be present")
             .expect("Previous session ended, we should have the hash.");
+        let abft_config = self.abft_config(last_block_of_previous_session_hash);
+        let bls_keychain = self.bls_keychain(
+            session_id,
+            node_id,
+            authorities.len(),
+            last_block_of_previous_session_hash,
+        );
+        debug!(target: "aleph-party", "Running session {:?} with AlephBFT config {:?}.", session_id, abft_config);
 
         let params = SubtasksParams {
             n_members: authorities.len(),
@@ -366,8 +432,10 @@ where
             chain_info,
             aggregator_io,
             multikeychain,
+            bls_keychain,
             exit_rx,
             backup,
+            abft_config,
             phantom: PhantomData,
         };
 
@@ -408,6 +476,55 @@ where
         }
     }
 
+    fn abft_config(&self, last_block_of_previous_session_hash: BlockHash) -> AbftConfig {
+        match self
+            .client
+            .runtime_api()
+            .next_session_abft_config(last_block_of_previous_session_hash)
+        {
+            Ok(abft_config) => abft_config,
+            // This might happen when there was no runtime upgrade yet. Fallback to the defaults,
+            // with the locally configured unit creation delay.
+            Err(_) => AbftConfig {
+                unit_creation_delay_ms: self.unit_creation_delay.0,
+                ..AbftConfig::default()
+            },
+        }
+    }
+
+    /// The keychain for aggregating BLS signatures, if the whole committee can do it. The decision
+    /// depends only on the chain, so all members of the committee agree on it, and it matches the
+    /// keys used for verifying the resulting justifications.
+    fn bls_keychain(
+        &self,
+        session_id: SessionId,
+        node_id: NodeIndex,
+        n_members: usize,
+        last_block_of_previous_session_hash: BlockHash,
+    ) -> Option<BlsKeychain> {
+        let runtime_api = self.client.runtime_api();
+        let keys = match session_id.0 {
+            0 => runtime_api.bls_keys(last_block_of_previous_session_hash),
+            _ => runtime_api.next_session_bls_keys(last_block_of_previous_session_hash),
+        };
+        // Runtimes that do not support BLS keys return an error here.
+        let keys = keys.ok()?;
+        if keys.len() != n_members {
+            warn!(target: "aleph-party", "Got {} BLS keys for a committee of {} in session {:?}, not aggregating BLS signatures.", keys.len(), n_members, session_id);
+            return None;
+        }
+        let verifier = BlsVerifier::new(&keys);
+        if !verifier.can_complete() {
+            debug!(target: "aleph-party", "Too few members of session {:?} have BLS keys, not aggregating BLS signatures.", session_id);
+            return None;
+        }
+        let secret_key = find_bls_secret_key(self.keystore.as_ref(), &keys[node_id.0]);
+        if secret_key.is_none() {
+            warn!(target: "aleph-party", "Missing the BLS secret key for session {:?}, our signatures will not count.", session_id);
+        }
+        Some(BlsKeychain::new(node_id, verifier, secret_key))
+    }
+
     #[cfg(feature = "only_legacy")]
     fn only_legacy(&self) -> bool {
         std::env::var(ONLY_LEGACY_ENV)
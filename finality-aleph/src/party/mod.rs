@@ -5,6 +5,7 @@ use log::{debug, error, info, trace, warn};
 use tokio::{task::spawn_blocking, time::sleep};
 
 use crate::{
+    event_log::{ConsensusEvent, EventLog},
     party::{
         manager::{Handle, Task, TaskCommon as AuthoritySubtaskCommon},
         traits::{ChainState, NodeSessionManager},
@@ -29,6 +30,7 @@ pub(crate) struct ConsensusPartyParams<CS, NSM> {
     pub backup_saving_path: Option<PathBuf>,
     pub session_manager: NSM,
     pub session_info: SessionBoundaryInfo,
+    pub event_log: EventLog,
 }
 
 pub(crate) struct ConsensusParty<CS, NSM>
@@ -42,6 +44,7 @@ where
     backup_saving_path: Option<PathBuf>,
     session_manager: NSM,
     session_info: SessionBoundaryInfo,
+    event_log: EventLog,
 }
 
 const SESSION_STATUS_CHECK_PERIOD: Duration = Duration::from_millis(1000);
@@ -59,7 +62,7 @@ where
             chain_state,
             session_manager,
             session_info,
-            ..
+            event_log,
         } = params;
         Self {
             sync_oracle,
@@ -68,6 +71,7 @@ where
             chain_state,
             session_manager,
             session_info,
+            event_log,
         }
     }
 
@@ -115,9 +119,12 @@ where
         let authorities = authority_data.authorities();
 
         trace!(target: "aleph-party", "Authority data for session {:?}: {:?}", session_id, authorities);
-        let mut maybe_authority_task = if let Some(node_id) =
-            self.session_manager.node_idx(authorities)
-        {
+        let maybe_node_id = self.session_manager.node_idx(authorities);
+        self.event_log.emit(ConsensusEvent::SessionStarted {
+            session: session_id.0,
+            node_index: maybe_node_id.map(|node_id| node_id.0 as u32),
+        });
+        let mut maybe_authority_task = if let Some(node_id) = maybe_node_id {
             match backup::rotate(self.backup_saving_path.clone(), session_id.0) {
                 Ok(backup) => {
                     debug!(target: "aleph-party", "Running session {:?} as authority id {:?}", session_id, node_id);
@@ -228,6 +235,9 @@ where
         if let Err(e) = self.session_manager.stop_session(session_id) {
             warn!(target: "aleph-party", "Session Manager failed to stop in session {:?}: {}", session_id, e)
         }
+        self.event_log.emit(ConsensusEvent::SessionStopped {
+            session: session_id.0,
+        });
     }
 
     pub async fn run(mut self) {
@@ -264,6 +274,7 @@ mod tests {
 
     use crate::{
         aleph_primitives::{AuthorityId, SessionAuthorityData},
+        event_log::EventLog,
         party::{
             mocks::{MockChainState, MockNodeSessionManager},
             ConsensusParty, ConsensusPartyParams, SESSION_STATUS_CHECK_PERIOD,
@@ -515,6 +526,7 @@ mod tests {
             backup_saving_path: None,
             session_manager,
             session_info,
+            event_log: EventLog::disabled(),
         };
 
         (ConsensusParty::new(params), controller)
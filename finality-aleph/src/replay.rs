@@ -0,0 +1,652 @@
+//! Replaying AlephBFT sessions from the backups of committee members, for debugging ordering.
+use std::{
+    fmt::{Display, Error as FmtError, Formatter},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use futures::channel::mpsc;
+use log::{debug, info, warn};
+use sc_client_api::{Backend, HeaderBackend};
+use sp_api::ProvideRuntimeApi;
+use sp_consensus_aura::AuraApi;
+use sp_keystore::{testing::MemoryKeystore, Keystore};
+
+use crate::{
+    abft::{replay_current_member, CurrentOrderedData, SpawnHandle},
+    aleph_primitives::{AbftConfig, AlephSessionApi, AuraId, Block, BlockNumber, Header, KEY_TYPE},
+    block::{
+        substrate::{SubstrateFinalizationInfo, VerifierCache},
+        HeaderVerifier,
+    },
+    crypto::{AuthorityPen, AuthorityVerifier},
+    data_io::{
+        AlephData, ChainInfoProvider, HistoricalChainInfoProvider, OrderedDataInterpreter,
+        PendingProposalStatus, SubstrateChainInfoProvider,
+    },
+    nodes::VERIFIER_CACHE_SIZE,
+    party::backup::{self, BackupLoadError, Loader},
+    runtime_api::RuntimeApiImpl,
+    session::SessionBoundaryInfo,
+    session_map::{AuthorityProvider, AuthorityProviderImpl},
+    BlockId, ClientForAleph, Keychain, NodeIndex, SessionId,
+};
+
+/// The backup of a single committee member.
+#[derive(Clone, Debug)]
+pub struct MemberBackup {
+    /// Index of the member in the committee of the session.
+    pub node_id: usize,
+    /// The backup directory of the member, i.e. its `--backup-saving-path`.
+    pub backup_path: PathBuf,
+}
+
+/// What the data interpreter makes of a single ordered item.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Interpretation {
+    /// The item finalizes these blocks.
+    Finalize(Vec<BlockId>),
+    /// The item does not finalize anything.
+    Ignore,
+    /// Some of the blocks in the item are not available locally, so it cannot be judged.
+    Unavailable(PendingProposalStatus),
+}
+
+/// A single item in the order produced by the session.
+#[derive(Clone, Debug)]
+pub struct OrderedItem {
+    pub creator: usize,
+    pub data: AlephData<Header>,
+    pub interpretation: Interpretation,
+}
+
+/// The results of replaying a session.
+#[derive(Clone, Debug)]
+pub struct SessionReplay {
+    pub session_id: SessionId,
+    /// How many items were ordered using the backup of each member.
+    pub ordered_per_member: Vec<(usize, usize)>,
+    /// Members whose order is not a prefix of the longest one, with the first position where it differs.
+    pub divergences: Vec<(usize, usize)>,
+    /// The longest order, interpreted.
+    pub ordered: Vec<OrderedItem>,
+}
+
+impl SessionReplay {
+    /// All the blocks the session would finalize, in order.
+    pub fn finalized_blocks(&self) -> Vec<BlockId> {
+        self.ordered
+            .iter()
+            .flat_map(|item| match &item.interpretation {
+                Interpretation::Finalize(blocks) => blocks.clone(),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    NoBackups,
+    NotSynced {
+        required: BlockNumber,
+        finalized: BlockNumber,
+    },
+    MissingAuthorities(SessionId),
+    UnknownMember {
+        node_id: usize,
+        committee_size: usize,
+    },
+    MissingGenesis,
+    Backup(usize, BackupLoadError),
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        use ReplayError::*;
+        match self {
+            NoBackups => write!(f, "no backups to replay"),
+            NotSynced {
+                required,
+                finalized,
+            } => write!(
+                f,
+                "block #{required} has to be finalized to replay the session, but the highest finalized block is #{finalized}"
+            ),
+            MissingAuthorities(session_id) => write!(
+                f,
+                "authorities for session {} are not available, most likely the state was pruned",
+                session_id.0
+            ),
+            UnknownMember {
+                node_id,
+                committee_size,
+            } => write!(
+                f,
+                "there is no member {node_id} in a committee of size {committee_size}"
+            ),
+            MissingGenesis => write!(f, "the genesis header is not available"),
+            Backup(node_id, e) => write!(f, "backup of member {node_id}: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+// The first position at which `ordered` differs from `reference`, if it is not a prefix of it.
+fn divergence(
+    ordered: &CurrentOrderedData<Header>,
+    reference: &CurrentOrderedData<Header>,
+) -> Option<usize> {
+    ordered
+        .iter()
+        .zip(reference.iter())
+        .position(|(item, reference_item)| item != reference_item)
+}
+
+/// Replays each of the given backups separately, returning the data ordered using each of them.
+async fn replay_backups(
+    backups: Vec<(usize, Loader)>,
+    authority_verifier: AuthorityVerifier,
+    session_id: SessionId,
+    abft_config: AbftConfig,
+    spawn_handle: SpawnHandle,
+    idle_timeout: Duration,
+) -> Vec<(usize, CurrentOrderedData<Header>)> {
+    // The replaying members never sign anything that matters, so any key will do.
+    let keystore = Arc::new(MemoryKeystore::new());
+    let key = keystore
+        .ed25519_generate_new(KEY_TYPE, None)
+        .expect("generating a key in memory should work");
+    let authority_pen = AuthorityPen::new(key.into(), keystore)
+        .expect("we just generated this key so everything should work");
+    let n_members = authority_verifier.node_count().0;
+
+    let mut orders = Vec::new();
+    for (node_id, loader) in backups {
+        let keychain = Keychain::new(
+            NodeIndex(node_id),
+            authority_verifier.clone(),
+            authority_pen.clone(),
+        );
+        let ordered = replay_current_member(
+            keychain,
+            n_members,
+            NodeIndex(node_id),
+            session_id,
+            loader,
+            abft_config,
+            spawn_handle.clone(),
+            idle_timeout,
+        )
+        .await;
+        info!(target: "aleph-party", "Backup of member {} ordered {} items in session {}.", node_id, ordered.len(), session_id.0);
+        orders.push((node_id, ordered));
+    }
+    orders
+}
+
+/// The number of items ordered using each backup, the members whose order is not a prefix of the
+/// longest one, and the longest order itself.
+#[allow(clippy::type_complexity)]
+fn compare_orders(
+    session_id: SessionId,
+    orders: Vec<(usize, CurrentOrderedData<Header>)>,
+) -> (
+    Vec<(usize, usize)>,
+    Vec<(usize, usize)>,
+    CurrentOrderedData<Header>,
+) {
+    let reference = orders
+        .iter()
+        .max_by_key(|(_, ordered)| ordered.len())
+        .map(|(_, ordered)| ordered.clone())
+        .unwrap_or_default();
+    let ordered_per_member = orders
+        .iter()
+        .map(|(node_id, ordered)| (*node_id, ordered.len()))
+        .collect();
+    let divergences = orders
+        .iter()
+        .filter_map(|(node_id, ordered)| {
+            let position = divergence(ordered, &reference)?;
+            warn!(target: "aleph-party", "Order of member {} diverges at position {} in session {}.", node_id, position, session_id.0);
+            Some((*node_id, position))
+        })
+        .collect();
+    (ordered_per_member, divergences, reference)
+}
+
+/// Judges the ordered data one by one, treating the blocks finalized by earlier items as finalized.
+fn interpret<CIP, V>(
+    interpreter: &mut OrderedDataInterpreter<CIP, Header, V>,
+    ordered: CurrentOrderedData<Header>,
+) -> Vec<OrderedItem>
+where
+    CIP: ChainInfoProvider,
+    V: HeaderVerifier<Header>,
+{
+    ordered
+        .into_iter()
+        .map(|(data, creator)| {
+            let interpretation = match interpreter.try_blocks_to_finalize_from_data(data.clone()) {
+                Ok(blocks) if blocks.is_empty() => Interpretation::Ignore,
+                Ok(blocks) => {
+                    if let Some(top) = blocks.last() {
+                        interpreter.mark_finalized(top.clone());
+                    }
+                    Interpretation::Finalize(blocks)
+                }
+                Err(status) => {
+                    debug!(target: "aleph-party", "Cannot interpret {:?}: {:?}.", data, status);
+                    Interpretation::Unavailable(status)
+                }
+            };
+            OrderedItem {
+                creator: creator.0,
+                data,
+                interpretation,
+            }
+        })
+        .collect()
+}
+
+/// Replays the session using the backups of the given committee members.
+///
+/// Each backup is replayed separately, without network access and without creating new units,
+/// so the resulting orders should be prefixes of one another – any divergence indicates a bug in
+/// ordering. The longest order is then interpreted against the local chain, as it would have been
+/// when the session started.
+pub async fn replay_session<C, BE>(
+    client: Arc<C>,
+    session_info: SessionBoundaryInfo,
+    session_id: SessionId,
+    backups: Vec<MemberBackup>,
+    spawn_handle: SpawnHandle,
+    idle_timeout: Duration,
+) -> Result<SessionReplay, ReplayError>
+where
+    C: ClientForAleph<Block, BE> + Send + Sync + 'static,
+    C::Api: AlephSessionApi<Block> + AuraApi<Block, AuraId>,
+    BE: Backend<Block> + 'static,
+{
+    if backups.is_empty() {
+        return Err(ReplayError::NoBackups);
+    }
+    let session_boundaries = session_info.boundaries_for_session(session_id);
+    let last_block_prev_session = session_boundaries.first_block().saturating_sub(1);
+    let finalized = client.info().finalized_number;
+    if finalized < last_block_prev_session {
+        return Err(ReplayError::NotSynced {
+            required: last_block_prev_session,
+            finalized,
+        });
+    }
+
+    let authority_provider =
+        AuthorityProviderImpl::new(client.clone(), RuntimeApiImpl::new(client.clone()));
+    let authorities = authority_provider
+        .authority_data(session_boundaries.first_block())
+        .ok_or(ReplayError::MissingAuthorities(session_id))?
+        .authorities()
+        .clone();
+    let abft_config = client
+        .block_hash(last_block_prev_session)
+        .ok()
+        .flatten()
+        .and_then(|hash| client.runtime_api().next_session_abft_config(hash).ok())
+        .unwrap_or_default();
+
+    let mut loaders = Vec::new();
+    for MemberBackup {
+        node_id,
+        backup_path,
+    } in backups
+    {
+        if node_id >= authorities.len() {
+            return Err(ReplayError::UnknownMember {
+                node_id,
+                committee_size: authorities.len(),
+            });
+        }
+        let loader = backup::load(&backup_path, session_id.0)
+            .map_err(|e| ReplayError::Backup(node_id, e))?;
+        loaders.push((node_id, loader));
+    }
+    let orders = replay_backups(
+        loaders,
+        AuthorityVerifier::new(authorities),
+        session_id,
+        abft_config,
+        spawn_handle,
+        idle_timeout,
+    )
+    .await;
+    let (ordered_per_member, divergences, reference) = compare_orders(session_id, orders);
+
+    let genesis_header = client
+        .header(client.info().genesis_hash)
+        .ok()
+        .flatten()
+        .ok_or(ReplayError::MissingGenesis)?;
+    let verifier = VerifierCache::new(
+        session_info,
+        SubstrateFinalizationInfo::new(client.clone()),
+        authority_provider,
+        VERIFIER_CACHE_SIZE,
+        genesis_header,
+    );
+    let chain_info = HistoricalChainInfoProvider::new(
+        SubstrateChainInfoProvider::new(client.clone()),
+        last_block_prev_session,
+    );
+    // Nobody listens, the blocks are reported in the results instead.
+    let (blocks_tx, _) = mpsc::unbounded();
+    let mut interpreter: OrderedDataInterpreter<_, Header, _> =
+        OrderedDataInterpreter::new(blocks_tx, chain_info, verifier, session_boundaries);
+    let ordered = interpret(&mut interpreter, reference);
+
+    Ok(SessionReplay {
+        session_id,
+        ordered_per_member,
+        divergences,
+        ordered,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Cursor, Result as IoResult, Write},
+        sync::Arc,
+        time::Duration,
+    };
+
+    use current_aleph_bft::{LocalIO, Terminator};
+    use futures::channel::{mpsc, oneshot};
+    use parking_lot::Mutex;
+    use sc_service::TaskManager;
+    use sp_runtime::traits::Header as _;
+    use tokio::{
+        runtime::Handle,
+        sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+        time::timeout,
+    };
+
+    use super::{compare_orders, interpret, replay_backups, Interpretation, SessionReplay};
+    use crate::{
+        abft::{current_create_aleph_config, CurrentOrderedData, NetworkWrapper, SpawnHandle},
+        aleph_primitives::{AbftConfig, Header},
+        crypto::{AuthorityPen, AuthorityVerifier},
+        data_io::{
+            AlephData, HistoricalChainInfoProvider, OrderedDataInterpreter,
+            SubstrateChainInfoProvider,
+        },
+        network::{
+            data::{Network, SendError},
+            mock::crypto_basics,
+            Data,
+        },
+        party::backup::Loader,
+        testing::{
+            client_chain_builder::ClientChainBuilder,
+            mocks::{
+                aleph_data_from_blocks, TestClientBuilder, TestClientBuilderExt, TestVerifier,
+            },
+        },
+        BlockId, CurrentNetworkData, Keychain, NodeIndex, Recipient, SessionBoundaryInfo,
+        SessionId, SessionPeriod,
+    };
+
+    const N_MEMBERS: usize = 4;
+    const QUORUM: usize = 2 * N_MEMBERS / 3 + 1;
+    const ORDERED_ITEMS: usize = 8;
+    const SESSION_ID: SessionId = SessionId(0);
+    const SESSION_PERIOD: SessionPeriod = SessionPeriod(20);
+    const TIMEOUT: Duration = Duration::from_secs(30);
+    const IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+    fn abft_config() -> AbftConfig {
+        AbftConfig {
+            max_rounds: 1000,
+            initial_delay_ms: 10,
+            unit_creation_delay_ms: 20,
+            slowdown_start_round: 1000,
+            slowdown_base_per_mille: 1000,
+            session_len_lower_bound_ms: 1000,
+            aggregation_batch_len: 1,
+        }
+    }
+
+    /// Delivers the data sent by a member directly to the other members.
+    struct LocalNetwork<D> {
+        node_id: NodeIndex,
+        members: Vec<UnboundedSender<D>>,
+        receiver: UnboundedReceiver<D>,
+    }
+
+    fn local_networks<D>(n_members: usize) -> Vec<LocalNetwork<D>> {
+        let (members, receivers): (Vec<_>, Vec<_>) =
+            (0..n_members).map(|_| unbounded_channel()).unzip();
+        receivers
+            .into_iter()
+            .enumerate()
+            .map(|(id, receiver)| LocalNetwork {
+                node_id: NodeIndex(id),
+                members: members.clone(),
+                receiver,
+            })
+            .collect()
+    }
+
+    #[async_trait::async_trait]
+    impl<D: Data> Network<D> for LocalNetwork<D> {
+        fn send(&self, data: D, recipient: Recipient) -> Result<(), SendError> {
+            let recipients: Vec<_> = match recipient {
+                Recipient::Everyone => (0..self.members.len())
+                    .filter(|id| *id != self.node_id.0)
+                    .collect(),
+                Recipient::Node(node_id) => vec![node_id.0],
+            };
+            for id in recipients {
+                // Members that already stopped do not need anything.
+                let _ = self.members[id].send(data.clone());
+            }
+            Ok(())
+        }
+
+        async fn next(&mut self) -> Option<D> {
+            self.receiver.recv().await
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct MemoryBackup(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for MemoryBackup {
+        fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> IoResult<()> {
+            Ok(())
+        }
+    }
+
+    struct FixedData(AlephData<Header>);
+
+    #[async_trait::async_trait]
+    impl current_aleph_bft::DataProvider<AlephData<Header>> for FixedData {
+        async fn get_data(&mut self) -> Option<AlephData<Header>> {
+            Some(self.0.clone())
+        }
+    }
+
+    struct OrderedDataCollector(UnboundedSender<(AlephData<Header>, NodeIndex)>);
+
+    impl current_aleph_bft::FinalizationHandler<AlephData<Header>> for OrderedDataCollector {
+        fn data_finalized(
+            &mut self,
+            data: AlephData<Header>,
+            creator: current_aleph_bft::NodeIndex,
+        ) {
+            let _ = self.0.send((data, creator.into()));
+        }
+    }
+
+    /// Runs a session in which every member always proposes its own data, until all of them
+    /// ordered some data. Returns the backup of every member and the data it ordered.
+    async fn run_committee(
+        data: Vec<AlephData<Header>>,
+        pens: Vec<(NodeIndex, AuthorityPen)>,
+        verifier: AuthorityVerifier,
+        spawn_handle: SpawnHandle,
+    ) -> Vec<(Vec<u8>, CurrentOrderedData<Header>)> {
+        let mut members = Vec::new();
+        for ((network, (node_id, pen)), data) in
+            local_networks::<CurrentNetworkData<Header>>(N_MEMBERS)
+                .into_iter()
+                .zip(pens)
+                .zip(data)
+        {
+            let backup = MemoryBackup::default();
+            let (ordered_tx, ordered_rx) = unbounded_channel();
+            let local_io = LocalIO::new(
+                FixedData(data),
+                OrderedDataCollector(ordered_tx),
+                backup.clone(),
+                Cursor::new(Vec::new()),
+            );
+            let network: NetworkWrapper<CurrentNetworkData<Header>, _> = network.into();
+            let (stop, exit) = oneshot::channel();
+            let session = current_aleph_bft::run_session(
+                current_create_aleph_config(N_MEMBERS, node_id, SESSION_ID, abft_config()),
+                local_io,
+                network,
+                Keychain::new(node_id, verifier.clone(), pen),
+                spawn_handle.clone(),
+                Terminator::create_root(exit, "member"),
+            );
+            members.push((tokio::spawn(session), stop, backup, ordered_rx));
+        }
+
+        // Members keep running until all of them ordered enough, as they need a quorum to progress.
+        let mut orders = Vec::new();
+        for (_, _, _, ordered_rx) in members.iter_mut() {
+            let mut ordered = Vec::new();
+            while ordered.len() < ORDERED_ITEMS {
+                let item = timeout(TIMEOUT, ordered_rx.recv())
+                    .await
+                    .expect("the committee should keep ordering data")
+                    .expect("the member should be running");
+                ordered.push(item);
+            }
+            orders.push(ordered);
+        }
+        let mut results = Vec::new();
+        for ((handle, stop, backup, _), ordered) in members.into_iter().zip(orders) {
+            stop.send(()).expect("the member should be running");
+            handle.await.expect("the member should stop cleanly");
+            let backup = backup.0.lock().clone();
+            results.push((backup, ordered));
+        }
+        results
+    }
+
+    fn id(header: &Header) -> BlockId {
+        (header.hash(), header.number).into()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn replays_session_from_quorum_of_backups() {
+        let task_manager = TaskManager::new(Handle::current(), None).unwrap();
+        let spawn_handle: SpawnHandle = task_manager.spawn_handle().into();
+        let client = Arc::new(TestClientBuilder::new().build());
+        let mut chain_builder =
+            ClientChainBuilder::new(client.clone(), Arc::new(TestClientBuilder::new().build()));
+        let blocks = chain_builder
+            .initialize_single_branch_and_import(N_MEMBERS)
+            .await;
+        // Every member proposes a different prefix of the same branch.
+        let data = (1..=N_MEMBERS)
+            .map(|len| aleph_data_from_blocks(blocks[..len].to_vec()))
+            .collect();
+        let (pens, verifier) = crypto_basics(N_MEMBERS);
+
+        let committee = run_committee(data, pens, verifier.clone(), spawn_handle.clone()).await;
+        let backups = committee
+            .iter()
+            .take(QUORUM)
+            .enumerate()
+            .map(|(node_id, (backup, _))| {
+                let loader: Loader = Box::new(Cursor::new(backup.clone()));
+                (node_id, loader)
+            })
+            .collect();
+        let orders = replay_backups(
+            backups,
+            verifier,
+            SESSION_ID,
+            abft_config(),
+            spawn_handle,
+            IDLE_TIMEOUT,
+        )
+        .await;
+
+        // Units in the backup are saved before they are ordered, so the replay orders at least
+        // as much as the member did, in the same order.
+        for ((_, replayed), (_, ordered)) in orders.iter().zip(&committee) {
+            assert!(replayed.len() >= ordered.len());
+            assert_eq!(replayed[..ordered.len()], ordered[..]);
+        }
+        let (ordered_per_member, divergences, reference) = compare_orders(SESSION_ID, orders);
+        assert_eq!(ordered_per_member.len(), QUORUM);
+        assert!(divergences.is_empty());
+
+        // The chain has moved on since, the interpreter should see it as it was in the session.
+        chain_builder.finalize_block(&blocks[N_MEMBERS - 1].header.hash());
+        let chain_info =
+            HistoricalChainInfoProvider::new(SubstrateChainInfoProvider::new(client), 0);
+        let session_boundaries =
+            SessionBoundaryInfo::new(SESSION_PERIOD).boundaries_for_session(SESSION_ID);
+        let (blocks_tx, _) = mpsc::unbounded();
+        let mut interpreter: OrderedDataInterpreter<_, Header, _> =
+            OrderedDataInterpreter::new(blocks_tx, chain_info, TestVerifier, session_boundaries);
+        let ordered = interpret(&mut interpreter, reference.clone());
+
+        let mut finalized = 0;
+        for (item, (data, creator)) in ordered.iter().zip(&reference) {
+            assert_eq!(item.data, *data);
+            assert_eq!(item.creator, creator.0);
+            let top = data.head_proposal.top_block().number() as usize;
+            let expected = if top > finalized {
+                Interpretation::Finalize(
+                    blocks[finalized..top]
+                        .iter()
+                        .map(|block| id(&block.header))
+                        .collect(),
+                )
+            } else {
+                Interpretation::Ignore
+            };
+            assert_eq!(item.interpretation, expected);
+            finalized = finalized.max(top);
+        }
+        let replay = SessionReplay {
+            session_id: SESSION_ID,
+            ordered_per_member,
+            divergences,
+            ordered,
+        };
+        assert!(finalized > 0);
+        assert_eq!(
+            replay.finalized_blocks(),
+            blocks[..finalized]
+                .iter()
+                .map(|block| id(&block.header))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -4,15 +4,13 @@ use std::{
     sync::Arc,
 };
 
-use aleph_runtime::SessionKeys;
 use parity_scale_codec::{Decode, DecodeAll, Error as DecodeError};
 use sc_client_api::Backend;
-use sp_application_crypto::key_types::AURA;
 use sp_core::twox_128;
-use sp_runtime::traits::{Block, OpaqueKeys};
+use sp_runtime::traits::Block;
 
 use crate::{
-    aleph_primitives::{AccountId, AlephSessionApi, AuraId},
+    aleph_primitives::{AccountId, AlephSessionApi, AuraId, AuthorityId},
     BlockHash, ClientForAleph,
 };
 
@@ -24,7 +22,9 @@ pub trait RuntimeApi: Clone + Send + Sync + 'static {
         -> Result<Vec<(AccountId, AuraId)>, Self::Error>;
 }
 
-type QueuedKeys = Vec<(AccountId, SessionKeys)>;
+/// Session keys of runtimes that predate `next_session_aura_authorities`, which encode the same
+/// as a tuple of their fields.
+type QueuedKeys = Vec<(AccountId, (AuraId, AuthorityId))>;
 
 pub struct RuntimeApiImpl<C, B, BE>
 where
@@ -118,7 +118,7 @@ where
         let queued_keys: QueuedKeys = self.read_storage("Session", "QueuedKeys", at)?;
         Ok(queued_keys
             .into_iter()
-            .filter_map(|(account_id, keys)| keys.get(AURA).map(|key| (account_id, key)))
+            .map(|(account_id, (aura_key, _))| (account_id, aura_key))
             .collect())
     }
 }
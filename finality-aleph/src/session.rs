@@ -1,7 +1,14 @@
+use std::sync::Arc;
+
+use log::debug;
 use parity_scale_codec::{Decode, Encode};
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 
-use crate::aleph_primitives::BlockNumber;
+use crate::{
+    aleph_primitives::{BlockNumber, SessionTimings, MILLISECS_PER_BLOCK},
+    MillisecsPerBlock,
+};
 
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct SessionBoundaries {
@@ -19,15 +26,32 @@ impl SessionBoundaries {
     }
 }
 
+/// Struct for getting the session boundaries. All the clones share the session timings, so they
+/// all follow the timing changes reported with `update`.
 #[derive(Clone, Debug)]
 pub struct SessionBoundaryInfo {
-    session_period: SessionPeriod,
+    timings: Arc<RwLock<SessionTimings>>,
 }
 
-/// Struct for getting the session boundaries.
 impl SessionBoundaryInfo {
-    pub const fn new(session_period: SessionPeriod) -> Self {
-        Self { session_period }
+    /// Boundaries of sessions that always have the given period.
+    pub fn new(session_period: SessionPeriod) -> Self {
+        Self::from_timings(SessionTimings::new(session_period.0, MILLISECS_PER_BLOCK))
+    }
+
+    pub fn from_timings(timings: SessionTimings) -> Self {
+        Self {
+            timings: Arc::new(RwLock::new(timings)),
+        }
+    }
+
+    /// Replaces the session timings, for all the clones.
+    pub fn update(&self, timings: SessionTimings) {
+        let mut current = self.timings.write();
+        if *current != timings {
+            debug!(target: "aleph-session", "Session timings changed to {:?}.", timings);
+            *current = timings;
+        }
     }
 
     pub fn boundaries_for_session(&self, session_id: SessionId) -> SessionBoundaries {
@@ -39,17 +63,27 @@ impl SessionBoundaryInfo {
 
     /// Returns session id of the session that block belongs to.
     pub fn session_id_from_block_num(&self, n: BlockNumber) -> SessionId {
-        SessionId(n / self.session_period.0)
+        SessionId(self.timings.read().session_of_block(n))
     }
 
     /// Returns block number which is the last block of the session.
     pub fn last_block_of_session(&self, session_id: SessionId) -> BlockNumber {
-        (session_id.0 + 1) * self.session_period.0 - 1
+        self.timings.read().last_block_of_session(session_id.0)
     }
 
     /// Returns block number which is the first block of the session.
     pub fn first_block_of_session(&self, session_id: SessionId) -> BlockNumber {
-        session_id.0 * self.session_period.0
+        self.timings.read().first_block_of_session(session_id.0)
+    }
+
+    /// Returns the number of blocks in the session.
+    pub fn session_period(&self, session_id: SessionId) -> SessionPeriod {
+        SessionPeriod(self.timings.read().of_session(session_id.0).session_period)
+    }
+
+    /// Returns the block time in force at the block.
+    pub fn millisecs_per_block(&self, n: BlockNumber) -> MillisecsPerBlock {
+        MillisecsPerBlock(self.timings.read().at_block(n).millisecs_per_block)
     }
 }
 
@@ -95,3 +129,31 @@ impl SessionId {
 
 #[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Hash, Ord, PartialOrd, Encode, Decode)]
 pub struct SessionPeriod(pub u32);
+
+#[cfg(test)]
+mod tests {
+    use super::{SessionBoundaryInfo, SessionId, SessionPeriod};
+    use crate::{aleph_primitives::SessionTimings, MillisecsPerBlock};
+
+    #[test]
+    fn follows_timing_changes_in_all_clones() {
+        let session_info = SessionBoundaryInfo::new(SessionPeriod(10));
+        let clone = session_info.clone();
+        assert_eq!(clone.session_id_from_block_num(45), SessionId(4));
+
+        let mut timings = SessionTimings::new(10, 1000);
+        timings.schedule(4, 20, 500);
+        session_info.update(timings);
+
+        assert_eq!(clone.session_id_from_block_num(39), SessionId(3));
+        assert_eq!(clone.session_id_from_block_num(45), SessionId(4));
+        assert_eq!(clone.session_id_from_block_num(60), SessionId(5));
+        assert_eq!(clone.first_block_of_session(SessionId(5)), 60);
+        assert_eq!(clone.last_block_of_session(SessionId(3)), 39);
+        assert_eq!(clone.last_block_of_session(SessionId(4)), 59);
+        assert_eq!(clone.session_period(SessionId(3)), SessionPeriod(10));
+        assert_eq!(clone.session_period(SessionId(4)), SessionPeriod(20));
+        assert_eq!(clone.millisecs_per_block(39), MillisecsPerBlock(1000));
+        assert_eq!(clone.millisecs_per_block(40), MillisecsPerBlock(500));
+    }
+}
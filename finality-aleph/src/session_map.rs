@@ -1,9 +1,17 @@
-use std::{collections::HashMap, marker::PhantomData, ops::Deref, sync::Arc};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Error as FmtError, Formatter},
+    marker::PhantomData,
+    ops::Deref,
+    sync::Arc,
+};
 
 use futures::StreamExt;
-use log::{debug, error, trace};
-use sc_client_api::{Backend, FinalityNotification};
+use log::{debug, error, trace, warn};
+use parity_scale_codec::{Decode, Encode, Error as CodecError};
+use sc_client_api::{AuxStore, Backend, FinalityNotification};
 use sc_utils::mpsc::TracingUnboundedReceiver;
+use sp_api::ApiExt;
 use sp_consensus_aura::AuraApi;
 use sp_runtime::traits::{Block, Header};
 use tokio::sync::{
@@ -13,14 +21,17 @@ use tokio::sync::{
 
 use crate::{
     aleph_primitives::{
-        AccountId, AlephSessionApi, AuraId, BlockHash, BlockNumber, SessionAuthorityData,
+        AccountId, AlephSessionApi, AuraId, BlockHash, BlockNumber, BlsPublicKey,
+        EmergencyFinalizers, SessionAuthorityData, SessionTimings, SESSION_TIMINGS_API_VERSION,
     },
     runtime_api::RuntimeApi,
     session::SessionBoundaryInfo,
-    ClientForAleph, SessionId, SessionPeriod,
+    ClientForAleph, SessionHistoryDepth, SessionId,
 };
 const PRUNING_THRESHOLD: u32 = 10;
 const LOG_TARGET: &str = "aleph-session-updater";
+const SESSION_HISTORY_PREFIX: &[u8] = b"aleph-session-history";
+const SESSION_HISTORY_FIRST_KEY: &[u8] = b"aleph-session-history-first";
 type SessionMap = HashMap<SessionId, SessionAuthorityData>;
 type SessionSubscribers = HashMap<SessionId, Vec<OneShotSender<SessionAuthorityData>>>;
 
@@ -33,6 +44,16 @@ pub trait AuthorityProvider: Clone + Send + Sync + 'static {
     fn aura_authorities(&self, block_number: BlockNumber) -> Option<Vec<AuraId>>;
     /// returns list of next session Aura authorities for a given block number
     fn next_aura_authorities(&self, block_number: BlockNumber) -> Option<Vec<(AccountId, AuraId)>>;
+    /// returns BLS keys of the committee for block, in the order of its authorities
+    fn bls_keys(&self, block_number: BlockNumber) -> Option<Vec<BlsPublicKey>>;
+    /// returns BLS keys of the next session committee where current session is for block
+    fn next_bls_keys(&self, block_number: BlockNumber) -> Option<Vec<BlsPublicKey>>;
+    /// returns the emergency finalizer set for block, if any
+    fn emergency_finalizers(&self, block_number: BlockNumber) -> Option<EmergencyFinalizers>;
+    /// returns the emergency finalizer set of the next session where current session is for block
+    fn next_emergency_finalizers(&self, block_number: BlockNumber) -> Option<EmergencyFinalizers>;
+    /// returns the schedule of session periods and block times as of block
+    fn session_timings(&self, block_number: BlockNumber) -> Option<SessionTimings>;
 }
 
 /// Default implementation of authority provider trait.
@@ -150,6 +171,60 @@ where
                 .flatten(),
         }
     }
+
+    fn bls_keys(&self, block_number: BlockNumber) -> Option<Vec<BlsPublicKey>> {
+        // Runtimes that do not support BLS keys return an error here.
+        self.client
+            .runtime_api()
+            .bls_keys(self.block_hash(block_number)?)
+            .ok()
+    }
+
+    fn next_bls_keys(&self, block_number: BlockNumber) -> Option<Vec<BlsPublicKey>> {
+        self.client
+            .runtime_api()
+            .next_session_bls_keys(self.block_hash(block_number)?)
+            .ok()
+    }
+
+    fn emergency_finalizers(&self, block_number: BlockNumber) -> Option<EmergencyFinalizers> {
+        self.client
+            .runtime_api()
+            .emergency_finalizers(self.block_hash(block_number)?)
+            .ok()
+            .flatten()
+    }
+
+    fn next_emergency_finalizers(&self, block_number: BlockNumber) -> Option<EmergencyFinalizers> {
+        self.client
+            .runtime_api()
+            .next_session_emergency_finalizers(self.block_hash(block_number)?)
+            .ok()
+            .flatten()
+    }
+
+    fn session_timings(&self, block_number: BlockNumber) -> Option<SessionTimings> {
+        let block_hash = self.block_hash(block_number)?;
+        let runtime_api = self.client.runtime_api();
+        let result = match runtime_api.api_version::<dyn AlephSessionApi<B>>(block_hash) {
+            Ok(Some(version)) if version >= SESSION_TIMINGS_API_VERSION => {
+                runtime_api.session_timings(block_hash)
+            }
+            // Runtimes with fixed session timings do not report them.
+            Ok(_) => return None,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(timings) => Some(timings),
+            Err(e) => {
+                warn!(
+                    target: LOG_TARGET,
+                    "Failed to read session timings at block #{}: {}.", block_number, e
+                );
+                None
+            }
+        }
+    }
 }
 
 #[async_trait::async_trait]
@@ -286,29 +361,143 @@ impl ReadOnlySessionMap {
     }
 }
 
+#[derive(Debug)]
+pub enum SessionHistoryError {
+    Backend(sp_blockchain::Error),
+    Decoding(CodecError),
+}
+
+impl Display for SessionHistoryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), FmtError> {
+        use SessionHistoryError::*;
+        match self {
+            Backend(e) => write!(f, "failed to access the aux storage: {e}"),
+            Decoding(e) => write!(f, "failed to decode stored authority data: {e}"),
+        }
+    }
+}
+
+/// Authority data of past sessions persisted in the aux storage of the client, so that it
+/// survives restarts and stays available after the state of the sessions gets pruned.
+pub struct SessionHistory<AS: AuxStore> {
+    store: Arc<AS>,
+}
+
+impl<AS: AuxStore> Clone for SessionHistory<AS> {
+    fn clone(&self) -> Self {
+        SessionHistory {
+            store: self.store.clone(),
+        }
+    }
+}
+
+fn session_history_key(id: SessionId) -> Vec<u8> {
+    let mut key = SESSION_HISTORY_PREFIX.to_vec();
+    key.extend(id.encode());
+    key
+}
+
+impl<AS: AuxStore> SessionHistory<AS> {
+    pub fn new(store: Arc<AS>) -> Self {
+        SessionHistory { store }
+    }
+
+    fn read<T: Decode>(&self, key: &[u8]) -> Result<Option<T>, SessionHistoryError> {
+        match self
+            .store
+            .get_aux(key)
+            .map_err(SessionHistoryError::Backend)?
+        {
+            Some(encoded) => T::decode(&mut &encoded[..])
+                .map(Some)
+                .map_err(SessionHistoryError::Decoding),
+            None => Ok(None),
+        }
+    }
+
+    /// The first session for which authority data might be stored.
+    fn first(&self) -> Result<Option<SessionId>, SessionHistoryError> {
+        self.read(SESSION_HISTORY_FIRST_KEY)
+    }
+
+    /// Returns the stored authority data of the session, if any.
+    pub fn get(&self, id: SessionId) -> Result<Option<SessionAuthorityData>, SessionHistoryError> {
+        self.read(&session_history_key(id))
+    }
+
+    /// Stores the authority data of the session.
+    pub fn insert(
+        &self,
+        id: SessionId,
+        authority_data: &SessionAuthorityData,
+    ) -> Result<(), SessionHistoryError> {
+        let key = session_history_key(id);
+        let value = authority_data.encode();
+        let first = match self.first()? {
+            Some(first) if first <= id => None,
+            _ => Some(id.encode()),
+        };
+        let mut insert = vec![(&key[..], &value[..])];
+        if let Some(first) = &first {
+            insert.push((SESSION_HISTORY_FIRST_KEY, &first[..]));
+        }
+        self.store
+            .insert_aux(&insert, &[])
+            .map_err(SessionHistoryError::Backend)
+    }
+
+    /// Removes the authority data of all the sessions older than `id`.
+    pub fn prune_below(&self, id: SessionId) -> Result<(), SessionHistoryError> {
+        let first = match self.first()? {
+            Some(first) if first < id => first,
+            _ => return Ok(()),
+        };
+        let keys: Vec<_> = (first.0..id.0)
+            .map(|session| session_history_key(SessionId(session)))
+            .collect();
+        let keys: Vec<_> = keys.iter().map(|key| &key[..]).collect();
+        let first = id.encode();
+        self.store
+            .insert_aux(&[(SESSION_HISTORY_FIRST_KEY, &first[..])], &keys)
+            .map_err(SessionHistoryError::Backend)
+    }
+}
+
 /// Struct responsible for updating session map
-pub struct SessionMapUpdater<AP, FN>
+pub struct SessionMapUpdater<AP, FN, AS>
 where
     AP: AuthorityProvider,
     FN: FinalityNotifier,
+    AS: AuxStore,
 {
     session_map: SharedSessionMap,
     authority_provider: AP,
     finality_notifier: FN,
     session_info: SessionBoundaryInfo,
+    session_history: SessionHistory<AS>,
+    session_history_depth: Option<SessionHistoryDepth>,
 }
 
-impl<AP, FN> SessionMapUpdater<AP, FN>
+impl<AP, FN, AS> SessionMapUpdater<AP, FN, AS>
 where
     AP: AuthorityProvider,
     FN: FinalityNotifier,
+    AS: AuxStore,
 {
-    pub fn new(authority_provider: AP, finality_notifier: FN, period: SessionPeriod) -> Self {
+    pub fn new(
+        authority_provider: AP,
+        finality_notifier: FN,
+        session_info: SessionBoundaryInfo,
+        session_history: SessionHistory<AS>,
+        session_history_depth: Option<SessionHistoryDepth>,
+    ) -> Self {
         Self {
             session_map: SharedSessionMap::new(),
             authority_provider,
             finality_notifier,
-            session_info: SessionBoundaryInfo::new(period),
+            session_info,
+            session_history,
+            session_history_depth,
         }
     }
 
@@ -317,6 +506,41 @@ where
         self.session_map.read_only()
     }
 
+    fn stored_authorities(&self, session_id: SessionId) -> Option<SessionAuthorityData> {
+        match self.session_history.get(session_id) {
+            Ok(authority_data) => authority_data,
+            Err(e) => {
+                warn!(
+                    target: LOG_TARGET,
+                    "Failed to read authorities of session {:?} from the session history: {}",
+                    session_id.0,
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Puts the authority data into the session map and persists it in the session history.
+    async fn update(&mut self, session_id: SessionId, authority_data: SessionAuthorityData) {
+        if let Err(e) = self.session_history.insert(session_id, &authority_data) {
+            warn!(
+                target: LOG_TARGET,
+                "Failed to store authorities of session {:?} in the session history: {}",
+                session_id.0,
+                e
+            );
+        }
+        self.session_map.update(session_id, authority_data).await;
+    }
+
+    /// Makes the session boundaries follow the session timings as of the finalized block.
+    fn update_session_timings(&self, finalized: BlockNumber) {
+        if let Some(timings) = self.authority_provider.session_timings(finalized) {
+            self.session_info.update(timings);
+        }
+    }
+
     /// Puts authority data for the next session into the session map
     async fn handle_first_block_of_session(&mut self, session_id: SessionId) {
         let first_block = self.session_info.first_block_of_session(session_id);
@@ -325,12 +549,25 @@ where
             "Handling first block #{:?} of session {:?}", first_block, session_id.0
         );
 
-        if let Some(authority_data) = self.authority_provider.next_authority_data(first_block) {
-            self.session_map
-                .update(SessionId(session_id.0 + 1), authority_data)
-                .await;
-        } else {
-            panic!("Authorities for next session {:?} must be available at first block #{:?} of current session", session_id.0, first_block);
+        let next_session_id = SessionId(session_id.0 + 1);
+        match self
+            .stored_authorities(next_session_id)
+            .or_else(|| self.authority_provider.next_authority_data(first_block))
+        {
+            Some(authority_data) => self.update(next_session_id, authority_data).await,
+            None => panic!("Authorities for next session {:?} must be available at first block #{:?} of current session", session_id.0, first_block),
+        }
+
+        if let Some(SessionHistoryDepth(depth)) = self.session_history_depth {
+            if let Err(e) = self
+                .session_history
+                .prune_below(SessionId((next_session_id.0 + 1).saturating_sub(depth)))
+            {
+                warn!(
+                    target: LOG_TARGET,
+                    "Failed to prune the session history: {}", e
+                );
+            }
         }
 
         if session_id.0 > PRUNING_THRESHOLD && session_id.0 % PRUNING_THRESHOLD == 0 {
@@ -346,14 +583,19 @@ where
     }
 
     fn authorities_for_session(&mut self, session_id: SessionId) -> Option<SessionAuthorityData> {
+        if let Some(authority_data) = self.stored_authorities(session_id) {
+            return Some(authority_data);
+        }
         let first_block = self.session_info.first_block_of_session(session_id);
         self.authority_provider.authority_data(first_block)
     }
 
     /// Puts current and next session authorities in the session map.
-    /// If previous authorities are still available in `AuthorityProvider`, also puts them in the session map.
+    /// Previous authorities are restored from the session history, or if they are not there,
+    /// taken from `AuthorityProvider`, as long as they are still available.
     async fn catch_up(&mut self) -> SessionId {
         let last_finalized = self.finality_notifier.last_finalized();
+        self.update_session_timings(last_finalized);
 
         let current_session = self.session_info.session_id_from_block_num(last_finalized);
         let starting_session = SessionId(current_session.0.saturating_sub(PRUNING_THRESHOLD - 1));
@@ -367,7 +609,7 @@ where
         for session in starting_session.0..current_session.0 {
             let id = SessionId(session);
             if let Some(authority_data) = self.authorities_for_session(id) {
-                self.session_map.update(id, authority_data).await;
+                self.update(id, authority_data).await;
             } else {
                 debug!(
                     target: LOG_TARGET,
@@ -380,9 +622,7 @@ where
         // lets catch up with previous session
         match self.authorities_for_session(current_session) {
             Some(current_authority_data) => {
-                self.session_map
-                    .update(current_session, current_authority_data)
-                    .await
+                self.update(current_session, current_authority_data).await
             }
             None => panic!(
                 "Authorities for current session {:?} must be available from the beginning",
@@ -405,6 +645,7 @@ where
                 last_finalized
             );
 
+            self.update_session_timings(last_finalized);
             let session_id = self.session_info.session_id_from_block_num(last_finalized);
 
             if last_updated >= session_id {
@@ -422,14 +663,14 @@ where
 
 #[cfg(test)]
 mod tests {
-    use std::time::Duration;
+    use std::{sync::Mutex, time::Duration};
 
     use futures_timer::Delay;
     use sc_utils::mpsc::tracing_unbounded;
     use tokio::sync::oneshot::error::TryRecvError;
 
     use super::*;
-    use crate::{aleph_primitives::BlockNumber, session::testing::authority_data};
+    use crate::{aleph_primitives::BlockNumber, session::testing::authority_data, SessionPeriod};
 
     const FIRST_THRESHOLD: u32 = PRUNING_THRESHOLD + 1;
     const SECOND_THRESHOLD: u32 = 2 * PRUNING_THRESHOLD + 1;
@@ -480,6 +721,68 @@ mod tests {
         ) -> Option<Vec<(AccountId, AuraId)>> {
             None
         }
+
+        fn bls_keys(&self, _block_number: BlockNumber) -> Option<Vec<BlsPublicKey>> {
+            None
+        }
+
+        fn next_bls_keys(&self, _block_number: BlockNumber) -> Option<Vec<BlsPublicKey>> {
+            None
+        }
+
+        fn emergency_finalizers(&self, _block_number: BlockNumber) -> Option<EmergencyFinalizers> {
+            None
+        }
+
+        fn next_emergency_finalizers(
+            &self,
+            _block_number: BlockNumber,
+        ) -> Option<EmergencyFinalizers> {
+            None
+        }
+
+        fn session_timings(&self, _block_number: BlockNumber) -> Option<SessionTimings> {
+            None
+        }
+    }
+
+    struct MockAuxStore {
+        storage: Mutex<HashMap<Vec<u8>, Vec<u8>>>,
+    }
+
+    impl MockAuxStore {
+        fn new() -> Self {
+            Self {
+                storage: Mutex::new(HashMap::new()),
+            }
+        }
+    }
+
+    impl AuxStore for MockAuxStore {
+        fn insert_aux<
+            'a,
+            'b: 'a,
+            'c: 'a,
+            I: IntoIterator<Item = &'a (&'c [u8], &'c [u8])>,
+            D: IntoIterator<Item = &'a &'b [u8]>,
+        >(
+            &self,
+            insert: I,
+            delete: D,
+        ) -> sp_blockchain::Result<()> {
+            let mut storage = self.storage.lock().expect("mutex works");
+            for (key, value) in insert {
+                storage.insert(key.to_vec(), value.to_vec());
+            }
+            for key in delete {
+                storage.remove(*key);
+            }
+            Ok(())
+        }
+
+        fn get_aux(&self, key: &[u8]) -> sp_blockchain::Result<Option<Vec<u8>>> {
+            Ok(self.storage.lock().expect("mutex works").get(key).cloned())
+        }
     }
 
     struct MockNotifier {
@@ -519,7 +822,13 @@ mod tests {
 
         mock_provider.add_session(0);
 
-        let updater = SessionMapUpdater::new(mock_provider, mock_notifier, SessionPeriod(1));
+        let updater = SessionMapUpdater::new(
+            mock_provider,
+            mock_notifier,
+            SessionBoundaryInfo::new(SessionPeriod(1)),
+            SessionHistory::new(Arc::new(MockAuxStore::new())),
+            None,
+        );
         let session_map = updater.readonly_session_map();
 
         let _handle = tokio::spawn(updater.run());
@@ -547,7 +856,13 @@ mod tests {
         mock_provider.add_session(1);
         mock_provider.add_session(2);
 
-        let updater = SessionMapUpdater::new(mock_provider, mock_notificator, SessionPeriod(1));
+        let updater = SessionMapUpdater::new(
+            mock_provider,
+            mock_notificator,
+            SessionBoundaryInfo::new(SessionPeriod(1)),
+            SessionHistory::new(Arc::new(MockAuxStore::new())),
+            None,
+        );
         let session_map = updater.readonly_session_map();
 
         for n in 1..3 {
@@ -589,7 +904,13 @@ mod tests {
 
         mock_notificator.last_finalized = 2;
 
-        let updater = SessionMapUpdater::new(mock_provider, mock_notificator, SessionPeriod(1));
+        let updater = SessionMapUpdater::new(
+            mock_provider,
+            mock_notificator,
+            SessionBoundaryInfo::new(SessionPeriod(1)),
+            SessionHistory::new(Arc::new(MockAuxStore::new())),
+            None,
+        );
         let session_map = updater.readonly_session_map();
 
         let _handle = tokio::spawn(updater.run());
@@ -627,7 +948,13 @@ mod tests {
 
         mock_notificator.last_finalized = 20;
 
-        let updater = SessionMapUpdater::new(mock_provider, mock_notificator, SessionPeriod(1));
+        let updater = SessionMapUpdater::new(
+            mock_provider,
+            mock_notificator,
+            SessionBoundaryInfo::new(SessionPeriod(1)),
+            SessionHistory::new(Arc::new(MockAuxStore::new())),
+            None,
+        );
         let session_map = updater.readonly_session_map();
 
         let _handle = tokio::spawn(updater.run());
@@ -660,7 +987,13 @@ mod tests {
         mock_provider.add_session(5);
         mock_notificator.last_finalized = 5;
 
-        let updater = SessionMapUpdater::new(mock_provider, mock_notificator, SessionPeriod(1));
+        let updater = SessionMapUpdater::new(
+            mock_provider,
+            mock_notificator,
+            SessionBoundaryInfo::new(SessionPeriod(1)),
+            SessionHistory::new(Arc::new(MockAuxStore::new())),
+            None,
+        );
         let session_map = updater.readonly_session_map();
 
         let _handle = tokio::spawn(updater.run());
@@ -696,7 +1029,13 @@ mod tests {
             mock_provider.add_session(i);
         }
 
-        let updater = SessionMapUpdater::new(mock_provider, mock_notificator, SessionPeriod(1));
+        let updater = SessionMapUpdater::new(
+            mock_provider,
+            mock_notificator,
+            SessionBoundaryInfo::new(SessionPeriod(1)),
+            SessionHistory::new(Arc::new(MockAuxStore::new())),
+            None,
+        );
         let session_map = updater.readonly_session_map();
 
         let _handle = tokio::spawn(updater.run());
@@ -747,6 +1086,93 @@ mod tests {
         }
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn restores_sessions_from_history() {
+        let (_sender, receiver) = tracing_unbounded("test", 1_000);
+        let mut mock_provider = MockProvider::new();
+        let mut mock_notificator = MockNotifier::new(receiver);
+
+        mock_provider.add_session(5);
+        mock_notificator.last_finalized = 5;
+
+        let session_history = SessionHistory::new(Arc::new(MockAuxStore::new()));
+        for i in 0..5 {
+            session_history
+                .insert(SessionId(i), &authority_data_for_session(i))
+                .unwrap();
+        }
+
+        let updater = SessionMapUpdater::new(
+            mock_provider,
+            mock_notificator,
+            SessionBoundaryInfo::new(SessionPeriod(1)),
+            session_history.clone(),
+            None,
+        );
+        let session_map = updater.readonly_session_map();
+
+        let _handle = tokio::spawn(updater.run());
+
+        // wait a bit
+        Delay::new(Duration::from_millis(50)).await;
+
+        for i in 0..=6 {
+            assert_eq!(
+                session_map.get(SessionId(i)).await,
+                Some(authority_data_for_session(i)),
+                "Session {i:?} should be available"
+            );
+        }
+        assert_eq!(
+            session_history.get(SessionId(6)).unwrap(),
+            Some(authority_data_for_session(6))
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn prunes_session_history() {
+        let (sender, receiver) = tracing_unbounded("test", 1_000);
+        let mut mock_provider = MockProvider::new();
+        let mock_notificator = MockNotifier::new(receiver);
+
+        for i in 0..SECOND_THRESHOLD {
+            mock_provider.add_session(i);
+        }
+
+        let session_history = SessionHistory::new(Arc::new(MockAuxStore::new()));
+        let updater = SessionMapUpdater::new(
+            mock_provider,
+            mock_notificator,
+            SessionBoundaryInfo::new(SessionPeriod(1)),
+            session_history.clone(),
+            Some(SessionHistoryDepth(3)),
+        );
+
+        let _handle = tokio::spawn(updater.run());
+
+        for n in 1..FIRST_THRESHOLD {
+            sender.unbounded_send(n).unwrap();
+        }
+
+        // wait a bit
+        Delay::new(Duration::from_millis(50)).await;
+
+        for i in 0..(FIRST_THRESHOLD - 2) {
+            assert_eq!(
+                session_history.get(SessionId(i)).unwrap(),
+                None,
+                "Session {i:?} should be pruned"
+            );
+        }
+        for i in (FIRST_THRESHOLD - 2)..=FIRST_THRESHOLD {
+            assert_eq!(
+                session_history.get(SessionId(i)).unwrap(),
+                Some(authority_data_for_session(i)),
+                "Session {i:?} should be stored"
+            );
+        }
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn subscription_with_already_defined_session_works() {
         let mut shared = SharedSessionMap::new();
@@ -0,0 +1,93 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use parking_lot::Mutex;
+
+use crate::{data_io::FavouriteBlocks, sync::PeerId, BlockId};
+
+/// How long we remember the favourite block of a peer we stopped hearing from.
+const FAVOURITE_BLOCK_TTL: Duration = Duration::from_secs(30);
+
+/// The favourite blocks of peers, as declared in the states they broadcast. These are the blocks
+/// the peers already have. Sync peers are identified by their network peer ids, which are not
+/// tied to the keys of the committee, so every peer counts, but each of them only once.
+#[derive(Clone)]
+pub struct PeerFavourites<I: PeerId> {
+    favourites: Arc<Mutex<HashMap<I, (BlockId, Instant)>>>,
+}
+
+impl<I: PeerId> Default for PeerFavourites<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I: PeerId> PeerFavourites<I> {
+    pub fn new() -> Self {
+        PeerFavourites {
+            favourites: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Records the favourite block of the peer, replacing any previous one.
+    pub fn update(&self, peer: I, block: BlockId) {
+        self.favourites.lock().insert(peer, (block, Instant::now()));
+    }
+
+    fn favourites_at(&self, now: Instant) -> Vec<BlockId> {
+        let mut favourites = self.favourites.lock();
+        favourites
+            .retain(|_, (_, seen)| now.saturating_duration_since(*seen) < FAVOURITE_BLOCK_TTL);
+        favourites
+            .values()
+            .map(|(block, _)| block.clone())
+            .collect()
+    }
+}
+
+impl<I: PeerId + Send + 'static> FavouriteBlocks for PeerFavourites<I> {
+    fn favourite_blocks(&self) -> Vec<BlockId> {
+        self.favourites_at(Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{PeerFavourites, FAVOURITE_BLOCK_TTL};
+    use crate::{sync::MockPeerId, BlockId};
+
+    #[test]
+    fn keeps_latest_favourite_per_peer() {
+        let favourites = PeerFavourites::<MockPeerId>::new();
+        let hash = Default::default();
+        favourites.update(0, BlockId::new(hash, 1));
+        favourites.update(1, BlockId::new(hash, 2));
+        favourites.update(0, BlockId::new(hash, 3));
+
+        let mut numbers: Vec<_> = favourites
+            .favourites_at(Instant::now())
+            .iter()
+            .map(|block| block.number())
+            .collect();
+        numbers.sort();
+        assert_eq!(numbers, vec![2, 3]);
+    }
+
+    #[test]
+    fn forgets_stale_favourites() {
+        let favourites = PeerFavourites::<MockPeerId>::new();
+        favourites.update(0, BlockId::new(Default::default(), 1));
+
+        assert_eq!(
+            favourites
+                .favourites_at(Instant::now() + FAVOURITE_BLOCK_TTL + Duration::from_secs(1))
+                .len(),
+            0
+        );
+    }
+}
@@ -11,6 +11,7 @@ use static_assertions::const_assert;
 use crate::{
     aleph_primitives::DEFAULT_SESSION_PERIOD,
     block::{Block, ChainStatus, Header, Justification, UnverifiedHeader, UnverifiedHeaderFor},
+    session::SessionBoundaryInfo,
     sync::{
         data::{BranchKnowledge, MaybeHeader},
         BlockId, PeerId,
@@ -148,7 +149,7 @@ impl<I: PeerId, J: Justification> VertexWithChildren<I, J> {
 }
 
 // How deep can the forest be, vaguely based on two sessions ahead, which is the most we expect to
-// ever need worst case scenario.
+// ever need worst case scenario. With longer sessions the forest gets deeper accordingly.
 //
 // At least one session must fit into the Forest.
 const MAX_DEPTH: u32 = 1800;
@@ -167,6 +168,7 @@ where
     root: J::Header,
     root_children: HashSet<BlockId>,
     compost_bin: HashSet<BlockId>,
+    session_info: SessionBoundaryInfo,
 }
 
 type Edge = (BlockId, BlockId);
@@ -178,7 +180,10 @@ where
 {
     /// Creates a new forest and returns whether we have too many nonfinalized blocks in the DB.
     //TODO(A0-2984): the latter part of the result should be removed after legacy sync is excised
-    pub fn new<B, CS>(chain_status: &CS) -> Result<(Self, bool), InitializationError<B, J, CS>>
+    pub fn new<B, CS>(
+        chain_status: &CS,
+        session_info: SessionBoundaryInfo,
+    ) -> Result<(Self, bool), InitializationError<B, J, CS>>
     where
         B: Block<UnverifiedHeader = UnverifiedHeaderFor<J>>,
         CS: ChainStatus<B, J>,
@@ -197,6 +202,7 @@ where
             root: top_finalized.clone(),
             root_children: HashSet::new(),
             compost_bin: HashSet::new(),
+            session_info,
         };
 
         // Populate the forest
@@ -219,13 +225,20 @@ where
         Ok((forest, false))
     }
 
+    /// How far above the root blocks are accepted, covering two sessions of the current length.
+    fn max_depth(&self) -> u32 {
+        let root_number = self.root.id().number();
+        let session = self.session_info.session_id_from_block_num(root_number);
+        MAX_DEPTH.max(2 * self.session_info.session_period(session).0)
+    }
+
     fn special_state(&self, id: &BlockId) -> Option<SpecialState> {
         use SpecialState::*;
         if id == &self.root.id() {
             Some(HighestFinalized)
         } else if id.number() <= self.root.id().number() {
             Some(BelowMinimal)
-        } else if id.number() > self.root.id().number() + MAX_DEPTH {
+        } else if id.number() > self.root.id().number() + self.max_depth() {
             Some(TooNew)
         } else if self.compost_bin.contains(id) {
             Some(HopelessFork)
@@ -705,16 +718,16 @@ mod tests {
 
     type MockForest = Forest<MockPeerId, MockJustification>;
 
-    const SESSION_BOUNDARY_INFO: SessionBoundaryInfo = SessionBoundaryInfo::new(SessionPeriod(20));
-
     fn setup() -> (MockHeader, MockForest) {
-        let (backend, _) = Backend::setup(SESSION_BOUNDARY_INFO);
+        let session_info = SessionBoundaryInfo::new(SessionPeriod(20));
+        let (backend, _) = Backend::setup(session_info.clone());
         let header = backend
             .top_finalized()
             .expect("should return genesis")
             .header()
             .clone();
-        let (forest, too_many_nonfinalized) = Forest::new(&backend).expect("should initialize");
+        let (forest, too_many_nonfinalized) =
+            Forest::new(&backend, session_info).expect("should initialize");
         assert!(!too_many_nonfinalized);
         (header, forest)
     }
@@ -406,8 +406,8 @@ where
             block_importer,
             ..
         } = database_io;
-        let (forest, too_many_nonfinalized) =
-            Forest::new(&chain_status).map_err(Error::ForestInitialization)?;
+        let (forest, too_many_nonfinalized) = Forest::new(&chain_status, session_info.clone())
+            .map_err(Error::ForestInitialization)?;
         let mut missed_import_data = MissedImportData::new();
         if too_many_nonfinalized {
             missed_import_data
@@ -896,7 +896,9 @@ mod tests {
         Handler<MockBlock, MockPeerId, MockJustification, Backend, Backend, Backend, Backend>;
     type MockResponseItems = ResponseItems<MockBlock, MockJustification>;
 
-    const SESSION_BOUNDARY_INFO: SessionBoundaryInfo = SessionBoundaryInfo::new(SessionPeriod(20));
+    fn session_boundary_info() -> SessionBoundaryInfo {
+        SessionBoundaryInfo::new(SessionPeriod(20))
+    }
 
     fn setup() -> (
         TestHandler,
@@ -904,14 +906,14 @@ mod tests {
         impl ChainStatusNotifier<MockHeader>,
         BlockId,
     ) {
-        let (backend, notifier) = Backend::setup(SESSION_BOUNDARY_INFO);
+        let (backend, notifier) = Backend::setup(session_boundary_info());
         let verifier = backend.clone();
         let database_io = DatabaseIO::new(backend.clone(), backend.clone(), backend.clone());
         let handler = Handler::new(
             database_io,
             verifier,
             SyncOracle::new(),
-            SESSION_BOUNDARY_INFO,
+            session_boundary_info(),
         )
         .expect("mock backend works");
         let genesis = backend.top_finalized().expect("genesis").header().id();
@@ -1305,7 +1307,7 @@ mod tests {
         let mut bottom = genesis;
         let peer_id = 0;
         for session in 0.. {
-            let top = SESSION_BOUNDARY_INFO.last_block_of_session(SessionId(session));
+            let top = session_boundary_info().last_block_of_session(SessionId(session));
             let branch = grow_light_branch_till(&mut handler, &bottom, &top, peer_id);
             bottom = branch.last().expect("should not be empty").id();
             // import blocks
@@ -1352,9 +1354,10 @@ mod tests {
     #[tokio::test]
     async fn skips_justification_gap_with_last_of_current_session_only() {
         let (mut handler, _backend, mut notifier, genesis) = setup();
-        let last_block_of_first_session = SESSION_BOUNDARY_INFO.last_block_of_session(SessionId(0));
+        let last_block_of_first_session =
+            session_boundary_info().last_block_of_session(SessionId(0));
         let last_block_of_second_session =
-            SESSION_BOUNDARY_INFO.last_block_of_session(SessionId(1));
+            session_boundary_info().last_block_of_session(SessionId(1));
         let peer_id = 0;
         let branch_low = grow_light_branch_till(
             &mut handler,
@@ -1817,7 +1820,7 @@ mod tests {
 
     #[test]
     fn initializes_forest_properly() {
-        let (mut backend, _keep) = Backend::setup(SESSION_BOUNDARY_INFO);
+        let (mut backend, _keep) = Backend::setup(session_boundary_info());
         let header = import_branch(&mut backend, 1)[0].clone();
         // header already imported, Handler should initialize Forest properly
         let verifier = backend.clone();
@@ -10,6 +10,7 @@ use crate::{
 };
 
 mod data;
+mod favourites;
 mod forest;
 mod handler;
 mod message_limiter;
@@ -20,6 +21,7 @@ mod tasks;
 mod ticker;
 
 pub use data::MAX_MESSAGE_SIZE;
+pub use favourites::PeerFavourites;
 pub use handler::DatabaseIO;
 pub use service::{Service, IO};
 
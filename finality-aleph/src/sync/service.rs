@@ -10,6 +10,7 @@ use crate::{
         EquivocationProof, Finalizer, Header, HeaderVerifier, Justification, JustificationVerifier,
         UnverifiedHeader, UnverifiedHeaderFor,
     },
+    event_log::{ConsensusEvent, EventLog},
     network::GossipNetwork,
     session::SessionBoundaryInfo,
     sync::{
@@ -24,7 +25,8 @@ use crate::{
         task_queue::TaskQueue,
         tasks::{Action as TaskAction, RequestTask},
         ticker::Ticker,
-        BlockId, JustificationSubmissions, LegacyRequestBlocks, RequestBlocks, LOG_TARGET,
+        BlockId, JustificationSubmissions, LegacyRequestBlocks, PeerFavourites, RequestBlocks,
+        LOG_TARGET,
     },
     SyncOracle,
 };
@@ -103,7 +105,9 @@ where
     block_requests_from_user: mpsc::UnboundedReceiver<B::UnverifiedHeader>,
     legacy_block_requests_from_user: mpsc::UnboundedReceiver<BlockId>,
     blocks_from_creator: mpsc::UnboundedReceiver<B>,
+    favourites: PeerFavourites<N::PeerId>,
     metrics: Metrics,
+    event_log: EventLog,
 }
 
 impl<J: Justification> JustificationSubmissions<J> for mpsc::UnboundedSender<J::Unverified> {
@@ -150,17 +154,19 @@ where
 {
     /// Create a new service using the provided network for communication.
     /// Also returns an interface for submitting additional justifications,
-    /// and an interface for requesting blocks.
+    /// an interface for requesting blocks, and the favourite blocks of peers.
     pub fn new(
         verifier: V,
         session_info: SessionBoundaryInfo,
         io: IO<B, J, N, CE, CS, F, BI>,
         metrics_registry: Option<Registry>,
+        event_log: EventLog,
     ) -> Result<
         (
             Self,
             impl JustificationSubmissions<J> + Clone,
             impl RequestBlocks<B::UnverifiedHeader> + LegacyRequestBlocks,
+            PeerFavourites<N::PeerId>,
         ),
         HandlerError<B, J, CS, V, F>,
     > {
@@ -187,6 +193,7 @@ where
                 Metrics::noop()
             }
         };
+        let favourites = PeerFavourites::new();
 
         Ok((
             Service {
@@ -201,13 +208,16 @@ where
                 blocks_from_creator,
                 block_requests_from_user,
                 legacy_block_requests_from_user,
+                favourites: favourites.clone(),
                 metrics,
+                event_log,
             },
             justifications_for_sync,
             CompatibilityRequestBlocks {
                 current: block_requests_for_sync,
                 legacy: legacy_block_requests_for_sync,
             },
+            favourites,
         ))
     }
 
@@ -338,6 +348,10 @@ where
     fn process_equivocation_proofs<I: IntoIterator<Item = V::EquivocationProof>>(&self, proofs: I) {
         for proof in proofs {
             warn!(target: LOG_TARGET, "Equivocation detected: {proof}");
+            self.event_log.emit(ConsensusEvent::Equivocation {
+                ours: proof.are_we_equivocating(),
+                proof: proof.to_string(),
+            });
             if proof.are_we_equivocating() {
                 panic!("We are equivocating, which is ILLEGAL - shutting down the node. This is probably caused by running two instances of the node with the same set of credentials. Make sure that you are running ONLY ONE instance of the node. If the problem persists, contact the Aleph Zero developers on Discord.");
             }
@@ -353,8 +367,10 @@ where
             state,
             peer
         );
+        let favourite_block = state.favourite_block().id();
         match self.handler.handle_state(state, peer.clone()) {
             Ok((action, maybe_proof)) => {
+                self.favourites.update(peer.clone(), favourite_block);
                 self.process_equivocation_proofs(maybe_proof);
                 match action {
                     Response(data) => self.send_to(data, peer),
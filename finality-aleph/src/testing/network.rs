@@ -24,7 +24,8 @@ use crate::{
         mock::{crypto_basics, MockData},
         session::{
             authentication, ConnectionManager, ConnectionManagerConfig, DataInSession,
-            ManagerError, SessionHandler, SessionManager, VersionedAuthentication,
+            ManagerError, SessionHandler, SessionManager, ValidatorNetworkStatus,
+            VersionedAuthentication,
         },
         GossipError, GossipNetwork, GossipService, MockEvent, MockRawNetwork, Protocol,
     },
@@ -112,6 +113,7 @@ async fn prepare_one_session_test_data() -> TestData {
         validator_network.clone(),
         gossip_network,
         noop_updater(),
+        ValidatorNetworkStatus::new(),
         ConnectionManagerConfig::with_session_period(&SESSION_PERIOD, &MILLISECS_PER_BLOCK),
     );
     let session_manager = Box::new(session_manager);
@@ -296,7 +298,8 @@ async fn test_sends_discovery_message() {
 
     for _ in 0..4 {
         match test_data.next_sent_auth().await {
-            Some((VersionedAuthentication::V2(new_authentication), peer_id, _)) => {
+            Some((VersionedAuthentication::V2(new_authentication), peer_id, _))
+            | Some((VersionedAuthentication::V3(new_authentication, _), peer_id, _)) => {
                 assert_eq!(peer_id, connected_peer_id);
                 assert_eq!(new_authentication, authentication(&handler));
             }
@@ -1,8 +1,15 @@
-use primitives::{FinalityCommitteeManager, SessionIndex};
-use sp_std::vec::Vec;
+use frame_support::{
+    pallet_prelude::Weight,
+    traits::{EstimateNextSessionRotation, Get},
+};
+use frame_system::pallet_prelude::BlockNumberFor;
+use primitives::{BlockNumber, FinalityCommitteeManager, SessionIndex};
+use sp_runtime::{traits::UniqueSaturatedInto, Permill};
+use sp_std::{marker::PhantomData, vec::Vec};
 
 use crate::{
-    Config, Event, FinalityScheduledVersionChange, FinalityVersion, NextFinalityCommittee, Pallet,
+    AbftScheduledConfigChange, Config, CurrentAbftConfig, Event, FinalityScheduledVersionChange,
+    FinalityVersion, NextFinalityCommittee, Pallet,
 };
 
 impl<T> pallet_session::SessionManager<T::AccountId> for Pallet<T>
@@ -24,6 +31,7 @@ where
     fn start_session(start_index: SessionIndex) {
         <T as Config>::SessionManager::start_session(start_index);
         Self::update_version_change_history();
+        Self::update_abft_config_change_history();
     }
 }
 
@@ -51,6 +59,21 @@ where
             }
         }
     }
+
+    // Same as above, but for the AlephBFT config.
+    fn update_abft_config_change_history() {
+        let current_session = Self::current_session();
+
+        if let Some(scheduled_config_change) = <AbftScheduledConfigChange<T>>::get() {
+            if scheduled_config_change.session == current_session {
+                <CurrentAbftConfig<T>>::put(scheduled_config_change.config_incoming);
+
+                <AbftScheduledConfigChange<T>>::kill();
+
+                Self::deposit_event(Event::AbftConfigChange(scheduled_config_change));
+            }
+        }
+    }
 }
 
 impl<T: Config> FinalityCommitteeManager<T::AccountId> for Pallet<T> {
@@ -58,3 +81,73 @@ impl<T: Config> FinalityCommitteeManager<T::AccountId> for Pallet<T> {
         NextFinalityCommittee::<T>::put(committee);
     }
 }
+
+/// Ends sessions according to the session timings stored in the pallet, so that the session
+/// period can change without a hard fork.
+pub struct SessionTimingRotation<T>(PhantomData<T>);
+
+fn block_number<T: Config>(now: BlockNumberFor<T>) -> BlockNumber {
+    now.unique_saturated_into()
+}
+
+impl<T: Config> pallet_session::ShouldEndSession<BlockNumberFor<T>> for SessionTimingRotation<T> {
+    fn should_end_session(now: BlockNumberFor<T>) -> bool {
+        let now = block_number::<T>(now);
+        let timings = Pallet::<T>::session_timings();
+        timings.first_block_of_session(timings.session_of_block(now)) == now
+    }
+}
+
+impl<T: Config> EstimateNextSessionRotation<BlockNumberFor<T>> for SessionTimingRotation<T> {
+    fn average_session_length() -> BlockNumberFor<T> {
+        Pallet::<T>::current_session_timing().session_period.into()
+    }
+
+    fn estimate_current_session_progress(now: BlockNumberFor<T>) -> (Option<Permill>, Weight) {
+        let now = block_number::<T>(now);
+        let timings = Pallet::<T>::session_timings();
+        let session = timings.session_of_block(now);
+        let elapsed = now - timings.first_block_of_session(session) + 1;
+        let period = timings.of_session(session).session_period;
+        (
+            Some(Permill::from_rational(elapsed, period)),
+            T::DbWeight::get().reads(1),
+        )
+    }
+
+    fn estimate_next_session_rotation(
+        now: BlockNumberFor<T>,
+    ) -> (Option<BlockNumberFor<T>>, Weight) {
+        let block = block_number::<T>(now);
+        let timings = Pallet::<T>::session_timings();
+        let session = timings.session_of_block(block);
+        let next_rotation = match timings.first_block_of_session(session) == block {
+            true => block,
+            false => timings.first_block_of_session(session.saturating_add(1)),
+        };
+        (Some(next_rotation.into()), T::DbWeight::get().reads(1))
+    }
+}
+
+/// The period of the current session.
+pub struct CurrentSessionPeriod<T>(PhantomData<T>);
+
+impl<T: Config> Get<u32> for CurrentSessionPeriod<T> {
+    fn get() -> u32 {
+        Pallet::<T>::current_session_timing().session_period
+    }
+}
+
+/// Half of the block time in force at the current block, to be used as the minimum period of the
+/// timestamp pallet, from which Aura derives the slot duration.
+pub struct MinimumTimestampPeriod<T>(PhantomData<T>);
+
+impl<T: Config> Get<u64> for MinimumTimestampPeriod<T> {
+    fn get() -> u64 {
+        let now = block_number::<T>(frame_system::Pallet::<T>::block_number());
+        Pallet::<T>::session_timings()
+            .at_block(now)
+            .millisecs_per_block
+            .saturating_div(2)
+    }
+}
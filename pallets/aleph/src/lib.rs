@@ -9,18 +9,22 @@ mod tests;
 mod impls;
 mod traits;
 
+pub use impls::{CurrentSessionPeriod, MinimumTimestampPeriod, SessionTimingRotation};
+
 use frame_support::{
     sp_runtime::BoundToRuntimeAppPublic,
     traits::{OneSessionHandler, StorageVersion},
 };
 pub use pallet::*;
 use primitives::{
-    SessionIndex, Version, VersionChange, DEFAULT_FINALITY_VERSION, LEGACY_FINALITY_VERSION,
+    AbftConfig, AbftConfigChange, AuthoringBackoffConfig, BlsPublicKey, EmergencyFinalizers,
+    SessionIndex, SessionTiming, SessionTimings, Version, VersionChange, DEFAULT_FINALITY_VERSION,
+    LEGACY_FINALITY_VERSION,
 };
 use sp_std::prelude::*;
 
 /// The current storage version.
-const STORAGE_VERSION: StorageVersion = StorageVersion::new(2);
+const STORAGE_VERSION: StorageVersion = StorageVersion::new(3);
 pub(crate) const LOG_TARGET: &str = "pallet-aleph";
 
 #[frame_support::pallet]
@@ -47,6 +51,8 @@ pub mod pallet {
         type SessionInfoProvider: SessionInfoProvider<BlockNumberFor<Self>>;
         type SessionManager: SessionManager<<Self as frame_system::Config>::AccountId>;
         type NextSessionAuthorityProvider: NextSessionAuthorityProvider<Self>;
+        /// Session timing changes start at era boundaries, which are multiples of this.
+        type SessionsPerEra: Get<SessionIndex>;
     }
 
     #[pallet::event]
@@ -55,6 +61,11 @@ pub mod pallet {
         ChangeEmergencyFinalizer(T::AuthorityId),
         ScheduleFinalityVersionChange(VersionChange),
         FinalityVersionChange(VersionChange),
+        ScheduleAbftConfigChange(AbftConfigChange),
+        AbftConfigChange(AbftConfigChange),
+        ChangeEmergencyFinalizers(EmergencyFinalizers<T::AuthorityId>),
+        AuthoringBackoffConfigChange(AuthoringBackoffConfig),
+        ScheduleSessionTimingChange(SessionTiming),
     }
 
     #[pallet::pallet]
@@ -68,6 +79,18 @@ pub mod pallet {
         DEFAULT_FINALITY_VERSION
     }
 
+    /// Default AlephBFT config. Relevant for sessions before the first config change occurs.
+    #[pallet::type_value]
+    pub(crate) fn DefaultAbftConfig() -> AbftConfig {
+        AbftConfig::default()
+    }
+
+    /// Default session timings, the compile time constants applying from the genesis.
+    #[pallet::type_value]
+    pub(crate) fn DefaultSessionTimings() -> SessionTimings {
+        SessionTimings::default()
+    }
+
     /// Default value for `NextAuthorities` storage.
     #[pallet::type_value]
     pub(crate) fn DefaultNextAuthorities<T: Config>() -> Vec<T::AuthorityId> {
@@ -99,6 +122,22 @@ pub mod pallet {
     #[pallet::storage]
     type NextEmergencyFinalizer<T: Config> = StorageValue<_, T::AuthorityId, OptionQuery>;
 
+    /// Emergency finalization keys with a threshold, an alternative to the single emergency
+    /// finalizer. Rotated the same way as `EmergencyFinalizer`.
+    #[pallet::storage]
+    #[pallet::getter(fn emergency_finalizers)]
+    pub(super) type EmergencyFinalizerSet<T: Config> =
+        StorageValue<_, EmergencyFinalizers<T::AuthorityId>, OptionQuery>;
+
+    #[pallet::storage]
+    #[pallet::getter(fn queued_emergency_finalizers)]
+    pub(super) type QueuedEmergencyFinalizerSet<T: Config> =
+        StorageValue<_, EmergencyFinalizers<T::AuthorityId>, OptionQuery>;
+
+    #[pallet::storage]
+    type NextEmergencyFinalizerSet<T: Config> =
+        StorageValue<_, EmergencyFinalizers<T::AuthorityId>, OptionQuery>;
+
     /// Current finality version.
     #[pallet::storage]
     #[pallet::getter(fn finality_version)]
@@ -111,6 +150,41 @@ pub mod pallet {
     pub(super) type FinalityScheduledVersionChange<T: Config> =
         StorageValue<_, VersionChange, OptionQuery>;
 
+    /// Current AlephBFT config.
+    #[pallet::storage]
+    #[pallet::getter(fn abft_config)]
+    pub(super) type CurrentAbftConfig<T: Config> =
+        StorageValue<_, AbftConfig, ValueQuery, DefaultAbftConfig>;
+
+    /// Scheduled AlephBFT config change.
+    #[pallet::storage]
+    #[pallet::getter(fn abft_config_change)]
+    pub(super) type AbftScheduledConfigChange<T: Config> =
+        StorageValue<_, AbftConfigChange, OptionQuery>;
+
+    /// Parameters of slowing down block production when finalization falls behind. Until root
+    /// sets them, nodes stop block production at their local nonfinalized blocks limit.
+    #[pallet::storage]
+    #[pallet::getter(fn authoring_backoff_config)]
+    pub(super) type AuthoringBackoff<T: Config> =
+        StorageValue<_, AuthoringBackoffConfig, OptionQuery>;
+
+    /// Session periods and block times since the genesis, including the scheduled changes.
+    #[pallet::storage]
+    #[pallet::getter(fn session_timings)]
+    pub(super) type SessionTimingSchedule<T: Config> =
+        StorageValue<_, SessionTimings, ValueQuery, DefaultSessionTimings>;
+
+    /// BLS session keys of the current committee, in the order of `Authorities`.
+    #[pallet::storage]
+    #[pallet::getter(fn bls_keys)]
+    pub(super) type BlsKeys<T: Config> = StorageValue<_, Vec<BlsPublicKey>, ValueQuery>;
+
+    /// BLS session keys of the committee of the next session, in the order of `NextAuthorities`.
+    #[pallet::storage]
+    #[pallet::getter(fn next_session_bls_keys)]
+    pub(super) type NextBlsKeys<T: Config> = StorageValue<_, Vec<BlsPublicKey>, ValueQuery>;
+
     impl<T: Config> Pallet<T> {
         pub(crate) fn initialize_authorities(
             authorities: &[T::AuthorityId],
@@ -129,40 +203,50 @@ pub mod pallet {
             }
         }
 
-        fn get_authorities_for_next_session(
-            next_authorities: Vec<(&T::AccountId, T::AuthorityId)>,
-        ) -> Vec<T::AuthorityId> {
+        fn get_keys_for_next_session<K>(next_keys: Vec<(&T::AccountId, K)>) -> Vec<K> {
             let next_committee_ids: BTreeSet<_> =
                 NextFinalityCommittee::<T>::get().into_iter().collect();
 
-            let next_committee_authorities: Vec<_> = next_authorities
+            let next_committee_keys: Vec<_> = next_keys
                 .into_iter()
-                .filter_map(|(account_id, auth_id)| {
+                .filter_map(|(account_id, key)| {
                     if next_committee_ids.contains(account_id) {
-                        Some(auth_id)
+                        Some(key)
                     } else {
                         None
                     }
                 })
                 .collect();
 
-            if next_committee_authorities.len() != next_committee_ids.len() {
+            if next_committee_keys.len() != next_committee_ids.len() {
                 log::error!(
                     target: LOG_TARGET,
                     "Not all committee members were converted to keys."
                 );
             }
 
-            next_committee_authorities
+            next_committee_keys
         }
 
         pub(crate) fn update_authorities(next_authorities: Vec<(&T::AccountId, T::AuthorityId)>) {
-            let next_authorities = Self::get_authorities_for_next_session(next_authorities);
+            let next_authorities = Self::get_keys_for_next_session(next_authorities);
 
             <Authorities<T>>::put(<NextAuthorities<T>>::get());
             <NextAuthorities<T>>::put(next_authorities);
         }
 
+        pub(crate) fn initialize_bls_keys(keys: &[BlsPublicKey]) {
+            <BlsKeys<T>>::put(keys);
+            <NextBlsKeys<T>>::put(keys);
+        }
+
+        pub(crate) fn update_bls_keys(next_keys: Vec<(&T::AccountId, BlsPublicKey)>) {
+            let next_keys = Self::get_keys_for_next_session(next_keys);
+
+            <BlsKeys<T>>::put(<NextBlsKeys<T>>::get());
+            <NextBlsKeys<T>>::put(next_keys);
+        }
+
         pub(crate) fn update_emergency_finalizer() {
             if let Some(emergency_finalizer) = <QueuedEmergencyFinalizer<T>>::get() {
                 <EmergencyFinalizer<T>>::put(emergency_finalizer)
@@ -171,12 +255,26 @@ pub mod pallet {
             if let Some(emergency_finalizer) = <NextEmergencyFinalizer<T>>::get() {
                 <QueuedEmergencyFinalizer<T>>::put(emergency_finalizer)
             }
+
+            if let Some(emergency_finalizers) = <QueuedEmergencyFinalizerSet<T>>::get() {
+                <EmergencyFinalizerSet<T>>::put(emergency_finalizers)
+            }
+
+            if let Some(emergency_finalizers) = <NextEmergencyFinalizerSet<T>>::get() {
+                <QueuedEmergencyFinalizerSet<T>>::put(emergency_finalizers)
+            }
         }
 
         pub(crate) fn set_next_emergency_finalizer(emergency_finalizer: T::AuthorityId) {
             <NextEmergencyFinalizer<T>>::put(emergency_finalizer);
         }
 
+        pub(crate) fn set_next_emergency_finalizers(
+            emergency_finalizers: EmergencyFinalizers<T::AuthorityId>,
+        ) {
+            <NextEmergencyFinalizerSet<T>>::put(emergency_finalizers);
+        }
+
         pub(crate) fn current_session() -> u32 {
             T::SessionInfoProvider::current_session()
         }
@@ -219,6 +317,89 @@ pub mod pallet {
 
             Self::finality_version()
         }
+
+        // Works just like scheduling finality version changes. To cancel a scheduled config
+        // change, reschedule it with the current config.
+        pub(crate) fn do_schedule_abft_config_change(
+            config_change: AbftConfigChange,
+        ) -> Result<(), &'static str> {
+            let current_session = Self::current_session();
+
+            let session_to_schedule = config_change.session;
+
+            if session_to_schedule < current_session {
+                return Err("Cannot schedule AlephBFT config changes for sessions in the past!");
+            } else if session_to_schedule < current_session + 2 {
+                return Err(
+                    "Tried to schedule an AlephBFT config change less than 2 sessions in advance!",
+                );
+            }
+
+            if !config_change.config_incoming.is_valid() {
+                return Err("AlephBFT config would allow sessions to run out of rounds!");
+            }
+
+            <AbftScheduledConfigChange<T>>::put(config_change);
+
+            Ok(())
+        }
+
+        pub fn next_session_abft_config() -> AbftConfig {
+            let next_session = Self::current_session() + 1;
+
+            if let Some(config_change) = Self::abft_config_change() {
+                if next_session == config_change.session {
+                    return config_change.config_incoming;
+                }
+            }
+
+            Self::abft_config()
+        }
+
+        /// Schedules the session period and block time to change from `timing.first_session` on,
+        /// replacing changes scheduled for that session or later. The first block of the session
+        /// is filled in from the current schedule.
+        pub(crate) fn do_schedule_session_timing_change(
+            session: SessionIndex,
+            session_period: u32,
+            millisecs_per_block: u64,
+        ) -> Result<SessionTiming, &'static str> {
+            let current_session = Self::current_session();
+
+            if session < current_session {
+                return Err("Cannot schedule session timing changes for sessions in the past!");
+            } else if session < current_session + 2 {
+                return Err(
+                    "Tried to schedule a session timing change less than 2 sessions in advance!",
+                );
+            }
+
+            if session % T::SessionsPerEra::get() != 0 {
+                return Err("Session timing changes have to start at an era boundary!");
+            }
+
+            let mut timings = Self::session_timings();
+            if millisecs_per_block > timings.of_session(session - 1).millisecs_per_block {
+                return Err("Block time cannot grow, as Aura slot numbers would decrease!");
+            }
+
+            timings.schedule(session, session_period, millisecs_per_block);
+            let timing = *timings.of_session(session);
+            if !timing.is_valid() {
+                return Err(
+                    "Sessions have to be nonempty and block time an even number of milliseconds!",
+                );
+            }
+
+            <SessionTimingSchedule<T>>::put(timings);
+
+            Ok(timing)
+        }
+
+        /// The timing of the current session.
+        pub fn current_session_timing() -> SessionTiming {
+            *Self::session_timings().of_session(Self::current_session())
+        }
     }
 
     #[pallet::call]
@@ -264,6 +445,103 @@ pub mod pallet {
             Self::deposit_event(Event::ScheduleFinalityVersionChange(version_change));
             Ok(())
         }
+
+        /// Schedules a change of the AlephBFT config for a future session, so that all the
+        /// members of the committee use the same parameters. Follows the same rules as
+        /// `schedule_finality_version_change`. Configs with which a session could run out of
+        /// rounds are rejected.
+        #[pallet::call_index(2)]
+        #[pallet::weight((T::BlockWeights::get().max_block, DispatchClass::Operational))]
+        pub fn schedule_abft_config_change(
+            origin: OriginFor<T>,
+            config_incoming: AbftConfig,
+            session: SessionIndex,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            let config_change = AbftConfigChange {
+                config_incoming,
+                session,
+            };
+
+            if let Err(e) = Self::do_schedule_abft_config_change(config_change.clone()) {
+                return Err(DispatchError::Other(e));
+            }
+
+            Self::deposit_event(Event::ScheduleAbftConfigChange(config_change));
+            Ok(())
+        }
+
+        /// Sets the emergency finalization keys, `threshold` of which have to sign a block to
+        /// finalize it. Works alongside the single emergency finalizer and follows the same
+        /// schedule: if called in session `N` the keys can be used to finalize blocks from
+        /// session `N+2` onwards, until they get overridden.
+        #[pallet::call_index(4)]
+        #[pallet::weight((T::BlockWeights::get().max_block, DispatchClass::Operational))]
+        pub fn set_emergency_finalizers(
+            origin: OriginFor<T>,
+            keys: Vec<T::AuthorityId>,
+            threshold: u32,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            let emergency_finalizers = EmergencyFinalizers { keys, threshold };
+            if !emergency_finalizers.is_valid() {
+                return Err(DispatchError::Other(
+                    "Emergency finalizer keys have to be distinct and the threshold reachable!",
+                ));
+            }
+
+            Self::set_next_emergency_finalizers(emergency_finalizers.clone());
+            Self::deposit_event(Event::ChangeEmergencyFinalizers(emergency_finalizers));
+            Ok(())
+        }
+
+        /// Sets the parameters of slowing down block production when finalization falls behind.
+        /// Nodes read them from the best block, so they apply as soon as the block is imported.
+        #[pallet::call_index(5)]
+        #[pallet::weight((T::BlockWeights::get().max_block, DispatchClass::Operational))]
+        pub fn set_authoring_backoff_config(
+            origin: OriginFor<T>,
+            config: AuthoringBackoffConfig,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            if !config.is_valid() {
+                return Err(DispatchError::Other(
+                    "Block production has to slow down before it stops!",
+                ));
+            }
+
+            <AuthoringBackoff<T>>::put(config);
+            Self::deposit_event(Event::AuthoringBackoffConfigChange(config));
+            Ok(())
+        }
+
+        /// Schedules a change of the session period and block time, starting at `session`, which
+        /// has to be the first session of an era at least 2 sessions in advance. Changes scheduled
+        /// for that session or later are replaced. Block time can only shrink, since Aura slot
+        /// numbers have to keep increasing.
+        #[pallet::call_index(6)]
+        #[pallet::weight((T::BlockWeights::get().max_block, DispatchClass::Operational))]
+        pub fn schedule_session_timing_change(
+            origin: OriginFor<T>,
+            session: SessionIndex,
+            session_period: u32,
+            millisecs_per_block: u64,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+
+            let timing = Self::do_schedule_session_timing_change(
+                session,
+                session_period,
+                millisecs_per_block,
+            )
+            .map_err(DispatchError::Other)?;
+
+            Self::deposit_event(Event::ScheduleSessionTimingChange(timing));
+            Ok(())
+        }
     }
 
     impl<T: Config> BoundToRuntimeAppPublic for Pallet<T> {
@@ -297,6 +575,39 @@ pub mod pallet {
         fn on_disabled(_validator_index: u32) {}
     }
 
+    /// The session handler of the BLS session keys, which keeps them in the same order as the
+    /// authorities of the committees.
+    pub struct BlsSessionKeys<T>(sp_std::marker::PhantomData<T>);
+
+    impl<T: Config> BoundToRuntimeAppPublic for BlsSessionKeys<T> {
+        type Public = BlsPublicKey;
+    }
+
+    impl<T: Config> OneSessionHandler<T::AccountId> for BlsSessionKeys<T> {
+        type Key = BlsPublicKey;
+
+        fn on_genesis_session<'a, I: 'a>(validators: I)
+        where
+            I: Iterator<Item = (&'a T::AccountId, BlsPublicKey)>,
+            T::AccountId: 'a,
+        {
+            let (_, keys): (Vec<_>, Vec<_>) = validators.unzip();
+            Pallet::<T>::initialize_bls_keys(keys.as_slice());
+        }
+
+        fn on_new_session<'a, I: 'a>(changed: bool, _: I, queued_validators: I)
+        where
+            I: Iterator<Item = (&'a T::AccountId, BlsPublicKey)>,
+            T::AccountId: 'a,
+        {
+            if changed {
+                Pallet::<T>::update_bls_keys(queued_validators.collect());
+            }
+        }
+
+        fn on_disabled(_validator_index: u32) {}
+    }
+
     #[pallet::genesis_config]
     pub struct GenesisConfig<T: Config> {
         pub finality_version: Version,
@@ -8,7 +8,7 @@ use frame_support::{
     weights::{RuntimeDbWeight, Weight},
 };
 use frame_system::pallet_prelude::BlockNumberFor;
-use primitives::{AuthorityId, SessionInfoProvider};
+use primitives::{AuthorityId, BlsPublicKey, BlsSecretKey, SessionInfoProvider};
 use sp_core::H256;
 use sp_runtime::{
     impl_opaque_keys,
@@ -37,6 +37,7 @@ construct_runtime!(
 impl_opaque_keys! {
     pub struct TestSessionKeys {
         pub aleph: super::Pallet<Test>,
+        pub bls: super::BlsSessionKeys<Test>,
     }
 }
 
@@ -79,6 +80,7 @@ impl frame_system::Config for Test {
 parameter_types! {
     pub const Period: u64 = 1;
     pub const Offset: u64 = 0;
+    pub const SessionsPerEra: u32 = 2;
 }
 
 parameter_types! {
@@ -153,6 +155,7 @@ impl Config for Test {
     type SessionInfoProvider = SessionInfoImpl;
     type SessionManager = ();
     type NextSessionAuthorityProvider = Session;
+    type SessionsPerEra = SessionsPerEra;
 }
 
 pub fn to_authority(id: &u64) -> AuthorityId {
@@ -163,6 +166,14 @@ pub fn to_authorities(authorities: &[u64]) -> Vec<AuthorityId> {
     authorities.iter().map(to_authority).collect()
 }
 
+pub fn to_bls_key(id: &u64) -> BlsPublicKey {
+    BlsSecretKey::from_seed(&id.to_le_bytes()).public()
+}
+
+pub fn to_bls_keys(ids: &[u64]) -> Vec<BlsPublicKey> {
+    ids.iter().map(to_bls_key).collect()
+}
+
 pub fn new_session_validators(validators: &[u64]) -> impl Iterator<Item = (&u64, AuthorityId)> {
     validators
         .iter()
@@ -185,9 +196,17 @@ pub fn new_test_ext(authorities: &[(u64, u64)]) -> sp_io::TestExternalities {
 
     let session_keys: Vec<_> = authorities
         .iter()
-        .map(|(id, weight)| (UintAuthorityId(*id).to_public_key::<AuthorityId>(), weight))
         .enumerate()
-        .map(|(i, (k, _))| (i as u64, i as u64, TestSessionKeys { aleph: k }))
+        .map(|(i, (id, _))| {
+            (
+                i as u64,
+                i as u64,
+                TestSessionKeys {
+                    aleph: UintAuthorityId(*id).to_public_key::<AuthorityId>(),
+                    bls: to_bls_key(id),
+                },
+            )
+        })
         .collect();
 
     pallet_session::GenesisConfig::<Test> { keys: session_keys }
@@ -1,9 +1,19 @@
 #![cfg(test)]
 
-use frame_support::{storage_alias, traits::OneSessionHandler};
-use primitives::VersionChange;
-
-use crate::{mock::*, NextFinalityCommittee};
+use frame_support::{
+    assert_ok, storage_alias,
+    traits::{EstimateNextSessionRotation, Get, OneSessionHandler},
+};
+use pallet_session::ShouldEndSession;
+use primitives::{
+    AbftConfig, AbftConfigChange, AuthoringBackoffConfig, EmergencyFinalizers, SessionTiming,
+    SessionTimings, VersionChange, DEFAULT_SESSION_PERIOD, MILLISECS_PER_BLOCK,
+};
+
+use crate::{
+    mock::*, BlsSessionKeys, CurrentSessionPeriod, MinimumTimestampPeriod, NextFinalityCommittee,
+    SessionTimingRotation,
+};
 
 #[storage_alias]
 type SessionForValidatorsChange = StorageValue<Aleph, u32>;
@@ -112,6 +122,254 @@ fn test_emergency_signer() {
     })
 }
 
+#[test]
+fn test_emergency_finalizers() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+
+        run_session(1);
+
+        assert_ok!(Aleph::set_emergency_finalizers(
+            RuntimeOrigin::root(),
+            to_authorities(&[21, 22, 23]),
+            2
+        ));
+
+        assert_eq!(Aleph::emergency_finalizers(), None);
+        assert_eq!(Aleph::queued_emergency_finalizers(), None);
+
+        run_session(2);
+
+        assert_eq!(Aleph::emergency_finalizers(), None);
+        assert_eq!(
+            Aleph::queued_emergency_finalizers(),
+            Some(EmergencyFinalizers {
+                keys: to_authorities(&[21, 22, 23]),
+                threshold: 2
+            })
+        );
+
+        run_session(3);
+
+        assert_eq!(
+            Aleph::emergency_finalizers(),
+            Some(EmergencyFinalizers {
+                keys: to_authorities(&[21, 22, 23]),
+                threshold: 2
+            })
+        );
+    })
+}
+
+#[test]
+fn test_invalid_emergency_finalizers_are_rejected() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+
+        run_session(1);
+
+        for (keys, threshold) in [
+            (to_authorities(&[21, 22]), 0),
+            (to_authorities(&[21, 22]), 3),
+            (to_authorities(&[21, 21]), 2),
+        ] {
+            assert!(
+                Aleph::set_emergency_finalizers(RuntimeOrigin::root(), keys, threshold).is_err()
+            );
+        }
+        assert!(Aleph::set_emergency_finalizers(
+            RuntimeOrigin::signed(1),
+            to_authorities(&[21, 22]),
+            1
+        )
+        .is_err());
+
+        run_session(2);
+
+        assert_eq!(Aleph::queued_emergency_finalizers(), None);
+    })
+}
+
+#[test]
+fn test_authoring_backoff_config() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+
+        run_session(1);
+
+        assert_eq!(Aleph::authoring_backoff_config(), None);
+
+        let config = AuthoringBackoffConfig {
+            slowdown_start: 5,
+            blocks_per_skipped_slot: 1,
+            max_nonfinalized_blocks: 30,
+        };
+        assert!(Aleph::set_authoring_backoff_config(RuntimeOrigin::signed(1), config).is_err());
+        for invalid_config in [
+            AuthoringBackoffConfig {
+                blocks_per_skipped_slot: 0,
+                ..config
+            },
+            AuthoringBackoffConfig {
+                slowdown_start: 31,
+                ..config
+            },
+        ] {
+            assert!(
+                Aleph::set_authoring_backoff_config(RuntimeOrigin::root(), invalid_config).is_err()
+            );
+        }
+        assert_eq!(Aleph::authoring_backoff_config(), None);
+
+        assert_ok!(Aleph::set_authoring_backoff_config(
+            RuntimeOrigin::root(),
+            config
+        ));
+        assert_eq!(Aleph::authoring_backoff_config(), Some(config));
+    })
+}
+
+#[test]
+fn test_session_timing_scheduling() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+
+        run_session(1);
+
+        assert_eq!(Aleph::session_timings(), SessionTimings::default());
+        assert_eq!(CurrentSessionPeriod::<Test>::get(), DEFAULT_SESSION_PERIOD);
+
+        assert!(Aleph::schedule_session_timing_change(
+            RuntimeOrigin::signed(1),
+            4,
+            10,
+            MILLISECS_PER_BLOCK
+        )
+        .is_err());
+        for (session, session_period, millisecs_per_block) in [
+            // Too early.
+            (2, 10, MILLISECS_PER_BLOCK),
+            // Not at an era boundary.
+            (5, 10, MILLISECS_PER_BLOCK),
+            // Slower blocks.
+            (4, 10, 2 * MILLISECS_PER_BLOCK),
+            // Empty sessions.
+            (4, 0, MILLISECS_PER_BLOCK),
+            // Odd block time.
+            (4, 10, MILLISECS_PER_BLOCK - 1),
+        ] {
+            assert!(Aleph::schedule_session_timing_change(
+                RuntimeOrigin::root(),
+                session,
+                session_period,
+                millisecs_per_block
+            )
+            .is_err());
+        }
+        assert_eq!(Aleph::session_timings(), SessionTimings::default());
+
+        assert_ok!(Aleph::schedule_session_timing_change(
+            RuntimeOrigin::root(),
+            4,
+            10,
+            MILLISECS_PER_BLOCK
+        ));
+        let first_block = 4 * DEFAULT_SESSION_PERIOD;
+        let timings = Aleph::session_timings();
+        assert_eq!(
+            timings.timings()[1],
+            SessionTiming {
+                first_session: 4,
+                first_block,
+                session_period: 10,
+                millisecs_per_block: MILLISECS_PER_BLOCK,
+            }
+        );
+        assert_eq!(timings.session_of_block(first_block - 1), 3);
+        assert_eq!(timings.session_of_block(first_block + 25), 6);
+        assert_eq!(timings.first_block_of_session(6), first_block + 20);
+        assert_eq!(timings.last_block_of_session(3), first_block - 1);
+
+        assert!(SessionTimingRotation::<Test>::should_end_session(
+            first_block.into()
+        ));
+        assert!(SessionTimingRotation::<Test>::should_end_session(
+            (first_block + 10).into()
+        ));
+        assert!(!SessionTimingRotation::<Test>::should_end_session(
+            (first_block + 15).into()
+        ));
+        assert_eq!(
+            SessionTimingRotation::<Test>::estimate_next_session_rotation(
+                (first_block + 15).into()
+            )
+            .0,
+            Some((first_block + 20).into())
+        );
+
+        // Rescheduling replaces the change.
+        assert_ok!(Aleph::schedule_session_timing_change(
+            RuntimeOrigin::root(),
+            6,
+            20,
+            MILLISECS_PER_BLOCK
+        ));
+        let timings = Aleph::session_timings();
+        assert_eq!(timings.timings().len(), 2);
+        assert_eq!(
+            timings.first_block_of_session(6),
+            6 * DEFAULT_SESSION_PERIOD
+        );
+    })
+}
+
+#[test]
+fn test_slots_keep_increasing_when_block_time_shrinks() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+
+        run_session(1);
+
+        let millisecs_per_block = MILLISECS_PER_BLOCK / 2;
+        assert_ok!(Aleph::schedule_session_timing_change(
+            RuntimeOrigin::root(),
+            4,
+            DEFAULT_SESSION_PERIOD,
+            millisecs_per_block
+        ));
+        let first_block = 4 * DEFAULT_SESSION_PERIOD;
+
+        // Aura slots are as long as two minimum timestamp periods, and the slot of a block is its
+        // timestamp divided by that.
+        let mut timestamp = 1_000_000 * MILLISECS_PER_BLOCK;
+        let mut last_slot = 0;
+        for block in first_block - 3..first_block + 3 {
+            System::set_block_number(block.into());
+            let slot_duration = 2 * MinimumTimestampPeriod::<Test>::get();
+            let expected_slot_duration = if block < first_block {
+                MILLISECS_PER_BLOCK
+            } else {
+                millisecs_per_block
+            };
+            assert_eq!(slot_duration, expected_slot_duration);
+
+            timestamp += slot_duration;
+            let slot = timestamp / slot_duration;
+            assert!(slot > last_slot);
+            last_slot = slot;
+        }
+
+        // Going back to slower blocks would make the slots decrease.
+        assert!(Aleph::schedule_session_timing_change(
+            RuntimeOrigin::root(),
+            6,
+            DEFAULT_SESSION_PERIOD,
+            MILLISECS_PER_BLOCK
+        )
+        .is_err());
+    })
+}
+
 #[test]
 fn test_finality_version_scheduling() {
     new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
@@ -148,3 +406,115 @@ fn test_finality_version_scheduling() {
         assert!(scheduling_result.is_err());
     })
 }
+
+#[test]
+fn test_abft_config_scheduling() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+
+        run_session(1);
+
+        assert_eq!(Aleph::abft_config(), AbftConfig::default());
+
+        let config_change = AbftConfigChange {
+            config_incoming: AbftConfig {
+                max_rounds: 8000,
+                ..AbftConfig::default()
+            },
+            session: 4,
+        };
+
+        let scheduling_result = Aleph::do_schedule_abft_config_change(config_change.clone());
+        assert_eq!(scheduling_result, Ok(()));
+        assert_eq!(Aleph::abft_config_change(), Some(config_change.clone()));
+
+        run_session(3);
+
+        assert_eq!(Aleph::abft_config(), AbftConfig::default());
+        assert_eq!(
+            Aleph::next_session_abft_config(),
+            config_change.config_incoming
+        );
+
+        run_session(4);
+
+        assert_eq!(Aleph::abft_config(), config_change.config_incoming);
+        assert_eq!(Aleph::abft_config_change(), None);
+
+        let config_change = AbftConfigChange {
+            config_incoming: AbftConfig::default(),
+            session: 5,
+        };
+
+        let scheduling_result = Aleph::do_schedule_abft_config_change(config_change);
+        assert!(scheduling_result.is_err());
+    })
+}
+
+#[test]
+fn test_abft_config_too_short_sessions_are_rejected() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+
+        run_session(1);
+
+        let config_change = AbftConfigChange {
+            config_incoming: AbftConfig {
+                max_rounds: 5000,
+                ..AbftConfig::default()
+            },
+            session: 4,
+        };
+
+        let scheduling_result = Aleph::do_schedule_abft_config_change(config_change);
+        assert!(scheduling_result.is_err());
+        assert_eq!(Aleph::abft_config_change(), None);
+    })
+}
+
+#[test]
+fn test_abft_config_empty_aggregation_batches_are_rejected() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        initialize_session();
+
+        run_session(1);
+
+        let config_change = AbftConfigChange {
+            config_incoming: AbftConfig {
+                aggregation_batch_len: 0,
+                ..AbftConfig::default()
+            },
+            session: 4,
+        };
+
+        let scheduling_result = Aleph::do_schedule_abft_config_change(config_change);
+        assert!(scheduling_result.is_err());
+        assert_eq!(Aleph::abft_config_change(), None);
+    })
+}
+
+#[test]
+fn test_bls_keys_follow_authorities() {
+    new_test_ext(&[(1u64, 1u64), (2u64, 2u64)]).execute_with(|| {
+        assert_eq!(Aleph::bls_keys(), to_bls_keys(&[1, 2]));
+        assert_eq!(Aleph::next_session_bls_keys(), to_bls_keys(&[1, 2]));
+
+        initialize_session();
+        run_session(1);
+
+        NextFinalityCommittee::<Test>::put(vec![5, 6]);
+        let queued_validators = || [4, 5, 6].iter().zip(to_bls_keys(&[4, 5, 6]));
+        BlsSessionKeys::<Test>::on_new_session(true, queued_validators(), queued_validators());
+        assert_eq!(Aleph::bls_keys(), to_bls_keys(&[1, 2]));
+        assert_eq!(Aleph::next_session_bls_keys(), to_bls_keys(&[5, 6]));
+
+        // Keys only change together with the validators.
+        BlsSessionKeys::<Test>::on_new_session(false, queued_validators(), queued_validators());
+        assert_eq!(Aleph::bls_keys(), to_bls_keys(&[1, 2]));
+        assert_eq!(Aleph::next_session_bls_keys(), to_bls_keys(&[5, 6]));
+
+        BlsSessionKeys::<Test>::on_new_session(true, queued_validators(), queued_validators());
+        assert_eq!(Aleph::bls_keys(), to_bls_keys(&[5, 6]));
+        assert_eq!(Aleph::next_session_bls_keys(), to_bls_keys(&[5, 6]));
+    })
+}
@@ -0,0 +1,472 @@
+//! BLS12-381 multisignatures, allowing a whole committee to justify a block with a single
+//! signature. Public keys live in G1 and signatures in G2.
+//!
+//! Keys are registered as session keys without proofs of possession, so to prevent rogue key
+//! attacks every key and signature is weighted by a coefficient derived from the keys of the whole
+//! committee, as in <https://eprint.iacr.org/2018/483>.
+//!
+//! The keystore only supports Substrate's own schemes, so it does not hold BLS secret keys
+//! directly. Instead it holds an ed25519 seed key of type [`BLS_KEY_TYPE`], and the secret key is
+//! derived from the (deterministic) signature of [`BLS_SEED_MESSAGE`] made with the seed key.
+//! Both the runtime, when generating session keys, and the node can derive it this way.
+
+use ark_bls12_381::{
+    g2::Config as G2Config, Bls12_381, Fr, G1Affine, G1Projective, G2Affine, G2Projective,
+};
+use ark_ec::{
+    hashing::{curve_maps::wb::WBMap, map_to_curve_hasher::MapToCurveBasedHasher, HashToCurve},
+    pairing::Pairing,
+    AffineRepr, CurveGroup, Group,
+};
+use ark_ff::{field_hashers::DefaultFieldHasher, PrimeField, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
+use scale_info::TypeInfo;
+use sha2::{Digest, Sha256, Sha512};
+use sp_application_crypto::{BoundToRuntimeAppPublic, RuntimeAppPublic};
+use sp_core::{crypto::KeyTypeId, ed25519};
+use sp_std::{vec, vec::Vec};
+
+pub const BLS_KEY_TYPE: KeyTypeId = KeyTypeId(*b"alpb");
+
+/// The message signed with the ed25519 seed key to derive the BLS secret key.
+pub const BLS_SEED_MESSAGE: &[u8] = b"aleph-bls-secret-key-seed";
+
+pub const BLS_PUBLIC_KEY_LEN: usize = 48;
+pub const BLS_SIGNATURE_LEN: usize = 96;
+
+const SIGNATURE_DOMAIN: &[u8] = b"ALEPH-BLS-SIG-V01-BLS12381G2_XMD:SHA-256_SSWU_RO_";
+const COEFFICIENT_DOMAIN: &[u8] = b"ALEPH-BLS-COEFFICIENT-V01";
+const COEFFICIENT_LEN: usize = 16;
+
+/// A BLS12-381 public key of an authority, a compressed G1 point. Used for verifying aggregated
+/// justifications of the committees the authority is a member of.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    TypeInfo,
+    Encode,
+    Decode,
+    MaxEncodedLen,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+)]
+pub struct BlsPublicKey(pub [u8; BLS_PUBLIC_KEY_LEN]);
+
+impl BlsPublicKey {
+    /// A key that is not a valid point, for validators that did not set a BLS key yet. Keys
+    /// created from different ids differ, as session keys have to be unique.
+    pub fn placeholder(id: &[u8]) -> Self {
+        let mut key = [0; BLS_PUBLIC_KEY_LEN];
+        // Without the compression flag in the first byte the key never parses.
+        let len = id.len().min(BLS_PUBLIC_KEY_LEN - 1);
+        key[1..=len].copy_from_slice(&id[..len]);
+        BlsPublicKey(key)
+    }
+
+    /// Verifies a signature of a single key, without any rogue key protection, so it is not
+    /// suitable for aggregation.
+    pub fn verify_signature(&self, message: &[u8], signature: &BlsSignature) -> bool {
+        match (parse_key(self), parse_signature(signature)) {
+            (Some(key), Some(signature)) => verify_pairing(message, key, signature),
+            _ => false,
+        }
+    }
+}
+
+impl AsRef<[u8]> for BlsPublicKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
+#[cfg(feature = "std")]
+impl serde::Serialize for BlsPublicKey {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        sp_core::bytes::serialize(&self.0, serializer)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de> serde::Deserialize<'de> for BlsPublicKey {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = sp_core::bytes::deserialize(deserializer)?;
+        bytes
+            .try_into()
+            .map(BlsPublicKey)
+            .map_err(|_| serde::de::Error::custom("a BLS public key has 48 bytes"))
+    }
+}
+
+/// A BLS signature, either of a single member or an aggregate.
+#[derive(Clone, Copy, Debug, TypeInfo, Encode, Decode, MaxEncodedLen, PartialEq, Eq, Hash)]
+pub struct BlsSignature(pub [u8; BLS_SIGNATURE_LEN]);
+
+/// A secret BLS key.
+pub struct BlsSecretKey(Fr);
+
+impl BlsSecretKey {
+    /// Derives a key from a seed, which should contain at least 32 bytes of entropy.
+    pub fn from_seed(seed: &[u8]) -> Self {
+        BlsSecretKey(Fr::from_le_bytes_mod_order(&Sha512::digest(seed)))
+    }
+
+    pub fn public(&self) -> BlsPublicKey {
+        BlsPublicKey(serialize(
+            (G1Projective::generator() * self.0).into_affine(),
+        ))
+    }
+
+    pub fn sign(&self, message: &[u8]) -> BlsSignature {
+        BlsSignature(serialize((hash_to_g2(message) * self.0).into_affine()))
+    }
+}
+
+fn secret_key_of_seed_key(seed_key: &ed25519::Public) -> Option<BlsSecretKey> {
+    sp_io::crypto::ed25519_sign(BLS_KEY_TYPE, seed_key, BLS_SEED_MESSAGE)
+        .map(|seed| BlsSecretKey::from_seed(seed.as_ref()))
+}
+
+fn secret_keys() -> impl Iterator<Item = BlsSecretKey> {
+    sp_io::crypto::ed25519_public_keys(BLS_KEY_TYPE)
+        .into_iter()
+        .filter_map(|seed_key| secret_key_of_seed_key(&seed_key))
+}
+
+impl RuntimeAppPublic for BlsPublicKey {
+    const ID: KeyTypeId = BLS_KEY_TYPE;
+
+    type Signature = BlsSignature;
+
+    fn all() -> Vec<Self> {
+        secret_keys().map(|key| key.public()).collect()
+    }
+
+    fn generate_pair(seed: Option<Vec<u8>>) -> Self {
+        let seed_key = sp_io::crypto::ed25519_generate(BLS_KEY_TYPE, seed);
+        secret_key_of_seed_key(&seed_key)
+            .expect("the seed key was just generated")
+            .public()
+    }
+
+    fn sign<M: AsRef<[u8]>>(&self, msg: &M) -> Option<Self::Signature> {
+        secret_keys()
+            .find(|key| key.public() == *self)
+            .map(|key| key.sign(msg.as_ref()))
+    }
+
+    fn verify<M: AsRef<[u8]>>(&self, msg: &M, signature: &Self::Signature) -> bool {
+        self.verify_signature(msg.as_ref(), signature)
+    }
+
+    fn to_raw_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+}
+
+impl BoundToRuntimeAppPublic for BlsPublicKey {
+    type Public = Self;
+}
+
+fn serialize<P: CanonicalSerialize, const N: usize>(point: P) -> [u8; N] {
+    let mut bytes = [0; N];
+    point
+        .serialize_compressed(&mut bytes[..])
+        .expect("compressed points have a constant length");
+    bytes
+}
+
+fn hash_to_g2(message: &[u8]) -> G2Affine {
+    MapToCurveBasedHasher::<G2Projective, DefaultFieldHasher<Sha256>, WBMap<G2Config>>::new(
+        SIGNATURE_DOMAIN,
+    )
+    .and_then(|hasher| hasher.hash(message))
+    .expect("hashing to G2 works for all messages")
+}
+
+fn verify_pairing(message: &[u8], key: G1Affine, signature: G2Affine) -> bool {
+    Bls12_381::pairing(G1Affine::generator(), signature)
+        == Bls12_381::pairing(key, hash_to_g2(message))
+}
+
+fn parse_key(key: &BlsPublicKey) -> Option<G1Affine> {
+    // The identity would allow anyone to sign in the name of the member.
+    G1Affine::deserialize_compressed(&key.0[..])
+        .ok()
+        .filter(|key| !key.is_zero())
+}
+
+fn parse_signature(signature: &BlsSignature) -> Option<G2Affine> {
+    G2Affine::deserialize_compressed(&signature.0[..]).ok()
+}
+
+fn bitmap_len(n_members: usize) -> usize {
+    (n_members + 7) / 8
+}
+
+/// A signature aggregated from the signatures of a subset of a committee.
+#[derive(Clone, Debug, TypeInfo, Encode, Decode, PartialEq, Eq)]
+pub struct BlsMultisignature {
+    /// A bitmap of the indices of the signers.
+    signers: Vec<u8>,
+    signature: BlsSignature,
+}
+
+impl BlsMultisignature {
+    /// The indices of the members whose signatures were aggregated.
+    pub fn signers(&self) -> impl Iterator<Item = usize> + '_ {
+        self.signers
+            .iter()
+            .enumerate()
+            .flat_map(|(byte_index, byte)| {
+                (0..8)
+                    .filter(move |bit| byte & (1 << bit) != 0)
+                    .map(move |bit| byte_index * 8 + bit)
+            })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Member {
+    coefficient: Fr,
+    /// The public key multiplied by the coefficient.
+    weighted_key: G1Affine,
+}
+
+/// Holds the BLS keys of a committee, allowing for aggregation and verification of its signatures.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlsVerifier {
+    /// Members whose key is not a valid point are `None`.
+    members: Vec<Option<Member>>,
+}
+
+impl BlsVerifier {
+    /// Constructs a verifier from the keys of the committee, in the order of the committee.
+    pub fn new(keys: &[BlsPublicKey]) -> Self {
+        let mut commitment = Sha256::new();
+        commitment.update(COEFFICIENT_DOMAIN);
+        for key in keys {
+            commitment.update(key.0);
+        }
+        let commitment = commitment.finalize();
+
+        let members = keys
+            .iter()
+            .enumerate()
+            .map(|(index, key)| {
+                let key = parse_key(key)?;
+                let mut hasher = Sha256::new();
+                hasher.update(commitment);
+                hasher.update((index as u64).to_le_bytes());
+                let coefficient =
+                    Fr::from_le_bytes_mod_order(&hasher.finalize()[..COEFFICIENT_LEN]);
+                Some(Member {
+                    coefficient,
+                    weighted_key: (key * coefficient).into_affine(),
+                })
+            })
+            .collect();
+        BlsVerifier { members }
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.members.len()
+    }
+
+    fn threshold(&self) -> usize {
+        2 * self.node_count() / 3 + 1
+    }
+
+    /// Whether enough members have valid keys to ever produce a complete multisignature.
+    pub fn can_complete(&self) -> bool {
+        self.members.iter().flatten().count() >= self.threshold()
+    }
+
+    fn member(&self, index: usize) -> Option<&Member> {
+        self.members.get(index)?.as_ref()
+    }
+
+    /// Verifies a signature of a single member of the committee.
+    pub fn verify(&self, message: &[u8], signature: &BlsSignature, index: usize) -> bool {
+        let (member, signature) = match (self.member(index), parse_signature(signature)) {
+            (Some(member), Some(signature)) => (member, signature),
+            _ => return false,
+        };
+        verify_pairing(
+            message,
+            member.weighted_key,
+            (signature * member.coefficient).into_affine(),
+        )
+    }
+
+    /// Aggregates signatures of members of the committee, without verifying them. Returns `None`
+    /// if any of the signatures is malformed or comes from an unknown member.
+    pub fn aggregate<I: IntoIterator<Item = (usize, BlsSignature)>>(
+        &self,
+        signatures: I,
+    ) -> Option<BlsMultisignature> {
+        let mut signers = vec![0; bitmap_len(self.members.len())];
+        let mut aggregate = G2Projective::zero();
+        for (index, signature) in signatures {
+            let member = self.member(index)?;
+            let signature = parse_signature(&signature)?;
+            let (byte, bit) = (index / 8, index % 8);
+            if signers[byte] & (1 << bit) != 0 {
+                continue;
+            }
+            signers[byte] |= 1 << bit;
+            aggregate += signature * member.coefficient;
+        }
+        Some(BlsMultisignature {
+            signers,
+            signature: BlsSignature(serialize(aggregate.into_affine())),
+        })
+    }
+
+    /// Verifies whether the given multisignature is correct and complete. Completeness requires
+    /// more than 2/3 of all members of the committee, including the ones without valid keys.
+    pub fn is_complete(&self, message: &[u8], multisignature: &BlsMultisignature) -> bool {
+        if multisignature.signers.len() != bitmap_len(self.members.len()) {
+            return false;
+        }
+        let mut signer_count = 0;
+        let mut aggregate_key = G1Projective::zero();
+        for index in multisignature.signers() {
+            match self.member(index) {
+                Some(member) => aggregate_key += member.weighted_key,
+                None => return false,
+            }
+            signer_count += 1;
+        }
+        if signer_count < self.threshold() {
+            return false;
+        }
+        match parse_signature(&multisignature.signature) {
+            Some(signature) => verify_pairing(message, aggregate_key.into_affine(), signature),
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use parity_scale_codec::{Decode, Encode};
+
+    use super::{BlsMultisignature, BlsPublicKey, BlsSecretKey, BlsSignature, BlsVerifier};
+
+    const MESSAGE: &[u8] = b"block hash";
+
+    fn prepare_test(n_members: usize) -> (Vec<BlsSecretKey>, BlsVerifier) {
+        let secret_keys: Vec<_> = (0..n_members)
+            .map(|i| BlsSecretKey::from_seed(format!("//{i}").as_bytes()))
+            .collect();
+        let public_keys: Vec<_> = secret_keys.iter().map(|key| key.public()).collect();
+        (secret_keys, BlsVerifier::new(&public_keys))
+    }
+
+    fn sign_by(secret_keys: &[BlsSecretKey], indices: &[usize]) -> Vec<(usize, BlsSignature)> {
+        indices
+            .iter()
+            .map(|i| (*i, secret_keys[*i].sign(MESSAGE)))
+            .collect()
+    }
+
+    #[test]
+    fn verifies_single_signatures() {
+        let (secret_keys, verifier) = prepare_test(4);
+        let signature = secret_keys[1].sign(MESSAGE);
+        assert!(verifier.verify(MESSAGE, &signature, 1));
+        assert!(!verifier.verify(MESSAGE, &signature, 2));
+        assert!(!verifier.verify(b"other", &signature, 1));
+        assert!(secret_keys[1]
+            .public()
+            .verify_signature(MESSAGE, &signature));
+        assert!(!secret_keys[2]
+            .public()
+            .verify_signature(MESSAGE, &signature));
+    }
+
+    #[test]
+    fn accepts_complete_multisignature() {
+        let (secret_keys, verifier) = prepare_test(10);
+        let multisignature = verifier
+            .aggregate(sign_by(&secret_keys, &[0, 2, 3, 4, 5, 6, 8, 9]))
+            .expect("signatures are well formed");
+        assert_eq!(
+            multisignature.signers().collect::<Vec<_>>(),
+            vec![0, 2, 3, 4, 5, 6, 8, 9]
+        );
+        assert!(verifier.is_complete(MESSAGE, &multisignature));
+        let decoded = BlsMultisignature::decode(&mut &multisignature.encode()[..])
+            .expect("encoding is correct");
+        assert!(verifier.is_complete(MESSAGE, &decoded));
+    }
+
+    #[test]
+    fn rejects_incomplete_multisignature() {
+        let (secret_keys, verifier) = prepare_test(10);
+        let multisignature = verifier
+            .aggregate(sign_by(&secret_keys, &[0, 1, 2, 3, 4, 5]))
+            .expect("signatures are well formed");
+        assert!(!verifier.is_complete(MESSAGE, &multisignature));
+    }
+
+    #[test]
+    fn rejects_multisignature_with_bad_signature() {
+        let (secret_keys, verifier) = prepare_test(4);
+        let mut signatures = sign_by(&secret_keys, &[0, 1]);
+        signatures.push((2, secret_keys[2].sign(b"other")));
+        let multisignature = verifier
+            .aggregate(signatures)
+            .expect("signatures are well formed");
+        assert!(!verifier.is_complete(MESSAGE, &multisignature));
+    }
+
+    #[test]
+    fn rejects_multisignature_with_wrong_signers() {
+        let (secret_keys, verifier) = prepare_test(4);
+        let mut multisignature = verifier
+            .aggregate(sign_by(&secret_keys, &[0, 1, 2]))
+            .expect("signatures are well formed");
+        multisignature.signers = vec![0b1011];
+        assert!(!verifier.is_complete(MESSAGE, &multisignature));
+    }
+
+    #[test]
+    fn members_with_placeholder_keys_cannot_sign() {
+        let (secret_keys, _) = prepare_test(4);
+        let mut public_keys: Vec<_> = secret_keys.iter().map(|key| key.public()).collect();
+        public_keys[3] = BlsPublicKey::placeholder(b"validator");
+        let verifier = BlsVerifier::new(&public_keys);
+        assert!(verifier.can_complete());
+        assert!(verifier
+            .aggregate(sign_by(&secret_keys, &[0, 1, 3]))
+            .is_none());
+        let multisignature = verifier
+            .aggregate(sign_by(&secret_keys, &[0, 1, 2]))
+            .expect("signatures are well formed");
+        assert!(verifier.is_complete(MESSAGE, &multisignature));
+
+        public_keys[2] = BlsPublicKey::placeholder(b"other validator");
+        assert!(!BlsVerifier::new(&public_keys).can_complete());
+    }
+
+    #[test]
+    fn rejects_identity_key() {
+        let (secret_keys, _) = prepare_test(4);
+        let mut public_keys: Vec<_> = secret_keys.iter().map(|key| key.public()).collect();
+        let mut identity = [0; 48];
+        // The compressed encoding of the point at infinity.
+        identity[0] = 0xc0;
+        public_keys[3] = BlsPublicKey(identity);
+        let verifier = BlsVerifier::new(&public_keys);
+        assert!(!verifier.verify(MESSAGE, &secret_keys[3].sign(MESSAGE), 3));
+        assert!(verifier
+            .aggregate(sign_by(&secret_keys, &[0, 1, 3]))
+            .is_none());
+    }
+}
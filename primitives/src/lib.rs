@@ -17,6 +17,13 @@ use sp_runtime::{
 pub use sp_staking::{EraIndex, SessionIndex};
 use sp_std::vec::Vec;
 
+mod bls;
+
+pub use bls::{
+    BlsMultisignature, BlsPublicKey, BlsSecretKey, BlsSignature, BlsVerifier, BLS_KEY_TYPE,
+    BLS_PUBLIC_KEY_LEN, BLS_SEED_MESSAGE, BLS_SIGNATURE_LEN,
+};
+
 pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"alp0");
 
 // Same as GRANDPA_ENGINE_ID because as of right now substrate sends only
@@ -253,6 +260,27 @@ impl SessionAuthorityData {
     }
 }
 
+/// A set of emergency finalization keys, at least `threshold` of which have to sign a block to
+/// finalize it.
+#[derive(Clone, Debug, TypeInfo, Encode, Decode, PartialEq, Eq)]
+pub struct EmergencyFinalizers<Id = AuthorityId> {
+    pub keys: Vec<Id>,
+    pub threshold: u32,
+}
+
+impl<Id: PartialEq> EmergencyFinalizers<Id> {
+    /// Whether the keys are distinct and the threshold is positive and reachable.
+    pub fn is_valid(&self) -> bool {
+        self.threshold > 0
+            && self.threshold as usize <= self.keys.len()
+            && self
+                .keys
+                .iter()
+                .enumerate()
+                .all(|(i, key)| !self.keys[..i].contains(key))
+    }
+}
+
 pub type Version = u32;
 
 #[derive(Clone, Debug, Decode, Encode, PartialEq, Eq, TypeInfo)]
@@ -261,7 +289,231 @@ pub struct VersionChange {
     pub session: SessionIndex,
 }
 
+/// Default maximal number of rounds in an AlephBFT session.
+pub const DEFAULT_ABFT_MAX_ROUNDS: u16 = 7000;
+/// Default delay before creating the first unit in an AlephBFT session.
+pub const DEFAULT_ABFT_INITIAL_DELAY_MS: u64 = 2000;
+/// Default round from which unit creation slows down exponentially.
+pub const DEFAULT_ABFT_SLOWDOWN_START_ROUND: u16 = 5000;
+/// Default base of the exponential slowdown, in thousandths.
+pub const DEFAULT_ABFT_SLOWDOWN_BASE_PER_MILLE: u32 = 1005;
+/// Default lower bound of the duration of an AlephBFT session, 7 days.
+pub const DEFAULT_ABFT_SESSION_LEN_LOWER_BOUND_MS: u64 = 1000 * 60 * 60 * 24 * 7;
+/// Default length of aggregation batches, every block gets a justification.
+pub const DEFAULT_AGGREGATION_BATCH_LEN: u32 = 1;
+
+// Fixed point precision used when computing the exponential slowdown.
+const ABFT_SLOWDOWN_PRECISION: u128 = 1_000_000_000;
+
+/// Parameters of AlephBFT that have to be identical for all the members of a committee.
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq, TypeInfo, Serialize, Deserialize)]
+pub struct AbftConfig {
+    /// Maximal number of rounds in a session.
+    pub max_rounds: u16,
+    /// Delay before creating the unit of the first round, in milliseconds.
+    pub initial_delay_ms: u64,
+    /// Delay between creating units before the slowdown starts, in milliseconds.
+    pub unit_creation_delay_ms: u64,
+    /// Round from which the delay grows exponentially.
+    pub slowdown_start_round: u16,
+    /// Ratio of the delays of consecutive rounds during the slowdown, in thousandths.
+    pub slowdown_base_per_mille: u32,
+    /// The total delay of all rounds has to exceed this, so that a session cannot run out of
+    /// rounds, in milliseconds.
+    pub session_len_lower_bound_ms: u64,
+    /// Only blocks with numbers divisible by this, and the last blocks of sessions, get
+    /// justifications, with the blocks in between finalized implicitly.
+    pub aggregation_batch_len: u32,
+}
+
+impl Default for AbftConfig {
+    fn default() -> Self {
+        AbftConfig {
+            max_rounds: DEFAULT_ABFT_MAX_ROUNDS,
+            initial_delay_ms: DEFAULT_ABFT_INITIAL_DELAY_MS,
+            unit_creation_delay_ms: DEFAULT_UNIT_CREATION_DELAY,
+            slowdown_start_round: DEFAULT_ABFT_SLOWDOWN_START_ROUND,
+            slowdown_base_per_mille: DEFAULT_ABFT_SLOWDOWN_BASE_PER_MILLE,
+            session_len_lower_bound_ms: DEFAULT_ABFT_SESSION_LEN_LOWER_BOUND_MS,
+            aggregation_batch_len: DEFAULT_AGGREGATION_BATCH_LEN,
+        }
+    }
+}
+
+impl AbftConfig {
+    /// Delay before creating the unit of the given round, in milliseconds.
+    ///
+    /// Computed using integers only, so that the runtime and the nodes agree on it exactly.
+    pub fn round_delay_ms(&self, round: usize) -> u64 {
+        if round == 0 {
+            return self.initial_delay_ms;
+        }
+        let mut power = round.saturating_sub(self.slowdown_start_round as usize);
+        let mut base = self.slowdown_base_per_mille as u128 * ABFT_SLOWDOWN_PRECISION / 1000;
+        let mut factor = ABFT_SLOWDOWN_PRECISION;
+        while power > 0 {
+            if power % 2 == 1 {
+                factor = factor.saturating_mul(base) / ABFT_SLOWDOWN_PRECISION;
+            }
+            base = base.saturating_mul(base) / ABFT_SLOWDOWN_PRECISION;
+            power /= 2;
+        }
+        let delay =
+            (self.unit_creation_delay_ms as u128).saturating_mul(factor) / ABFT_SLOWDOWN_PRECISION;
+        delay.try_into().unwrap_or(u64::MAX)
+    }
+
+    /// The total delay of all the rounds of a session, in milliseconds, if it fits in a `u64`.
+    pub fn total_delay_ms(&self) -> Option<u64> {
+        (0..=self.max_rounds as usize).try_fold(0u64, |total, round| {
+            total.checked_add(self.round_delay_ms(round))
+        })
+    }
+
+    /// Whether a session using this config is guaranteed not to run out of rounds too early.
+    pub fn is_valid(&self) -> bool {
+        self.max_rounds > 0
+            && self.aggregation_batch_len > 0
+            && self.slowdown_base_per_mille >= 1000
+            && self
+                .total_delay_ms()
+                .map_or(false, |total| total > self.session_len_lower_bound_ms)
+    }
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq, Eq, TypeInfo)]
+pub struct AbftConfigChange {
+    pub config_incoming: AbftConfig,
+    pub session: SessionIndex,
+}
+
+/// Parameters of slowing down block production when finalization falls behind. Block producers
+/// using different parameters would produce at different rates, so they are kept in the runtime.
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq, TypeInfo, Serialize, Deserialize)]
+pub struct AuthoringBackoffConfig {
+    /// Number of nonfinalized blocks from which producers skip slots after the best block.
+    pub slowdown_start: u32,
+    /// Number of additional nonfinalized blocks for which producers skip one more slot.
+    pub blocks_per_skipped_slot: u32,
+    /// Number of nonfinalized blocks at which block production stops.
+    pub max_nonfinalized_blocks: u32,
+}
+
+impl AuthoringBackoffConfig {
+    /// Whether the slowdown starts before block production stops and grows at all.
+    pub fn is_valid(&self) -> bool {
+        self.blocks_per_skipped_slot > 0 && self.slowdown_start <= self.max_nonfinalized_blocks
+    }
+}
+
+/// Session period and block time in force from a given session on.
+#[derive(Clone, Copy, Debug, Decode, Encode, PartialEq, Eq, TypeInfo, Serialize, Deserialize)]
+pub struct SessionTiming {
+    /// The first session with this timing.
+    pub first_session: SessionIndex,
+    /// The first block of that session.
+    pub first_block: BlockNumber,
+    pub session_period: u32,
+    pub millisecs_per_block: u64,
+}
+
+impl SessionTiming {
+    /// Whether sessions have any blocks and slots can be half a block time long, as Aura slots
+    /// are twice the minimum period of the timestamp pallet.
+    pub fn is_valid(&self) -> bool {
+        self.session_period > 0 && self.millisecs_per_block > 0 && self.millisecs_per_block % 2 == 0
+    }
+}
+
+/// All the session timings of the chain, sorted by the first session they apply to, the first one
+/// applying from the genesis. Allows mapping blocks to sessions across timing changes.
+#[derive(Clone, Debug, Decode, Encode, PartialEq, Eq, TypeInfo, Serialize, Deserialize)]
+pub struct SessionTimings(Vec<SessionTiming>);
+
+impl Default for SessionTimings {
+    fn default() -> Self {
+        SessionTimings::new(DEFAULT_SESSION_PERIOD, MILLISECS_PER_BLOCK)
+    }
+}
+
+impl SessionTimings {
+    /// Timings of a chain on which the timing never changes.
+    pub fn new(session_period: u32, millisecs_per_block: u64) -> Self {
+        SessionTimings(sp_std::vec![SessionTiming {
+            first_session: 0,
+            first_block: 0,
+            session_period,
+            millisecs_per_block,
+        }])
+    }
+
+    pub fn timings(&self) -> &[SessionTiming] {
+        &self.0
+    }
+
+    /// The timing of the given session.
+    pub fn of_session(&self, session: SessionIndex) -> &SessionTiming {
+        self.0
+            .iter()
+            .rev()
+            .find(|timing| timing.first_session <= session)
+            .unwrap_or(&self.0[0])
+    }
+
+    /// The timing in force at the given block.
+    pub fn at_block(&self, block: BlockNumber) -> &SessionTiming {
+        self.0
+            .iter()
+            .rev()
+            .find(|timing| timing.first_block <= block)
+            .unwrap_or(&self.0[0])
+    }
+
+    /// The session the given block belongs to.
+    pub fn session_of_block(&self, block: BlockNumber) -> SessionIndex {
+        let timing = self.at_block(block);
+        timing.first_session + (block - timing.first_block) / timing.session_period
+    }
+
+    /// The first block of the given session.
+    pub fn first_block_of_session(&self, session: SessionIndex) -> BlockNumber {
+        let timing = self.of_session(session);
+        timing.first_block + (session - timing.first_session) * timing.session_period
+    }
+
+    /// The last block of the given session.
+    pub fn last_block_of_session(&self, session: SessionIndex) -> BlockNumber {
+        self.first_block_of_session(session + 1) - 1
+    }
+
+    /// Schedules a new timing from `first_session` on, replacing all the timings scheduled for
+    /// that session or later.
+    pub fn schedule(
+        &mut self,
+        first_session: SessionIndex,
+        session_period: u32,
+        millisecs_per_block: u64,
+    ) {
+        self.0.retain(|timing| timing.first_session < first_session);
+        let first_block = match self.0.is_empty() {
+            true => 0,
+            false => self.first_block_of_session(first_session),
+        };
+        self.0.push(SessionTiming {
+            first_session,
+            first_block,
+            session_period,
+            millisecs_per_block,
+        });
+    }
+}
+
+/// The version of `AlephSessionApi` from which runtimes report `session_timings`, older ones
+/// only have a fixed session period.
+pub const SESSION_TIMINGS_API_VERSION: u32 = 2;
+
 sp_api::decl_runtime_apis! {
+    #[api_version(2)]
     pub trait AlephSessionApi {
         fn next_session_authorities() -> Result<Vec<AuthorityId>, ApiError>;
         fn authorities() -> Vec<AuthorityId>;
@@ -269,8 +521,24 @@ sp_api::decl_runtime_apis! {
         fn authority_data() -> SessionAuthorityData;
         fn session_period() -> u32;
         fn millisecs_per_block() -> u64;
+        /// All the session timings, including the scheduled ones.
+        #[api_version(2)]
+        fn session_timings() -> SessionTimings;
         fn finality_version() -> Version;
         fn next_session_finality_version() -> Version;
+        /// AlephBFT parameters the committee of the next session has to use.
+        fn next_session_abft_config() -> AbftConfig;
+        /// BLS session keys of the current committee, in the order of `authorities`.
+        fn bls_keys() -> Vec<BlsPublicKey>;
+        /// BLS session keys of the committee of the next session, in the order of
+        /// `next_session_authorities`.
+        fn next_session_bls_keys() -> Vec<BlsPublicKey>;
+        /// The emergency finalization keys of the current session with their threshold, if set.
+        fn emergency_finalizers() -> Option<EmergencyFinalizers>;
+        /// The emergency finalization keys of the next session with their threshold, if set.
+        fn next_session_emergency_finalizers() -> Option<EmergencyFinalizers>;
+        /// Parameters of slowing down block production when finalization falls behind, if set.
+        fn authoring_backoff_config() -> Option<AuthoringBackoffConfig>;
         /// Predict finality committee and block producers for the given session. `session` must be
         /// within the current era (current, in the staking context).
         ///
@@ -0,0 +1,324 @@
+use std::{
+    cmp::{max, min},
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use log::trace;
+use parking_lot::Mutex;
+
+use crate::{token_bucket::TokenBucket, RateLimit, LOG_TARGET};
+
+type ChildId = u64;
+
+/// Millionths of a token, the unit in which backlogs of children are kept to avoid rounding errors.
+const MICRO_TOKENS: u128 = 1_000_000;
+
+/// State of the bucket shared by all children of a [HierarchicalTokenBucket].
+///
+/// As long as nobody is waiting, it behaves like a regular token bucket. Once some children are backlogged, tokens stop
+/// accumulating and the total rate is divided equally between all the backlogged children, so a child that starts
+/// waiting slows down the ones already waiting. Since their delays were already handed out, they are charged the
+/// difference on their next request.
+#[derive(Debug)]
+struct SharedBucket {
+    rate_per_second: usize,
+    available: usize,
+    last_update: Instant,
+    next_child_id: ChildId,
+    backlogs: HashMap<ChildId, u128>,
+}
+
+impl SharedBucket {
+    fn new(rate_per_second: usize, now: Instant) -> Self {
+        Self {
+            rate_per_second,
+            available: rate_per_second,
+            last_update: now,
+            next_child_id: 0,
+            backlogs: HashMap::new(),
+        }
+    }
+
+    fn register_child(&mut self) -> ChildId {
+        let id = self.next_child_id;
+        self.next_child_id += 1;
+        id
+    }
+
+    fn unregister_child(&mut self, id: ChildId) {
+        self.backlogs.remove(&id);
+    }
+
+    fn rate(&self) -> u128 {
+        max(1, self.rate_per_second) as u128
+    }
+
+    /// Serves the backlogged children equally for the time elapsed since the last update, whatever remains after all
+    /// of them are served refills the bucket.
+    fn update_units(&mut self, now: Instant) {
+        let now = max(now, self.last_update);
+        let mut capacity = now
+            .duration_since(self.last_update)
+            .as_micros()
+            .saturating_mul(self.rate());
+        self.last_update = now;
+
+        let mut backlogs: Vec<_> = self.backlogs.values().copied().collect();
+        backlogs.sort_unstable();
+        let mut level = 0;
+        for (served, backlog) in backlogs.iter().enumerate() {
+            let sharing = (backlogs.len() - served) as u128;
+            let needed = (backlog - level).saturating_mul(sharing);
+            if needed > capacity {
+                level += capacity / sharing;
+                capacity = 0;
+                break;
+            }
+            capacity -= needed;
+            level = *backlog;
+        }
+        self.backlogs.retain(|_, backlog| {
+            *backlog = backlog.saturating_sub(level);
+            *backlog > 0
+        });
+
+        let new_units = (capacity / MICRO_TOKENS).try_into().unwrap_or(usize::MAX);
+        self.available = min(
+            self.available.saturating_add(new_units),
+            self.rate_per_second,
+        );
+    }
+
+    fn rate_limit(&mut self, id: ChildId, requested: usize, now: Instant) -> Option<Duration> {
+        self.update_units(now);
+
+        let from_bucket = min(self.available, requested);
+        self.available -= from_bucket;
+        let missing = ((requested - from_bucket) as u128).saturating_mul(MICRO_TOKENS);
+        let backlog = self.backlogs.get(&id).copied().unwrap_or(0);
+        if missing == 0 && backlog == 0 {
+            return None;
+        }
+
+        let backlog = backlog.saturating_add(missing);
+        self.backlogs.insert(id, backlog);
+        // Assuming nobody else starts waiting, every other child is served alongside this one until either of them
+        // is done.
+        let delay_micros = self
+            .backlogs
+            .values()
+            .map(|other| min(*other, backlog))
+            .fold(0u128, |total, served| total.saturating_add(served))
+            / self.rate();
+        let delay = Duration::from_micros(delay_micros.try_into().unwrap_or(u64::MAX));
+        trace!(
+            target: LOG_TARGET,
+            "SharedBucket delays child {} by {:?}, {} children contending.",
+            id,
+            delay,
+            self.backlogs.len()
+        );
+        Some(delay)
+    }
+}
+
+/// Composition of a per-child [TokenBucket] and a bucket shared by all the children created from it.
+///
+/// Every clone is a new child: it gets its own, fresh per-child bucket but draws from the same shared bucket as all the
+/// other clones. When the shared bucket is saturated, its rate is divided equally between the children currently waiting
+/// for it, so a single busy child cannot starve the others.
+pub struct HierarchicalTokenBucket {
+    id: ChildId,
+    own: TokenBucket,
+    shared: Arc<Mutex<SharedBucket>>,
+}
+
+impl HierarchicalTokenBucket {
+    /// Constructs an instance of [HierarchicalTokenBucket] limiting every child to `rate_per_second_per_child` and all of
+    /// them together to `total_rate_per_second`.
+    pub fn new(rate_per_second_per_child: usize, total_rate_per_second: usize) -> Self {
+        Self::new_with_now(
+            rate_per_second_per_child,
+            total_rate_per_second,
+            Instant::now(),
+        )
+    }
+
+    fn new_with_now(
+        rate_per_second_per_child: usize,
+        total_rate_per_second: usize,
+        now: Instant,
+    ) -> Self {
+        let mut shared = SharedBucket::new(total_rate_per_second, now);
+        let id = shared.register_child();
+        Self {
+            id,
+            own: TokenBucket::new_with_now(rate_per_second_per_child, now),
+            shared: Arc::new(Mutex::new(shared)),
+        }
+    }
+
+    fn new_child(&self, own: TokenBucket) -> Self {
+        Self {
+            id: self.shared.lock().register_child(),
+            own,
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl Clone for HierarchicalTokenBucket {
+    fn clone(&self) -> Self {
+        self.new_child(self.own.fresh())
+    }
+}
+
+impl Drop for HierarchicalTokenBucket {
+    fn drop(&mut self) {
+        self.shared.lock().unregister_child(self.id);
+    }
+}
+
+impl RateLimit for HierarchicalTokenBucket {
+    fn rate_limit(&mut self, requested: usize, now: Instant) -> Option<Duration> {
+        let own_delay = self.own.rate_limit(requested, now);
+        let shared_delay = self.shared.lock().rate_limit(self.id, requested, now);
+        max(own_delay, shared_delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::HierarchicalTokenBucket;
+    use crate::RateLimit;
+
+    #[test]
+    fn no_slowdown_while_within_both_limits() {
+        let now = Instant::now();
+        let mut first = HierarchicalTokenBucket::new_with_now(10, 100, now);
+        let mut second = first.clone();
+
+        assert_eq!(first.rate_limit(10, now), None);
+        assert_eq!(second.rate_limit(10, now), None);
+        assert_eq!(first.rate_limit(9, now + Duration::from_secs(1)), None);
+        assert_eq!(second.rate_limit(9, now + Duration::from_secs(1)), None);
+    }
+
+    #[test]
+    fn child_is_limited_by_own_rate() {
+        let now = Instant::now();
+        let mut child = HierarchicalTokenBucket::new_with_now(10, 100, now);
+
+        assert_eq!(child.rate_limit(10, now), None);
+        assert_eq!(
+            child.rate_limit(20, now),
+            Some(Duration::from_secs(2)),
+            "per-child limit should apply even though the shared bucket is not empty"
+        );
+    }
+
+    #[test]
+    fn clones_are_independent_children() {
+        let now = Instant::now();
+        let mut first = HierarchicalTokenBucket::new_with_now(10, 100, now);
+        let mut second = first.clone();
+
+        assert_eq!(first.rate_limit(10, now), None);
+        assert!(first.rate_limit(1, now).is_some());
+        assert_eq!(
+            second.rate_limit(10, now),
+            None,
+            "exhausting one child should not affect another one"
+        );
+    }
+
+    #[test]
+    fn children_are_limited_by_shared_rate() {
+        let now = Instant::now();
+        let mut first = HierarchicalTokenBucket::new_with_now(100, 10, now);
+        let mut second = first.clone();
+
+        assert_eq!(first.rate_limit(10, now), None);
+        assert_eq!(
+            second.rate_limit(10, now),
+            Some(Duration::from_secs(1)),
+            "shared bucket is empty, so the second child should wait for a full refill"
+        );
+    }
+
+    #[test]
+    fn shared_rate_is_divided_fairly_between_waiting_children() {
+        let now = Instant::now();
+        let mut greedy = HierarchicalTokenBucket::new_with_now(1000, 10, now);
+        let mut modest = greedy.clone();
+
+        assert_eq!(greedy.rate_limit(10, now), None);
+        assert_eq!(greedy.rate_limit(100, now), Some(Duration::from_secs(10)));
+
+        // `greedy` is still waiting, so `modest` gets half of the shared rate instead of queueing behind `greedy`
+        assert_eq!(modest.rate_limit(5, now), Some(Duration::from_secs(1)));
+        assert_eq!(
+            modest.rate_limit(5, now + Duration::from_secs(1)),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn tokens_are_not_accumulated_while_children_wait() {
+        let now = Instant::now();
+        let mut first = HierarchicalTokenBucket::new_with_now(100, 10, now);
+        let mut second = first.clone();
+
+        assert_eq!(first.rate_limit(30, now), Some(Duration::from_secs(2)));
+        assert_eq!(
+            second.rate_limit(10, now + Duration::from_secs(1)),
+            Some(Duration::from_secs(2)),
+            "first child is still being served, so the second one gets only half of the rate"
+        );
+        assert_eq!(
+            first.rate_limit(10, now + Duration::from_secs(4)),
+            None,
+            "after everybody is served the shared bucket refills again"
+        );
+    }
+
+    #[test]
+    fn waiting_children_are_slowed_down_by_new_contenders() {
+        let now = Instant::now();
+        let mut first = HierarchicalTokenBucket::new_with_now(100, 10, now);
+        let mut second = first.clone();
+
+        assert_eq!(first.rate_limit(30, now), Some(Duration::from_secs(2)));
+        assert_eq!(
+            second.rate_limit(10, now + Duration::from_secs(1)),
+            Some(Duration::from_secs(2))
+        );
+        // Since `second` started waiting, `first` was served at half the rate, so 5 of its tokens are still missing.
+        assert_eq!(
+            first.rate_limit(10, now + Duration::from_secs(2)),
+            Some(Duration::from_secs(2)),
+            "the total rate should never exceed the shared limit"
+        );
+    }
+
+    #[test]
+    fn dropped_child_stops_contending() {
+        let now = Instant::now();
+        let mut first = HierarchicalTokenBucket::new_with_now(1000, 10, now);
+        let mut second = first.clone();
+
+        assert_eq!(first.rate_limit(10, now), None);
+        assert_eq!(first.rate_limit(100, now), Some(Duration::from_secs(10)));
+        drop(first);
+
+        assert_eq!(
+            second.rate_limit(10, now),
+            Some(Duration::from_secs(1)),
+            "a dropped child should not reduce the share of the remaining ones"
+        );
+    }
+}
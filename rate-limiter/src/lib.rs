@@ -1,6 +1,20 @@
+use std::time::{Duration, Instant};
+
+mod hierarchical_token_bucket;
 mod rate_limiter;
 mod token_bucket;
 
-pub use crate::rate_limiter::{RateLimiter, SleepingRateLimiter};
+pub use crate::{
+    hierarchical_token_bucket::HierarchicalTokenBucket,
+    rate_limiter::{RateLimiter, SleepingRateLimiter},
+    token_bucket::TokenBucket,
+};
 
 const LOG_TARGET: &str = "rate-limiter";
+
+/// Algorithm deciding how long access to some rate-limited resource should be delayed.
+pub trait RateLimit: Clone + Send + 'static {
+    /// Calculates [Duration] by which we should delay next call to some governed resource in order to satisfy
+    /// configured rate limit.
+    fn rate_limit(&mut self, requested: usize, now: Instant) -> Option<Duration>;
+}
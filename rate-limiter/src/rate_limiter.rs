@@ -4,15 +4,18 @@ use futures::{future::BoxFuture, FutureExt};
 use log::trace;
 use tokio::{io::AsyncRead, time::sleep};
 
-use crate::{token_bucket::TokenBucket, LOG_TARGET};
+use crate::{
+    hierarchical_token_bucket::HierarchicalTokenBucket, token_bucket::TokenBucket, RateLimit,
+    LOG_TARGET,
+};
 
 /// Allows to limit access to some resource. Given a preferred rate (units of something) and last used amount of units of some
 /// resource, it calculates how long we should delay our next access to that resource in order to satisfy that rate.
-pub struct SleepingRateLimiter {
-    rate_limiter: TokenBucket,
+pub struct SleepingRateLimiter<RL: RateLimit = TokenBucket> {
+    rate_limiter: RL,
 }
 
-impl Clone for SleepingRateLimiter {
+impl<RL: RateLimit> Clone for SleepingRateLimiter<RL> {
     fn clone(&self) -> Self {
         Self {
             rate_limiter: self.rate_limiter.clone(),
@@ -23,9 +26,28 @@ impl Clone for SleepingRateLimiter {
 impl SleepingRateLimiter {
     /// Constructs a instance of [SleepingRateLimiter] with given target rate-per-second.
     pub fn new(rate_per_second: usize) -> Self {
-        Self {
-            rate_limiter: TokenBucket::new(rate_per_second),
-        }
+        Self::with_rate_limit(TokenBucket::new(rate_per_second))
+    }
+}
+
+impl SleepingRateLimiter<HierarchicalTokenBucket> {
+    /// Constructs a instance of [SleepingRateLimiter] whose clones are limited to `rate_per_second_per_child` each, and
+    /// to `total_rate_per_second` all together.
+    pub fn new_hierarchical(
+        rate_per_second_per_child: usize,
+        total_rate_per_second: usize,
+    ) -> Self {
+        Self::with_rate_limit(HierarchicalTokenBucket::new(
+            rate_per_second_per_child,
+            total_rate_per_second,
+        ))
+    }
+}
+
+impl<RL: RateLimit> SleepingRateLimiter<RL> {
+    /// Constructs a instance of [SleepingRateLimiter] using given rate-limiting algorithm.
+    pub fn with_rate_limit(rate_limiter: RL) -> Self {
+        Self { rate_limiter }
     }
 
     /// Given `read_size`, that is an amount of units of some governed resource, delays return of `Self` to satisfy configure
@@ -55,14 +77,14 @@ impl SleepingRateLimiter {
 }
 
 /// Wrapper around [SleepingRateLimiter] to simplify implementation of the [AsyncRead](tokio::io::AsyncRead) trait.
-pub struct RateLimiter {
-    rate_limiter: BoxFuture<'static, SleepingRateLimiter>,
+pub struct RateLimiter<RL: RateLimit = TokenBucket> {
+    rate_limiter: BoxFuture<'static, SleepingRateLimiter<RL>>,
 }
 
-impl RateLimiter {
+impl<RL: RateLimit> RateLimiter<RL> {
     /// Constructs an instance of [RateLimiter] that uses already configured rate-limiting access governor
     /// ([SleepingRateLimiter]).
-    pub fn new(rate_limiter: SleepingRateLimiter) -> Self {
+    pub fn new(rate_limiter: SleepingRateLimiter<RL>) -> Self {
         Self {
             rate_limiter: Box::pin(rate_limiter.rate_limit(0)),
         }
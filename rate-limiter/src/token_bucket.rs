@@ -5,7 +5,7 @@ use std::{
 
 use log::trace;
 
-use crate::LOG_TARGET;
+use crate::{RateLimit, LOG_TARGET};
 
 /// Implementation of the `Token Bucket` algorithm for the purpose of rate-limiting access to some abstract resource.
 #[derive(Clone, Debug)]
@@ -27,14 +27,18 @@ impl TokenBucket {
         }
     }
 
-    #[cfg(test)]
-    pub fn new_with_now(rate_per_second: usize, now: Instant) -> Self {
+    pub(crate) fn new_with_now(rate_per_second: usize, now: Instant) -> Self {
         Self {
             last_update: now,
             ..Self::new(rate_per_second)
         }
     }
 
+    /// Constructs a full [TokenBucket] with the same rate, as if it was created at the time of the last update of `self`.
+    pub(crate) fn fresh(&self) -> Self {
+        Self::new_with_now(self.rate_per_second, self.last_update)
+    }
+
     fn calculate_delay(&self) -> Duration {
         let delay_micros = (self.requested - self.available)
             .saturating_mul(1_000_000)
@@ -60,9 +64,13 @@ impl TokenBucket {
         self.available
     }
 
-    /// Calculates [Duration](time::Duration) by which we should delay next call to some governed resource in order to satisfy
-    /// configured rate limit.
-    pub fn rate_limit(&mut self, requested: usize, now: Instant) -> Option<Duration> {
+    fn token_limit(&self) -> usize {
+        self.rate_per_second
+    }
+}
+
+impl RateLimit for TokenBucket {
+    fn rate_limit(&mut self, requested: usize, now: Instant) -> Option<Duration> {
         trace!(
             target: LOG_TARGET,
             "TokenBucket called for {} of requested bytes. Internal state: {:?}.",
@@ -86,10 +94,6 @@ impl TokenBucket {
         self.available = min(self.available, self.token_limit());
         None
     }
-
-    fn token_limit(&self) -> usize {
-        self.rate_per_second
-    }
 }
 
 #[cfg(test)]
@@ -97,6 +101,7 @@ mod tests {
     use std::time::{Duration, Instant};
 
     use super::TokenBucket;
+    use crate::RateLimit;
 
     #[test]
     fn token_bucket_sanity_check() {
@@ -0,0 +1,98 @@
+#!/usr/bin/env bash
+
+# This script instantiates the contracts declared in a network description, once the chain described
+# is running. Contracts cannot be instantiated in the genesis, so `aleph-node bootstrap-chain
+# --from-config` writes them to contracts.json under its base path instead, and this script reads
+# that file and instantiates every contract with cliain, in order, signed by its deployer.
+#
+# You need to have installed following prerequisites in order to use that script:
+#   * jq
+#   * cliain (see bin/cliain)
+
+set -euo pipefail
+
+# ------------------------ argument parsing and usage -----------------------
+
+script_path="${BASH_SOURCE[0]}"
+script_dir=$(dirname "${script_path}")
+aleph_node_root_dir=$(realpath "${script_dir}/..")
+source "${aleph_node_root_dir}/scripts/common.sh"
+
+function usage(){
+  cat << EOF
+Usage:
+   $0
+    --deployments DEPLOYMENTS
+      contracts.json written by bootstrap-chain --from-config
+    [--node NODE_URL]
+      WS endpoint of a node of the chain, ws://127.0.0.1:9944 by default
+    [--cliain CLIAIN]
+      path to the cliain binary, bin/cliain/target/release/cliain by default
+EOF
+  exit 0
+}
+
+DEPLOYMENTS=${DEPLOYMENTS:-""}
+NODE_URL=${NODE_URL:-"ws://127.0.0.1:9944"}
+CLIAIN=${CLIAIN:-"${aleph_node_root_dir}/bin/cliain/target/release/cliain"}
+
+while [[ $# -gt 0 ]]; do
+  case "$1" in
+    --deployments)
+      DEPLOYMENTS="$2"
+      shift;shift
+      ;;
+    --node)
+      NODE_URL="$2"
+      shift;shift
+      ;;
+    --cliain)
+      CLIAIN="$2"
+      shift;shift
+      ;;
+    --help)
+      usage
+      shift
+      ;;
+    *)
+      error "Unrecognized argument $1!"
+      ;;
+  esac
+done
+
+# ------------------------- input checks ----------------------------------
+
+if [[ ! -f "${DEPLOYMENTS}" ]]; then
+  error "Contract deployments file ${DEPLOYMENTS} does not exist!"
+fi
+if [[ ! -x "${CLIAIN}" ]]; then
+  error "${CLIAIN} does not exist or it's not an executable file!"
+fi
+if ! command -v jq &> /dev/null; then
+    error "jq could not be found on PATH!"
+fi
+
+# ------------------- main script starts here ------------------------------
+
+contracts_count=$(jq 'length' "${DEPLOYMENTS}")
+for i in $(seq 0 "$(( contracts_count - 1 ))"); do
+  deployment=$(jq ".[${i}]" "${DEPLOYMENTS}")
+  wasm_path=$(jq -r '.wasm_path' <<< "${deployment}")
+  mapfile -t constructor_args < <(jq -r '.args[]' <<< "${deployment}")
+
+  cliain_args=(
+    --node "${NODE_URL}"
+    --seed "$(jq -r '.deployer_seed' <<< "${deployment}")"
+    contract-instantiate-with-code
+    --wasm-path "${wasm_path}"
+    --metadata-path "$(jq -r '.metadata_path' <<< "${deployment}")"
+    --constructor "$(jq -r '.constructor' <<< "${deployment}")"
+    --balance "$(jq -r '.balance' <<< "${deployment}")"
+  )
+  if [[ "${#constructor_args[@]}" -gt 0 ]]; then
+    cliain_args+=(--args "${constructor_args[@]}")
+  fi
+
+  info "Instantiating ${wasm_path}."
+  "${CLIAIN}" "${cliain_args[@]}"
+done
@@ -5,7 +5,10 @@
 # one RPC node that is a bootnode, and 6 validator nodes.
 #
 # Before run, a chainspec is generated that is an initial testing AlephZero chain configuration,
-# that is a starting point for all aleph-nodes. Together with the chainspec, we generate a keystore
+# that is a starting point for all aleph-nodes. The chainspec is generated from a network description
+# (see bin/node/src/network_config.rs) listing the validators, to which a description of the rest of
+# the network (endowments, vesting, nomination pools, contracts to deploy, ...) can be appended with
+# --network-config. Together with the chainspec, we generate a keystore
 # that consist of two types of keys:
 #   * two session keys for each validator (one for authoring blocks called AURA key and one for
 #     participating in AlephBFT consensus called ALEPH key)
@@ -31,6 +34,7 @@
 #
 # You need to have installed following prerequisites in order to use that script:
 #   * jq
+#   * cliain (see bin/cliain), if the network description declares contracts to deploy
 #
 # This script also accepts env variables instead of arguments, see --help for details. All arguments
 # are optional.
@@ -63,6 +67,12 @@ Usage:
     [-p|--base-path BASE_PATH]
         if specified, use given base path (keystore, db, AlephBFT backups)
         if not specified, base path is ./run-nodes-local
+    [-c|--network-config NETWORK_CONFIG]
+      TOML network description appended to the generated one, which sets chain_type, sudo and
+      validators; contracts declared in it are deployed once the nodes are running, relative paths
+      of their artifacts are resolved against the base path
+    [--cliain CLIAIN]
+      path to the cliain binary used to deploy contracts, bin/cliain/target/release/cliain by default
     [--dont-bootstrap]
       set if you don't want to bootstrap chain, ie generate keystore and chainspec
     [--dont-build]
@@ -80,6 +90,8 @@ EOF
 VALIDATORS=${VALIDATORS:-6}
 RPC_NODES=${RPC_NODES:-1}
 BASE_PATH=${BASE_PATH:-"./run-nodes-local"}
+NETWORK_CONFIG=${NETWORK_CONFIG:-""}
+CLIAIN=${CLIAIN:-"bin/cliain/target/release/cliain"}
 DONT_BOOTSTRAP=${DONT_BOOTSTRAP:-""}
 DONT_BUILD_ALEPH_NODE=${DONT_BUILD_ALEPH_NODE:-""}
 DONT_DELETE_DB=${DONT_DELETE_DB:-""}
@@ -99,6 +111,14 @@ while [[ $# -gt 0 ]]; do
       BASE_PATH="$2"
       shift;shift
       ;;
+    -c|--network-config)
+      NETWORK_CONFIG="$2"
+      shift;shift
+      ;;
+    --cliain)
+      CLIAIN="$2"
+      shift;shift
+      ;;
     --dont-bootstrap)
       DONT_BOOTSTRAP="true"
       shift
@@ -212,6 +232,9 @@ if [[ "${DONT_BOOTSTRAP}" == "true" && ! -f "${BASE_PATH}/chainspec.json" ]]; th
   error "Flag --dont-bootstrap is set and there is no ${BASE_PATH}/chainspec.json file, maybe you
         forget to bootstrap chain?"
 fi
+if [[ -n "${NETWORK_CONFIG}" && ! -f "${NETWORK_CONFIG}" ]]; then
+  error "Network description ${NETWORK_CONFIG} does not exist!"
+fi
 if ! command -v jq &> /dev/null; then
     error "jq could not be found on PATH!"
 fi
@@ -251,13 +274,24 @@ info "Validator nodes: ${validator_account_ids[@]}"
 if [[ -z "${DONT_BOOTSTRAP}" ]]; then
   info "Bootstrapping chain for ${NUMBER_OF_NODES_TO_BOOTSTRAP} nodes."
 
-  # space separated ids
-  validator_ids_string="${validator_account_ids[*]}"
-  # comma separated ids
-  validator_ids_string="${validator_ids_string//${IFS:0:1}/,}"
+  info "Creating network description for validators accounts."
+  network_config="${BASE_PATH}/network.toml"
+  {
+    echo 'chain_type = "local"'
+    echo 'sudo = "//Alice"'
+    echo 'validators = ['
+    for validator_account_id in "${validator_account_ids[@]}"; do
+      echo "  { account = \"${validator_account_id}\" },"
+    done
+    echo ']'
+    if [[ -n "${NETWORK_CONFIG}" ]]; then
+      cat "${NETWORK_CONFIG}"
+    fi
+  } > "${network_config}"
 
   info "Creating chainspec and generating keystore for validators accounts."
-  "${ALEPH_NODE}" bootstrap-chain --raw --base-path "${BASE_PATH}" --account-ids "${validator_ids_string}" --chain-type local > "${BASE_PATH}/chainspec.json"
+  rm -f "${BASE_PATH}/contracts.json"
+  "${ALEPH_NODE}" bootstrap-chain --raw --base-path "${BASE_PATH}" --from-config "${network_config}" > "${BASE_PATH}/chainspec.json"
 
   info "Generating keystores for ${RPC_NODES} RPC nodes"
   for i in $(seq 0 "$(( RPC_NODES - 1 ))"); do
@@ -313,5 +347,14 @@ for i in $(seq 0 "$(( VALIDATORS - 1 ))"); do
   run_node $(( i + RPC_NODES )) "${validator_account_id}" "${bootnode_multiaddress}"
 done
 
+if [[ -z "${DONT_BOOTSTRAP}" && -f "${BASE_PATH}/contracts.json" ]]; then
+  info "Waiting for the RPC of node 0 before deploying contracts."
+  until (exec 3<>"/dev/tcp/127.0.0.1/${NODE_RPC_PORT_RANGE_START}") 2> /dev/null; do
+    sleep 1
+  done
+  ./scripts/deploy_contracts.sh --deployments "${BASE_PATH}/contracts.json" \
+    --node "ws://127.0.0.1:${NODE_RPC_PORT_RANGE_START}" --cliain "${CLIAIN}"
+fi
+
 popd > /dev/null
 exit 0